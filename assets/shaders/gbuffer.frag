@@ -1,7 +1,19 @@
 #version 450
+layout(location = 0) in vec3 vNormal;
+layout(location = 1) in vec2 vUV;
+layout(location = 2) in vec4 vColor;
+layout(location = 3) in vec3 vPosition;
 layout(location = 0) out vec4 outAlbedo;
 layout(location = 1) out vec4 outNormal;
+layout(location = 2) out vec4 outMaterial;
+layout(location = 3) out vec4 outPosition;
 void main() {
-    outAlbedo = vec4(1.0, 0.0, 0.0, 1.0);
-    outNormal = vec4(0.0, 0.0, 1.0, 1.0);
+    outAlbedo = vColor;
+    // Normals are stored in an unsigned format, so remap them to [0, 1].
+    outNormal = vec4(normalize(vNormal) * 0.5 + 0.5, 1.0);
+    // Metallic, roughness and ambient occlusion.
+    outMaterial = vec4(0.0, 1.0, 1.0, 1.0);
+    // Lights are positioned in the same space, so the lighting pass reads
+    // this instead of reconstructing it from depth.
+    outPosition = vec4(vPosition, 1.0);
 }
@@ -1,32 +1,65 @@
 #version 450
 
-struct Light {
+// Must match `MAX_DEFERRED_LIGHTS` in src/renderer/deferred.rs.
+const uint MAX_LIGHTS = 64u;
+
+// Raw `LightKind` values.
+const uint LIGHT_POINT = 0u;
+const uint LIGHT_SPOT = 1u;
+const uint LIGHT_DIRECTIONAL = 2u;
+const uint LIGHT_AREA = 3u;
+
+layout(set = 0, binding = 0) uniform sampler2D gbuffer_albedo;
+layout(set = 0, binding = 1) uniform sampler2D gbuffer_normal;
+layout(set = 0, binding = 2) uniform sampler2D gbuffer_material;
+layout(set = 0, binding = 3) uniform sampler2D gbuffer_position;
+layout(set = 0, binding = 4) uniform LightCount { uint count; } light_count;
+// One buffer per light, as allocated by `BindlessLights`.
+// Members must match `LIGHT_BLOCK_GLSL` in src/material/bindless_lighting.rs.
+layout(set = 1, binding = 0) readonly buffer BindlessLight {
+    // koji light layout v2
     vec3 position;
     float intensity;
     vec3 color;
-    uint _pad;
-};
-
-layout(set = 0, binding = 0) uniform sampler2D albedoTex;
-layout(set = 0, binding = 1) uniform sampler2D normalTex;
-layout(set = 0, binding = 2) buffer Lights { Light lights[]; };
-layout(set = 0, binding = 3) uniform LightCount { uint count; } light_count;
+    float range;
+    vec3 direction;
+    uint kind;
+    float spot_inner;
+    float spot_outer;
+    vec2 area_extent;
+} bindless_lights[MAX_LIGHTS];
 
 layout(location = 0) out vec4 outColor;
 
 void main() {
-    vec2 uv = gl_FragCoord.xy / vec2(textureSize(albedoTex, 0));
-    vec3 albedo = texture(albedoTex, uv).rgb;
-    vec3 normal = normalize(texture(normalTex, uv).xyz * 2.0 - 1.0);
+    vec2 uv = gl_FragCoord.xy / vec2(textureSize(gbuffer_albedo, 0));
+    vec3 albedo = texture(gbuffer_albedo, uv).rgb;
+    vec3 normal = normalize(texture(gbuffer_normal, uv).xyz * 2.0 - 1.0);
+    float roughness = texture(gbuffer_material, uv).g;
+    vec3 position = texture(gbuffer_position, uv).xyz;
+    float shininess = mix(64.0, 4.0, roughness);
 
     vec3 view_dir = vec3(0.0, 0.0, 1.0);
     vec3 result = vec3(0.0);
-    for (uint i = 0u; i < light_count.count; ++i) {
-        vec3 light_dir = normalize(lights[i].position);
+    uint count = min(light_count.count, MAX_LIGHTS);
+    for (uint i = 0u; i < count; ++i) {
+        uint kind = bindless_lights[i].kind;
+        // Direction from the surface towards the light.
+        vec3 light_dir = kind == LIGHT_DIRECTIONAL
+            ? normalize(-bindless_lights[i].direction)
+            : normalize(bindless_lights[i].position - position);
+        float cone = 1.0;
+        if (kind == LIGHT_SPOT) {
+            // Angle between the spot axis and the ray from the light to the
+            // surface.
+            float cos_angle = dot(-light_dir, normalize(bindless_lights[i].direction));
+            cone = smoothstep(cos(bindless_lights[i].spot_outer),
+                              cos(bindless_lights[i].spot_inner) + 1e-4, cos_angle);
+        }
         float diff = max(dot(normal, light_dir), 0.0);
         vec3 half_dir = normalize(light_dir + view_dir);
-        float spec = pow(max(dot(normal, half_dir), 0.0), 16.0);
-        vec3 light_color = lights[i].color * lights[i].intensity;
+        float spec = pow(max(dot(normal, half_dir), 0.0), shininess);
+        vec3 light_color = bindless_lights[i].color * bindless_lights[i].intensity * cone;
         result += albedo * diff * light_color + spec * light_color;
     }
     outColor = vec4(result, 1.0);
@@ -6,8 +6,12 @@ layout(location = 3) in vec2 inUV;
 layout(location = 4) in vec4 inColor;
 layout(location = 0) out vec3 vNormal;
 layout(location = 1) out vec2 vUV;
+layout(location = 2) out vec4 vColor;
+layout(location = 3) out vec3 vPosition;
 void main() {
     vNormal = inNormal;
+    vPosition = inPos;
     vUV = inUV;
+    vColor = inColor;
     gl_Position = vec4(inPos, 1.0);
 }
@@ -0,0 +1,24 @@
+#version 450
+layout(location = 0) in vec3 inPos;
+layout(location = 1) in vec3 inNormal;
+layout(location = 2) in vec4 inTangent;
+layout(location = 3) in vec2 inUV;
+layout(location = 4) in vec4 inColor;
+
+// Layout of `MeshInstance`, see instancing.slang.
+struct Instance {
+    mat4 transform;
+    vec4 color;
+    uint material_index;
+    uint _pad0;
+    uint _pad1;
+    uint _pad2;
+};
+
+layout(set = 0, binding = 5) readonly buffer InstanceBuffer { Instance instances[]; } KOJI_instances;
+layout(set = 1, binding = 0) uniform ShadowView { mat4 view_proj; } shadow_view;
+
+void main() {
+    mat4 model = KOJI_instances.instances[gl_InstanceIndex].transform;
+    gl_Position = shadow_view.view_proj * model * vec4(inPos, 1.0);
+}
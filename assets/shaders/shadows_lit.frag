@@ -2,17 +2,53 @@
 layout(location = 0) in vec3 vWorldPos;
 layout(location = 1) in vec3 vNormal;
 
-layout(set = 0, binding = 1) uniform sampler2DShadow shadowMap;
+// Layout of `ShadowView`.
+struct ShadowView {
+    mat4 view_proj;
+    vec4 atlas_rect;
+    uint light_index;
+    uint cascade;
+    float split_far;
+    float bias;
+};
+
+layout(set = 0, binding = 1) uniform sampler2D shadow_atlas;
+layout(set = 0, binding = 2) readonly buffer ShadowViews {
+    uint count;
+    uint _pad0;
+    uint _pad1;
+    uint _pad2;
+    ShadowView views[];
+} shadow_views;
 
 layout(location = 0) out vec4 outColor;
 
+// 1.0 when lit, 0.0 when `light` is occluded. Cascades are stored nearest
+// first, so the first view containing the point has the finest texels.
+float shadow_factor(uint light, vec3 world_pos) {
+    for (uint i = 0u; i < shadow_views.count; ++i) {
+        ShadowView v = shadow_views.views[i];
+        if (v.light_index != light) {
+            continue;
+        }
+        vec4 clip = v.view_proj * vec4(world_pos, 1.0);
+        vec3 ndc = clip.xyz / clip.w;
+        vec2 uv = ndc.xy * 0.5 + 0.5;
+        if (any(lessThan(uv, vec2(0.0))) || any(greaterThan(uv, vec2(1.0))) || ndc.z > 1.0) {
+            continue;
+        }
+        float stored = texture(shadow_atlas, v.atlas_rect.xy + uv * v.atlas_rect.zw).r;
+        return ndc.z - v.bias > stored ? 0.0 : 1.0;
+    }
+    return 1.0;
+}
+
 void main() {
     vec3 lightDir = normalize(vec3(-0.5, -1.0, -0.3));
     vec3 normal = normalize(vNormal);
     float NdotL = max(dot(normal, -lightDir), 0.0);
 
-    // Sample the shadow map
-    float shadow = texture(shadowMap, vec3(vWorldPos.xy * 0.5 + 0.5, vWorldPos.z));
+    float shadow = shadow_factor(0u, vWorldPos);
 
     // Combine lighting with shadow factor
     vec3 litColor = vec3(1.0, 1.0, 0.8) * NdotL * shadow;
@@ -42,7 +42,7 @@ pub fn run(ctx: &mut Context) {
         .render_pass(renderer.graph().output("color"))
         .build();
     let bgr = pso.create_bind_groups(&renderer.resources()).unwrap();
-    renderer.register_pipeline_for_pass("main", pso, bgr);
+    renderer.register_pipeline_for_pass("main", pso, bgr).unwrap();
 
     let mut bindless = BindlessData::new();
     let tex_data: [u8; 4] = [255, 0, 0, 255];
@@ -101,8 +101,9 @@ pub fn run(ctx: &mut Context) {
         vertex_buffer: None,
         index_buffer: None,
         index_count: 0,
+        bounds: None,
     };
-    renderer.register_static_mesh(mesh, None, "bindless".into(), "canvas");
+    renderer.register_static_mesh(mesh, None, "bindless".into(), "canvas").unwrap();
     renderer.present_frame().unwrap();
 }
 
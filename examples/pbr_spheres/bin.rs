@@ -166,7 +166,7 @@ pub fn run(ctx: &mut Context) {
 
     // Create bind groups now that all resources are registered
     let bgr = pso.create_bind_groups(&renderer.resources()).unwrap();
-    renderer.register_pipeline_for_pass("main", pso, bgr);
+    renderer.register_pipeline_for_pass("main", pso, bgr).unwrap();
 
     let (base_verts, inds) = make_sphere(32, 32);
     let offsets = [-3.0f32, 0.0, 3.0];
@@ -182,8 +182,9 @@ pub fn run(ctx: &mut Context) {
             vertex_buffer: None,
             index_buffer: None,
             index_count: 0,
+            bounds: None,
         };
-        renderer.register_static_mesh(mesh, None, "pbr".into(), "canvas");
+        renderer.register_static_mesh(mesh, None, "pbr".into(), "canvas").unwrap();
     }
 
     let mut angle: f32 = 0.0;
@@ -226,7 +227,8 @@ pub fn run(ctx: &mut Context) {
             }
             _ => {}
         }
-    });
+    })
+    .unwrap();
 }
 
 pub fn main() {
@@ -51,7 +51,7 @@ pub fn run(ctx: &mut Context) {
         .build();
 
     let bind_groups = pso.create_bind_groups(renderer.resources()).unwrap();
-    renderer.register_pipeline_for_pass("main", pso, bind_groups);
+    renderer.register_pipeline_for_pass("main", pso, bind_groups).unwrap();
 
     let mesh = StaticMesh {
         material_id: "color".into(),
@@ -82,10 +82,11 @@ pub fn run(ctx: &mut Context) {
         vertex_buffer: None,
         index_buffer: None,
         index_count: 0,
+        bounds: None,
     };
-    renderer.register_static_mesh(mesh, None, "color".into(), "canvas");
+    renderer.register_static_mesh(mesh, None, "color".into(), "canvas").unwrap();
 
-    renderer.render_loop(|_r, _event| {});
+    renderer.render_loop(|_r, _event| {}).unwrap();
 }
 
 pub fn main() {
@@ -25,14 +25,14 @@ pub fn run(ctx: &mut Context) {
     let player = AnimationPlayer::new(clip);
     let animator = Animator::new(mesh.skeleton.clone());
     let instance = SkeletalInstance::with_player(ctx, animator, player).unwrap();
-    renderer.register_skeletal_mesh(mesh, vec![instance], "skin".into(), "canvas");
+    let handle = renderer.register_skeletal_mesh(mesh, vec![instance], "skin".into(), "canvas");
 
     let mut pso = build_skinning_pipeline(ctx, renderer.graph().output("color"));
 
     let bgr = pso.create_bind_groups(&renderer.resources()).unwrap();
-    renderer.register_skeletal_pso(pso, bgr);
+    renderer.register_skeletal_pso(pso, bgr).unwrap();
 
-    renderer.play_animation(0, 0, 0.5);
+    renderer.play_animation(handle, 0, 0.5);
     renderer.present_frame().unwrap();
 }
 
@@ -123,7 +123,7 @@ pub fn run(ctx: &mut Context) {
         .render_pass(renderer.graph().output("color"))
         .build();
     let bgr = pso.create_bind_groups(renderer.resources()).unwrap();
-    renderer.register_pso(RenderStage::Text, pso, bgr);
+    renderer.register_pso(RenderStage::Text, pso, bgr).unwrap();
 
     renderer.render_loop(|r, event| {
         let mut changed = false;
@@ -159,7 +159,8 @@ pub fn run(ctx: &mut Context) {
                 .update_text(ctx, r.resources(), &mut text, &input, 32.0, [-0.5, 0.5])
                 .unwrap();
         }
-    });
+    })
+    .unwrap();
 }
 
 fn main() {
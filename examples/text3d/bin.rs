@@ -62,9 +62,8 @@ pub fn run(ctx: &mut Context) {
     let view = Mat4::look_at_rh(Vec3::new(0.0, 0.0, 2.0), Vec3::ZERO, Vec3::Y);
     let mat = proj * view * Mat4::IDENTITY;
     let mesh = text.make_quad_3d(dim, mat, _idx, [1.0; 4], true);
-    renderer.register_text_mesh(mesh, "canvas");
+    let text_handle = renderer.register_text_mesh(mesh, "canvas");
     text.register_textures(renderer.resources());
-    let mesh_idx = 0usize;
 
     let vert_spv = make_vert();
     let frag_spv = make_frag();
@@ -74,7 +73,7 @@ pub fn run(ctx: &mut Context) {
         .render_pass(renderer.graph().output("color"))
         .build();
     let bgr = pso.create_bind_groups(renderer.resources()).unwrap();
-    renderer.register_pso(RenderStage::Text, pso, bgr);
+    renderer.register_pso(RenderStage::Text, pso, bgr).unwrap();
 
     let mut angle: f32 = 0.0;
     renderer.render_loop(|r, event| {
@@ -84,9 +83,10 @@ pub fn run(ctx: &mut Context) {
                 * view
                 * Mat4::from_rotation_y(angle);
             let mesh2 = text.make_quad_3d(dim, mat, _idx, [1.0; 4], true);
-            r.update_text_mesh("canvas", mesh_idx, mesh2);
+            r.update_text_mesh(text_handle, mesh2);
         }
-    });
+    })
+    .unwrap();
 }
 
 fn main() {
@@ -6,8 +6,25 @@
 //! these passes. The
 //! resulting `Canvas` exposes its attachments for pipeline creation and can be
 //! connected to other graph nodes.
+//!
+//! A canvas built with [`CanvasBuilder::relative_extent`] is sized as a
+//! fraction of the renderer's output and is reallocated by [`Canvas::resize`]
+//! when the output changes size.
+//!
+//! A canvas can also test against the depth attachment of another canvas
+//! without writing it, see [`CanvasBuilder::read_only_depth_attachment`].
+//!
+//! By default a canvas has a single `main` subpass writing every color
+//! attachment. [`CanvasBuilder::subpass`] and
+//! [`CanvasBuilder::subpass_with_inputs`] declare several subpasses instead.
+//! Input attachments can be declared and serialized, but dashi render passes
+//! cannot bind them yet, so building a canvas that reads any fails.
 
-use crate::render_pass::{RenderAttachment, RenderPassBuilder, RenderTarget};
+use crate::format::FormatClass;
+use crate::utils::GpuObject;
+use crate::render_pass::{
+    make_attachment_image, supports_samples, RenderAttachment, RenderPassBuilder, RenderTarget,
+};
 use dashi::utils::*;
 use dashi::*;
 use indexmap::IndexMap;
@@ -16,12 +33,131 @@ use serde::{Deserialize, Serialize};
 pub mod io;
 pub use io::*;
 
+/// An error from building a [`Canvas`].
+#[derive(Debug)]
+pub enum CanvasError {
+    /// A read-only depth attachment is not a depth attachment of its source
+    /// canvas.
+    MissingDepthSource { attachment: String },
+    /// A borrowed depth attachment has a different sample count than the
+    /// canvas.
+    DepthSampleMismatch {
+        attachment: String,
+        canvas: u32,
+        source: u32,
+    },
+    /// A subpass names an attachment the canvas does not declare.
+    UnknownAttachment { subpass: String, attachment: String },
+    /// A subpass depends on a subpass the canvas does not declare.
+    UnknownSubpass { subpass: String, dependency: String },
+    /// The sample count is not 1. dashi images and render passes are
+    /// single-sampled.
+    UnsupportedSamples(u32),
+    /// An attachment description has a different sample count than its
+    /// canvas.
+    MixedSamples {
+        attachment: String,
+        samples: u32,
+        canvas: u32,
+    },
+    /// A subpass reads input attachments, which dashi render passes cannot
+    /// declare yet.
+    InputAttachments { subpass: String },
+    /// A GPU operation failed.
+    Gpu(GPUError),
+}
+
+impl std::fmt::Display for CanvasError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CanvasError::MissingDepthSource { attachment } => write!(
+                f,
+                "no source canvas has a depth attachment '{}'",
+                attachment
+            ),
+            CanvasError::DepthSampleMismatch {
+                attachment,
+                canvas,
+                source,
+            } => write!(
+                f,
+                "read-only depth '{}' has {} samples but the canvas uses {}",
+                attachment, source, canvas
+            ),
+            CanvasError::UnknownAttachment {
+                subpass,
+                attachment,
+            } => write!(
+                f,
+                "subpass '{}' uses unknown attachment '{}'",
+                subpass, attachment
+            ),
+            CanvasError::UnknownSubpass {
+                subpass,
+                dependency,
+            } => write!(
+                f,
+                "subpass '{}' depends on unknown subpass '{}'",
+                subpass, dependency
+            ),
+            CanvasError::UnsupportedSamples(samples) => write!(
+                f,
+                "unsupported sample count {}, only single-sampled canvases are supported",
+                samples
+            ),
+            CanvasError::MixedSamples {
+                attachment,
+                samples,
+                canvas,
+            } => write!(
+                f,
+                "attachment '{}' has {} samples but the canvas uses {}",
+                attachment, samples, canvas
+            ),
+            CanvasError::InputAttachments { subpass } => write!(
+                f,
+                "subpass '{}' reads input attachments, which are not supported",
+                subpass
+            ),
+            CanvasError::Gpu(err) => write!(f, "GPU error: {:?}", err),
+        }
+    }
+}
+
+impl std::error::Error for CanvasError {}
+
+impl From<GPUError> for CanvasError {
+    fn from(err: GPUError) -> Self {
+        CanvasError::Gpu(err)
+    }
+}
+
 #[derive(Clone)]
 pub struct Canvas {
     render_pass: Handle<RenderPass>,
     target: RenderTarget,
     attachments: IndexMap<String, RenderAttachment>,
     extent: [u32; 2],
+    scale: Option<f32>,
+    samples: u32,
+    subpasses: Vec<SubpassDesc>,
+}
+
+/// An attachment image replaced by [`Canvas::resize`]. It must be kept alive
+/// until the GPU has finished with frames that used it.
+#[derive(Clone, Debug)]
+pub struct RetiredAttachment {
+    pub name: String,
+    pub image: Handle<Image>,
+    pub view: Handle<ImageView>,
+}
+
+/// Extent of a canvas drawn at `scale` times `output`, at least one texel.
+pub fn scaled_extent(output: [u32; 2], scale: f32) -> [u32; 2] {
+    [
+        ((output[0] as f32 * scale).round() as u32).max(1),
+        ((output[1] as f32 * scale).round() as u32).max(1),
+    ]
 }
 
 /// Helper to reference a specific canvas attachment when creating a pipeline.
@@ -40,14 +176,60 @@ pub struct AttachmentDesc {
     pub clear_depth: Option<f32>,
     #[serde(default)]
     pub clear_stencil: Option<u32>,
+    /// Samples per texel. Must match the canvas's `samples`.
+    #[serde(default = "single_sample", skip_serializing_if = "is_single_sample")]
+    pub samples: u32,
+    /// Depth attachment of another canvas, with the same name, that this
+    /// canvas tests against without writing.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub read_only: bool,
+}
+
+/// A subpass of a canvas and the attachments it uses.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SubpassDesc {
+    pub name: String,
+    pub color_attachments: Vec<String>,
+    /// Attachments written by earlier subpasses and read with
+    /// `subpassLoad`. Canvases that declare any fail to build.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub input_attachments: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct CanvasDesc {
     pub extent: [u32; 2],
     pub attachments: Vec<AttachmentDesc>,
+    /// Size relative to the renderer output. `extent` holds the size the
+    /// canvas was last allocated at.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<f32>,
+    /// Samples per texel of every attachment.
+    #[serde(default = "single_sample", skip_serializing_if = "is_single_sample")]
+    pub samples: u32,
+    /// Subpasses in execution order. Empty for a single `main` subpass
+    /// writing every color attachment.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subpasses: Vec<SubpassDesc>,
+}
+
+fn single_sample() -> u32 {
+    1
+}
+
+fn is_single_sample(samples: &u32) -> bool {
+    *samples <= 1
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
+/// Name of the subpass created when a canvas declares none.
+const MAIN_SUBPASS: &str = "main";
+
 impl From<&Canvas> for CanvasDesc {
     fn from(c: &Canvas) -> Self {
         let mut attachments = Vec::new();
@@ -63,6 +245,8 @@ impl From<&Canvas> for CanvasDesc {
                 clear_color,
                 clear_depth: None,
                 clear_stencil: None,
+                samples: att.samples,
+                read_only: false,
             });
         }
         if let Some(depth) = &c.target.depth {
@@ -76,11 +260,23 @@ impl From<&Canvas> for CanvasDesc {
                 clear_color: None,
                 clear_depth,
                 clear_stencil,
+                samples: depth.samples,
+                read_only: depth.read_only,
             });
         }
+        let default_subpass = c.subpasses.len() == 1
+            && c.subpasses[0].name == MAIN_SUBPASS
+            && c.subpasses[0].input_attachments.is_empty();
         Self {
             extent: c.extent,
             attachments,
+            scale: c.scale,
+            samples: c.samples,
+            subpasses: if default_subpass {
+                Vec::new()
+            } else {
+                c.subpasses.clone()
+            },
         }
     }
 }
@@ -98,6 +294,7 @@ impl Canvas {
         &mut self.target
     }
 
+    /// View of an attachment for sampling in later passes.
     pub fn view(&self, name: &str) -> Option<Handle<ImageView>> {
         self.attachments.get(name).map(|a| a.attachment.img)
     }
@@ -115,10 +312,182 @@ impl Canvas {
         self.extent
     }
 
-    pub fn from_desc(ctx: &mut Context, desc: &CanvasDesc) -> Result<Self, GPUError> {
-        let mut builder = CanvasBuilder::new().extent(desc.extent);
+    /// Scale relative to the renderer output, or `None` for a fixed extent.
+    pub fn scale(&self) -> Option<f32> {
+        self.scale
+    }
+
+    /// Image behind [`Canvas::view`].
+    pub fn image(&self, name: &str) -> Option<Handle<Image>> {
+        self.attachments.get(name).map(|a| a.image)
+    }
+
+    /// Samples per texel of the canvas attachments.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Subpasses in execution order.
+    pub fn subpasses(&self) -> &[SubpassDesc] {
+        &self.subpasses
+    }
+
+    /// Index of a subpass, looked up by subpass name or by the name of a
+    /// color attachment it writes. The depth attachment resolves to the
+    /// first subpass.
+    pub fn subpass_index(&self, name: &str) -> Option<u32> {
+        self.subpasses
+            .iter()
+            .position(|sp| sp.name == name)
+            .or_else(|| {
+                self.subpasses
+                    .iter()
+                    .position(|sp| sp.color_attachments.iter().any(|c| c == name))
+            })
+            .or_else(|| {
+                self.target
+                    .depth
+                    .as_ref()
+                    .filter(|d| d.name == name)
+                    .map(|_| 0)
+            })
+            .map(|i| i as u32)
+    }
+
+    /// Whether the depth attachment belongs to another canvas and is only
+    /// tested against.
+    pub fn depth_read_only(&self) -> bool {
+        self.target.depth.as_ref().is_some_and(|d| d.read_only)
+    }
+
+    /// GPU objects owned by this canvas: its render pass and the images of
+    /// every attachment it doesn't borrow. Clones share these, so only
+    /// release them once for all clones.
+    pub fn gpu_objects(&self) -> Vec<GpuObject> {
+        let mut objects = vec![GpuObject::RenderPass(self.render_pass)];
+        for att in self.attachments.values().filter(|a| !a.read_only) {
+            objects.push(GpuObject::ImageView(att.attachment.img));
+            objects.push(GpuObject::Image(att.image));
+        }
+        objects
+    }
+
+    /// Reallocate the attachments of an output-relative canvas for a new
+    /// output size.
+    ///
+    /// The render pass is kept, so pipelines built against it stay valid.
+    /// Returns the replaced images, which the caller must destroy once no
+    /// frame in flight uses them. Fixed-size canvases, and canvases already
+    /// at the right size, are left alone.
+    pub fn resize(
+        &mut self,
+        ctx: &mut Context,
+        output: [u32; 2],
+    ) -> Result<Vec<RetiredAttachment>, GPUError> {
+        let Some(scale) = self.scale else {
+            return Ok(Vec::new());
+        };
+        let extent = scaled_extent(output, scale);
+        if extent == self.extent {
+            return Ok(Vec::new());
+        }
+
+        let mut retired = Vec::new();
+        // Read-only depth is resized by the canvas that owns it.
+        for att in self.attachments.values_mut().filter(|a| !a.read_only) {
+            let (image, view) = make_attachment_image(ctx, &att.name, att.format, extent)?;
+            retired.push(RetiredAttachment {
+                name: att.name.clone(),
+                image: att.image,
+                view: att.attachment.img,
+            });
+            att.image = image;
+            att.attachment.img = view;
+        }
+        self.extent = extent;
+        self.sync_target();
+        Ok(retired)
+    }
+
+    /// Use the images of `resized`, a resized copy of this canvas, while
+    /// keeping this canvas's clear values.
+    pub(crate) fn adopt_images(&mut self, resized: &Canvas) {
+        for (name, att) in self.attachments.iter_mut() {
+            if let Some(new) = resized.attachments.get(name) {
+                att.image = new.image;
+                att.attachment.img = new.attachment.img;
+            }
+        }
+        self.extent = resized.extent;
+        self.sync_target();
+    }
+
+    /// Point a read-only depth attachment at the current image of the
+    /// canvas that owns it. Returns whether an attachment was updated.
+    pub(crate) fn rebind_read_only(
+        &mut self,
+        name: &str,
+        image: Handle<Image>,
+        view: Handle<ImageView>,
+    ) -> bool {
+        let Some(att) = self.attachments.get_mut(name).filter(|a| a.read_only) else {
+            return false;
+        };
+        att.image = image;
+        att.attachment.img = view;
+        self.sync_target();
+        true
+    }
+
+    fn sync_target(&mut self) {
+        let target = &mut self.target;
+        for att in target.colors.iter_mut().chain(target.depth.as_mut()) {
+            if let Some(new) = self.attachments.get(&att.name) {
+                att.image = new.image;
+                att.attachment.img = new.attachment.img;
+            }
+        }
+    }
+
+    pub fn from_desc(ctx: &mut Context, desc: &CanvasDesc) -> Result<Self, CanvasError> {
+        Self::from_desc_with_sources(ctx, desc, &[])
+    }
+
+    /// Like [`Canvas::from_desc`], taking read-only depth attachments from
+    /// the first canvas in `sources` that has an attachment of that name.
+    pub fn from_desc_with_sources(
+        ctx: &mut Context,
+        desc: &CanvasDesc,
+        sources: &[&Canvas],
+    ) -> Result<Self, CanvasError> {
+        let samples = desc.samples.max(1);
+        if let Some(att) = desc.attachments.iter().find(|a| a.samples.max(1) != samples) {
+            return Err(CanvasError::MixedSamples {
+                attachment: att.name.clone(),
+                samples: att.samples,
+                canvas: samples,
+            });
+        }
+        let mut builder = CanvasBuilder::new().extent(desc.extent).samples(samples);
+        builder.scale = desc.scale;
+        for sp in &desc.subpasses {
+            builder = builder.subpass_with_inputs(
+                sp.name.clone(),
+                sp.color_attachments.clone(),
+                sp.input_attachments.clone(),
+                sp.depends_on.clone(),
+            );
+        }
         for att in &desc.attachments {
-            if matches!(att.format, Format::D24S8) {
+            if att.read_only {
+                let source = sources
+                    .iter()
+                    .find(|c| c.format(&att.name).is_some())
+                    .ok_or_else(|| CanvasError::MissingDepthSource {
+                        attachment: att.name.clone(),
+                    })?;
+                builder = builder.read_only_depth_attachment(source, &att.name);
+            } else if !FormatClass::of(att.format).is_color() {
                 if att.clear_depth.is_some() || att.clear_stencil.is_some() {
                     builder = builder.depth_attachment_with_clear(
                         att.name.clone(),
@@ -145,7 +514,13 @@ pub struct CanvasBuilder {
     color_names: Vec<String>,
     color_clears: IndexMap<String, [f32; 4]>,
     depth_clear: Option<(String, f32, u32)>,
+    /// Name and sample count of the borrowed read-only depth attachment,
+    /// with no count if the source canvas has no such attachment.
+    read_only_depth: Option<(String, Option<u32>)>,
+    subpasses: Vec<SubpassDesc>,
     extent: [u32; 2],
+    scale: Option<f32>,
+    samples: u32,
 }
 
 impl CanvasBuilder {
@@ -164,6 +539,26 @@ impl CanvasBuilder {
         self
     }
 
+    /// Size the canvas at `scale` times the renderer output, e.g. `1.0` for
+    /// full resolution or `0.5` for a half-resolution effect. `output` is
+    /// the current output size; the renderer resizes the canvas when it
+    /// changes.
+    pub fn relative_extent(self, output: [u32; 2], scale: f32) -> Self {
+        let mut builder = self.extent(scaled_extent(output, scale));
+        builder.scale = Some(scale);
+        builder
+    }
+
+    /// Render with `samples` samples per texel.
+    ///
+    /// dashi creates single-sampled images and render passes only, so
+    /// counts other than 1 make [`CanvasBuilder::build`] fail.
+    pub fn samples(mut self, samples: u32) -> Self {
+        self.builder = self.builder.samples(samples);
+        self.samples = samples;
+        self
+    }
+
     pub fn viewport(mut self, viewport: Viewport) -> Self {
         self.builder = self.builder.viewport(viewport);
         self
@@ -209,11 +604,156 @@ impl CanvasBuilder {
         self
     }
 
-    pub fn build(mut self, ctx: &mut Context) -> Result<Canvas, GPUError> {
-        let sub_colors = self.color_names.clone();
-        self.builder = self.builder.subpass("main", sub_colors, &[] as &[&str]);
-        let (rp, mut targets, all) = self.builder.build_with_images(ctx)?;
-        let mut target = targets.remove(0);
+    /// Test against the depth attachment `name` of `source` without
+    /// clearing or writing it, for example to draw decals or transparent
+    /// geometry over the depth of an earlier geometry pass. The image stays
+    /// owned by `source`, and pipelines built against this canvas don't
+    /// write depth.
+    pub fn read_only_depth_attachment(mut self, source: &Canvas, name: &str) -> Self {
+        let att = source
+            .attachments
+            .get(name)
+            .filter(|a| !FormatClass::of(a.format).is_color())
+            .cloned();
+        if let Some(att) = &att {
+            self.builder = self.builder.read_only_depth_attachment(
+                name,
+                att.format,
+                att.image,
+                att.attachment.img,
+            );
+        }
+        self.read_only_depth = Some((name.to_string(), att.map(|a| a.samples)));
+        self
+    }
+
+    /// Add a subpass writing `color_attachments`. Subpasses run in the order
+    /// they are added. Without any, the canvas has one `main` subpass
+    /// writing every color attachment.
+    pub fn subpass<C, Dep>(
+        self,
+        name: impl Into<String>,
+        color_attachments: C,
+        depends_on: Dep,
+    ) -> Self
+    where
+        C: IntoIterator,
+        C::Item: ToString,
+        Dep: IntoIterator,
+        Dep::Item: ToString,
+    {
+        self.subpass_with_inputs(name, color_attachments, Vec::<String>::new(), depends_on)
+    }
+
+    /// Add a subpass that also reads `input_attachments`, written by the
+    /// subpasses in `depends_on`, e.g. a lighting subpass reading the
+    /// G-buffer. Pipelines target it with `canvas.output("lighting")` or
+    /// with the name of an attachment it writes.
+    ///
+    /// dashi render passes cannot declare input attachments yet, so
+    /// [`CanvasBuilder::build`] rejects subpasses that read any.
+    pub fn subpass_with_inputs<C, I, Dep>(
+        mut self,
+        name: impl Into<String>,
+        color_attachments: C,
+        input_attachments: I,
+        depends_on: Dep,
+    ) -> Self
+    where
+        C: IntoIterator,
+        C::Item: ToString,
+        I: IntoIterator,
+        I::Item: ToString,
+        Dep: IntoIterator,
+        Dep::Item: ToString,
+    {
+        self.subpasses.push(SubpassDesc {
+            name: name.into(),
+            color_attachments: color_attachments.into_iter().map(|c| c.to_string()).collect(),
+            input_attachments: input_attachments.into_iter().map(|i| i.to_string()).collect(),
+            depends_on: depends_on.into_iter().map(|d| d.to_string()).collect(),
+        });
+        self
+    }
+
+    pub fn build(mut self, ctx: &mut Context) -> Result<Canvas, CanvasError> {
+        if !supports_samples(self.samples) {
+            return Err(CanvasError::UnsupportedSamples(self.samples));
+        }
+        // The borrowed depth must exist and match the canvas sample count.
+        if let Some((attachment, source_samples)) = &self.read_only_depth {
+            let Some(source) = *source_samples else {
+                return Err(CanvasError::MissingDepthSource {
+                    attachment: attachment.clone(),
+                });
+            };
+            if source != self.samples.max(1) {
+                return Err(CanvasError::DepthSampleMismatch {
+                    attachment: attachment.clone(),
+                    canvas: self.samples.max(1),
+                    source,
+                });
+            }
+        }
+        if self.subpasses.is_empty() {
+            self.subpasses.push(SubpassDesc {
+                name: MAIN_SUBPASS.to_string(),
+                color_attachments: self.color_names.clone(),
+                input_attachments: Vec::new(),
+                depends_on: Vec::new(),
+            });
+        }
+        // Every subpass must name declared attachments and subpasses.
+        let depth_name = self.depth_clear.as_ref().map(|(n, _, _)| n);
+        for sp in &self.subpasses {
+            let known = |n: &&String| self.color_names.contains(n) || Some(*n) == depth_name;
+            let unknown_color = sp
+                .color_attachments
+                .iter()
+                .find(|c| !self.color_names.contains(c));
+            let unknown_input = sp.input_attachments.iter().find(|i| !known(i));
+            if let Some(attachment) = unknown_color.or(unknown_input) {
+                return Err(CanvasError::UnknownAttachment {
+                    subpass: sp.name.clone(),
+                    attachment: attachment.clone(),
+                });
+            }
+            if !sp.input_attachments.is_empty() {
+                return Err(CanvasError::InputAttachments {
+                    subpass: sp.name.clone(),
+                });
+            }
+            let unknown_dep = sp
+                .depends_on
+                .iter()
+                .find(|d| !self.subpasses.iter().any(|other| &other.name == *d));
+            if let Some(dependency) = unknown_dep {
+                return Err(CanvasError::UnknownSubpass {
+                    subpass: sp.name.clone(),
+                    dependency: dependency.clone(),
+                });
+            }
+        }
+        for sp in &self.subpasses {
+            self.builder = self.builder.subpass_with_inputs(
+                sp.name.clone(),
+                sp.color_attachments.clone(),
+                sp.input_attachments.clone(),
+                sp.depends_on.clone(),
+            );
+        }
+        let (rp, targets, all) = self.builder.build_with_images(ctx)?;
+        // The canvas target lists every color attachment, in subpass order,
+        // so a single render pass instance covers all subpasses.
+        let mut targets = targets.into_iter();
+        let mut target = targets.next().unwrap();
+        for sub in targets {
+            for att in sub.colors {
+                if !target.colors.iter().any(|c| c.name == att.name) {
+                    target.colors.push(att);
+                }
+            }
+        }
         for att in &mut target.colors {
             if let Some(clear) = self.color_clears.get(&att.name) {
                 att.attachment.clear = ClearValue::Color(*clear);
@@ -233,6 +773,9 @@ impl CanvasBuilder {
             target,
             attachments,
             extent: self.extent,
+            scale: self.scale,
+            samples: self.samples.max(1),
+            subpasses: self.subpasses,
         })
     }
 }
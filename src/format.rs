@@ -0,0 +1,82 @@
+//! Classification of image formats by the aspects they contain.
+//!
+//! Attachments are routed by [`FormatClass`] instead of by comparing against
+//! individual formats, so render passes, canvases and barrier planning agree
+//! on which attachments are depth or stencil targets.
+
+use dashi::{AspectMask, Format};
+
+/// Aspects stored by an image format.
+///
+/// The only depth format dashi exposes is [`Format::D24S8`], so there are no
+/// depth-only or stencil-only classes yet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FormatClass {
+    Color,
+    DepthStencil,
+}
+
+impl FormatClass {
+    /// Classify `format`.
+    pub fn of(format: Format) -> Self {
+        // Keep this match exhaustive so new formats must be classified.
+        match format {
+            Format::R8Sint
+            | Format::R8Uint
+            | Format::RGB8
+            | Format::BGRA8
+            | Format::BGRA8Unorm
+            | Format::RGBA8
+            | Format::RGBA8Unorm
+            | Format::RGBA32F => FormatClass::Color,
+            Format::D24S8 => FormatClass::DepthStencil,
+        }
+    }
+
+    pub fn is_color(self) -> bool {
+        self == FormatClass::Color
+    }
+
+    pub fn has_depth(self) -> bool {
+        self == FormatClass::DepthStencil
+    }
+
+    pub fn has_stencil(self) -> bool {
+        self == FormatClass::DepthStencil
+    }
+
+    /// Whether the format is bound as a depth/stencil attachment.
+    pub fn is_depth_stencil_attachment(self) -> bool {
+        !self.is_color()
+    }
+
+    /// Aspect used for views of the whole image.
+    pub fn aspect(self) -> AspectMask {
+        match self {
+            FormatClass::Color => AspectMask::Color,
+            FormatClass::DepthStencil => AspectMask::DepthStencil,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_formats() {
+        assert_eq!(FormatClass::of(Format::RGBA8), FormatClass::Color);
+        assert_eq!(FormatClass::of(Format::RGBA32F), FormatClass::Color);
+        assert_eq!(FormatClass::of(Format::D24S8), FormatClass::DepthStencil);
+    }
+
+    #[test]
+    fn class_aspects() {
+        assert!(FormatClass::DepthStencil.has_depth());
+        assert!(FormatClass::DepthStencil.has_stencil());
+        assert!(!FormatClass::Color.has_depth());
+        assert!(FormatClass::Color.is_color());
+        assert!(FormatClass::DepthStencil.is_depth_stencil_attachment());
+        assert!(!FormatClass::Color.is_depth_stencil_attachment());
+    }
+}
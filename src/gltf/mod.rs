@@ -1,4 +1,4 @@
-use crate::renderer::{SkeletalMesh, SkeletalVertex, StaticMesh, Vertex};
+use crate::renderer::{Bounds, SkeletalMesh, SkeletalVertex, StaticMesh, Vertex};
 use crate::animation::{Bone, Skeleton};
 use crate::animation::clip::{AnimationClip, Keyframe, Transform};
 use glam::{Mat4, Quat, Vec3};
@@ -139,6 +139,18 @@ fn load_animation(
     AnimationClip { length, tracks }
 }
 
+/// Bounds from the min/max of the primitive's position accessor, or `None`
+/// if the file leaves them out. Meshes without them are bounded by their
+/// vertices instead.
+fn accessor_bounds(prim: &gltf::Primitive) -> Option<Bounds> {
+    let positions = prim.get(&gltf::Semantic::Positions)?;
+    let corner = |value: Option<gltf::json::Value>| -> Option<Vec3> {
+        let values: Vec<f32> = gltf::json::deserialize::from_value(value?).ok()?;
+        (values.len() == 3).then(|| Vec3::new(values[0], values[1], values[2]))
+    };
+    Some(Bounds::from_aabb(corner(positions.min())?, corner(positions.max())?))
+}
+
 fn load_node(
     node: &gltf::Node,
     parent: Mat4,
@@ -194,6 +206,7 @@ fn load_node(
                     index_count: 0,
                     skeleton: Default::default(),
                     bone_buffer: None,
+                    bounds: accessor_bounds(&prim),
                 };
                 if let Some(skin) = node.skin() {
                     mesh.skeleton = load_skin(&skin, buffers);
@@ -219,6 +232,7 @@ fn load_node(
                     vertex_buffer: None,
                     index_buffer: None,
                     index_count: 0,
+                    bounds: accessor_bounds(&prim),
                 })
             };
             meshes.push(SceneMesh { mesh, transform });
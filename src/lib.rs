@@ -6,6 +6,7 @@ pub mod gltf;
 pub mod animation;
 pub mod render_pass;
 pub mod canvas;
+pub mod format;
 pub mod text;
 pub mod texture_manager;
 pub mod render_graph;
@@ -15,6 +16,7 @@ pub use material::*;
 pub use material::{ComputePipelineBuilder, CPSO};
 pub use render_pass::*;
 pub use canvas::*;
+pub use format::*;
 pub use text::*;
 pub use texture_manager::*;
 pub use render_graph::*;
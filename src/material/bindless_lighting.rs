@@ -2,15 +2,175 @@ use crate::utils::{ResourceList, ResourceBuffer, ResourceManager, DHObject};
 use dashi::Context;
 use std::sync::{Arc, Mutex};
 
+/// Version of the [`LightDesc`] GPU layout. Bump it whenever
+/// [`LIGHT_LAYOUT`] changes so stale shaders fail with a clear error.
+pub const LIGHT_LAYOUT_VERSION: u32 = 2;
+
+/// Instance name shaders give the light buffer array.
+pub const BINDLESS_LIGHTS: &str = "bindless_lights";
+
+/// `(member, byte offset, byte size)` of each [`LightDesc`] field as seen by
+/// shaders. Checked against the reflected `bindless_lights` block when a
+/// pipeline is built.
+pub const LIGHT_LAYOUT: &[(&str, u32, u32)] = &[
+    ("position", 0, 12),
+    ("intensity", 12, 4),
+    ("color", 16, 12),
+    ("range", 28, 4),
+    ("direction", 32, 12),
+    ("kind", 44, 4),
+    ("spot_inner", 48, 4),
+    ("spot_outer", 52, 4),
+    ("area_extent", 56, 8),
+];
+
+/// GLSL block members matching [`LIGHT_LAYOUT`]. Shaders paste this verbatim
+/// into their `bindless_lights` block, see `lighting.frag`.
+pub const LIGHT_BLOCK_GLSL: &str = "    // koji light layout v2
+    vec3 position;
+    float intensity;
+    vec3 color;
+    float range;
+    vec3 direction;
+    uint kind;
+    float spot_inner;
+    float spot_outer;
+    vec2 area_extent;
+";
+
+/// What kind of emitter a [`LightDesc`] describes.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum LightKind {
+    /// Emits in all directions from `position` out to `range`.
+    #[default]
+    Point = 0,
+    /// Cone from `position` along `direction`, fading between the inner and
+    /// outer half-angles.
+    Spot = 1,
+    /// Parallel rays along `direction`.
+    Directional = 2,
+    /// Rectangle of `area_extent` centered on `position`, facing `direction`.
+    Area = 3,
+}
+
+impl LightKind {
+    pub fn from_raw(raw: u32) -> Option<Self> {
+        match raw {
+            0 => Some(Self::Point),
+            1 => Some(Self::Spot),
+            2 => Some(Self::Directional),
+            3 => Some(Self::Area),
+            _ => None,
+        }
+    }
+}
+
 #[repr(C)]
-#[derive(Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Default, Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct LightDesc {
     pub position: [f32; 3],
     pub intensity: f32,
     pub color: [f32; 3],
     pub range: f32,
     pub direction: [f32; 3],
-    pub _pad: u32,
+    /// Raw [`LightKind`].
+    pub kind: u32,
+    /// Spot cone half-angles in radians.
+    pub spot_inner: f32,
+    pub spot_outer: f32,
+    /// Width and height of area lights.
+    pub area_extent: [f32; 2],
+}
+
+impl LightDesc {
+    pub fn point(position: [f32; 3], range: f32) -> Self {
+        Self {
+            position,
+            range,
+            ..Self::white(LightKind::Point)
+        }
+    }
+
+    pub fn spot(position: [f32; 3], direction: [f32; 3], inner: f32, outer: f32) -> Self {
+        Self {
+            position,
+            direction,
+            spot_inner: inner,
+            spot_outer: outer.max(inner),
+            ..Self::white(LightKind::Spot)
+        }
+    }
+
+    pub fn directional(direction: [f32; 3]) -> Self {
+        Self {
+            direction,
+            ..Self::white(LightKind::Directional)
+        }
+    }
+
+    pub fn area(position: [f32; 3], direction: [f32; 3], extent: [f32; 2]) -> Self {
+        Self {
+            position,
+            direction,
+            area_extent: extent,
+            ..Self::white(LightKind::Area)
+        }
+    }
+
+    fn white(kind: LightKind) -> Self {
+        Self {
+            intensity: 1.0,
+            color: [1.0; 3],
+            kind: kind as u32,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_color(mut self, color: [f32; 3], intensity: f32) -> Self {
+        self.color = color;
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn with_range(mut self, range: f32) -> Self {
+        self.range = range;
+        self
+    }
+
+    /// The light's kind. Unknown raw values read as [`LightKind::Point`].
+    pub fn kind(&self) -> LightKind {
+        LightKind::from_raw(self.kind).unwrap_or_default()
+    }
+}
+
+/// Check a reflected `bindless_lights` block against [`LIGHT_LAYOUT`].
+pub fn check_light_layout(block_size: u32, members: &[(String, u32, u32)]) -> Result<(), String> {
+    let size = std::mem::size_of::<LightDesc>() as u32;
+    if block_size != size {
+        return Err(format!(
+            "light block is {} bytes, layout v{} expects {}",
+            block_size, LIGHT_LAYOUT_VERSION, size
+        ));
+    }
+    for &(name, offset, len) in LIGHT_LAYOUT {
+        match members.iter().find(|m| m.0 == name) {
+            Some(m) if m.1 == offset && m.2 == len => {}
+            Some(m) => {
+                return Err(format!(
+                    "light member '{}' is at {}+{}, layout v{} expects {}+{}",
+                    name, m.1, m.2, LIGHT_LAYOUT_VERSION, offset, len
+                ))
+            }
+            None => {
+                return Err(format!(
+                    "light member '{}' missing, layout v{} declares it",
+                    name, LIGHT_LAYOUT_VERSION
+                ))
+            }
+        }
+    }
+    Ok(())
 }
 
 pub struct BindlessLights {
@@ -70,14 +230,47 @@ impl BindlessLights {
         let mut list = self.lights.lock().unwrap();
         if let Some(handle) = list.entries.get(index).copied() {
             list.release(handle);
+            // Later lights shift down in the list, keep the CPU copies aligned.
+            if index < self.cpu.len() {
+                self.cpu.remove(index);
+            }
         }
     }
 
+    /// Indices and CPU copies of every light of `kind`.
+    pub fn lights_of_kind(&self, kind: LightKind) -> impl Iterator<Item = (usize, &LightDesc)> {
+        self.cpu
+            .iter()
+            .enumerate()
+            .filter(move |(_, l)| l.kind() == kind)
+    }
+
+    /// Number of lights of `kind`.
+    pub fn count_of_kind(&self, kind: LightKind) -> usize {
+        self.lights_of_kind(kind).count()
+    }
+
+    /// CPU copy of the light at `index`.
+    pub fn light(&self, index: usize) -> Option<LightDesc> {
+        self.cpu.get(index).copied()
+    }
+
+    /// Number of lights currently allocated.
+    pub fn len(&self) -> usize {
+        self.lights.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     /// Register the internal buffer array with the [`ResourceManager`].
     ///
-    /// The shader in the tests expects the storage buffer to be named
-    /// `lights`, so we register under that key.
+    /// Shaders declare the array with the instance name
+    /// [`BINDLESS_LIGHTS`], as in `lighting.frag`, so we register under that
+    /// key.
     pub fn register(&self, res: &mut ResourceManager) {
+        res.register_buffer_array(BINDLESS_LIGHTS, self.lights.clone());
         // Descriptor reflection for unsized arrays does not preserve the
         // variable name, so the pipeline builder ends up looking for an empty
         // string key. Register under an empty name to satisfy that lookup.
@@ -99,6 +292,68 @@ mod tests {
         Context::headless(&ContextInfo::default()).unwrap()
     }
 
+    #[test]
+    fn light_layout_matches_the_struct() {
+        let d = LightDesc::default();
+        let base = &d as *const LightDesc as usize;
+        let offsets = [
+            &d.position as *const _ as usize,
+            &d.intensity as *const _ as usize,
+            &d.color as *const _ as usize,
+            &d.range as *const _ as usize,
+            &d.direction as *const _ as usize,
+            &d.kind as *const _ as usize,
+            &d.spot_inner as *const _ as usize,
+            &d.spot_outer as *const _ as usize,
+            &d.area_extent as *const _ as usize,
+        ];
+        for (field, addr) in LIGHT_LAYOUT.iter().zip(offsets) {
+            assert_eq!(field.1 as usize, addr - base, "{}", field.0);
+        }
+        let end = LIGHT_LAYOUT.last().map(|f| f.1 + f.2).unwrap();
+        assert_eq!(end as usize, std::mem::size_of::<LightDesc>());
+
+        let shader = include_str!("../../assets/shaders/lighting.frag");
+        assert!(shader.contains(LIGHT_BLOCK_GLSL));
+        assert!(LIGHT_BLOCK_GLSL.contains(&format!("v{}", LIGHT_LAYOUT_VERSION)));
+    }
+
+    #[test]
+    fn layout_check_reports_mismatches() {
+        let mut members: Vec<(String, u32, u32)> = LIGHT_LAYOUT
+            .iter()
+            .map(|&(n, o, s)| (n.to_string(), o, s))
+            .collect();
+        assert!(check_light_layout(64, &members).is_ok());
+        assert!(check_light_layout(48, &members).unwrap_err().contains("48 bytes"));
+        members[5].1 = 60;
+        let err = check_light_layout(64, &members).unwrap_err();
+        assert!(err.contains("'kind'") && err.contains("v2"));
+        members.remove(5);
+        assert!(check_light_layout(64, &members).unwrap_err().contains("missing"));
+    }
+
+    #[test]
+    fn lights_are_typed() {
+        let spot = LightDesc::spot([0.0; 3], [0.0, -1.0, 0.0], 0.3, 0.2);
+        assert_eq!(spot.kind(), LightKind::Spot);
+        assert_eq!(spot.spot_outer, 0.3);
+        let area = LightDesc::area([0.0; 3], [0.0, -1.0, 0.0], [2.0, 1.0]).with_range(5.0);
+        assert_eq!(area.kind(), LightKind::Area);
+        assert_eq!(area.range, 5.0);
+        let sun = LightDesc::directional([0.0, -1.0, 0.0]).with_color([1.0, 0.9, 0.8], 3.0);
+        assert_eq!(sun.kind(), LightKind::Directional);
+        assert_eq!(sun.intensity, 3.0);
+        assert_eq!(LightDesc::point([1.0; 3], 4.0).kind(), LightKind::Point);
+        assert_eq!(LightDesc::default().kind(), LightKind::Point);
+        let unknown = LightDesc {
+            kind: 42,
+            ..Default::default()
+        };
+        assert_eq!(unknown.kind(), LightKind::Point);
+        assert_eq!(LightKind::from_raw(42), None);
+    }
+
     #[test]
     #[serial]
     #[should_panic]
@@ -119,7 +374,10 @@ mod tests {
 
         let frag = inline_spirv!(
             r"#version 450
-            struct Light { vec3 pos; float intensity; vec3 color; float range; vec3 dir; uint pad; };
+            struct Light {
+                vec3 position; float intensity; vec3 color; float range;
+                vec3 direction; uint kind; float spot_inner; float spot_outer; vec2 area_extent;
+            };
             layout(set=0,binding=0) buffer Lights { Light lights[]; };
             layout(set=0,binding=1) uniform Count { uint count; };
             layout(location=0) out vec4 o;
@@ -147,7 +405,8 @@ mod tests {
                 color: [1.0, 1.0, 1.0],
                 range: 1.0,
                 direction: [0.0; 3],
-                _pad: 0,
+                kind: LightKind::Point as u32,
+                ..Default::default()
             };
             lights.add_light(&mut ctx, &mut res, ld);
         }
@@ -171,11 +430,11 @@ mod tests {
         let mut res = ResourceManager::new(&mut ctx, 1024).unwrap();
 
         let mut lights = BindlessLights::new();
-        let ld = LightDesc { position: [1.0, 2.0, 3.0], intensity: 1.0, color: [4.0, 5.0, 6.0], _pad: 0, ..Default::default() };
+        let ld = LightDesc { position: [1.0, 2.0, 3.0], intensity: 1.0, color: [4.0, 5.0, 6.0], ..Default::default() };
         lights.add_light(&mut ctx, &mut res, ld);
 
         // Update the light
-        let new_ld = LightDesc { position: [0.0, 0.0, 0.0], intensity: 2.0, color: [1.0, 1.0, 1.0], _pad: 0, ..Default::default() };
+        let new_ld = LightDesc { position: [0.0, 0.0, 0.0], intensity: 2.0, color: [1.0, 1.0, 1.0], ..Default::default() };
 
         lights.update_light(&mut ctx, 0, new_ld);
         
@@ -213,7 +472,8 @@ mod tests {
             color: [2.0, 3.0, 4.0],
             range: 1.0,
             direction: [0.0; 3],
-            _pad: 0,
+            kind: LightKind::Point as u32,
+            ..Default::default()
         };
         lights.add_light(&mut ctx, &mut res, ld);
 
@@ -273,6 +533,31 @@ mod tests {
         ctx.destroy();
     }
 
+    #[test]
+    #[serial]
+    fn query_lights_by_kind() {
+        let mut ctx = make_ctx();
+        let mut res = ResourceManager::new(&mut ctx, 1024).unwrap();
+        let mut lights = BindlessLights::new();
+        lights.add_light(&mut ctx, &mut res, LightDesc::point([0.0; 3], 1.0));
+        lights.add_light(&mut ctx, &mut res, LightDesc::directional([0.0, -1.0, 0.0]));
+        lights.add_light(&mut ctx, &mut res, LightDesc::point([1.0; 3], 1.0));
+        assert_eq!(lights.count_of_kind(LightKind::Point), 2);
+        assert_eq!(lights.count_of_kind(LightKind::Spot), 0);
+        let suns: Vec<usize> = lights
+            .lights_of_kind(LightKind::Directional)
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(suns, vec![1]);
+
+        lights.remove_light(0);
+        assert_eq!(lights.count_of_kind(LightKind::Point), 1);
+        assert_eq!(lights.light(0).unwrap().kind(), LightKind::Directional);
+
+        res.destroy(&mut ctx);
+        ctx.destroy();
+    }
+
     #[test]
     #[serial]
     fn update_nonexistent_index() {
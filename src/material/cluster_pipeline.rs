@@ -0,0 +1,16 @@
+use crate::material::compute_pipeline_builder::{ComputePipelineBuilder, CPSO};
+use crate::material::PipelineError;
+use crate::utils::ResourceManager;
+use dashi::*;
+use inline_spirv::include_spirv;
+
+/// Compute pipeline binning lights into the cluster grid.
+pub fn build_cluster_pipeline(
+    ctx: &mut Context,
+    res: &mut ResourceManager,
+) -> Result<CPSO, PipelineError> {
+    let comp: &[u32] = include_spirv!("assets/shaders/light_clusters.comp", comp, glsl);
+    ComputePipelineBuilder::new(ctx, "light_cluster_pipeline")
+        .shader(comp)
+        .build_with_resources(res)
+}
@@ -6,11 +6,13 @@ use std::collections::HashMap;
 enum DefaultResource {
     Time,
     Cameras,
+    Instances,
 }
 
 const DEFAULT_RESOURCES: &[(&str, DefaultResource)] = &[
     ("KOJI_time", DefaultResource::Time),
     ("KOJI_cameras", DefaultResource::Cameras),
+    ("KOJI_instances", DefaultResource::Instances),
 ];
 
 /// Default size used for runtime descriptor arrays when no resource data is
@@ -175,11 +177,17 @@ pub struct ComputePipelineBuilder<'a> {
     ctx: &'a mut Context,
     shader_spirv: &'a [u32],
     pipeline_name: &'static str,
+    specializations: Vec<(String, SpecializationValue)>,
 }
 
 impl<'a> ComputePipelineBuilder<'a> {
     pub fn new(ctx: &'a mut Context, name: &'static str) -> Self {
-        Self { ctx, shader_spirv: &[], pipeline_name: name }
+        Self {
+            ctx,
+            shader_spirv: &[],
+            pipeline_name: name,
+            specializations: Vec::new(),
+        }
     }
 
     pub fn shader(mut self, spirv: &'a [u32]) -> Self {
@@ -187,6 +195,15 @@ impl<'a> ComputePipelineBuilder<'a> {
         self
     }
 
+    /// Set specialization constant `name`, e.g. `.specialize("GROUP_SIZE", 64u32)`.
+    ///
+    /// dashi cannot pass specialization data yet, so building then fails
+    /// with [`PipelineError::Unsupported`].
+    pub fn specialize(mut self, name: &str, value: impl Into<SpecializationValue>) -> Self {
+        specialization::set_value(&mut self.specializations, name, value.into());
+        self
+    }
+
     fn register_default_resources(
         ctx: &mut Context,
         descs: &HashMap<String, (usize, u32, u32)>,
@@ -213,6 +230,11 @@ impl<'a> ComputePipelineBuilder<'a> {
                             }
                         }
                     }
+                    DefaultResource::Instances => {
+                        if res.get("KOJI_instances").is_none() && res.get("instances").is_none() {
+                            res.register_instance_buffers(ctx);
+                        }
+                    }
                 }
             }
         }
@@ -223,6 +245,11 @@ impl<'a> ComputePipelineBuilder<'a> {
         mut res: Option<&mut ResourceManager>,
     ) -> Result<CPSO, PipelineError> {
         let info = reflect_shader(self.shader_spirv);
+        let spec_data = specialization::specialization_data(
+            &[&info.specialization_constants],
+            &self.specializations,
+        )?;
+        check_shader_support(&[&info], &spec_data)?;
         let mut desc_map = HashMap::new();
         let mut bg_layouts: [Option<Handle<BindGroupLayout>>; 4] = [None, None, None, None];
 
@@ -237,6 +264,10 @@ impl<'a> ComputePipelineBuilder<'a> {
                         set, b.binding
                     );
                 }
+                if b.name == BINDLESS_LIGHTS {
+                    check_light_layout(b.block_size, &b.members)
+                        .map_err(PipelineError::LightLayout)?;
+                }
                 if desc_map.contains_key(&b.name) {
                     panic!(
                         "Descriptor name '{}' already used by another binding. Provide unique instance names in the shader source.",
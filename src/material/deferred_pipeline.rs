@@ -0,0 +1,34 @@
+use crate::material::pipeline_builder::PipelineBuilder;
+use crate::renderer::Vertex;
+use dashi::*;
+use inline_spirv::include_spirv;
+
+/// Pipeline writing static meshes into the G-buffer attachments.
+pub fn build_gbuffer_pipeline(
+    ctx: &mut Context,
+    target: crate::canvas::CanvasOutput,
+) -> crate::material::PSO {
+    let vert: &[u32] = include_spirv!("assets/shaders/pbr.vert", vert, glsl);
+    let frag: &[u32] = include_spirv!("assets/shaders/gbuffer.frag", frag, glsl);
+    PipelineBuilder::new(ctx, "gbuffer_pipeline")
+        .vertex_shader(vert)
+        .fragment_shader(frag)
+        .vertex_layout::<Vertex>()
+        .render_pass(target)
+        .depth_enable(true)
+        .build()
+}
+
+/// Full-screen pipeline shading the G-buffer with every registered light.
+pub fn build_lighting_pipeline(
+    ctx: &mut Context,
+    target: crate::canvas::CanvasOutput,
+) -> crate::material::PSO {
+    let vert: &[u32] = include_spirv!("assets/shaders/deferred.vert", vert, glsl);
+    let frag: &[u32] = include_spirv!("assets/shaders/lighting.frag", frag, glsl);
+    PipelineBuilder::new(ctx, "lighting_pipeline")
+        .vertex_shader(vert)
+        .fragment_shader(frag)
+        .render_pass(target)
+        .build()
+}
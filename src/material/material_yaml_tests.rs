@@ -14,9 +14,9 @@ fn simple_vert() -> Vec<u32> {
     inline_spirv!(
         r#"
         #version 450
-        layout(location=0) in vec2 pos;
+        layout(location=0) in vec3 pos;
         layout(set=0, binding=0) uniform B0 { float x; } b0;
-        void main(){ gl_Position = vec4(pos,0,1); }
+        void main(){ gl_Position = vec4(pos,1); }
         "#,
         vert
     ).to_vec()
@@ -61,12 +61,176 @@ fn from_yaml_builds_pipeline() {
     );
     let mapping: serde_yaml::Mapping = serde_yaml::from_str(&yaml_src).unwrap();
     let mut res = ResourceManager::default();
-    let mat = MaterialPipeline::from_yaml(&mut ctx, &mut res, &mapping, rp, 0).unwrap();
+    let mat = MaterialPipeline::from_yaml(&mut ctx, &mut res, &mapping, rp, 0, 1).unwrap();
 
     assert!(mat.pipeline.valid());
     assert!(mat.layout.valid());
     assert_eq!(mat.bind_map.get("b0"), Some(&0));
     assert_eq!(mat.bind_map.get("tex"), Some(&1));
+    assert_eq!(mat.vertex_buffers, vec![crate::renderer::Vertex::vertex_layout()]);
+
+    fs::remove_file(vert_path).unwrap();
+    fs::remove_file(frag_path).unwrap();
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn from_yaml_reads_pipeline_state() {
+    let mut ctx = make_ctx();
+    let rp = RenderPassBuilder::new("rp", Viewport::default())
+        .add_subpass(&[AttachmentDescription::default()], None, &[])
+        .build(&mut ctx)
+        .unwrap();
+
+    let vert_path = write_temp_spv(&simple_vert(), "vert_state");
+    let frag_path = write_temp_spv(&simple_frag(), "frag_state");
+    let yaml_src = format!(
+        "name: particles\nshaders:\n  vertex: {}\n  fragment: {}\n\
+         blend: additive\ndepth_write: false\n",
+        vert_path.display(),
+        frag_path.display()
+    );
+    let mapping: serde_yaml::Mapping = serde_yaml::from_str(&yaml_src).unwrap();
+    let mut res = ResourceManager::default();
+    let mat = MaterialPipeline::from_yaml(&mut ctx, &mut res, &mapping, rp, 0, 1).unwrap();
+    assert!(mat.pipeline.valid());
+
+    fs::remove_file(vert_path).unwrap();
+    fs::remove_file(frag_path).unwrap();
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn from_yaml_rejects_specialization_constants() {
+    let mut ctx = make_ctx();
+    let rp = RenderPassBuilder::new("rp", Viewport::default())
+        .add_subpass(&[AttachmentDescription::default()], None, &[])
+        .build(&mut ctx)
+        .unwrap();
+
+    let frag: Vec<u32> = inline_spirv!(
+        r#"
+        #version 450
+        layout(constant_id = 0) const uint MAX_LIGHTS = 16;
+        layout(location=0) out vec4 o;
+        void main(){ o = vec4(float(MAX_LIGHTS)); }
+        "#,
+        frag
+    )
+    .to_vec();
+    let vert_path = write_temp_spv(&simple_vert(), "vert_spec");
+    let frag_path = write_temp_spv(&frag, "frag_spec");
+    let yaml_src = format!(
+        "name: lit\nshaders:\n  vertex: {}\n  fragment: {}\nspecialization:\n  MAX_LIGHTS: 64\n",
+        vert_path.display(),
+        frag_path.display()
+    );
+    let mapping: serde_yaml::Mapping = serde_yaml::from_str(&yaml_src).unwrap();
+    let mut res = ResourceManager::default();
+    assert!(MaterialPipeline::from_yaml(&mut ctx, &mut res, &mapping, rp, 0, 1).is_err());
+
+    fs::remove_file(vert_path).unwrap();
+    fs::remove_file(frag_path).unwrap();
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn from_yaml_reads_vertex_buffers() {
+    let mut ctx = make_ctx();
+    let rp = RenderPassBuilder::new("rp", Viewport::default())
+        .add_subpass(&[AttachmentDescription::default()], None, &[])
+        .build(&mut ctx)
+        .unwrap();
+
+    let vert_path = write_temp_spv(&simple_vert(), "vert_buffers");
+    let frag_path = write_temp_spv(&simple_frag(), "frag_buffers");
+    let yaml_src = format!(
+        "name: positions\nshaders:\n  vertex: {}\n  fragment: {}\n\
+         vertex_buffers:\n  - {{ stride: 12, attributes: [{{ location: 0, format: vec3, offset: 0 }}] }}\n",
+        vert_path.display(),
+        frag_path.display()
+    );
+    let mapping: serde_yaml::Mapping = serde_yaml::from_str(&yaml_src).unwrap();
+    let mut res = ResourceManager::default();
+    let mat = MaterialPipeline::from_yaml(&mut ctx, &mut res, &mapping, rp, 0, 1).unwrap();
+    assert!(mat.pipeline.valid());
+    assert_eq!(
+        mat.vertex_buffers,
+        vec![VertexBufferLayout::new(12).attribute(0, VertexFormat::Vec3, 0)]
+    );
+
+    fs::remove_file(vert_path).unwrap();
+    fs::remove_file(frag_path).unwrap();
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn from_yaml_mismatched_vertex_layout_fails() {
+    let mut ctx = make_ctx();
+    let rp = RenderPassBuilder::new("rp", Viewport::default())
+        .add_subpass(&[AttachmentDescription::default()], None, &[])
+        .build(&mut ctx)
+        .unwrap();
+    let vert: Vec<u32> = inline_spirv!(
+        r#"
+        #version 450
+        layout(location=0) in vec2 pos;
+        void main(){ gl_Position = vec4(pos,0,1); }
+        "#,
+        vert
+    )
+    .to_vec();
+    let vert_path = write_temp_spv(&vert, "vert_mismatch");
+    let frag_path = write_temp_spv(&simple_frag(), "frag_mismatch");
+    let yaml_src = format!(
+        "name: bad\nshaders:\n  vertex: {}\n  fragment: {}\nvertex_layout: skeletal\n",
+        vert_path.display(),
+        frag_path.display()
+    );
+    let mapping: serde_yaml::Mapping = serde_yaml::from_str(&yaml_src).unwrap();
+    let mut res = ResourceManager::default();
+    assert!(MaterialPipeline::from_yaml(&mut ctx, &mut res, &mapping, rp, 0, 1).is_err());
+
+    fs::remove_file(vert_path).unwrap();
+    fs::remove_file(frag_path).unwrap();
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn from_yaml_rejects_bad_state() {
+    let mut ctx = make_ctx();
+    let rp = RenderPassBuilder::new("rp", Viewport::default())
+        .add_subpass(&[AttachmentDescription::default()], None, &[])
+        .build(&mut ctx)
+        .unwrap();
+    let vert_path = write_temp_spv(&simple_vert(), "vert_bad_state");
+    let frag_path = write_temp_spv(&simple_frag(), "frag_bad_state");
+    let shaders = format!(
+        "name: bad\nshaders:\n  vertex: {}\n  fragment: {}\n",
+        vert_path.display(),
+        frag_path.display()
+    );
+    let mut res = ResourceManager::default();
+    for extra in [
+        "blend: sparkle\n",
+        "specialization:\n  MISSING: 1\n",
+        "attachment_blends: [~, additive]\n",
+        "polygon_mode: line\n",
+        "depth_bias: { constant: 1.0, slope: 2.0 }\n",
+        "depth_compare: greater\n",
+    ] {
+        let yaml = shaders.clone() + extra;
+        let mapping: serde_yaml::Mapping = serde_yaml::from_str(&yaml).unwrap();
+        assert!(
+            MaterialPipeline::from_yaml(&mut ctx, &mut res, &mapping, rp, 0, 1).is_err(),
+            "{extra}"
+        );
+    }
 
     fs::remove_file(vert_path).unwrap();
     fs::remove_file(frag_path).unwrap();
@@ -84,7 +248,7 @@ fn from_yaml_missing_shaders_panics() {
         .unwrap();
     let mapping: serde_yaml::Mapping = serde_yaml::from_str("name: bad").unwrap();
     let mut res = ResourceManager::default();
-    let _ = MaterialPipeline::from_yaml(&mut ctx, &mut res, &mapping, rp, 0);
+    let _ = MaterialPipeline::from_yaml(&mut ctx, &mut res, &mapping, rp, 0, 1);
 }
 
 #[test]
@@ -105,5 +269,5 @@ fn from_yaml_unknown_stage_panics() {
     );
     let mapping: serde_yaml::Mapping = serde_yaml::from_str(&yaml_src).unwrap();
     let mut res = ResourceManager::default();
-    let _ = MaterialPipeline::from_yaml(&mut ctx, &mut res, &mapping, rp, 0);
+    let _ = MaterialPipeline::from_yaml(&mut ctx, &mut res, &mapping, rp, 0, 1);
 }
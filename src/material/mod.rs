@@ -2,10 +2,16 @@ use dashi::{utils::Handle, *};
 use std::collections::HashMap;
 pub mod bindless;
 pub mod bindless_lighting;
+pub mod cluster_pipeline;
 pub mod compute_pipeline_builder;
+pub mod deferred_pipeline;
 pub mod pipeline_builder;
+pub mod pipeline_state;
 pub mod shader_reflection;
+pub mod specialization;
+pub mod shadow_pipeline;
 pub mod skin_pipeline;
+pub mod vertex_layout;
 
 #[cfg(test)]
 mod material_yaml_tests;
@@ -14,36 +20,85 @@ mod pipeline_builder_tests;
 #[cfg(test)]
 mod shader_reflection_tests;
 
+use crate::render_graph::PipelineCull;
 use crate::utils::ResourceManager;
+use serde::Deserialize;
+use std::collections::BTreeMap;
 pub use bindless::*;
 pub use bindless_lighting::*;
+pub use cluster_pipeline::build_cluster_pipeline;
 pub use compute_pipeline_builder::*;
+pub use deferred_pipeline::{build_gbuffer_pipeline, build_lighting_pipeline};
 pub use pipeline_builder::*;
+pub use pipeline_state::*;
 pub use shader_reflection::*;
+pub use specialization::SpecializationValue;
+pub use shadow_pipeline::{build_shadow_pipeline, build_skinned_shadow_pipeline};
 pub use skin_pipeline::build_skinning_pipeline;
+pub use vertex_layout::{
+    VertexAttribute, VertexBufferLayout, VertexFormat, VertexKind, VertexLayout, VertexStep,
+};
+
+fn enabled() -> bool {
+    true
+}
+
+/// Fixed-function keys of a material YAML file, next to `name` and `shaders`.
+#[derive(Deserialize)]
+struct MaterialState {
+    #[serde(default = "enabled")]
+    depth: bool,
+    #[serde(default = "enabled")]
+    depth_write: bool,
+    #[serde(default)]
+    cull: PipelineCull,
+    /// Specialization constants by name, set in every stage declaring them.
+    #[serde(default)]
+    specialization: BTreeMap<String, SpecializationValue>,
+    /// Named layout of the vertex buffer, [`Vertex`](crate::renderer::Vertex)
+    /// unless given.
+    #[serde(default)]
+    vertex_layout: VertexKind,
+    /// Explicit vertex buffers, used instead of `vertex_layout`.
+    #[serde(default)]
+    vertex_buffers: Vec<VertexBufferLayout>,
+    #[serde(flatten)]
+    state: PipelineState,
+}
 
 pub struct MaterialPipeline {
     pub name: String,
     pub pipeline: Handle<GraphicsPipeline>,
     pub layout: Handle<GraphicsPipelineLayout>,
     pub bind_map: HashMap<String, u32>, // Maps material names to binding slots
-    pub vertex_info: VertexDescriptionInfo<'static>,
+    pub vertex_buffers: Vec<VertexBufferLayout>,
 }
 
 impl MaterialPipeline {
+    /// Build a material pipeline from its YAML description for subpass
+    /// `subpass_id` of `render_pass`, which writes `color_count` color
+    /// attachments.
+    ///
+    /// Invalid state, vertex layouts, specialization constants and blending
+    /// of attachments past `color_count` return an error, as do settings
+    /// dashi pipelines cannot express yet (see [`PipelineError::Unsupported`]).
+    ///
+    /// The `.spv` files it names are read once. They are not watched by
+    /// [`Renderer::watch_graph_file`](crate::renderer::Renderer::watch_graph_file).
     pub fn from_yaml(
         ctx: &mut Context,
         _res: &mut ResourceManager,
         yaml: &serde_yaml::Mapping,
         render_pass: Handle<RenderPass>,
         subpass_id: u32,
+        color_count: usize,
     ) -> Result<Self, GPUError> {
         pub struct OwnedPipelineShaderInfo {
             pub stage: ShaderType,
             pub spirv: Vec<u32>,
         }
         impl OwnedPipelineShaderInfo {
-            pub fn as_info(&self) -> PipelineShaderInfo {
+            pub fn as_info(&self) -> PipelineShaderInfo<'_> {
                 PipelineShaderInfo {
                     stage: self.stage,
                     spirv: &self.spirv,
@@ -80,38 +135,6 @@ impl MaterialPipeline {
             });
         }
 
-        let vertex_info = VertexDescriptionInfo {
-            entries: &[
-                VertexEntryInfo {
-                    format: ShaderPrimitiveType::Vec3,
-                    location: 0,
-                    offset: 0,
-                },
-                VertexEntryInfo {
-                    format: ShaderPrimitiveType::Vec3,
-                    location: 1,
-                    offset: 12,
-                },
-                VertexEntryInfo {
-                    format: ShaderPrimitiveType::Vec4,
-                    location: 2,
-                    offset: 24,
-                },
-                VertexEntryInfo {
-                    format: ShaderPrimitiveType::Vec2,
-                    location: 3,
-                    offset: 40,
-                },
-                VertexEntryInfo {
-                    format: ShaderPrimitiveType::Vec4,
-                    location: 4,
-                    offset: 48,
-                },
-            ],
-            stride: 64,
-            rate: VertexRate::Vertex,
-        };
-
         // Reflect descriptor bindings and create layouts
         let vert_spv = owned_shaders
             .iter()
@@ -124,8 +147,36 @@ impl MaterialPipeline {
             .map(|s| s.spirv.as_slice())
             .unwrap_or(&[]);
 
+        // Validate the fixed-function keys before creating any GPU objects,
+        // so a bad material file fails without leaking layouts.
+        let material: MaterialState =
+            serde_yaml::from_value(serde_yaml::Value::Mapping(yaml.clone()))
+                .map_err(|_| GPUError::LibraryError())?;
+        if material.state.blended_attachments() > color_count
+            || material.state.unsupported().is_some()
+        {
+            return Err(GPUError::LibraryError());
+        }
+        let vertex_buffers = vertex_layout::vertex_buffers(
+            material.vertex_layout.resolve(&material.vertex_buffers),
+            &reflect_vertex_inputs(vert_spv),
+        )
+        .map_err(|_| GPUError::LibraryError())?;
+        let reflected: Vec<Vec<ShaderSpecializationConstant>> = owned_shaders
+            .iter()
+            .map(|s| reflect_specialization_constants(&s.spirv))
+            .collect();
+        let constants: Vec<&[ShaderSpecializationConstant]> =
+            reflected.iter().map(Vec::as_slice).collect();
+        let values: Vec<(String, SpecializationValue)> =
+            material.specialization.into_iter().collect();
+        let spec_data = specialization::specialization_data(&constants, &values)
+            .map_err(|_| GPUError::LibraryError())?;
+
         let vert_info = reflect_shader(vert_spv);
         let frag_info = reflect_shader(frag_spv);
+        check_shader_support(&[&vert_info, &frag_info], &spec_data)
+            .map_err(|_| GPUError::LibraryError())?;
         let mut combined: HashMap<u32, Vec<ShaderDescriptorBinding>> = HashMap::new();
         for (set, binds) in vert_info.bindings.into_iter().chain(frag_info.bindings) {
             combined.entry(set).or_default().extend(binds);
@@ -152,17 +203,22 @@ impl MaterialPipeline {
             bg_layouts[*set as usize] = Some(ctx.make_bind_group_layout(&info)?);
         }
 
+        let vertex_entries = vertex_buffers[0].entries();
+        let vertex_info = vertex_layout::vertex_description(&vertex_buffers[0], &vertex_entries);
+
         let shader_infos: Vec<PipelineShaderInfo> =
             owned_shaders.iter().map(|s| s.as_info()).collect();
         let layout = ctx.make_graphics_pipeline_layout(&GraphicsPipelineLayoutInfo {
             debug_name: name,
-            vertex_info: vertex_info.clone(),
+            vertex_info,
             shaders: &shader_infos,
             bg_layouts,
             details: GraphicsPipelineDetails {
-                depth_test: Some(DepthInfo {
+                color_blend_states: material.state.color_blend_states(color_count),
+                culling: material.cull.into(),
+                depth_test: material.depth.then(|| DepthInfo {
                     should_test: true,
-                    should_write: true,
+                    should_write: material.depth_write,
                 }),
                 dynamic_states: vec![DynamicState::Viewport, DynamicState::Scissor],
                 ..Default::default()
@@ -181,7 +237,7 @@ impl MaterialPipeline {
             name: name.to_string(),
             pipeline,
             layout,
-            vertex_info,
+            vertex_buffers,
             bind_map,
         })
     }
@@ -1,22 +1,22 @@
 use crate::canvas::Canvas;
 use crate::material::*;
 use crate::render_graph::RenderGraph;
-use crate::utils::{ResourceBinding, ResourceManager, Texture};
+use crate::render_pass::supports_samples;
+use crate::utils::{GpuObject, ResourceBinding, ResourceManager, Texture};
 use bytemuck::Pod;
 use dashi::{DynamicState, Format};
 use std::collections::HashMap;
 
-use spirv_reflect::types::ReflectFormat;
-use spirv_reflect::ShaderModule;
-
 enum DefaultResource {
     Time,
     Cameras,
+    Instances,
 }
 
 const DEFAULT_RESOURCES: &[(&str, DefaultResource)] = &[
     ("KOJI_time", DefaultResource::Time),
     ("KOJI_cameras", DefaultResource::Cameras),
+    ("KOJI_instances", DefaultResource::Instances),
 ];
 
 /// Fallback size for runtime descriptor arrays when no resource information is
@@ -24,19 +24,6 @@ const DEFAULT_RESOURCES: &[(&str, DefaultResource)] = &[
 /// typical use cases while staying within reasonable descriptor limits.
 const DEFAULT_DESCRIPTOR_ARRAY_CAPACITY: u32 = 64;
 
-/// Map SPIR-V reflect format to shader primitive enum
-pub(crate) fn reflect_format_to_shader_primitive(fmt: ReflectFormat) -> ShaderPrimitiveType {
-    use ReflectFormat::*;
-    match fmt {
-        R32G32B32A32_SFLOAT => ShaderPrimitiveType::Vec4,
-        R32G32B32A32_SINT => ShaderPrimitiveType::IVec4,
-        R32G32B32A32_UINT => ShaderPrimitiveType::UVec4,
-        R32G32B32_SFLOAT => ShaderPrimitiveType::Vec3,
-        R32G32_SFLOAT => ShaderPrimitiveType::Vec2,
-        other => panic!("Unsupported vertex input format: {:?}", other),
-    }
-}
-
 pub struct ShaderVariable {
     allocation: crate::utils::DHObject,
     members: Vec<(String, u32, u32)>,
@@ -164,6 +151,34 @@ pub enum PipelineError {
     UndefinedCanvasOutput(String),
     UndefinedGraphNode(String),
     FormatMismatch { expected: Format, found: Format },
+    /// A `bindless_lights` block disagrees with [`LIGHT_LAYOUT`].
+    LightLayout(String),
+    /// Blending was set for a color attachment the target subpass lacks.
+    BlendAttachment { index: u32, count: usize },
+    /// A specialization constant is undeclared or its value does not fit.
+    Specialization(String),
+    /// The vertex layout does not match the vertex shader inputs.
+    VertexInput(String),
+    /// The sample count is not 1. Only single-sampled pipelines are supported.
+    UnsupportedSamples(u32),
+    /// A setting or shader feature dashi pipelines cannot be created with
+    /// yet, such as a stencil test or push constants.
+    Unsupported(String),
+}
+
+/// Fail when any of `shaders` declares push constants or `specialization`
+/// sets a constant. dashi pipeline layouts take neither yet.
+pub(crate) fn check_shader_support(
+    shaders: &[&ShaderReflectionInfo],
+    specialization: &[Vec<(u32, Vec<u8>)>],
+) -> Result<(), PipelineError> {
+    if shaders.iter().any(|s| !s.push_constants.is_empty()) {
+        return Err(PipelineError::Unsupported("push constants".into()));
+    }
+    if specialization.iter().any(|stage| !stage.is_empty()) {
+        return Err(PipelineError::Unsupported("specialization constants".into()));
+    }
+    Ok(())
 }
 
 enum PipelineTarget<'a> {
@@ -259,11 +274,15 @@ pub struct PipelineBuilder<'a> {
     vert_spirv: &'a [u32],
     frag_spirv: &'a [u32],
     target: Option<PipelineTarget<'a>>,
-    pipeline_name: &'static str,
+    pipeline_name: String,
     depth_enable: bool,
     cull_mode: CullMode,
     subpass: u32,
     dynamic_viewport_scissor: bool,
+    samples: Option<u32>,
+    state: PipelineState,
+    specializations: Vec<(String, SpecializationValue)>,
+    vertex_buffers: Option<Vec<VertexBufferLayout>>,
 }
 
 /// A pipeline state object (PSO) that holds the GPU pipeline handle,
@@ -272,12 +291,30 @@ pub struct PSO {
     pub pipeline: Handle<GraphicsPipeline>,
     pub layout: Handle<GraphicsPipelineLayout>,
     pub bind_group_layouts: [Option<Handle<BindGroupLayout>>; 4],
+    /// Vertex buffer read by the pipeline. Pipelines built here read one.
+    pub vertex_buffers: Vec<VertexBufferLayout>,
     /// Mapping from descriptor name to (set_index, binding_index, block_size)
     desc_map: HashMap<String, (usize, u32, u32)>,
     ctx: *mut Context,
 }
 
 impl PSO {
+    /// GPU objects owned by this pipeline: the pipeline, its layout and its
+    /// bind group layouts. Bind groups made from it are owned by the caller.
+    pub fn gpu_objects(&self) -> Vec<GpuObject> {
+        let mut objects = vec![
+            GpuObject::GraphicsPipeline(self.pipeline),
+            GpuObject::GraphicsPipelineLayout(self.layout),
+        ];
+        objects.extend(
+            self.bind_group_layouts
+                .iter()
+                .flatten()
+                .map(|l| GpuObject::BindGroupLayout(*l)),
+        );
+        objects
+    }
+
     /// Create a bind group for the given set index with provided bindings.
     pub fn create_bind_group(
         &mut self,
@@ -429,10 +466,10 @@ impl PSO {
 
 impl<'a> PipelineBuilder<'a> {
     /// Create a new builder with context and pipeline name
-    pub fn new(ctx: &'a mut Context, name: &'static str) -> Self {
+    pub fn new(ctx: &'a mut Context, name: impl Into<String>) -> Self {
         Self {
             ctx,
-            pipeline_name: name,
+            pipeline_name: name.into(),
             vert_spirv: &[],
             frag_spirv: &[],
             target: None,
@@ -440,9 +477,15 @@ impl<'a> PipelineBuilder<'a> {
             depth_enable: false,
             cull_mode: CullMode::None,
             dynamic_viewport_scissor: true,
+            samples: None,
+            state: PipelineState::default(),
+            specializations: Vec::new(),
+            vertex_buffers: None,
         }
     }
 
+    /// Test against the target's depth attachment. Depth is also written
+    /// unless the target canvas borrows its depth read-only.
     pub fn depth_enable(mut self, enable: bool) -> Self {
         self.depth_enable = enable;
         self
@@ -453,6 +496,88 @@ impl<'a> PipelineBuilder<'a> {
         self
     }
 
+    /// Replace the blend, stencil, rasterizer and depth compare state.
+    pub fn state(mut self, state: PipelineState) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// Blend every color attachment without its own blending.
+    pub fn blend(mut self, blend: Blend) -> Self {
+        self.state.blend = blend;
+        self
+    }
+
+    /// Blend color attachment `index` of the target subpass.
+    pub fn attachment_blend(mut self, index: u32, blend: Blend) -> Self {
+        self.state.set_attachment_blend(index, blend);
+        self
+    }
+
+    /// Comparison of the depth test. Defaults to [`PipelineCompare::Less`],
+    /// the only comparison dashi pipelines support so far.
+    pub fn depth_compare(mut self, compare: PipelineCompare) -> Self {
+        self.state.depth_compare = compare;
+        self
+    }
+
+    /// Not supported by dashi pipelines yet; building fails when set.
+    pub fn depth_bias(mut self, bias: DepthBias) -> Self {
+        self.state.depth_bias = Some(bias);
+        self
+    }
+
+    /// Test and update the target's stencil attachment. Not supported by
+    /// dashi pipelines yet; building fails when set.
+    pub fn stencil(mut self, stencil: StencilTest) -> Self {
+        self.state.stencil = Some(stencil);
+        self
+    }
+
+    /// Only [`PipelinePolygonMode::Fill`] is supported by dashi pipelines so
+    /// far.
+    pub fn polygon_mode(mut self, mode: PipelinePolygonMode) -> Self {
+        self.state.polygon_mode = mode;
+        self
+    }
+
+    /// Set specialization constant `name` in every stage declaring it, e.g.
+    /// `.specialize("MAX_LIGHTS", 64u32)`.
+    ///
+    /// The name and value are checked against the shaders, but dashi cannot
+    /// pass specialization data yet, so building then fails with
+    /// [`PipelineError::Unsupported`].
+    pub fn specialize(mut self, name: &str, value: impl Into<SpecializationValue>) -> Self {
+        specialization::set_value(&mut self.specializations, name, value.into());
+        self
+    }
+
+    /// Read vertices laid out as `V` from one vertex buffer.
+    pub fn vertex_layout<V: VertexLayout>(self) -> Self {
+        self.vertex_buffers(vec![V::vertex_layout()])
+    }
+
+    /// Read vertex inputs from `buffers`.
+    ///
+    /// Without a layout the reflected inputs are packed into one buffer.
+    /// Building fails with [`PipelineError::VertexInput`] when the buffers do
+    /// not provide every shader input with its reflected format, or when
+    /// there is more than one buffer, which dashi pipelines cannot describe.
+    pub fn vertex_buffers(mut self, buffers: Vec<VertexBufferLayout>) -> Self {
+        self.vertex_buffers = Some(buffers);
+        self
+    }
+
+    /// Rasterize with `samples` samples per texel.
+    ///
+    /// Canvas and graph targets use the sample count of the canvas they draw
+    /// into. Only single-sampled pipelines can be built, so any other count
+    /// makes the build fail with [`PipelineError::UnsupportedSamples`].
+    pub fn samples(mut self, samples: u32) -> Self {
+        self.samples = Some(samples);
+        self
+    }
+
     /// Enable or disable dynamic viewport and scissor state.
     ///
     /// When enabled (default), the pipeline expects the viewport and scissor to be set
@@ -474,7 +599,10 @@ impl<'a> PipelineBuilder<'a> {
         self
     }
 
-    /// Specify the render target: a render pass/subpass, canvas attachment, or graph output
+    /// Specify the render target: a render pass/subpass, canvas attachment, or graph output.
+    ///
+    /// Canvas targets may also name a subpass of the canvas. Canvas and graph
+    /// targets draw in the subpass that writes the named attachment.
     pub fn render_pass<T>(mut self, target: T) -> Self
     where
         T: Into<PipelineTarget<'a>>,
@@ -485,11 +613,14 @@ impl<'a> PipelineBuilder<'a> {
                 self.target = Some(PipelineTarget::RenderPass { pass, subpass });
             }
             PipelineTarget::Canvas { canvas, output } => {
-                self.subpass = 0;
+                self.subpass = canvas.subpass_index(&output).unwrap_or(0);
                 self.target = Some(PipelineTarget::Canvas { canvas, output });
             }
             PipelineTarget::Graph { graph, output } => {
-                self.subpass = 0;
+                self.subpass = graph
+                    .canvas_for_output(&output)
+                    .and_then(|c| c.subpass_index(&output))
+                    .unwrap_or(0);
                 self.target = Some(PipelineTarget::Graph { graph, output });
             }
         }
@@ -524,32 +655,82 @@ impl<'a> PipelineBuilder<'a> {
                             }
                         }
                     }
+                    DefaultResource::Instances => {
+                        if res.get("KOJI_instances").is_none() && res.get("instances").is_none() {
+                            res.register_instance_buffers(ctx);
+                        }
+                    }
                 }
             }
         }
     }
 
     fn build_internal(self, mut res: Option<&mut ResourceManager>) -> Result<PSO, PipelineError> {
-        let rp = match self.target {
-            Some(PipelineTarget::RenderPass { pass, .. }) => pass,
+        // Number of color attachments written by the target subpass.
+        let subpass_colors = |canvas: &Canvas| {
+            canvas
+                .subpasses()
+                .get(self.subpass as usize)
+                .map_or(1, |sp| sp.color_attachments.len())
+        };
+        let (rp, target_samples, depth_read_only, color_count) = match self.target {
+            Some(PipelineTarget::RenderPass { pass, .. }) => (pass, 1, false, 1),
             Some(PipelineTarget::Canvas { canvas, ref output }) => {
-                if canvas.format(output).is_none() {
-                    return Err(PipelineError::UndefinedCanvasOutput(output.clone()));
+                if !canvas.subpasses().iter().any(|sp| sp.name == *output) {
+                    if canvas.format(output).is_none() {
+                        return Err(PipelineError::UndefinedCanvasOutput(output.clone()));
+                    }
+                    pass_canvas_format_check(canvas, output)?;
                 }
-                pass_canvas_format_check(canvas, output)?;
-                canvas.render_pass()
+                (
+                    canvas.render_pass(),
+                    canvas.samples(),
+                    canvas.depth_read_only(),
+                    subpass_colors(canvas),
+                )
             }
             Some(PipelineTarget::Graph { graph, ref output }) => {
                 match graph.render_pass_for_output(output) {
-                    Some((pass, _)) => pass,
+                    Some((pass, _)) => {
+                        let canvas = graph.canvas_for_output(output);
+                        (
+                            pass,
+                            graph.samples_for_output(output),
+                            canvas.is_some_and(|c| c.depth_read_only()),
+                            canvas.map_or(1, subpass_colors),
+                        )
+                    }
                     None => return Err(PipelineError::UndefinedGraphNode(output.clone())),
                 }
             }
             None => panic!("Render pass must be set before build"),
         };
+        let samples = self.samples.unwrap_or(target_samples);
+        if !supports_samples(samples) {
+            return Err(PipelineError::UnsupportedSamples(samples));
+        }
+        let blended = self.state.blended_attachments();
+        if blended > color_count {
+            return Err(PipelineError::BlendAttachment {
+                index: blended as u32 - 1,
+                count: color_count,
+            });
+        }
+
+        if let Some(key) = self.state.unsupported() {
+            return Err(PipelineError::Unsupported(key.to_string()));
+        }
 
         let vert_info = reflect_shader(self.vert_spirv);
         let frag_info = reflect_shader(self.frag_spirv);
+        let spec_data = specialization::specialization_data(
+            &[
+                &vert_info.specialization_constants,
+                &frag_info.specialization_constants,
+            ],
+            &self.specializations,
+        )?;
+        check_shader_support(&[&vert_info, &frag_info], &spec_data)?;
 
         let mut combined: HashMap<u32, Vec<ShaderDescriptorBinding>> = HashMap::new();
         for (set, binds) in vert_info.bindings.into_iter().chain(frag_info.bindings) {
@@ -575,6 +756,10 @@ impl<'a> PipelineBuilder<'a> {
                         set, b.binding
                     );
                 }
+                if b.name == BINDLESS_LIGHTS {
+                    check_light_layout(b.block_size, &b.members)
+                        .map_err(PipelineError::LightLayout)?;
+                }
                 if desc_map.contains_key(&b.name) {
                     panic!(
                         "Descriptor name '{}' already used by another binding. Provide unique instance names in the shader source.",
@@ -623,7 +808,7 @@ impl<'a> PipelineBuilder<'a> {
             }
 
             let info = BindGroupLayoutInfo {
-                debug_name: self.pipeline_name,
+                debug_name: &self.pipeline_name,
                 shaders: &[ShaderInfo {
                     shader_type: ShaderType::All,
                     variables: &vars,
@@ -642,36 +827,13 @@ impl<'a> PipelineBuilder<'a> {
             }
         }
 
-        let module = ShaderModule::load_u32_data(self.vert_spirv).unwrap();
-        let mut inputs = module.enumerate_input_variables(None).unwrap();
-        inputs.sort_by_key(|v| v.location);
-
-        let mut entries = Vec::new();
-        let mut offset = 0;
-        for var in inputs {
-            let fmt = reflect_format_to_shader_primitive(var.format);
-            entries.push(VertexEntryInfo {
-                format: fmt,
-                location: var.location as usize,
-                offset: offset as usize,
-            });
-            offset += match fmt {
-                ShaderPrimitiveType::Vec4
-                | ShaderPrimitiveType::IVec4
-                | ShaderPrimitiveType::UVec4 => 16,
-                ShaderPrimitiveType::Vec3 => 12,
-                ShaderPrimitiveType::Vec2 => 8,
-            };
-        }
-
-        let vertex_info = VertexDescriptionInfo {
-            entries: &entries,
-            stride: offset as usize,
-            rate: VertexRate::Vertex,
-        };
+        let inputs = reflect_vertex_inputs(self.vert_spirv);
+        let buffers = vertex_layout::vertex_buffers(self.vertex_buffers.clone(), &inputs)?;
+        let entries = buffers[0].entries();
+        let vertex_info = vertex_layout::vertex_description(&buffers[0], &entries);
 
         let layout_info = GraphicsPipelineLayoutInfo {
-            debug_name: self.pipeline_name,
+            debug_name: &self.pipeline_name,
             vertex_info,
             bg_layouts,
             shaders: &[
@@ -688,14 +850,15 @@ impl<'a> PipelineBuilder<'a> {
             ],
             details: GraphicsPipelineDetails {
                 subpass: self.subpass as u8,
-                color_blend_states: vec![ColorBlendState::default()],
+                color_blend_states: self.state.color_blend_states(color_count),
                 topology: Topology::TriangleList,
                 culling: self.cull_mode,
                 front_face: VertexOrdering::CounterClockwise,
+                // Canvases with read-only depth are tested against only.
                 depth_test: if self.depth_enable {
                     Some(DepthInfo {
                         should_test: true,
-                        should_write: true,
+                        should_write: !depth_read_only,
                     })
                 } else {
                     None
@@ -717,7 +880,7 @@ impl<'a> PipelineBuilder<'a> {
         let pipeline_handle = self
             .ctx
             .make_graphics_pipeline(&GraphicsPipelineInfo {
-                debug_name: self.pipeline_name,
+                debug_name: &self.pipeline_name,
                 layout,
                 render_pass: rp,
                 subpass_id: self.subpass as u8,
@@ -729,6 +892,7 @@ impl<'a> PipelineBuilder<'a> {
             pipeline: pipeline_handle,
             layout,
             bind_group_layouts: bg_layouts,
+            vertex_buffers: buffers,
             desc_map,
             ctx: self.ctx,
         })
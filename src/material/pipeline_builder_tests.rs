@@ -4,7 +4,6 @@ use super::*;
 use crate::canvas::CanvasBuilder;
 use crate::render_graph::{RenderGraph, RenderPassNode, ResourceDesc};
 use crate::{
-    material::pipeline_builder::reflect_format_to_shader_primitive,
     shader_reflection::ShaderDescriptorType,
     utils::{
         allocator::GpuAllocator, resource_list::ResourceList, CombinedTextureSampler, DHObject,
@@ -99,13 +98,14 @@ fn descriptor_mapping_roundtrip() {
 fn reflect_format_mapping() {
     use ReflectFormat::*;
     assert_eq!(
-        reflect_format_to_shader_primitive(R32G32_SFLOAT),
-        ShaderPrimitiveType::Vec2
+        VertexFormat::from_reflect(R32G32_SFLOAT).map(ShaderPrimitiveType::from),
+        Some(ShaderPrimitiveType::Vec2)
     );
     assert_eq!(
-        reflect_format_to_shader_primitive(R32G32B32A32_UINT),
-        ShaderPrimitiveType::UVec4
+        VertexFormat::from_reflect(R32G32B32A32_UINT).map(ShaderPrimitiveType::from),
+        Some(ShaderPrimitiveType::UVec4)
     );
+    assert_eq!(VertexFormat::from_reflect(R32_SINT), None);
 }
 
 #[test]
@@ -815,7 +815,7 @@ fn undefined_graph_node_error() {
         }],
     );
     let mut graph = RenderGraph::new();
-    graph.add_node(node);
+    graph.add_node(node).unwrap();
 
     let vert = simple_vertex_spirv();
     let frag = simple_fragment_spirv();
@@ -833,3 +833,199 @@ fn undefined_graph_node_error() {
 
     ctx.destroy();
 }
+
+#[test]
+#[serial]
+fn fixed_function_state_builds_pipelines() {
+    let mut ctx = make_ctx();
+    let canvas = CanvasBuilder::new()
+        .extent([1, 1])
+        .color_attachment("color", Format::RGBA8)
+        .depth_attachment("depth", Format::D24S8)
+        .build(&mut ctx)
+        .unwrap();
+
+    let vert = simple_vertex_spirv();
+    let frag = simple_fragment_spirv();
+    let pipeline = PipelineBuilder::new(&mut ctx, "state")
+        .vertex_shader(&vert)
+        .fragment_shader(&frag)
+        .render_pass(canvas.output("color"))
+        .depth_enable(true)
+        .blend(Blend::Additive)
+        .build();
+    assert!(pipeline.pipeline.valid());
+
+    let mut res = ResourceManager::new(&mut ctx, 1024).unwrap();
+    let result = PipelineBuilder::new(&mut ctx, "bad_blend")
+        .vertex_shader(&vert)
+        .fragment_shader(&frag)
+        .render_pass(canvas.output("color"))
+        .attachment_blend(1, Blend::Premultiplied)
+        .build_with_resources(&mut res);
+    match result {
+        Err(PipelineError::BlendAttachment { index, count }) => {
+            assert_eq!((index, count), (1, 1))
+        }
+        _ => panic!("expected blend attachment error"),
+    }
+
+    let unsupported: [(&str, fn(PipelineBuilder) -> PipelineBuilder); 4] = [
+        ("depth_compare", |b| b.depth_compare(PipelineCompare::LessOrEqual)),
+        ("depth_bias", |b| {
+            b.depth_bias(DepthBias {
+                constant: 1.25,
+                slope: 1.75,
+                clamp: 0.0,
+            })
+        }),
+        ("stencil", |b| {
+            let portal = StencilFace {
+                compare: PipelineCompare::Equal,
+                ..Default::default()
+            };
+            b.stencil(StencilTest::both(portal, 1))
+        }),
+        ("polygon_mode", |b| b.polygon_mode(PipelinePolygonMode::Line)),
+    ];
+    for (key, set) in unsupported {
+        let builder = PipelineBuilder::new(&mut ctx, "unsupported_state")
+            .vertex_shader(&vert)
+            .fragment_shader(&frag)
+            .render_pass(canvas.output("color"));
+        match set(builder).build_with_resources(&mut res) {
+            Err(PipelineError::Unsupported(found)) => assert_eq!(found, key),
+            _ => panic!("expected {key} to be rejected"),
+        }
+    }
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn push_constants_are_rejected() {
+    let mut ctx = make_ctx();
+    let rp = RenderPassBuilder::new("rp", Viewport::default())
+        .add_subpass(&[AttachmentDescription::default()], None, &[])
+        .build(&mut ctx)
+        .unwrap();
+
+    let vert = inline_spirv!(
+        r#"
+        #version 450
+        layout(location=0) in vec2 pos;
+        layout(push_constant) uniform Push { uint object; } push;
+        void main(){ gl_Position=vec4(pos, float(push.object), 1); }"#,
+        vert
+    )
+    .to_vec();
+    let frag = simple_fragment_spirv();
+    let mut res = ResourceManager::new(&mut ctx, 1024).unwrap();
+    let result = PipelineBuilder::new(&mut ctx, "push")
+        .vertex_shader(&vert)
+        .fragment_shader(&frag)
+        .render_pass((rp, 0))
+        .build_with_resources(&mut res);
+    match result {
+        Err(PipelineError::Unsupported(what)) => assert_eq!(what, "push constants"),
+        _ => panic!("expected push constants to be rejected"),
+    }
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn specialization_constants_are_rejected() {
+    let mut ctx = make_ctx();
+    let rp = RenderPassBuilder::new("rp", Viewport::default())
+        .add_subpass(&[AttachmentDescription::default()], None, &[])
+        .build(&mut ctx)
+        .unwrap();
+
+    let vert = simple_vertex_spirv();
+    let frag = inline_spirv!(
+        r#"
+        #version 450
+        layout(constant_id = 0) const uint MAX_LIGHTS = 16;
+        layout(location=0) out vec4 outCol;
+        void main(){ outCol=vec4(float(MAX_LIGHTS)); }"#,
+        frag
+    )
+    .to_vec();
+    let pso = PipelineBuilder::new(&mut ctx, "defaults")
+        .vertex_shader(&vert)
+        .fragment_shader(&frag)
+        .render_pass((rp, 0))
+        .build();
+    assert!(pso.pipeline.valid());
+
+    let mut res = ResourceManager::new(&mut ctx, 1024).unwrap();
+    let result = PipelineBuilder::new(&mut ctx, "variant")
+        .vertex_shader(&vert)
+        .fragment_shader(&frag)
+        .render_pass((rp, 0))
+        .specialize("MAX_LIGHTS", 64u32)
+        .build_with_resources(&mut res);
+    match result {
+        Err(PipelineError::Unsupported(what)) => assert_eq!(what, "specialization constants"),
+        _ => panic!("expected specialization constants to be rejected"),
+    }
+
+    let result = PipelineBuilder::new(&mut ctx, "bad_variant")
+        .vertex_shader(&vert)
+        .fragment_shader(&frag)
+        .render_pass((rp, 0))
+        .specialize("MAX_SHADOWS", 4u32)
+        .build_with_resources(&mut res);
+    assert!(matches!(result, Err(PipelineError::Specialization(_))));
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn vertex_layouts_checked_against_shader_inputs() {
+    let mut ctx = make_ctx();
+    let rp = RenderPassBuilder::new("rp", Viewport::default())
+        .add_subpass(&[AttachmentDescription::default()], None, &[])
+        .build(&mut ctx)
+        .unwrap();
+
+    let vert = inline_spirv!(
+        r#"
+        #version 450
+        layout(location=0) in vec3 pos;
+        layout(location=3) in vec2 uv;
+        void main(){ gl_Position=vec4(pos + vec3(uv, 0), 1); }"#,
+        vert
+    )
+    .to_vec();
+    let frag = simple_fragment_spirv();
+    let pso = PipelineBuilder::new(&mut ctx, "mesh_vertex")
+        .vertex_shader(&vert)
+        .fragment_shader(&frag)
+        .render_pass((rp, 0))
+        .vertex_layout::<crate::renderer::Vertex>()
+        .build();
+    assert!(pso.pipeline.valid());
+
+    let mut res = ResourceManager::new(&mut ctx, 1024).unwrap();
+    let split = PipelineBuilder::new(&mut ctx, "split_buffers")
+        .vertex_shader(&vert)
+        .fragment_shader(&frag)
+        .render_pass((rp, 0))
+        .vertex_buffers(vec![
+            VertexBufferLayout::new(12).attribute(0, VertexFormat::Vec3, 0),
+            VertexBufferLayout::new(8).attribute(3, VertexFormat::Vec2, 0),
+        ])
+        .build_with_resources(&mut res);
+    assert!(matches!(split, Err(PipelineError::VertexInput(_))));
+
+    let result = PipelineBuilder::new(&mut ctx, "bad_vertex")
+        .vertex_shader(&simple_vertex_spirv())
+        .fragment_shader(&frag)
+        .render_pass((rp, 0))
+        .vertex_layout::<crate::renderer::Vertex>()
+        .build_with_resources(&mut res);
+    assert!(matches!(result, Err(PipelineError::VertexInput(_))));
+    ctx.destroy();
+}
@@ -0,0 +1,406 @@
+//! Fixed-function pipeline state beyond depth testing and culling.
+//!
+//! [`PipelineState`] holds blending, stencil, rasterizer and depth compare
+//! settings. [`PipelineBuilder`](crate::material::PipelineBuilder) applies it
+//! when building, and material and graph pipeline YAML read the same keys.
+//! dashi pipelines only take blending so far, so building with a depth
+//! compare other than `less`, a depth bias, a stencil test or a polygon mode
+//! other than `fill` fails with
+//! [`PipelineError::Unsupported`](crate::material::PipelineError::Unsupported):
+//!
+//! ```yaml
+//! blend: additive
+//! attachment_blends: [~, premultiplied]
+//! depth_compare: less_or_equal
+//! depth_bias: { constant: 1.25, slope: 1.75 }
+//! polygon_mode: line
+//! stencil:
+//!   reference: 1
+//!   front: { compare: equal }
+//!   back: { compare: equal }
+//! ```
+
+use dashi::{BlendFactor, BlendOp, ColorBlendState, WriteMask};
+use serde::{Deserialize, Serialize};
+
+/// Blend factor of a custom [`AttachmentBlend`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineBlendFactor {
+    Zero,
+    One,
+    SrcColor,
+    OneMinusSrcColor,
+    DstColor,
+    OneMinusDstColor,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstAlpha,
+    OneMinusDstAlpha,
+}
+
+impl From<PipelineBlendFactor> for BlendFactor {
+    fn from(factor: PipelineBlendFactor) -> Self {
+        match factor {
+            PipelineBlendFactor::Zero => BlendFactor::Zero,
+            PipelineBlendFactor::One => BlendFactor::One,
+            PipelineBlendFactor::SrcColor => BlendFactor::SrcColor,
+            PipelineBlendFactor::OneMinusSrcColor => BlendFactor::InvSrcColor,
+            PipelineBlendFactor::DstColor => BlendFactor::DstColor,
+            PipelineBlendFactor::OneMinusDstColor => BlendFactor::InvDstColor,
+            PipelineBlendFactor::SrcAlpha => BlendFactor::SrcAlpha,
+            PipelineBlendFactor::OneMinusSrcAlpha => BlendFactor::InvSrcAlpha,
+            PipelineBlendFactor::DstAlpha => BlendFactor::DstAlpha,
+            PipelineBlendFactor::OneMinusDstAlpha => BlendFactor::InvDstAlpha,
+        }
+    }
+}
+
+/// Blend equation of a custom [`AttachmentBlend`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineBlendOp {
+    #[default]
+    Add,
+    Subtract,
+    ReverseSubtract,
+    Min,
+    Max,
+}
+
+impl From<PipelineBlendOp> for BlendOp {
+    fn from(op: PipelineBlendOp) -> Self {
+        match op {
+            PipelineBlendOp::Add => BlendOp::Add,
+            PipelineBlendOp::Subtract => BlendOp::Subtract,
+            PipelineBlendOp::ReverseSubtract => BlendOp::InvSubtract,
+            PipelineBlendOp::Min => BlendOp::Min,
+            PipelineBlendOp::Max => BlendOp::Max,
+        }
+    }
+}
+
+/// Comparison used by the depth and stencil tests.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineCompare {
+    Never,
+    #[default]
+    Less,
+    Equal,
+    LessOrEqual,
+    Greater,
+    NotEqual,
+    GreaterOrEqual,
+    Always,
+}
+
+/// What a stencil test does to the stencil value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineStencilOp {
+    #[default]
+    Keep,
+    Zero,
+    Replace,
+    IncrementAndClamp,
+    DecrementAndClamp,
+    Invert,
+    IncrementAndWrap,
+    DecrementAndWrap,
+}
+
+/// How triangles are rasterized.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelinePolygonMode {
+    #[default]
+    Fill,
+    /// Edges only, for wireframe views.
+    Line,
+    Point,
+}
+
+fn all_channels() -> [bool; 4] {
+    [true; 4]
+}
+
+/// Blend equation for one color attachment.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AttachmentBlend {
+    pub src_color: PipelineBlendFactor,
+    pub dst_color: PipelineBlendFactor,
+    #[serde(default)]
+    pub color_op: PipelineBlendOp,
+    pub src_alpha: PipelineBlendFactor,
+    pub dst_alpha: PipelineBlendFactor,
+    #[serde(default)]
+    pub alpha_op: PipelineBlendOp,
+    /// Red, green, blue and alpha channels written.
+    #[serde(default = "all_channels")]
+    pub write_mask: [bool; 4],
+}
+
+/// Blending of a color attachment.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Blend {
+    /// Fragments replace the attachment.
+    #[default]
+    Opaque,
+    /// Straight alpha: `src * a + dst * (1 - a)`.
+    Alpha,
+    /// Premultiplied alpha: `src + dst * (1 - a)`.
+    Premultiplied,
+    /// `src * a + dst`, for particles and light accumulation.
+    Additive,
+    Custom(AttachmentBlend),
+}
+
+impl Blend {
+    /// The blend equation, or `None` when blending is off.
+    pub fn equation(&self) -> Option<AttachmentBlend> {
+        use PipelineBlendFactor::*;
+        let uniform = |src, dst| AttachmentBlend {
+            src_color: src,
+            dst_color: dst,
+            color_op: PipelineBlendOp::Add,
+            src_alpha: One,
+            dst_alpha: dst,
+            alpha_op: PipelineBlendOp::Add,
+            write_mask: [true; 4],
+        };
+        match self {
+            Blend::Opaque => None,
+            Blend::Alpha => Some(uniform(SrcAlpha, OneMinusSrcAlpha)),
+            Blend::Premultiplied => Some(uniform(One, OneMinusSrcAlpha)),
+            Blend::Additive => Some(uniform(SrcAlpha, One)),
+            Blend::Custom(custom) => Some(*custom),
+        }
+    }
+
+    pub(crate) fn color_blend_state(&self) -> ColorBlendState {
+        match self.equation() {
+            None => ColorBlendState {
+                enable: false,
+                ..Default::default()
+            },
+            Some(eq) => ColorBlendState {
+                enable: true,
+                src_blend: eq.src_color.into(),
+                dst_blend: eq.dst_color.into(),
+                blend_op: eq.color_op.into(),
+                src_alpha_blend: eq.src_alpha.into(),
+                dst_alpha_blend: eq.dst_alpha.into(),
+                alpha_blend_op: eq.alpha_op.into(),
+                write_mask: WriteMask {
+                    r: eq.write_mask[0],
+                    g: eq.write_mask[1],
+                    b: eq.write_mask[2],
+                    a: eq.write_mask[3],
+                },
+            },
+        }
+    }
+}
+
+/// Stencil operations for one face.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StencilFace {
+    /// Applied when the stencil test fails.
+    pub fail: PipelineStencilOp,
+    /// Applied when both the stencil and depth tests pass.
+    pub pass: PipelineStencilOp,
+    /// Applied when the stencil test passes and the depth test fails.
+    pub depth_fail: PipelineStencilOp,
+    pub compare: PipelineCompare,
+}
+
+impl Default for StencilFace {
+    fn default() -> Self {
+        Self {
+            fail: PipelineStencilOp::Keep,
+            pass: PipelineStencilOp::Keep,
+            depth_fail: PipelineStencilOp::Keep,
+            compare: PipelineCompare::Always,
+        }
+    }
+}
+
+/// Stencil test against the target's depth-stencil attachment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StencilTest {
+    pub front: StencilFace,
+    pub back: StencilFace,
+    pub read_mask: u32,
+    pub write_mask: u32,
+    pub reference: u32,
+}
+
+impl Default for StencilTest {
+    fn default() -> Self {
+        Self {
+            front: StencilFace::default(),
+            back: StencilFace::default(),
+            read_mask: 0xff,
+            write_mask: 0xff,
+            reference: 0,
+        }
+    }
+}
+
+impl StencilTest {
+    /// The same operations on both faces, against `reference`.
+    pub fn both(face: StencilFace, reference: u32) -> Self {
+        Self {
+            front: face,
+            back: face,
+            reference,
+            ..Default::default()
+        }
+    }
+}
+
+/// Depth offset added while rasterizing, for shadow maps and decals.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DepthBias {
+    pub constant: f32,
+    pub slope: f32,
+    /// Largest bias applied, or `0.0` for no limit.
+    pub clamp: f32,
+}
+
+/// Blend, stencil, rasterizer and depth compare state of a graphics pipeline.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PipelineState {
+    /// Blending of every color attachment without an entry in
+    /// [`PipelineState::attachment_blends`].
+    pub blend: Blend,
+    /// Blending by color attachment index within the subpass. `None`
+    /// entries use [`PipelineState::blend`].
+    pub attachment_blends: Vec<Option<Blend>>,
+    /// Comparison of the depth test, when depth is enabled.
+    pub depth_compare: PipelineCompare,
+    pub depth_bias: Option<DepthBias>,
+    pub stencil: Option<StencilTest>,
+    pub polygon_mode: PipelinePolygonMode,
+}
+
+impl PipelineState {
+    /// Blending of color attachment `index`.
+    pub fn blend_for(&self, index: u32) -> Blend {
+        self.attachment_blends
+            .get(index as usize)
+            .copied()
+            .flatten()
+            .unwrap_or(self.blend)
+    }
+
+    /// Set the blending of color attachment `index`.
+    pub fn set_attachment_blend(&mut self, index: u32, blend: Blend) {
+        let index = index as usize;
+        if self.attachment_blends.len() <= index {
+            self.attachment_blends.resize(index + 1, None);
+        }
+        self.attachment_blends[index] = Some(blend);
+    }
+
+    /// Number of color attachments with blending of their own, counting
+    /// those before the last one.
+    pub(crate) fn blended_attachments(&self) -> usize {
+        self.attachment_blends
+            .iter()
+            .rposition(Option::is_some)
+            .map_or(0, |i| i + 1)
+    }
+
+    /// Key of the first setting dashi pipelines cannot apply yet, or `None`
+    /// when only blending differs from the defaults.
+    pub fn unsupported(&self) -> Option<&'static str> {
+        if self.depth_compare != PipelineCompare::Less {
+            Some("depth_compare")
+        } else if self.depth_bias.is_some() {
+            Some("depth_bias")
+        } else if self.stencil.is_some() {
+            Some("stencil")
+        } else if self.polygon_mode != PipelinePolygonMode::Fill {
+            Some("polygon_mode")
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn color_blend_states(&self, count: usize) -> Vec<ColorBlendState> {
+        (0..count as u32)
+            .map(|i| self.blend_for(i).color_blend_state())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_expand_to_blend_equations() {
+        use PipelineBlendFactor::*;
+        assert_eq!(Blend::Opaque.equation(), None);
+        let additive = Blend::Additive.equation().unwrap();
+        assert_eq!((additive.src_color, additive.dst_color), (SrcAlpha, One));
+        let premultiplied = Blend::Premultiplied.equation().unwrap();
+        assert_eq!(
+            (premultiplied.src_color, premultiplied.dst_color),
+            (One, OneMinusSrcAlpha)
+        );
+    }
+
+    #[test]
+    fn state_parses_from_yaml_with_defaults() {
+        let yaml = r#"
+blend: additive
+attachment_blends:
+  - ~
+  - premultiplied
+  - custom:
+      src_color: one
+      dst_color: zero
+      src_alpha: one
+      dst_alpha: zero
+      write_mask: [true, true, true, false]
+depth_compare: less_or_equal
+depth_bias: { constant: 1.25, slope: 1.75 }
+polygon_mode: line
+stencil:
+  reference: 1
+  front: { compare: equal, pass: replace }
+"#;
+        let state: PipelineState = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(state.blend_for(0), Blend::Additive);
+        assert_eq!(state.blend_for(1), Blend::Premultiplied);
+        assert_eq!(state.blend_for(3), Blend::Additive);
+        let Blend::Custom(custom) = state.blend_for(2) else {
+            panic!("attachment 2 should use a custom blend");
+        };
+        assert_eq!(custom.color_op, PipelineBlendOp::Add);
+        assert_eq!(custom.write_mask, [true, true, true, false]);
+        assert_eq!(state.depth_compare, PipelineCompare::LessOrEqual);
+        assert_eq!(state.depth_bias.unwrap().clamp, 0.0);
+        assert_eq!(state.polygon_mode, PipelinePolygonMode::Line);
+
+        let stencil = state.stencil.unwrap();
+        assert_eq!(stencil.reference, 1);
+        assert_eq!(stencil.read_mask, 0xff);
+        assert_eq!(stencil.front.compare, PipelineCompare::Equal);
+        assert_eq!(stencil.front.pass, PipelineStencilOp::Replace);
+        assert_eq!(stencil.back, StencilFace::default());
+
+        assert_eq!(state.blended_attachments(), 3);
+        assert_eq!(state.unsupported(), Some("depth_compare"));
+        assert_eq!(serde_yaml::from_str::<PipelineState>("{}").unwrap(), PipelineState::default());
+        assert_eq!(PipelineState::default().unsupported(), None);
+    }
+}
@@ -1,5 +1,5 @@
 use crate::material::*;
-use spirv_reflect::types::ReflectDescriptorType;
+use spirv_reflect::types::{ReflectDecorationFlags, ReflectDescriptorType};
 use spirv_reflect::ShaderModule;
 use std::collections::HashMap;
 
@@ -20,18 +20,40 @@ pub enum ShaderDescriptorType {
     Unknown,
 }
 
-#[derive(Debug)]
+/// Scalar type of a specialization constant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderScalarType {
+    Bool,
+    Int,
+    UInt,
+    Float,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ShaderSpecializationConstant {
+    /// `constant_id` of the constant.
     pub id: u32,
     pub name: String,
+    /// Offset in the stage's specialization data, in declaration order.
     pub offset: u32,
     pub size: u32,
+    pub ty: ShaderScalarType,
+}
+
+/// An input of a vertex shader read from a vertex buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShaderVertexInput {
+    pub name: String,
+    pub location: u32,
+    /// `None` for formats vertex buffers cannot supply.
+    pub format: Option<VertexFormat>,
 }
 
 #[derive(Debug)]
 pub struct ShaderReflectionInfo {
     pub bindings: HashMap<u32, Vec<ShaderDescriptorBinding>>,
     pub push_constants: Vec<ShaderPushConstant>,
+    pub specialization_constants: Vec<ShaderSpecializationConstant>,
 }
 
 #[derive(Debug)]
@@ -47,7 +69,7 @@ pub struct ShaderDescriptorBinding {
     pub members: Vec<(String, u32, u32)>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ShaderPushConstant {
     pub offset: u32,
     pub size: u32,
@@ -58,6 +80,7 @@ pub fn reflect_shader(spirv: &[u32]) -> ShaderReflectionInfo {
         return ShaderReflectionInfo {
             bindings: Default::default(),
             push_constants: Default::default(),
+            specialization_constants: Default::default(),
         };
     }
 
@@ -107,7 +130,111 @@ pub fn reflect_shader(spirv: &[u32]) -> ShaderReflectionInfo {
     ShaderReflectionInfo {
         bindings,
         push_constants,
+        specialization_constants: reflect_specialization_constants(spirv),
+    }
+}
+
+/// Read the inputs of the vertex shader `spirv` in location order, skipping
+/// built-ins such as `gl_VertexIndex`.
+pub fn reflect_vertex_inputs(spirv: &[u32]) -> Vec<ShaderVertexInput> {
+    if spirv.is_empty() {
+        return Vec::new();
     }
+    let module = ShaderModule::load_u32_data(spirv).expect("Failed to parse SPIR-V");
+    let mut inputs: Vec<ShaderVertexInput> = module
+        .enumerate_input_variables(None)
+        .expect("Failed to reflect vertex inputs")
+        .into_iter()
+        .filter(|v| !v.decoration_flags.contains(ReflectDecorationFlags::BUILT_IN))
+        .map(|v| ShaderVertexInput {
+            name: v.name,
+            location: v.location,
+            format: VertexFormat::from_reflect(v.format),
+        })
+        .collect();
+    inputs.sort_by_key(|i| i.location);
+    inputs
+}
+
+/// Read the specialization constants declared in `spirv`.
+///
+/// The instructions are walked directly, since the reflection library does
+/// not report specialization constants. Booleans take 4 bytes, as in Vulkan.
+pub fn reflect_specialization_constants(spirv: &[u32]) -> Vec<ShaderSpecializationConstant> {
+    const OP_NAME: u32 = 5;
+    const OP_TYPE_BOOL: u32 = 20;
+    const OP_TYPE_INT: u32 = 21;
+    const OP_TYPE_FLOAT: u32 = 22;
+    const OP_SPEC_CONSTANT_TRUE: u32 = 48;
+    const OP_SPEC_CONSTANT_FALSE: u32 = 49;
+    const OP_SPEC_CONSTANT: u32 = 50;
+    const OP_DECORATE: u32 = 71;
+    const DECORATION_SPEC_ID: u32 = 1;
+
+    let mut names: HashMap<u32, String> = HashMap::new();
+    let mut spec_ids: HashMap<u32, u32> = HashMap::new();
+    let mut types: HashMap<u32, (ShaderScalarType, u32)> = HashMap::new();
+    // (result id, result type) in declaration order.
+    let mut constants: Vec<(u32, u32)> = Vec::new();
+
+    let mut i = 5;
+    while i < spirv.len() {
+        let word_count = (spirv[i] >> 16) as usize;
+        let opcode = spirv[i] & 0xffff;
+        if word_count == 0 || i + word_count > spirv.len() {
+            break;
+        }
+        let operands = &spirv[i + 1..i + word_count];
+        match opcode {
+            OP_NAME if operands.len() > 1 => {
+                let bytes: Vec<u8> = operands[1..].iter().flat_map(|w| w.to_le_bytes()).collect();
+                let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+                names.insert(operands[0], String::from_utf8_lossy(&bytes[..end]).into_owned());
+            }
+            OP_DECORATE if operands.len() > 2 && operands[1] == DECORATION_SPEC_ID => {
+                spec_ids.insert(operands[0], operands[2]);
+            }
+            OP_TYPE_BOOL if !operands.is_empty() => {
+                types.insert(operands[0], (ShaderScalarType::Bool, 4));
+            }
+            OP_TYPE_INT if operands.len() > 2 => {
+                let ty = if operands[2] == 0 {
+                    ShaderScalarType::UInt
+                } else {
+                    ShaderScalarType::Int
+                };
+                types.insert(operands[0], (ty, operands[1] / 8));
+            }
+            OP_TYPE_FLOAT if operands.len() > 1 => {
+                types.insert(operands[0], (ShaderScalarType::Float, operands[1] / 8));
+            }
+            OP_SPEC_CONSTANT_TRUE | OP_SPEC_CONSTANT_FALSE | OP_SPEC_CONSTANT
+                if operands.len() > 1 =>
+            {
+                constants.push((operands[1], operands[0]));
+            }
+            _ => {}
+        }
+        i += word_count;
+    }
+
+    let mut offset = 0;
+    constants
+        .into_iter()
+        .filter_map(|(result, ty)| {
+            let id = *spec_ids.get(&result)?;
+            let (ty, size) = *types.get(&ty)?;
+            let constant = ShaderSpecializationConstant {
+                id,
+                name: names.get(&result).cloned().unwrap_or_default(),
+                offset,
+                size,
+                ty,
+            };
+            offset += size;
+            Some(constant)
+        })
+        .collect()
 }
 
 fn map_descriptor_type(ty: ReflectDescriptorType) -> ShaderDescriptorType {
@@ -138,6 +265,7 @@ pub fn descriptor_to_var_type(ty: ShaderDescriptorType) -> BindGroupVariableType
         ShaderDescriptorType::UniformBuffer => BindGroupVariableType::Uniform,
         ShaderDescriptorType::StorageBuffer => BindGroupVariableType::Storage,
         ShaderDescriptorType::StorageImage => BindGroupVariableType::StorageImage,
+        ShaderDescriptorType::InputAttachment => BindGroupVariableType::InputAttachment,
         other => panic!("Unsupported descriptor type: {:?}", other),
     }
 }
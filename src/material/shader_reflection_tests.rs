@@ -56,6 +56,10 @@ fn descriptor_var_type_mappings() {
         descriptor_to_var_type(StorageImage),
         BindGroupVariableType::StorageImage
     );
+    assert_eq!(
+        descriptor_to_var_type(InputAttachment),
+        BindGroupVariableType::InputAttachment
+    );
 }
 
 #[test]
@@ -81,3 +85,76 @@ fn include_file_uniform_reflected() {
     assert!(set0.iter().any(|b| b.name == "KOJI_time" || b.members.iter().any(|m| m.0 == "KOJI_time")));
 }
 
+
+#[test]
+fn push_constant_blocks_reflected() {
+    let spirv: Vec<u32> = inline_spirv!(
+        r"#version 450
+        layout(push_constant) uniform Push { mat4 model; } push;
+        void main() { gl_Position = push.model * vec4(1.0); }",
+        vert
+    )
+    .to_vec();
+
+    let info = reflect_shader(&spirv);
+    assert_eq!(info.push_constants, vec![ShaderPushConstant { offset: 0, size: 64 }]);
+}
+
+#[test]
+fn specialization_constants_reflected_by_name() {
+    let spirv: Vec<u32> = inline_spirv!(
+        r"#version 450
+        layout(constant_id = 3) const uint MAX_LIGHTS = 16;
+        layout(constant_id = 0) const bool USE_FOG = false;
+        layout(constant_id = 1) const float SCALE = 1.0;
+        layout(location = 0) out vec4 o;
+        void main() { o = vec4(float(MAX_LIGHTS) * SCALE, USE_FOG ? 1.0 : 0.0, 0.0, 1.0); }",
+        frag
+    )
+    .to_vec();
+
+    let mut constants = reflect_shader(&spirv).specialization_constants;
+    constants.sort_by_key(|c| c.id);
+    let summary: Vec<(u32, &str, u32, ShaderScalarType)> = constants
+        .iter()
+        .map(|c| (c.id, c.name.as_str(), c.size, c.ty))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (0, "USE_FOG", 4, ShaderScalarType::Bool),
+            (1, "SCALE", 4, ShaderScalarType::Float),
+            (3, "MAX_LIGHTS", 4, ShaderScalarType::UInt),
+        ]
+    );
+}
+
+#[test]
+fn vertex_inputs_reflected_without_builtins() {
+    let spirv: Vec<u32> = inline_spirv!(
+        r"#version 450
+        layout(location = 3) in vec2 inUV;
+        layout(location = 0) in vec3 inPos;
+        layout(location = 5) in uvec4 inJoints;
+        void main() {
+            gl_Position = vec4(inPos + vec3(inUV, float(inJoints.x) + float(gl_VertexIndex)), 1.0);
+        }",
+        vert
+    )
+    .to_vec();
+
+    let inputs = reflect_vertex_inputs(&spirv);
+    let summary: Vec<(u32, &str, Option<VertexFormat>)> = inputs
+        .iter()
+        .map(|i| (i.location, i.name.as_str(), i.format))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (0, "inPos", Some(VertexFormat::Vec3)),
+            (3, "inUV", Some(VertexFormat::Vec2)),
+            (5, "inJoints", Some(VertexFormat::UVec4)),
+        ]
+    );
+    assert!(reflect_vertex_inputs(&[]).is_empty());
+}
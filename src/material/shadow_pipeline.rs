@@ -0,0 +1,36 @@
+use crate::material::pipeline_builder::PipelineBuilder;
+use crate::renderer::{SkeletalVertex, Vertex};
+use dashi::*;
+use inline_spirv::include_spirv;
+
+/// Depth-only pipeline rendering static meshes from a shadow view.
+pub fn build_shadow_pipeline(
+    ctx: &mut Context,
+    target: crate::canvas::CanvasOutput,
+) -> crate::material::PSO {
+    let vert: &[u32] = include_spirv!("assets/shaders/shadow_pass.vert", vert, glsl);
+    let frag: &[u32] = include_spirv!("assets/shaders/shadow_pass.frag", frag, glsl);
+    PipelineBuilder::new(ctx, "shadow_pipeline")
+        .vertex_shader(vert)
+        .fragment_shader(frag)
+        .vertex_layout::<Vertex>()
+        .render_pass(target)
+        .depth_enable(true)
+        .build()
+}
+
+/// Depth-only pipeline rendering skinned meshes from a shadow view.
+pub fn build_skinned_shadow_pipeline(
+    ctx: &mut Context,
+    target: crate::canvas::CanvasOutput,
+) -> crate::material::PSO {
+    let vert: &[u32] = include_spirv!("assets/shaders/shadow_skinned.vert", vert, glsl);
+    let frag: &[u32] = include_spirv!("assets/shaders/shadow_pass.frag", frag, glsl);
+    PipelineBuilder::new(ctx, "skinned_shadow_pipeline")
+        .vertex_shader(vert)
+        .fragment_shader(frag)
+        .vertex_layout::<SkeletalVertex>()
+        .render_pass(target)
+        .depth_enable(true)
+        .build()
+}
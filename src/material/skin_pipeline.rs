@@ -1,6 +1,7 @@
 use inline_spirv::include_spirv;
 use dashi::*;
 use crate::material::pipeline_builder::PipelineBuilder;
+use crate::renderer::SkeletalVertex;
 
 pub fn build_skinning_pipeline(
     ctx: &mut Context,
@@ -11,6 +12,7 @@ pub fn build_skinning_pipeline(
     PipelineBuilder::new(ctx, "skinning_pipeline")
         .vertex_shader(vert)
         .fragment_shader(frag)
+        .vertex_layout::<SkeletalVertex>()
         .render_pass(target)
         .build()
 }
@@ -0,0 +1,190 @@
+//! Specialization constants set by name.
+//!
+//! Builders and material YAML name the constants declared with
+//! `layout(constant_id = N)` and give each a [`SpecializationValue`]. Values
+//! are checked and encoded with the type reflected from the shader. dashi
+//! cannot pass the encoded data to pipelines yet, so builds that set any
+//! constant fail with [`PipelineError::Unsupported`] and shaders use their
+//! declared defaults.
+
+use crate::material::{PipelineError, ShaderScalarType, ShaderSpecializationConstant};
+use serde::{Deserialize, Serialize};
+
+/// Value of a specialization constant.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SpecializationValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+}
+
+impl From<bool> for SpecializationValue {
+    fn from(value: bool) -> Self {
+        SpecializationValue::Bool(value)
+    }
+}
+
+impl From<i32> for SpecializationValue {
+    fn from(value: i32) -> Self {
+        SpecializationValue::Int(value.into())
+    }
+}
+
+impl From<u32> for SpecializationValue {
+    fn from(value: u32) -> Self {
+        SpecializationValue::Int(value.into())
+    }
+}
+
+impl From<i64> for SpecializationValue {
+    fn from(value: i64) -> Self {
+        SpecializationValue::Int(value)
+    }
+}
+
+impl From<f32> for SpecializationValue {
+    fn from(value: f32) -> Self {
+        SpecializationValue::Float(value.into())
+    }
+}
+
+impl From<f64> for SpecializationValue {
+    fn from(value: f64) -> Self {
+        SpecializationValue::Float(value)
+    }
+}
+
+impl SpecializationValue {
+    /// Bytes of this value as `constant`, or `None` when it does not fit.
+    /// Integers may specialize float constants.
+    pub fn encode(&self, constant: &ShaderSpecializationConstant) -> Option<Vec<u8>> {
+        use SpecializationValue::*;
+        let bytes = match (self, constant.ty, constant.size) {
+            (Bool(b), ShaderScalarType::Bool, 4) => (*b as u32).to_le_bytes().to_vec(),
+            (Int(v), ShaderScalarType::Int, 4) => i32::try_from(*v).ok()?.to_le_bytes().to_vec(),
+            (Int(v), ShaderScalarType::Int, 8) => v.to_le_bytes().to_vec(),
+            (Int(v), ShaderScalarType::UInt, 4) => u32::try_from(*v).ok()?.to_le_bytes().to_vec(),
+            (Int(v), ShaderScalarType::UInt, 8) => u64::try_from(*v).ok()?.to_le_bytes().to_vec(),
+            (Int(v), ShaderScalarType::Float, 4) => (*v as f32).to_le_bytes().to_vec(),
+            (Int(v), ShaderScalarType::Float, 8) => (*v as f64).to_le_bytes().to_vec(),
+            (Float(v), ShaderScalarType::Float, 4) => (*v as f32).to_le_bytes().to_vec(),
+            (Float(v), ShaderScalarType::Float, 8) => v.to_le_bytes().to_vec(),
+            _ => return None,
+        };
+        Some(bytes)
+    }
+}
+
+/// Encode `values` for each stage's reflected constants.
+///
+/// Returns `(constant_id, bytes)` per stage. Fails if a name is declared by
+/// no stage or a value does not fit the reflected type.
+pub(crate) fn specialization_data(
+    stages: &[&[ShaderSpecializationConstant]],
+    values: &[(String, SpecializationValue)],
+) -> Result<Vec<Vec<(u32, Vec<u8>)>>, PipelineError> {
+    let mut data = vec![Vec::new(); stages.len()];
+    for (name, value) in values {
+        let mut found = false;
+        for (stage, constants) in stages.iter().enumerate() {
+            for constant in constants.iter().filter(|c| c.name == *name) {
+                let bytes = value.encode(constant).ok_or_else(|| {
+                    PipelineError::Specialization(format!(
+                        "'{}' is a {:?} of {} bytes, which {:?} does not fit",
+                        name, constant.ty, constant.size, value
+                    ))
+                })?;
+                data[stage].push((constant.id, bytes));
+                found = true;
+            }
+        }
+        if !found {
+            return Err(PipelineError::Specialization(format!(
+                "no shader stage declares '{}'",
+                name
+            )));
+        }
+    }
+    Ok(data)
+}
+
+/// Set `name` to `value` in `values`, replacing an earlier value.
+pub(crate) fn set_value(
+    values: &mut Vec<(String, SpecializationValue)>,
+    name: &str,
+    value: SpecializationValue,
+) {
+    values.retain(|(n, _)| n != name);
+    values.push((name.to_string(), value));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constant(name: &str, id: u32, ty: ShaderScalarType) -> ShaderSpecializationConstant {
+        ShaderSpecializationConstant {
+            id,
+            name: name.into(),
+            offset: 0,
+            size: 4,
+            ty,
+        }
+    }
+
+    #[test]
+    fn values_encode_with_the_reflected_type() {
+        let max_lights = constant("MAX_LIGHTS", 0, ShaderScalarType::UInt);
+        let scale = constant("SCALE", 1, ShaderScalarType::Float);
+        let fog = constant("FOG", 2, ShaderScalarType::Bool);
+        assert_eq!(
+            SpecializationValue::from(64u32).encode(&max_lights),
+            Some(64u32.to_le_bytes().to_vec())
+        );
+        assert_eq!(SpecializationValue::from(-1).encode(&max_lights), None);
+        assert_eq!(
+            SpecializationValue::from(2).encode(&scale),
+            Some(2.0f32.to_le_bytes().to_vec())
+        );
+        assert_eq!(
+            SpecializationValue::from(true).encode(&fog),
+            Some(1u32.to_le_bytes().to_vec())
+        );
+        assert_eq!(SpecializationValue::from(0.5).encode(&fog), None);
+    }
+
+    #[test]
+    fn names_resolve_per_stage() {
+        let vert = [constant("MAX_LIGHTS", 3, ShaderScalarType::UInt)];
+        let frag = [
+            constant("MAX_LIGHTS", 0, ShaderScalarType::UInt),
+            constant("FOG", 1, ShaderScalarType::Bool),
+        ];
+        let values = vec![
+            ("MAX_LIGHTS".to_string(), SpecializationValue::from(64u32)),
+            ("FOG".to_string(), SpecializationValue::from(false)),
+        ];
+        let data = specialization_data(&[&vert, &frag], &values).unwrap();
+        assert_eq!(data[0], vec![(3, 64u32.to_le_bytes().to_vec())]);
+        assert_eq!(
+            data[1],
+            vec![(0, 64u32.to_le_bytes().to_vec()), (1, vec![0; 4])]
+        );
+
+        let unknown = vec![("MISSING".to_string(), SpecializationValue::from(1))];
+        assert!(matches!(
+            specialization_data(&[&vert], &unknown),
+            Err(PipelineError::Specialization(_))
+        ));
+    }
+
+    #[test]
+    fn values_parse_from_yaml() {
+        let values: std::collections::BTreeMap<String, SpecializationValue> =
+            serde_yaml::from_str("{ MAX_LIGHTS: 64, FOG: true, SCALE: 0.5 }").unwrap();
+        assert_eq!(values["MAX_LIGHTS"], SpecializationValue::Int(64));
+        assert_eq!(values["FOG"], SpecializationValue::Bool(true));
+        assert_eq!(values["SCALE"], SpecializationValue::Float(0.5));
+    }
+}
@@ -0,0 +1,401 @@
+//! Vertex buffer layouts.
+//!
+//! A pipeline reads its vertex shader inputs from a vertex buffer described
+//! by a [`VertexBufferLayout`]. Vertex structs implement [`VertexLayout`] to
+//! describe themselves. Without a layout, the reflected inputs are packed
+//! into one buffer in location order. Layouts are checked against the
+//! reflected inputs when a pipeline is built, so a shader reading a location
+//! no buffer provides, or reading it with another format, fails with
+//! [`PipelineError::VertexInput`]. dashi pipelines describe a single vertex
+//! buffer, so layouts listing several fail the same way.
+
+use crate::material::{PipelineError, ShaderVertexInput};
+use crate::renderer::{SkeletalVertex, Vertex};
+use dashi::{ShaderPrimitiveType, VertexDescriptionInfo, VertexEntryInfo, VertexRate};
+use serde::{Deserialize, Serialize};
+use spirv_reflect::types::ReflectFormat;
+
+/// Format of one vertex attribute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VertexFormat {
+    Vec2,
+    Vec3,
+    Vec4,
+    IVec4,
+    UVec4,
+}
+
+impl VertexFormat {
+    /// Size of the attribute in bytes.
+    pub fn size(self) -> u32 {
+        match self {
+            VertexFormat::Vec2 => 8,
+            VertexFormat::Vec3 => 12,
+            VertexFormat::Vec4 | VertexFormat::IVec4 | VertexFormat::UVec4 => 16,
+        }
+    }
+
+    /// Format of a reflected shader input, or `None` when vertex buffers
+    /// cannot supply it.
+    pub(crate) fn from_reflect(fmt: ReflectFormat) -> Option<Self> {
+        use ReflectFormat::*;
+        match fmt {
+            R32G32B32A32_SFLOAT => Some(VertexFormat::Vec4),
+            R32G32B32A32_SINT => Some(VertexFormat::IVec4),
+            R32G32B32A32_UINT => Some(VertexFormat::UVec4),
+            R32G32B32_SFLOAT => Some(VertexFormat::Vec3),
+            R32G32_SFLOAT => Some(VertexFormat::Vec2),
+            _ => None,
+        }
+    }
+}
+
+impl From<VertexFormat> for ShaderPrimitiveType {
+    fn from(format: VertexFormat) -> Self {
+        match format {
+            VertexFormat::Vec2 => ShaderPrimitiveType::Vec2,
+            VertexFormat::Vec3 => ShaderPrimitiveType::Vec3,
+            VertexFormat::Vec4 => ShaderPrimitiveType::Vec4,
+            VertexFormat::IVec4 => ShaderPrimitiveType::IVec4,
+            VertexFormat::UVec4 => ShaderPrimitiveType::UVec4,
+        }
+    }
+}
+
+/// How often a vertex buffer advances.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VertexStep {
+    #[default]
+    Vertex,
+    Instance,
+}
+
+impl From<VertexStep> for VertexRate {
+    fn from(step: VertexStep) -> Self {
+        match step {
+            VertexStep::Vertex => VertexRate::Vertex,
+            VertexStep::Instance => VertexRate::Instance,
+        }
+    }
+}
+
+/// One shader input read from a vertex buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VertexAttribute {
+    pub location: u32,
+    pub format: VertexFormat,
+    /// Byte offset within one element of the buffer.
+    pub offset: u32,
+}
+
+/// Layout of the elements of one vertex buffer.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VertexBufferLayout {
+    pub stride: u32,
+    #[serde(default)]
+    pub step: VertexStep,
+    pub attributes: Vec<VertexAttribute>,
+}
+
+impl VertexBufferLayout {
+    pub fn new(stride: u32) -> Self {
+        Self {
+            stride,
+            ..Default::default()
+        }
+    }
+
+    /// Advance the buffer once per instance instead of once per vertex.
+    pub fn per_instance(mut self) -> Self {
+        self.step = VertexStep::Instance;
+        self
+    }
+
+    pub fn attribute(mut self, location: u32, format: VertexFormat, offset: u32) -> Self {
+        self.attributes.push(VertexAttribute {
+            location,
+            format,
+            offset,
+        });
+        self
+    }
+
+    /// One buffer holding `inputs` tightly packed in location order.
+    pub fn packed(inputs: &[ShaderVertexInput]) -> Result<Self, PipelineError> {
+        let mut sorted: Vec<&ShaderVertexInput> = inputs.iter().collect();
+        sorted.sort_by_key(|i| i.location);
+        let mut layout = Self::default();
+        for input in sorted {
+            let format = input.format.ok_or_else(|| unsupported(input))?;
+            layout = layout.attribute(input.location, format, layout.stride);
+            layout.stride += format.size();
+        }
+        Ok(layout)
+    }
+
+    pub(crate) fn entries(&self) -> Vec<VertexEntryInfo> {
+        self.attributes
+            .iter()
+            .map(|a| VertexEntryInfo {
+                format: a.format.into(),
+                location: a.location as usize,
+                offset: a.offset as usize,
+            })
+            .collect()
+    }
+}
+
+/// A vertex type usable as the single vertex buffer of a pipeline.
+///
+/// ```ignore
+/// impl VertexLayout for MyVertex {
+///     fn vertex_layout() -> VertexBufferLayout {
+///         VertexBufferLayout::new(size_of::<Self>() as u32)
+///             .attribute(0, VertexFormat::Vec3, offset_of!(Self, position) as u32)
+///             .attribute(1, VertexFormat::Vec2, offset_of!(Self, uv) as u32)
+///     }
+/// }
+/// ```
+pub trait VertexLayout {
+    fn vertex_layout() -> VertexBufferLayout;
+}
+
+impl VertexLayout for Vertex {
+    fn vertex_layout() -> VertexBufferLayout {
+        use std::mem::{offset_of, size_of};
+        VertexBufferLayout::new(size_of::<Self>() as u32)
+            .attribute(0, VertexFormat::Vec3, offset_of!(Self, position) as u32)
+            .attribute(1, VertexFormat::Vec3, offset_of!(Self, normal) as u32)
+            .attribute(2, VertexFormat::Vec4, offset_of!(Self, tangent) as u32)
+            .attribute(3, VertexFormat::Vec2, offset_of!(Self, uv) as u32)
+            .attribute(4, VertexFormat::Vec4, offset_of!(Self, color) as u32)
+    }
+}
+
+impl VertexLayout for SkeletalVertex {
+    fn vertex_layout() -> VertexBufferLayout {
+        use std::mem::{offset_of, size_of};
+        VertexBufferLayout::new(size_of::<Self>() as u32)
+            .attribute(0, VertexFormat::Vec3, offset_of!(Self, position) as u32)
+            .attribute(1, VertexFormat::Vec3, offset_of!(Self, normal) as u32)
+            .attribute(2, VertexFormat::Vec4, offset_of!(Self, tangent) as u32)
+            .attribute(3, VertexFormat::Vec2, offset_of!(Self, uv) as u32)
+            .attribute(4, VertexFormat::Vec4, offset_of!(Self, color) as u32)
+            .attribute(
+                5,
+                VertexFormat::UVec4,
+                offset_of!(Self, joint_indices) as u32,
+            )
+            .attribute(
+                6,
+                VertexFormat::Vec4,
+                offset_of!(Self, joint_weights) as u32,
+            )
+    }
+}
+
+/// Vertex layout named in material and graph files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VertexKind {
+    /// Pack the reflected inputs into one buffer.
+    Reflected,
+    /// [`Vertex`] of static meshes.
+    #[default]
+    Static,
+    /// [`SkeletalVertex`] of skinned meshes.
+    Skeletal,
+}
+
+impl VertexKind {
+    /// Buffers to build with, preferring `custom` when any are listed.
+    /// `None` packs the reflected inputs.
+    pub fn resolve(self, custom: &[VertexBufferLayout]) -> Option<Vec<VertexBufferLayout>> {
+        if !custom.is_empty() {
+            return Some(custom.to_vec());
+        }
+        match self {
+            VertexKind::Reflected => None,
+            VertexKind::Static => Some(vec![Vertex::vertex_layout()]),
+            VertexKind::Skeletal => Some(vec![SkeletalVertex::vertex_layout()]),
+        }
+    }
+}
+
+fn unsupported(input: &ShaderVertexInput) -> PipelineError {
+    PipelineError::VertexInput(format!(
+        "'{}' at location {} has a format vertex buffers cannot supply",
+        input.name, input.location
+    ))
+}
+
+/// Check that `buffers` provide every input in `inputs` with its format.
+/// Attributes no input reads are allowed.
+pub(crate) fn check_vertex_inputs(
+    buffers: &[VertexBufferLayout],
+    inputs: &[ShaderVertexInput],
+) -> Result<(), PipelineError> {
+    let attributes: Vec<&VertexAttribute> = buffers.iter().flat_map(|b| &b.attributes).collect();
+    for (i, a) in attributes.iter().enumerate() {
+        if attributes[..i].iter().any(|b| b.location == a.location) {
+            return Err(PipelineError::VertexInput(format!(
+                "location {} is provided by more than one attribute",
+                a.location
+            )));
+        }
+    }
+    for input in inputs {
+        let format = input.format.ok_or_else(|| unsupported(input))?;
+        match attributes.iter().find(|a| a.location == input.location) {
+            None => {
+                return Err(PipelineError::VertexInput(format!(
+                    "'{}' at location {} is not provided by the vertex layout",
+                    input.name, input.location
+                )))
+            }
+            Some(a) if a.format != format => {
+                return Err(PipelineError::VertexInput(format!(
+                    "'{}' at location {} is {:?} but the vertex layout provides {:?}",
+                    input.name, input.location, format, a.format
+                )))
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Buffers for a pipeline: `buffers` checked against `inputs`, or `inputs`
+/// packed into one buffer when none are given. Fails unless there is
+/// exactly one buffer.
+pub(crate) fn vertex_buffers(
+    buffers: Option<Vec<VertexBufferLayout>>,
+    inputs: &[ShaderVertexInput],
+) -> Result<Vec<VertexBufferLayout>, PipelineError> {
+    match buffers {
+        Some(buffers) => {
+            if buffers.len() != 1 {
+                return Err(PipelineError::VertexInput(format!(
+                    "pipelines read one vertex buffer, the layout lists {}",
+                    buffers.len()
+                )));
+            }
+            check_vertex_inputs(&buffers, inputs)?;
+            Ok(buffers)
+        }
+        None => Ok(vec![VertexBufferLayout::packed(inputs)?]),
+    }
+}
+
+/// Description of `buffer`, borrowing its [`VertexBufferLayout::entries`].
+pub(crate) fn vertex_description<'a>(
+    buffer: &VertexBufferLayout,
+    entries: &'a [VertexEntryInfo],
+) -> VertexDescriptionInfo<'a> {
+    VertexDescriptionInfo {
+        entries,
+        stride: buffer.stride as usize,
+        rate: buffer.step.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(location: u32, format: VertexFormat) -> ShaderVertexInput {
+        ShaderVertexInput {
+            name: format!("in{}", location),
+            location,
+            format: Some(format),
+        }
+    }
+
+    #[test]
+    fn mesh_vertices_describe_their_fields() {
+        let stat = Vertex::vertex_layout();
+        assert_eq!(stat.stride, 64);
+        let offsets: Vec<u32> = stat.attributes.iter().map(|a| a.offset).collect();
+        assert_eq!(offsets, vec![0, 12, 24, 40, 48]);
+
+        let skel = SkeletalVertex::vertex_layout();
+        assert_eq!(skel.stride, 96);
+        assert_eq!(skel.attributes[5].format, VertexFormat::UVec4);
+        assert_eq!(skel.attributes[5].offset, 64);
+        assert_eq!(skel.attributes[6].offset, 80);
+        assert_eq!(VertexKind::Reflected.resolve(&[]), None);
+        assert_eq!(VertexKind::Skeletal.resolve(&[]), Some(vec![skel]));
+        assert_eq!(
+            VertexKind::Skeletal.resolve(&[stat.clone()]),
+            Some(vec![stat])
+        );
+    }
+
+    #[test]
+    fn reflected_inputs_pack_in_location_order() {
+        let inputs = [input(1, VertexFormat::Vec2), input(0, VertexFormat::Vec4)];
+        let packed = VertexBufferLayout::packed(&inputs).unwrap();
+        assert_eq!(
+            packed,
+            VertexBufferLayout::new(24)
+                .attribute(0, VertexFormat::Vec4, 0)
+                .attribute(1, VertexFormat::Vec2, 16)
+        );
+
+        let unknown = ShaderVertexInput {
+            format: None,
+            ..input(2, VertexFormat::Vec2)
+        };
+        assert!(VertexBufferLayout::packed(&[unknown]).is_err());
+    }
+
+    #[test]
+    fn layouts_are_checked_against_shader_inputs() {
+        let inputs = [input(0, VertexFormat::Vec3), input(3, VertexFormat::Vec2)];
+        assert!(check_vertex_inputs(&[Vertex::vertex_layout()], &inputs).is_ok());
+
+        // Positions in one buffer, the remaining attributes in a second.
+        let split = [
+            VertexBufferLayout::new(12).attribute(0, VertexFormat::Vec3, 0),
+            VertexBufferLayout::new(8).attribute(3, VertexFormat::Vec2, 0),
+        ];
+        assert!(check_vertex_inputs(&split, &inputs).is_ok());
+
+        let missing = check_vertex_inputs(&split[..1], &inputs).unwrap_err();
+        assert!(matches!(missing, PipelineError::VertexInput(m) if m.contains("location 3")));
+
+        let mismatched = [VertexBufferLayout::new(16)
+            .attribute(0, VertexFormat::Vec4, 0)
+            .attribute(3, VertexFormat::Vec2, 0)];
+        let err = check_vertex_inputs(&mismatched, &inputs).unwrap_err();
+        assert!(matches!(err, PipelineError::VertexInput(m) if m.contains("Vec3")));
+
+        let duplicated = [split[0].clone(), split[0].clone(), split[1].clone()];
+        assert!(check_vertex_inputs(&duplicated, &inputs).is_err());
+
+        // Pipelines take a single buffer.
+        assert!(vertex_buffers(Some(split.to_vec()), &inputs).is_err());
+        assert!(vertex_buffers(Some(vec![Vertex::vertex_layout()]), &inputs).is_ok());
+    }
+
+    #[test]
+    fn buffers_parse_from_yaml() {
+        let buffers: Vec<VertexBufferLayout> = serde_yaml::from_str(
+            "- { stride: 12, attributes: [{ location: 0, format: vec3, offset: 0 }] }\n\
+             - { stride: 16, step: instance, attributes: [{ location: 1, format: uvec4, offset: 0 }] }\n",
+        )
+        .unwrap();
+        assert_eq!(
+            buffers[0],
+            VertexBufferLayout::new(12).attribute(0, VertexFormat::Vec3, 0)
+        );
+        assert_eq!(
+            buffers[1],
+            VertexBufferLayout::new(16)
+                .per_instance()
+                .attribute(1, VertexFormat::UVec4, 0)
+        );
+    }
+}
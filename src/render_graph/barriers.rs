@@ -0,0 +1,292 @@
+//! Resource usage tracking and barrier planning for [`RenderGraph`].
+//!
+//! The plan is computed purely on the CPU from each node's declared
+//! [`ResourceUsage`] so it can be inspected in tests without a device. The
+//! renderer applies it by transitioning images before each node records.
+
+use super::{GraphNode, RenderGraph, ResourceDesc};
+use crate::format::FormatClass;
+use dashi::Format;
+use std::collections::HashMap;
+
+/// How a node accesses a graph resource.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ResourceUsage {
+    ColorWrite,
+    DepthWrite,
+    /// Depth testing against an attachment that the pass does not write.
+    DepthRead,
+    ShaderRead,
+    TransferSrc,
+    TransferDst,
+}
+
+/// Image layout required by a [`ResourceUsage`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ImageLayout {
+    Undefined,
+    ColorAttachment,
+    DepthStencilAttachment,
+    ShaderReadOnly,
+    TransferSrc,
+    TransferDst,
+}
+
+impl ResourceUsage {
+    pub fn layout(self) -> ImageLayout {
+        match self {
+            ResourceUsage::ColorWrite => ImageLayout::ColorAttachment,
+            ResourceUsage::DepthWrite | ResourceUsage::DepthRead => {
+                ImageLayout::DepthStencilAttachment
+            }
+            ResourceUsage::ShaderRead => ImageLayout::ShaderReadOnly,
+            ResourceUsage::TransferSrc => ImageLayout::TransferSrc,
+            ResourceUsage::TransferDst => ImageLayout::TransferDst,
+        }
+    }
+
+    pub fn is_write(self) -> bool {
+        matches!(
+            self,
+            ResourceUsage::ColorWrite | ResourceUsage::DepthWrite | ResourceUsage::TransferDst
+        )
+    }
+
+    /// Usage implied by writing an output of the given format.
+    pub fn write_for_format(format: Format) -> Self {
+        if FormatClass::of(format).is_color() {
+            ResourceUsage::ColorWrite
+        } else {
+            ResourceUsage::DepthWrite
+        }
+    }
+}
+
+/// Default usage for a node: outputs are written as attachments and inputs
+/// are sampled.
+pub fn default_resource_usage(
+    inputs: &[ResourceDesc],
+    outputs: &[ResourceDesc],
+) -> Vec<(String, ResourceUsage)> {
+    let mut usage: Vec<(String, ResourceUsage)> = inputs
+        .iter()
+        .map(|r| (r.name.clone(), ResourceUsage::ShaderRead))
+        .collect();
+    usage.extend(
+        outputs
+            .iter()
+            .map(|r| (r.name.clone(), ResourceUsage::write_for_format(r.format))),
+    );
+    usage
+}
+
+/// One node's access to one resource, in execution order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResourceAccess {
+    pub node: String,
+    pub resource: String,
+    pub usage: ResourceUsage,
+}
+
+/// A synchronization point inserted before `node` accesses `resource`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Barrier {
+    pub resource: String,
+    /// Node whose access must complete first, if any.
+    pub src_node: Option<String>,
+    /// Node that the barrier is recorded before.
+    pub node: String,
+    pub src_usage: Option<ResourceUsage>,
+    pub dst_usage: ResourceUsage,
+    pub old_layout: ImageLayout,
+    pub new_layout: ImageLayout,
+}
+
+impl Barrier {
+    /// Whether the barrier changes the image layout.
+    pub fn is_transition(&self) -> bool {
+        self.old_layout != self.new_layout
+    }
+}
+
+/// Ordered list of barriers for one execution of a graph.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BarrierPlan {
+    pub barriers: Vec<Barrier>,
+}
+
+impl BarrierPlan {
+    /// Build a plan from accesses listed in execution order.
+    ///
+    /// A barrier is emitted whenever the layout changes or a write is
+    /// involved. Read-after-read in the same layout needs no synchronization
+    /// and is elided, as are duplicate accesses within a single node.
+    pub fn from_accesses(accesses: &[ResourceAccess]) -> Self {
+        let mut state: HashMap<&str, (&str, ResourceUsage)> = HashMap::new();
+        let mut barriers = Vec::new();
+        for access in accesses {
+            let new_layout = access.usage.layout();
+            match state.get(access.resource.as_str()) {
+                Some(&(node, usage)) if node == access.node && usage == access.usage => continue,
+                Some(&(node, usage)) => {
+                    let read_after_read = !usage.is_write()
+                        && !access.usage.is_write()
+                        && usage.layout() == new_layout;
+                    if !read_after_read {
+                        barriers.push(Barrier {
+                            resource: access.resource.clone(),
+                            src_node: Some(node.to_string()),
+                            node: access.node.clone(),
+                            src_usage: Some(usage),
+                            dst_usage: access.usage,
+                            old_layout: usage.layout(),
+                            new_layout,
+                        });
+                    }
+                }
+                None => barriers.push(Barrier {
+                    resource: access.resource.clone(),
+                    src_node: None,
+                    node: access.node.clone(),
+                    src_usage: None,
+                    dst_usage: access.usage,
+                    old_layout: ImageLayout::Undefined,
+                    new_layout,
+                }),
+            }
+            state.insert(&access.resource, (&access.node, access.usage));
+        }
+        Self { barriers }
+    }
+
+    /// Barriers to record before `node` executes.
+    pub fn before<'a>(&'a self, node: &'a str) -> impl Iterator<Item = &'a Barrier> + 'a {
+        self.barriers.iter().filter(move |b| b.node == node)
+    }
+
+    /// Number of barriers that change an image layout.
+    pub fn transition_count(&self) -> usize {
+        self.barriers.iter().filter(|b| b.is_transition()).count()
+    }
+}
+
+impl RenderGraph {
+    /// Every resource access of the scheduled nodes, in execution order.
+    pub fn resource_accesses(&self) -> Vec<ResourceAccess> {
+        let mut accesses = Vec::new();
+        for idx in self.execution_order() {
+            let node: &dyn GraphNode = self.node(idx);
+            for (resource, usage) in node.resource_usage() {
+                accesses.push(ResourceAccess {
+                    node: node.name().to_string(),
+                    resource,
+                    usage,
+                });
+            }
+        }
+        accesses
+    }
+
+    /// Compute the barriers and layout transitions needed to execute the graph.
+    pub fn barrier_plan(&self) -> BarrierPlan {
+        BarrierPlan::from_accesses(&self.resource_accesses())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_graph::{GraphNodeDesc, SimpleNode};
+
+    fn res(name: &str, format: Format) -> ResourceDesc {
+        ResourceDesc {
+            name: name.into(),
+            format,
+        }
+    }
+
+    fn node(name: &str, inputs: Vec<ResourceDesc>, outputs: Vec<ResourceDesc>) -> SimpleNode {
+        SimpleNode::from(GraphNodeDesc {
+            name: name.into(),
+            inputs,
+            outputs,
+            kind: Default::default(),
+            pipelines: Vec::new(),
+        })
+    }
+
+    fn access(node: &str, resource: &str, usage: ResourceUsage) -> ResourceAccess {
+        ResourceAccess {
+            node: node.into(),
+            resource: resource.into(),
+            usage,
+        }
+    }
+
+    #[test]
+    fn write_then_sample_transitions_layout() {
+        let mut graph = RenderGraph::new();
+        graph.add_node(node(
+            "gbuffer",
+            Vec::new(),
+            vec![res("albedo", Format::RGBA8), res("depth", Format::D24S8)],
+        )).unwrap();
+        graph.add_node(node(
+            "lighting",
+            vec![res("albedo", Format::RGBA8), res("depth", Format::D24S8)],
+            vec![res("hdr", Format::RGBA32F)],
+        )).unwrap();
+        graph.connect("gbuffer", "lighting").unwrap();
+
+        let plan = graph.barrier_plan();
+        let lighting: Vec<_> = plan.before("lighting").collect();
+        assert_eq!(lighting.len(), 3);
+        let albedo = lighting.iter().find(|b| b.resource == "albedo").unwrap();
+        assert_eq!(albedo.src_node.as_deref(), Some("gbuffer"));
+        assert_eq!(albedo.old_layout, ImageLayout::ColorAttachment);
+        assert_eq!(albedo.new_layout, ImageLayout::ShaderReadOnly);
+        let depth = lighting.iter().find(|b| b.resource == "depth").unwrap();
+        assert_eq!(depth.old_layout, ImageLayout::DepthStencilAttachment);
+
+        let first: Vec<_> = plan.before("gbuffer").collect();
+        assert!(first.iter().all(|b| b.old_layout == ImageLayout::Undefined));
+    }
+
+    #[test]
+    fn repeated_reads_are_elided() {
+        let plan = BarrierPlan::from_accesses(&[
+            access("a", "img", ResourceUsage::ColorWrite),
+            access("b", "img", ResourceUsage::ShaderRead),
+            access("c", "img", ResourceUsage::ShaderRead),
+            access("c", "img", ResourceUsage::ShaderRead),
+        ]);
+        assert_eq!(plan.barriers.len(), 2);
+        assert_eq!(plan.before("c").count(), 0);
+    }
+
+    #[test]
+    fn write_after_write_keeps_barrier_without_transition() {
+        let plan = BarrierPlan::from_accesses(&[
+            access("a", "img", ResourceUsage::ColorWrite),
+            access("b", "img", ResourceUsage::ColorWrite),
+            access("c", "img", ResourceUsage::TransferSrc),
+        ]);
+        let b: Vec<_> = plan.before("b").collect();
+        assert_eq!(b.len(), 1);
+        assert!(!b[0].is_transition());
+        assert_eq!(plan.transition_count(), 2);
+    }
+
+    #[test]
+    fn depth_reads_share_the_attachment_layout() {
+        let plan = BarrierPlan::from_accesses(&[
+            access("gbuffer", "depth", ResourceUsage::DepthWrite),
+            access("decals", "depth", ResourceUsage::DepthRead),
+            access("particles", "depth", ResourceUsage::DepthRead),
+        ]);
+        let decals: Vec<_> = plan.before("decals").collect();
+        assert_eq!(decals.len(), 1);
+        assert!(!decals[0].is_transition());
+        assert_eq!(plan.before("particles").count(), 0);
+    }
+}
@@ -1,4 +1,4 @@
-use super::{GraphNode, RenderGraph};
+use super::{GraphNode, GraphValidationError, RenderGraph};
 use crate::canvas::Canvas;
 use dashi::gpu::Format;
 
@@ -8,18 +8,22 @@ use dashi::gpu::Format;
 /// # use koji::render_graph::RenderGraphBuilder;
 /// # use koji::canvas::CanvasBuilder;
 /// # use dashi::gpu::{Context, Format};
-/// # fn build_graph(ctx: &mut Context) -> Result<(), dashi::GPUError> {
+/// # fn build_graph(ctx: &mut Context) -> Result<(), koji::canvas::CanvasError> {
 /// let canvas = CanvasBuilder::new()
 ///     .extent([800, 600])
 ///     .color_attachment("color", Format::RGBA8)
 ///     .build(ctx)?;
 /// let mut builder = RenderGraphBuilder::new();
 /// builder.add_canvas(&canvas);
-/// let graph = builder.build();
+/// let graph = builder.build().expect("valid graph");
 /// # Ok(()) }
 /// ```
+///
+/// Errors from adding or connecting nodes are collected and returned by
+/// [`RenderGraphBuilder::build`], so calls can be chained.
 pub struct RenderGraphBuilder {
     graph: RenderGraph,
+    errors: Vec<GraphValidationError>,
 }
 
 impl RenderGraphBuilder {
@@ -27,35 +31,49 @@ impl RenderGraphBuilder {
     pub fn new() -> Self {
         Self {
             graph: RenderGraph::new(),
+            errors: Vec::new(),
         }
     }
 
     /// Insert a generic [`GraphNode`] into the graph.
     pub fn add_node<N: GraphNode + 'static>(&mut self, node: N) -> &mut Self {
-        self.graph.add_node(node);
+        if let Err(e) = self.graph.add_node(node) {
+            self.errors.push(e);
+        }
         self
     }
 
     /// Add a [`Canvas`] to the graph.
     pub fn add_canvas(&mut self, canvas: &Canvas) -> &mut Self {
-        self.graph.add_canvas(canvas);
+        if let Err(e) = self.graph.add_canvas(canvas) {
+            self.errors.push(e);
+        }
         self
     }
 
     /// Register an external image resource by name and format.
     pub fn register_external_image(&mut self, name: &str, format: Format) -> &mut Self {
-        self.graph.register_external_image(name, format);
+        if let Err(e) = self.graph.register_external_image(name, format) {
+            self.errors.push(e);
+        }
         self
     }
 
     /// Connect two nodes by name.
     pub fn connect(&mut self, from: &str, to: &str) -> &mut Self {
-        self.graph.connect(from, to);
+        if let Err(e) = self.graph.connect(from, to) {
+            self.errors.push(e);
+        }
         self
     }
 
-    /// Finalize and return the constructed [`RenderGraph`].
-    pub fn build(self) -> RenderGraph {
-        self.graph
+    /// Finalize and return the constructed [`RenderGraph`], or every error
+    /// recorded while building it.
+    pub fn build(self) -> Result<RenderGraph, Vec<GraphValidationError>> {
+        if self.errors.is_empty() {
+            Ok(self.graph)
+        } else {
+            Err(self.errors)
+        }
     }
 }
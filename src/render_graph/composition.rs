@@ -1,13 +1,75 @@
-use super::{GraphNode, ResourceDesc};
+use super::{GraphNode, PassContext, ResourceDesc};
+use crate::canvas::{Canvas, CanvasBuilder, CanvasError};
+use crate::material::{PipelineBuilder, PSO};
+use crate::utils::GpuObject;
+use dashi::utils::*;
 use dashi::*;
+use inline_spirv::inline_spirv;
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of inputs a [`CompositionNode`] blends in one pass.
+pub const MAX_COMPOSITION_INPUTS: usize = 8;
 
 /// Describes how multiple inputs are composited.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum BlendMode {
     /// Basic alpha blending of inputs in order.
+    #[default]
     Alpha,
 }
 
+/// An error from creating a [`CompositionNode`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CompositionError {
+    /// More inputs were given than the node blends in one pass.
+    TooManyInputs { count: usize, max: usize },
+}
+
+impl std::fmt::Display for CompositionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompositionError::TooManyInputs { count, max } => write!(
+                f,
+                "composition has {} inputs but blends at most {}",
+                count, max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CompositionError {}
+
+/// GPU objects created the first time a [`CompositionNode`] is recorded.
+struct CompositionState {
+    extent: [u32; 2],
+    target: Canvas,
+    pso: PSO,
+    sampler: Handle<Sampler>,
+    vertices: Handle<Buffer>,
+    blank_image: Handle<Image>,
+    blank: Handle<ImageView>,
+    /// Bind group for the most recently used input views.
+    bind_group: Option<(Vec<Handle<ImageView>>, Handle<BindGroup>)>,
+}
+
+impl CompositionState {
+    fn gpu_objects(&self) -> Vec<GpuObject> {
+        let mut objects = self.target.gpu_objects();
+        objects.extend(self.pso.gpu_objects());
+        objects.extend([
+            GpuObject::Sampler(self.sampler),
+            GpuObject::Buffer(self.vertices),
+            GpuObject::ImageView(self.blank),
+            GpuObject::Image(self.blank_image),
+        ]);
+        if let Some((_, bg)) = self.bind_group {
+            objects.push(GpuObject::BindGroup(bg));
+        }
+        objects
+    }
+}
+
 /// A graph node that composites multiple images into the swapchain image.
 pub struct CompositionNode {
     name: String,
@@ -15,27 +77,148 @@ pub struct CompositionNode {
     inputs: Vec<ResourceDesc>,
     /// Description of how inputs should be blended.
     mode: BlendMode,
-    /// Format of the swapchain image.
+    /// Format of the swapchain image, which the composited image is created
+    /// in as well.
     output: ResourceDesc,
+    state: Option<CompositionState>,
+    /// Objects replaced by a rebuild, handed out by `take_retired`.
+    retired: Vec<GpuObject>,
 }
 
 impl CompositionNode {
     /// Create a new composition node using the given inputs and swapchain format.
-    pub fn new(inputs: Vec<ResourceDesc>, swapchain_format: Format) -> Self {
-        Self {
+    ///
+    /// Fails if there are more than [`MAX_COMPOSITION_INPUTS`] inputs.
+    pub fn new(
+        inputs: Vec<ResourceDesc>,
+        swapchain_format: Format,
+    ) -> Result<Self, CompositionError> {
+        if inputs.len() > MAX_COMPOSITION_INPUTS {
+            return Err(CompositionError::TooManyInputs {
+                count: inputs.len(),
+                max: MAX_COMPOSITION_INPUTS,
+            });
+        }
+        Ok(Self {
             name: "composition".to_string(),
             inputs,
             mode: BlendMode::Alpha,
             output: ResourceDesc { name: "swapchain".into(), format: swapchain_format },
-        }
+            state: None,
+            retired: Vec::new(),
+        })
+    }
+
+    /// Override the default node name of `composition`.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
     }
 
     /// Set the blend mode for compositing.
-    #[allow(dead_code)]
     pub fn blend_mode(mut self, mode: BlendMode) -> Self {
         self.mode = mode;
         self
     }
+
+    pub fn mode(&self) -> BlendMode {
+        self.mode
+    }
+
+    fn init_state(
+        &self,
+        ctx: &mut Context,
+        extent: [u32; 2],
+    ) -> Result<CompositionState, CanvasError> {
+        let target = CanvasBuilder::new()
+            .debug_name("composition")
+            .extent(extent)
+            .color_attachment_with_clear(
+                self.output.name.clone(),
+                self.output.format,
+                [0.0; 4],
+            )
+            .build(ctx)?;
+
+        let vert: &[u32] = inline_spirv!(
+            r"#version 450
+            layout(location=0) in vec2 pos;
+            layout(location=0) out vec2 v_uv;
+            void main() {
+                v_uv = pos * 0.5 + 0.5;
+                gl_Position = vec4(pos, 0.0, 1.0);
+            }",
+            vert
+        );
+        let frag: &[u32] = match self.mode {
+            BlendMode::Alpha => inline_spirv!(
+                r"#version 450
+                layout(set=0, binding=0) uniform sampler2D KOJI_composite_inputs[8];
+                layout(location=0) in vec2 v_uv;
+                layout(location=0) out vec4 out_color;
+                void main() {
+                    vec4 acc = vec4(0.0);
+                    for (int i = 0; i < 8; ++i) {
+                        vec4 c = texture(KOJI_composite_inputs[i], v_uv);
+                        acc.rgb = c.rgb * c.a + acc.rgb * (1.0 - c.a);
+                        acc.a = c.a + acc.a * (1.0 - c.a);
+                    }
+                    out_color = acc;
+                }",
+                frag
+            ),
+        };
+        let pso = PipelineBuilder::new(ctx, "composition_pso")
+            .vertex_shader(vert)
+            .fragment_shader(frag)
+            .render_pass(target.output(&self.output.name))
+            .build();
+
+        let sampler = ctx.make_sampler(&SamplerInfo::default())?;
+        let tri: [[f32; 2]; 3] = [[-1.0, -1.0], [3.0, -1.0], [-1.0, 3.0]];
+        let bytes: &[u8] = bytemuck::cast_slice(&tri);
+        let vertices = ctx.make_buffer(&BufferInfo {
+            debug_name: "composition_vertices",
+            byte_size: bytes.len() as u32,
+            visibility: MemoryVisibility::Gpu,
+            usage: BufferUsage::VERTEX,
+            initial_data: Some(bytes),
+        })?;
+
+        // Unused sampler slots read a transparent texel, which leaves the
+        // accumulated color unchanged.
+        let blank_img = ctx.make_image(&ImageInfo {
+            debug_name: "composition_blank",
+            dim: [1, 1, 1],
+            format: Format::RGBA8,
+            mip_levels: 1,
+            layers: 1,
+            initial_data: Some(&[0u8; 4]),
+        })?;
+        let blank = ctx.make_image_view(&ImageViewInfo {
+            debug_name: "composition_blank",
+            img: blank_img,
+            ..Default::default()
+        })?;
+
+        Ok(CompositionState {
+            extent,
+            target,
+            pso,
+            sampler,
+            vertices,
+            blank_image: blank_img,
+            blank,
+            bind_group: None,
+        })
+    }
+
+    /// View of the composited image, available after the first recorded frame.
+    pub fn target_view(&self) -> Option<Handle<ImageView>> {
+        self.state
+            .as_ref()
+            .and_then(|s| s.target.view(&self.output.name))
+    }
 }
 
 impl GraphNode for CompositionNode {
@@ -43,10 +226,104 @@ impl GraphNode for CompositionNode {
     fn inputs(&self) -> Vec<ResourceDesc> { self.inputs.clone() }
     fn outputs(&self) -> Vec<ResourceDesc> { vec![self.output.clone()] }
     fn execute(&mut self, _ctx: &mut Context) -> Result<(), GPUError> {
-        // In a full implementation, this would record a full-screen draw
-        // that blends all inputs into the swapchain image according to `mode`.
         Ok(())
     }
+    fn record(&mut self, pass: &mut PassContext) -> Result<(), GPUError> {
+        if self.state.as_ref().map(|s| s.extent) != Some(pass.extent) {
+            // The target has one single-sampled color attachment and no
+            // subpasses, so only the GPU calls can fail.
+            let state = self.init_state(pass.ctx, pass.extent).map_err(|err| match err {
+                CanvasError::Gpu(err) => err,
+                err => unreachable!("composition target rejected: {}", err),
+            })?;
+            // Earlier frames in flight may still use the previous objects.
+            if let Some(old) = self.state.replace(state) {
+                self.retired.extend(old.gpu_objects());
+            }
+        }
+        let state = self.state.as_mut().unwrap();
+
+        let views: Vec<Handle<ImageView>> = self
+            .inputs
+            .iter()
+            .filter_map(|i| pass.image(&i.name))
+            .collect();
+        if views.is_empty() {
+            return Ok(());
+        }
+
+        let cached = matches!(&state.bind_group, Some((c, _)) if *c == views);
+        if !cached {
+            let resources: Vec<IndexedResource> = (0..MAX_COMPOSITION_INPUTS)
+                .map(|i| IndexedResource {
+                    resource: ShaderResource::SampledImage(
+                        views.get(i).copied().unwrap_or(state.blank),
+                        state.sampler,
+                    ),
+                    slot: i as u32,
+                })
+                .collect();
+            let bg = pass.ctx.make_indexed_bind_group(&IndexedBindGroupInfo {
+                debug_name: "composition_inputs",
+                layout: state.pso.bind_group_layouts[0].expect("composition layout"),
+                bindings: &[IndexedBindingInfo {
+                    resources: &resources,
+                    binding: 0,
+                }],
+                set: 0,
+                ..Default::default()
+            })?;
+            if let Some((_, old)) = state.bind_group.replace((views, bg)) {
+                self.retired.push(GpuObject::BindGroup(old));
+            }
+        }
+        let bind_group = state.bind_group.as_ref().map(|(_, bg)| *bg).unwrap();
+
+        let target = state.target.target();
+        let attachments: Vec<Attachment> = target.colors.iter().map(|a| a.attachment).collect();
+        let viewport = pass.full_viewport();
+        pass.list.begin_drawing(&DrawBegin {
+            viewport,
+            pipeline: state.pso.pipeline,
+            attachments: &attachments,
+        })?;
+        pass.list.set_viewport(viewport);
+        pass.list.set_scissor(viewport.scissor);
+        pass.list.append(Command::Draw(Draw {
+            count: 3,
+            instance_count: 1,
+            vertices: state.vertices,
+            bind_groups: [Some(bind_group), None, None, None],
+            ..Default::default()
+        }));
+        pass.list.end_drawing()?;
+
+        if let Some(swapchain) = pass.swapchain() {
+            pass.list.blit_image(ImageBlit {
+                src: target.colors[0].attachment.img,
+                dst: swapchain,
+                filter: Filter::Linear,
+                ..Default::default()
+            });
+        }
+        Ok(())
+    }
+    fn take_retired(&mut self) -> Vec<GpuObject> {
+        std::mem::take(&mut self.retired)
+    }
+    fn gpu_objects(&self) -> Vec<GpuObject> {
+        self.state
+            .as_ref()
+            .map(|s| s.gpu_objects())
+            .unwrap_or_default()
+    }
+    fn output_view(&self, name: &str) -> Option<Handle<ImageView>> {
+        if name == self.output.name {
+            self.target_view()
+        } else {
+            None
+        }
+    }
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
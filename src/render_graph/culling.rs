@@ -0,0 +1,197 @@
+//! Output-driven scheduling for [`RenderGraph`].
+//!
+//! Resources can be marked as graph outputs. When at least one output is
+//! marked, only nodes that contribute to a marked output are executed; the
+//! rest are culled. Without marked outputs every node runs.
+
+use super::{GraphValidationError, RenderGraph};
+use petgraph::graph::NodeIndex;
+use petgraph::Direction;
+use std::collections::HashSet;
+
+impl RenderGraph {
+    /// Mark a resource as a required output of the graph.
+    ///
+    /// Fails once [`RenderGraph::allocate_transients`] has run, since the
+    /// shared transient images were planned for the nodes live at that time.
+    pub fn mark_output(&mut self, resource: impl Into<String>) -> Result<(), GraphValidationError> {
+        let resource = resource.into();
+        if self.marked_outputs.contains(&resource) {
+            return Ok(());
+        }
+        self.check_outputs_open(&resource)?;
+        self.marked_outputs.push(resource);
+        Ok(())
+    }
+
+    /// Remove a resource from the set of required outputs. Fails once
+    /// transients are allocated, like [`RenderGraph::mark_output`].
+    pub fn unmark_output(&mut self, resource: &str) -> Result<(), GraphValidationError> {
+        if !self.marked_outputs.iter().any(|r| r == resource) {
+            return Ok(());
+        }
+        self.check_outputs_open(resource)?;
+        self.marked_outputs.retain(|r| r != resource);
+        Ok(())
+    }
+
+    fn check_outputs_open(&self, resource: &str) -> Result<(), GraphValidationError> {
+        if self.transients_allocated {
+            return Err(GraphValidationError::OutputsFixed {
+                resource: resource.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn marked_outputs(&self) -> &[String] {
+        &self.marked_outputs
+    }
+
+    /// Nodes that contribute to a marked output.
+    ///
+    /// A node contributes if it produces a marked resource, or if a
+    /// contributing node depends on it through an edge or by consuming one of
+    /// its outputs.
+    fn live_nodes(&self) -> HashSet<NodeIndex> {
+        if self.marked_outputs.is_empty() {
+            return self.graph.node_indices().collect();
+        }
+        let mut live = HashSet::new();
+        let mut stack: Vec<NodeIndex> = self
+            .graph
+            .node_indices()
+            .filter(|&i| {
+                self.graph[i]
+                    .outputs()
+                    .iter()
+                    .any(|o| self.marked_outputs.contains(&o.name))
+            })
+            .collect();
+        while let Some(idx) = stack.pop() {
+            if !live.insert(idx) {
+                continue;
+            }
+            stack.extend(self.graph.neighbors_directed(idx, Direction::Incoming));
+            for input in self.graph[idx].inputs() {
+                stack.extend(self.graph.node_indices().filter(|&i| {
+                    i != idx && self.graph[i].outputs().iter().any(|o| o.name == input.name)
+                }));
+            }
+        }
+        live
+    }
+
+    /// Node indices to execute, in topological order, with culled nodes removed.
+    pub fn execution_order(&self) -> Vec<NodeIndex> {
+        let live = self.live_nodes();
+        self.topo_indices()
+            .into_iter()
+            .filter(|i| live.contains(i))
+            .collect()
+    }
+
+    /// Names of nodes that do not contribute to any marked output.
+    pub fn culled_nodes(&self) -> Vec<String> {
+        let live = self.live_nodes();
+        self.topo_indices()
+            .into_iter()
+            .filter(|i| !live.contains(i))
+            .map(|i| self.graph[i].name().to_string())
+            .collect()
+    }
+
+    /// Whether the named node is culled. Unknown names are reported as culled.
+    pub fn is_culled(&self, node: &str) -> bool {
+        match self.indices.get(node) {
+            Some(idx) => !self.live_nodes().contains(idx),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::render_graph::{
+        GraphNodeDesc, GraphValidationError, RenderGraph, ResourceDesc, SimpleNode,
+    };
+    use dashi::Format;
+
+    fn node(name: &str, inputs: &[&str], outputs: &[&str]) -> SimpleNode {
+        let res = |n: &&str| ResourceDesc {
+            name: n.to_string(),
+            format: Format::RGBA8,
+        };
+        SimpleNode::from(GraphNodeDesc {
+            name: name.into(),
+            inputs: inputs.iter().map(res).collect(),
+            outputs: outputs.iter().map(res).collect(),
+            kind: Default::default(),
+            pipelines: Vec::new(),
+        })
+    }
+
+    fn graph() -> RenderGraph {
+        let mut graph = RenderGraph::new();
+        graph.add_node(node("scene", &[], &["color"])).unwrap();
+        graph.add_node(node("debug", &["color"], &["debug_view"])).unwrap();
+        graph.add_node(node("post", &["color"], &["final"])).unwrap();
+        graph.connect("scene", "debug").unwrap();
+        graph.connect("scene", "post").unwrap();
+        graph
+    }
+
+    #[test]
+    fn nothing_is_culled_without_marked_outputs() {
+        let graph = graph();
+        assert!(graph.culled_nodes().is_empty());
+        assert_eq!(graph.execution_order().len(), 3);
+    }
+
+    #[test]
+    fn unused_branch_is_culled() {
+        let mut graph = graph();
+        graph.mark_output("final").unwrap();
+        assert_eq!(graph.culled_nodes(), vec!["debug".to_string()]);
+        assert!(graph.is_culled("debug"));
+        assert!(!graph.is_culled("scene"));
+        let order: Vec<_> = graph
+            .execution_order()
+            .into_iter()
+            .map(|i| graph.node(i).name().to_string())
+            .collect();
+        assert_eq!(order, vec!["scene".to_string(), "post".to_string()]);
+
+        graph.mark_output("debug_view").unwrap();
+        assert!(graph.culled_nodes().is_empty());
+        graph.unmark_output("debug_view").unwrap();
+        assert!(graph.is_culled("debug"));
+    }
+
+    #[test]
+    fn resource_consumers_keep_producers_without_edges() {
+        let mut graph = RenderGraph::new();
+        graph.add_node(node("shadow", &[], &["shadow_map"])).unwrap();
+        graph.add_node(node("lit", &["shadow_map"], &["final"])).unwrap();
+        graph.mark_output("final").unwrap();
+        assert!(graph.culled_nodes().is_empty());
+    }
+
+    #[test]
+    fn outputs_are_fixed_once_transients_are_allocated() {
+        let mut graph = graph();
+        graph.mark_output("final").unwrap();
+        graph.transients_allocated = true;
+        assert_eq!(
+            graph.mark_output("debug_view"),
+            Err(GraphValidationError::OutputsFixed {
+                resource: "debug_view".into()
+            })
+        );
+        assert!(graph.unmark_output("final").is_err());
+        // Repeating the current state changes nothing and is allowed.
+        graph.mark_output("final").unwrap();
+        graph.unmark_output("debug_view").unwrap();
+        assert!(graph.is_culled("debug"));
+    }
+}
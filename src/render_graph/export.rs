@@ -0,0 +1,269 @@
+//! Text exports of a [`RenderGraph`] for debugging and review.
+//!
+//! [`to_dot`] emits Graphviz and [`to_mermaid`] emits a Mermaid flowchart.
+//! Nodes are styled by kind, and edges are labelled with the resources they
+//! carry. Resources whose formats disagree between producer and consumer are
+//! drawn in red. Output follows node and edge insertion order, so exports of
+//! the same graph diff cleanly.
+
+use super::{
+    CanvasNode, CompositionNode, ExternalImageNode, GraphNode, RenderGraph, RenderPassNode,
+};
+use dashi::Format;
+use petgraph::visit::EdgeRef;
+use std::fmt::Write;
+
+/// Kind of a graph node as shown in exports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    Canvas,
+    RenderPass,
+    ExternalImage,
+    Composition,
+    Simple,
+}
+
+impl NodeKind {
+    pub fn of(node: &dyn GraphNode) -> Self {
+        let any = node.as_any();
+        if any.is::<CanvasNode>() {
+            NodeKind::Canvas
+        } else if any.is::<RenderPassNode>() {
+            NodeKind::RenderPass
+        } else if any.is::<ExternalImageNode>() {
+            NodeKind::ExternalImage
+        } else if any.is::<CompositionNode>() {
+            NodeKind::Composition
+        } else {
+            NodeKind::Simple
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            NodeKind::Canvas => "CanvasNode",
+            NodeKind::RenderPass => "RenderPassNode",
+            NodeKind::ExternalImage => "ExternalImageNode",
+            NodeKind::Composition => "CompositionNode",
+            NodeKind::Simple => "SimpleNode",
+        }
+    }
+
+    fn dot_style(self) -> &'static str {
+        match self {
+            NodeKind::Canvas => "shape=box, style=filled, fillcolor=lightblue",
+            NodeKind::RenderPass => "shape=box, style=\"rounded,filled\", fillcolor=lightyellow",
+            NodeKind::ExternalImage => "shape=cylinder, style=filled, fillcolor=lightgrey",
+            NodeKind::Composition => "shape=hexagon, style=filled, fillcolor=palegreen",
+            NodeKind::Simple => "shape=ellipse",
+        }
+    }
+
+    fn mermaid_shape(self, id: &str, label: &str) -> String {
+        match self {
+            NodeKind::Canvas => format!("{}[\"{}\"]", id, label),
+            NodeKind::RenderPass => format!("{}(\"{}\")", id, label),
+            NodeKind::ExternalImage => format!("{}[(\"{}\")]", id, label),
+            NodeKind::Composition => format!("{}{{{{\"{}\"}}}}", id, label),
+            NodeKind::Simple => format!("{}([\"{}\"])", id, label),
+        }
+    }
+
+    fn mermaid_class(self) -> &'static str {
+        match self {
+            NodeKind::Canvas => "canvas",
+            NodeKind::RenderPass => "renderpass",
+            NodeKind::ExternalImage => "external",
+            NodeKind::Composition => "composition",
+            NodeKind::Simple => "simple",
+        }
+    }
+}
+
+/// A resource carried along an edge.
+struct EdgeResource {
+    name: String,
+    produced: Format,
+    consumed: Format,
+}
+
+impl EdgeResource {
+    fn mismatched(&self) -> bool {
+        self.produced != self.consumed
+    }
+
+    fn label(&self) -> String {
+        if self.mismatched() {
+            format!("{} ({:?} != {:?})", self.name, self.produced, self.consumed)
+        } else {
+            format!("{} ({:?})", self.name, self.produced)
+        }
+    }
+}
+
+struct ExportEdge {
+    from: usize,
+    to: usize,
+    resources: Vec<EdgeResource>,
+}
+
+impl ExportEdge {
+    fn label(&self) -> String {
+        self.resources
+            .iter()
+            .map(EdgeResource::label)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn mismatched(&self) -> bool {
+        self.resources.iter().any(EdgeResource::mismatched)
+    }
+}
+
+fn export_edges(graph: &RenderGraph) -> Vec<ExportEdge> {
+    graph
+        .graph
+        .edge_references()
+        .map(|e| {
+            let src = &graph.graph[e.source()];
+            let dst = &graph.graph[e.target()];
+            let inputs = dst.inputs();
+            let resources = src
+                .outputs()
+                .into_iter()
+                .filter_map(|out| {
+                    inputs.iter().find(|i| i.name == out.name).map(|inp| EdgeResource {
+                        name: out.name.clone(),
+                        produced: out.format,
+                        consumed: inp.format,
+                    })
+                })
+                .collect();
+            ExportEdge {
+                from: e.source().index(),
+                to: e.target().index(),
+                resources,
+            }
+        })
+        .collect()
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Render the graph in Graphviz DOT format.
+pub fn to_dot(graph: &RenderGraph) -> String {
+    let mut out = String::from("digraph render_graph {\n    rankdir=LR;\n");
+    for idx in graph.graph.node_indices() {
+        let node = &graph.graph[idx];
+        let kind = NodeKind::of(node.as_ref());
+        let _ = writeln!(
+            out,
+            "    n{} [label=\"{}\\n<{}>\", {}];",
+            idx.index(),
+            escape(node.name()),
+            kind.label(),
+            kind.dot_style()
+        );
+    }
+    for edge in export_edges(graph) {
+        let color = if edge.mismatched() { ", color=red, fontcolor=red, penwidth=2" } else { "" };
+        let _ = writeln!(
+            out,
+            "    n{} -> n{} [label=\"{}\"{}];",
+            edge.from,
+            edge.to,
+            escape(&edge.label()),
+            color
+        );
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Render the graph as a Mermaid flowchart.
+pub fn to_mermaid(graph: &RenderGraph) -> String {
+    let mut out = String::from("flowchart LR\n");
+    for idx in graph.graph.node_indices() {
+        let node = &graph.graph[idx];
+        let kind = NodeKind::of(node.as_ref());
+        let id = format!("n{}", idx.index());
+        let label = format!("{}<br/>&lt;{}&gt;", node.name().replace('"', "#quot;"), kind.label());
+        let _ = writeln!(out, "    {}:::{}", kind.mermaid_shape(&id, &label), kind.mermaid_class());
+    }
+    let mut mismatched = Vec::new();
+    for (i, edge) in export_edges(graph).iter().enumerate() {
+        let label = edge.label();
+        if label.is_empty() {
+            let _ = writeln!(out, "    n{} --> n{}", edge.from, edge.to);
+        } else {
+            let _ = writeln!(
+                out,
+                "    n{} -->|\"{}\"| n{}",
+                edge.from,
+                label.replace('"', "#quot;"),
+                edge.to
+            );
+        }
+        if edge.mismatched() {
+            mismatched.push(i);
+        }
+    }
+    out.push_str("    classDef canvas fill:#add8e6\n");
+    out.push_str("    classDef renderpass fill:#ffffe0\n");
+    out.push_str("    classDef external fill:#d3d3d3\n");
+    out.push_str("    classDef composition fill:#98fb98\n");
+    out.push_str("    classDef simple fill:#ffffff\n");
+    for i in mismatched {
+        let _ = writeln!(out, "    linkStyle {} stroke:red,stroke-width:2px,color:red", i);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_graph::{GraphNodeDesc, ResourceDesc, SimpleNode};
+
+    fn graph() -> RenderGraph {
+        let mut graph = RenderGraph::new();
+        graph.add_node(ExternalImageNode::new("env", Format::RGBA8)).unwrap();
+        graph
+            .add_node(SimpleNode::from(GraphNodeDesc {
+                name: "sky".into(),
+                inputs: vec![ResourceDesc {
+                    name: "env".into(),
+                    format: Format::RGBA32F,
+                }],
+                outputs: vec![ResourceDesc {
+                    name: "color".into(),
+                    format: Format::RGBA8,
+                }],
+                kind: Default::default(),
+                pipelines: Vec::new(),
+            }))
+            .unwrap();
+        graph.connect("env", "sky").unwrap();
+        graph
+    }
+
+    #[test]
+    fn dot_labels_nodes_and_highlights_mismatches() {
+        let dot = to_dot(&graph());
+        assert!(dot.starts_with("digraph render_graph {"));
+        assert!(dot.contains("n0 [label=\"env\\n<ExternalImageNode>\", shape=cylinder"));
+        assert!(dot.contains("n1 [label=\"sky\\n<SimpleNode>\""));
+        assert!(dot.contains("n0 -> n1 [label=\"env (RGBA8 != RGBA32F)\", color=red"));
+    }
+
+    #[test]
+    fn mermaid_labels_edges_and_styles_mismatches() {
+        let mermaid = to_mermaid(&graph());
+        assert!(mermaid.starts_with("flowchart LR\n"));
+        assert!(mermaid.contains("n0[(\"env<br/>&lt;ExternalImageNode&gt;\")]:::external"));
+        assert!(mermaid.contains("n0 -->|\"env (RGBA8 != RGBA32F)\"| n1"));
+        assert!(mermaid.contains("linkStyle 0 stroke:red"));
+    }
+}
@@ -1,4 +1,4 @@
-use super::{RenderGraph, SerializableRenderGraph};
+use super::{GraphError, NodeRegistry, RenderGraph, SerializableRenderGraph};
 use dashi::gpu::Context;
 
 pub fn to_yaml(graph: &RenderGraph) -> Result<String, serde_yaml::Error> {
@@ -6,10 +6,19 @@ pub fn to_yaml(graph: &RenderGraph) -> Result<String, serde_yaml::Error> {
     serde_yaml::to_string(&desc)
 }
 
-pub fn from_yaml(ctx: &mut Context, data: &str) -> Result<RenderGraph, String> {
+pub fn from_yaml(ctx: &mut Context, data: &str) -> Result<RenderGraph, GraphError> {
+    from_yaml_with_registry(ctx, data, &NodeRegistry::new())
+}
+
+/// Load a YAML graph, building custom nodes with the factories in `registry`.
+pub fn from_yaml_with_registry(
+    ctx: &mut Context,
+    data: &str,
+    registry: &NodeRegistry,
+) -> Result<RenderGraph, GraphError> {
     let desc: SerializableRenderGraph =
-        serde_yaml::from_str(data).map_err(|e| e.to_string())?;
-    RenderGraph::from_desc(desc, ctx).map_err(|e| format!("{:?}", e))
+        serde_yaml::from_str(data).map_err(|e| GraphError::Desc(e.to_string()))?;
+    RenderGraph::from_desc_with_registry(desc, ctx, registry)
 }
 
 pub fn to_json(graph: &RenderGraph) -> Result<String, serde_json::Error> {
@@ -17,8 +26,17 @@ pub fn to_json(graph: &RenderGraph) -> Result<String, serde_json::Error> {
     serde_json::to_string_pretty(&desc)
 }
 
-pub fn from_json(ctx: &mut Context, data: &str) -> Result<RenderGraph, String> {
+pub fn from_json(ctx: &mut Context, data: &str) -> Result<RenderGraph, GraphError> {
+    from_json_with_registry(ctx, data, &NodeRegistry::new())
+}
+
+/// Load a JSON graph, building custom nodes with the factories in `registry`.
+pub fn from_json_with_registry(
+    ctx: &mut Context,
+    data: &str,
+    registry: &NodeRegistry,
+) -> Result<RenderGraph, GraphError> {
     let desc: SerializableRenderGraph =
-        serde_json::from_str(data).map_err(|e| e.to_string())?;
-    RenderGraph::from_desc(desc, ctx).map_err(|e| format!("{:?}", e))
+        serde_json::from_str(data).map_err(|e| GraphError::Desc(e.to_string()))?;
+    RenderGraph::from_desc_with_registry(desc, ctx, registry)
 }
@@ -9,23 +9,44 @@
 //! [`Canvas`] and can be inserted via [`RenderGraph::add_canvas`]. Canvas
 //! attachments become graph outputs for
 //! pipeline creation.
+//!
+//! Each frame the renderer walks the graph in topological order and calls
+//! [`GraphNode::record`] with a [`PassContext`], letting nodes record their
+//! own commands into the frame command list. Resources marked with
+//! [`RenderGraph::mark_output`] drive scheduling: nodes that do not contribute
+//! to a marked output are culled and skipped.
 
 use dashi::utils::*;
 use dashi::*;
-use petgraph::algo::is_cyclic_directed;
 use petgraph::graph::DiGraph;
 use petgraph::visit::{EdgeRef, Topo};
 pub use petgraph::graph::NodeIndex;
 use std::collections::HashMap;
 
-use crate::canvas::{Canvas, CanvasDesc};
+use crate::canvas::{Canvas, CanvasDesc, RetiredAttachment};
+use crate::utils::GpuObject;
 use dashi::gpu::RenderPass;
 use serde::{Deserialize, Serialize};
 
 mod composition;
 pub use composition::*;
+mod pass;
+pub use pass::*;
+mod barriers;
+pub use barriers::*;
+mod transient;
+pub use transient::*;
+mod culling;
+mod registry;
+pub use registry::*;
+mod pipelines;
+pub use pipelines::*;
+mod validation;
+pub use validation::*;
 pub mod io;
 pub use io::*;
+pub mod export;
+pub use export::*;
 pub mod builder;
 pub use builder::*;
 
@@ -40,14 +61,71 @@ pub trait GraphNode {
     fn inputs(&self) -> Vec<ResourceDesc>;
     fn outputs(&self) -> Vec<ResourceDesc>;
     fn execute(&mut self, ctx: &mut Context) -> Result<(), GPUError>;
+    /// Record this node's GPU work into the frame command list.
+    ///
+    /// Called by the renderer once per frame in topological order. The
+    /// default implementation records nothing.
+    fn record(&mut self, _pass: &mut PassContext) -> Result<(), GPUError> {
+        Ok(())
+    }
+    /// How this node accesses each of its resources, used to plan barriers.
+    ///
+    /// Defaults to writing every output as an attachment and sampling every
+    /// input.
+    fn resource_usage(&self) -> Vec<(String, ResourceUsage)> {
+        default_resource_usage(&self.inputs(), &self.outputs())
+    }
+    /// View of an output image owned by this node, if it has one.
+    fn output_view(&self, _name: &str) -> Option<Handle<ImageView>> {
+        None
+    }
+    /// Type name and parameters used to serialize a node defined outside this
+    /// crate. Nodes returning `Some` are rebuilt on load by the factory
+    /// registered under that name in a [`NodeRegistry`].
+    fn custom_desc(&self) -> Option<CustomNodeDesc> {
+        None
+    }
+    /// Reallocate images that depend on the output size. Returns the images
+    /// that were replaced so the caller can destroy them once unused.
+    fn resize(
+        &mut self,
+        _ctx: &mut Context,
+        _output: [u32; 2],
+    ) -> Result<Vec<RetiredAttachment>, GPUError> {
+        Ok(Vec::new())
+    }
+    /// Point attachments borrowed from other nodes, such as read-only depth,
+    /// at the images that now back them. Called after the graph is resized,
+    /// with the image and view of every node-owned resource.
+    fn rebind_borrowed(
+        &mut self,
+        _images: &HashMap<String, (Handle<Image>, Handle<ImageView>)>,
+    ) {
+    }
+    /// GPU objects this node replaced since the last call, such as those of
+    /// a rebuild while recording. The renderer destroys them once the frames
+    /// in flight are done with them.
+    fn take_retired(&mut self) -> Vec<GpuObject> {
+        Vec::new()
+    }
+    /// GPU objects owned by this node, released when its graph is replaced.
+    /// Borrowed objects, such as external images, are not included.
+    fn gpu_objects(&self) -> Vec<GpuObject> {
+        Vec::new()
+    }
     fn as_any(&self) -> &dyn std::any::Any;
 }
 
+/// Callback recording the commands of a [`RenderPassNode`].
+pub type PassRecorder =
+    Box<dyn FnMut(&mut PassContext, Handle<RenderPass>) -> Result<(), GPUError>>;
+
 pub struct RenderPassNode {
     name: String,
     pass: Handle<RenderPass>,
     inputs: Vec<ResourceDesc>,
     outputs: Vec<ResourceDesc>,
+    recorder: Option<PassRecorder>,
 }
 
 impl RenderPassNode {
@@ -62,9 +140,23 @@ impl RenderPassNode {
             pass,
             inputs,
             outputs,
+            recorder: None,
         }
     }
 
+    /// Set the callback that records this pass each frame.
+    pub fn recorder<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&mut PassContext, Handle<RenderPass>) -> Result<(), GPUError> + 'static,
+    {
+        self.recorder = Some(Box::new(f));
+        self
+    }
+
+    pub fn pass(&self) -> Handle<RenderPass> {
+        self.pass
+    }
+
     pub fn geometry(pass: Handle<RenderPass>) -> Self {
         Self::new("geometry", pass, Vec::new(), Vec::new())
     }
@@ -85,10 +177,14 @@ impl GraphNode for RenderPassNode {
         self.outputs.clone()
     }
     fn execute(&mut self, _ctx: &mut Context) -> Result<(), GPUError> {
-        // In a full implementation this would record commands for the render pass
-        let _ = self.pass; // silence unused field
         Ok(())
     }
+    fn record(&mut self, pass: &mut PassContext) -> Result<(), GPUError> {
+        match self.recorder.as_mut() {
+            Some(recorder) => recorder(pass, self.pass),
+            None => Ok(()),
+        }
+    }
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
@@ -97,6 +193,7 @@ impl GraphNode for RenderPassNode {
 pub struct ExternalImageNode {
     name: String,
     format: Format,
+    view: Option<Handle<ImageView>>,
 }
 
 impl ExternalImageNode {
@@ -104,6 +201,16 @@ impl ExternalImageNode {
         Self {
             name: name.into(),
             format,
+            view: None,
+        }
+    }
+
+    /// Create an external image backed by an existing view.
+    pub fn with_view(name: impl Into<String>, format: Format, view: Handle<ImageView>) -> Self {
+        Self {
+            name: name.into(),
+            format,
+            view: Some(view),
         }
     }
 }
@@ -124,6 +231,17 @@ impl GraphNode for ExternalImageNode {
     fn execute(&mut self, _ctx: &mut Context) -> Result<(), GPUError> {
         Ok(())
     }
+    /// External images are provided ready to be sampled.
+    fn resource_usage(&self) -> Vec<(String, ResourceUsage)> {
+        vec![(self.name.clone(), ResourceUsage::ShaderRead)]
+    }
+    fn output_view(&self, name: &str) -> Option<Handle<ImageView>> {
+        if name == self.name {
+            self.view
+        } else {
+            None
+        }
+    }
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
@@ -132,6 +250,7 @@ impl GraphNode for ExternalImageNode {
 pub struct CanvasNode {
     name: String,
     canvas: Canvas,
+    sampled: Vec<ResourceDesc>,
 }
 
 impl CanvasNode {
@@ -139,9 +258,20 @@ impl CanvasNode {
         Self {
             name: name.into(),
             canvas,
+            sampled: Vec::new(),
         }
     }
 
+    /// Declare an image produced by another node that this canvas's
+    /// pipelines sample, so it is transitioned for shader reads first.
+    pub fn sample(mut self, name: impl Into<String>, format: Format) -> Self {
+        self.sampled.push(ResourceDesc {
+            name: name.into(),
+            format,
+        });
+        self
+    }
+
     pub fn canvas(&self) -> &Canvas {
         &self.canvas
     }
@@ -157,8 +287,21 @@ impl GraphNode for CanvasNode {
     fn name(&self) -> &str {
         &self.name
     }
+    /// A read-only depth attachment is consumed from the canvas that writes
+    /// it, as are the images declared with [`CanvasNode::sample`].
     fn inputs(&self) -> Vec<ResourceDesc> {
-        Vec::new()
+        let target = self.canvas.target();
+        let mut ins: Vec<ResourceDesc> = target
+            .depth
+            .iter()
+            .filter(|d| d.read_only)
+            .map(|d| ResourceDesc {
+                name: d.name.clone(),
+                format: d.format,
+            })
+            .collect();
+        ins.extend(self.sampled.iter().cloned());
+        ins
     }
     fn outputs(&self) -> Vec<ResourceDesc> {
         let target = self.canvas.target();
@@ -169,7 +312,7 @@ impl GraphNode for CanvasNode {
                 format: att.format,
             });
         }
-        if let Some(depth) = &target.depth {
+        if let Some(depth) = target.depth.as_ref().filter(|d| !d.read_only) {
             outs.push(ResourceDesc {
                 name: depth.name.clone(),
                 format: depth.format,
@@ -177,9 +320,42 @@ impl GraphNode for CanvasNode {
         }
         outs
     }
+    fn resource_usage(&self) -> Vec<(String, ResourceUsage)> {
+        let mut usage = default_resource_usage(&self.sampled, &self.outputs());
+        usage.extend(
+            self.inputs()
+                .into_iter()
+                .filter(|r| self.sampled.iter().all(|s| s.name != r.name))
+                .map(|r| (r.name, ResourceUsage::DepthRead)),
+        );
+        usage
+    }
     fn execute(&mut self, _ctx: &mut Context) -> Result<(), GPUError> {
         Ok(())
     }
+    fn output_view(&self, name: &str) -> Option<Handle<ImageView>> {
+        self.canvas.view(name)
+    }
+    fn resize(
+        &mut self,
+        ctx: &mut Context,
+        output: [u32; 2],
+    ) -> Result<Vec<RetiredAttachment>, GPUError> {
+        self.canvas.resize(ctx, output)
+    }
+    fn rebind_borrowed(
+        &mut self,
+        images: &HashMap<String, (Handle<Image>, Handle<ImageView>)>,
+    ) {
+        for input in self.inputs() {
+            if let Some(&(image, view)) = images.get(&input.name) {
+                self.canvas.rebind_read_only(&input.name, image, view);
+            }
+        }
+    }
+    fn gpu_objects(&self) -> Vec<GpuObject> {
+        self.canvas.gpu_objects()
+    }
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
@@ -188,6 +364,14 @@ impl GraphNode for CanvasNode {
 pub struct RenderGraph {
     graph: DiGraph<Box<dyn GraphNode>, ()>,
     indices: HashMap<String, NodeIndex>,
+    transients: Vec<TransientDesc>,
+    transient_views: HashMap<String, Handle<ImageView>>,
+    transient_images: Vec<(Handle<Image>, Handle<ImageView>)>,
+    /// Set by [`RenderGraph::allocate_transients`]. The shared images were
+    /// planned for the marked outputs, which are fixed from then on.
+    transients_allocated: bool,
+    marked_outputs: Vec<String>,
+    pipelines: HashMap<String, Vec<GraphPipelineDesc>>,
 }
 
 /// Helper referencing an output image of a render graph node.
@@ -201,6 +385,12 @@ pub struct GraphNodeDesc {
     pub name: String,
     pub inputs: Vec<ResourceDesc>,
     pub outputs: Vec<ResourceDesc>,
+    /// Concrete node type; older files without it load as [`SimpleNode`].
+    #[serde(default)]
+    pub kind: GraphNodeKind,
+    /// Pipelines drawing into this node's outputs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pipelines: Vec<GraphPipelineDesc>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -215,6 +405,11 @@ pub struct SerializableRenderGraph {
     #[serde(default)]
     pub canvases: Vec<CanvasNodeDesc>,
     pub edges: Vec<(String, String)>,
+    #[serde(default)]
+    pub transients: Vec<TransientDesc>,
+    /// Resources marked as graph outputs; see [`RenderGraph::mark_output`].
+    #[serde(default)]
+    pub outputs: Vec<String>,
 }
 
 pub struct SimpleNode {
@@ -256,6 +451,12 @@ impl RenderGraph {
         Self {
             graph: DiGraph::new(),
             indices: HashMap::new(),
+            transients: Vec::new(),
+            transient_views: HashMap::new(),
+            transient_images: Vec::new(),
+            transients_allocated: false,
+            marked_outputs: Vec::new(),
+            pipelines: HashMap::new(),
         }
     }
 
@@ -264,24 +465,75 @@ impl RenderGraph {
         GraphOutput { graph: self, name }
     }
 
-    pub fn add_node<N: GraphNode + 'static>(&mut self, node: N) {
+    /// Insert a node. Fails if another node already uses the same name.
+    pub fn add_node<N: GraphNode + 'static>(
+        &mut self,
+        node: N,
+    ) -> Result<(), GraphValidationError> {
+        self.add_boxed_node(Box::new(node))
+    }
+
+    /// Insert an already boxed node, such as one built by a [`NodeRegistry`].
+    pub fn add_boxed_node(
+        &mut self,
+        node: Box<dyn GraphNode>,
+    ) -> Result<(), GraphValidationError> {
         let name = node.name().to_string();
-        let idx = self.graph.add_node(Box::new(node));
+        if self.indices.contains_key(&name) {
+            return Err(GraphValidationError::DuplicateNode { name });
+        }
+        let idx = self.graph.add_node(node);
         self.indices.insert(name, idx);
+        Ok(())
     }
 
-    pub fn connect(&mut self, from: &str, to: &str) {
-        if let (Some(&a), Some(&b)) = (self.indices.get(from), self.indices.get(to)) {
-            self.graph.add_edge(a, b, ());
+    /// Remove the node called `name`, its edges and its pipeline
+    /// declarations, and return it. The caller owns the GPU objects of the
+    /// returned node.
+    pub fn remove_node(&mut self, name: &str) -> Option<Box<dyn GraphNode>> {
+        let idx = self.indices.remove(name)?;
+        let node = self.graph.remove_node(idx)?;
+        // petgraph moves the last node into the freed index.
+        if let Some(moved) = self.graph.node_weight(idx) {
+            self.indices.insert(moved.name().to_string(), idx);
         }
+        self.pipelines.remove(name);
+        Some(node)
+    }
+
+    /// Add an edge so `to` runs after `from`. Both nodes must already exist.
+    pub fn connect(&mut self, from: &str, to: &str) -> Result<(), GraphValidationError> {
+        let unknown = |name: &str| GraphValidationError::UnknownNode {
+            name: name.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+        };
+        let a = *self.indices.get(from).ok_or_else(|| unknown(from))?;
+        let b = *self.indices.get(to).ok_or_else(|| unknown(to))?;
+        self.graph.add_edge(a, b, ());
+        Ok(())
     }
 
-    pub fn register_external_image(&mut self, name: &str, format: Format) {
-        self.add_node(ExternalImageNode::new(name, format));
+    pub fn register_external_image(
+        &mut self,
+        name: &str,
+        format: Format,
+    ) -> Result<(), GraphValidationError> {
+        self.add_node(ExternalImageNode::new(name, format))
     }
 
-    pub fn add_canvas(&mut self, canvas: &Canvas) {
-        self.add_node(CanvasNode::from(canvas));
+    /// Register an external image together with the view passes should read.
+    pub fn register_external_image_view(
+        &mut self,
+        name: &str,
+        format: Format,
+        view: Handle<ImageView>,
+    ) -> Result<(), GraphValidationError> {
+        self.add_node(ExternalImageNode::with_view(name, format, view))
+    }
+
+    pub fn add_canvas(&mut self, canvas: &Canvas) -> Result<(), GraphValidationError> {
+        self.add_node(CanvasNode::from(canvas))
     }
 
     /// Retrieve all canvases present in the graph.
@@ -297,6 +549,63 @@ impl RenderGraph {
             .collect()
     }
 
+    /// Resize every node that depends on the output size, such as canvases
+    /// built with [`CanvasBuilder::relative_extent`](crate::CanvasBuilder::relative_extent).
+    pub fn resize(
+        &mut self,
+        ctx: &mut Context,
+        output: [u32; 2],
+    ) -> Result<Vec<RetiredAttachment>, GPUError> {
+        let mut retired = Vec::new();
+        for idx in self.graph.node_indices() {
+            retired.extend(self.graph[idx].resize(ctx, output)?);
+        }
+        if !retired.is_empty() {
+            let mut images = HashMap::new();
+            for cn in self
+                .graph
+                .node_weights()
+                .filter_map(|n| n.as_any().downcast_ref::<CanvasNode>())
+            {
+                for out in cn.outputs() {
+                    if let (Some(image), Some(view)) =
+                        (cn.canvas().image(&out.name), cn.canvas().view(&out.name))
+                    {
+                        images.insert(out.name, (image, view));
+                    }
+                }
+            }
+            for idx in self.graph.node_indices() {
+                self.graph[idx].rebind_borrowed(&images);
+            }
+        }
+        Ok(retired)
+    }
+
+    /// Collect the GPU objects every node replaced since the last call, see
+    /// [`GraphNode::take_retired`].
+    pub fn take_retired(&mut self) -> Vec<GpuObject> {
+        self.graph
+            .node_weights_mut()
+            .flat_map(|n| n.take_retired())
+            .collect()
+    }
+
+    /// Every GPU object the graph owns: node canvases and state, objects
+    /// nodes replaced earlier, and transient images. Used to release a graph
+    /// that is being replaced or failed to load.
+    pub fn into_gpu_objects(mut self) -> Vec<GpuObject> {
+        let mut objects = self.take_retired();
+        for node in self.graph.node_weights() {
+            objects.extend(node.gpu_objects());
+        }
+        for (image, view) in self.transient_images {
+            objects.push(GpuObject::ImageView(view));
+            objects.push(GpuObject::Image(image));
+        }
+        objects
+    }
+
     /// Return node indices in topological order.
     pub fn topo_indices(&self) -> Vec<NodeIndex> {
         let mut topo = Topo::new(&self.graph);
@@ -335,41 +644,62 @@ impl RenderGraph {
         None
     }
 
-    /// Retrieve the name of the node that produces the specified output resource.
-    pub fn node_name_for_output(&self, output: &str) -> Option<String> {
+    /// Samples per texel of the canvas producing `output`, or 1 if it is
+    /// not produced by a canvas.
+    pub fn samples_for_output(&self, output: &str) -> u32 {
+        self.canvas_for_output(output).map_or(1, |c| c.samples())
+    }
+
+    /// Canvas that writes `output`, if any.
+    pub fn canvas_for_output(&self, output: &str) -> Option<&Canvas> {
+        self.graph
+            .node_weights()
+            .filter_map(|n| n.as_any().downcast_ref::<CanvasNode>())
+            .find(|cn| cn.outputs().iter().any(|o| o.name == output))
+            .map(|cn| cn.canvas())
+    }
+
+    /// Look up the image view backing a graph resource.
+    pub fn image_view(&self, name: &str) -> Option<Handle<ImageView>> {
+        self.graph
+            .node_indices()
+            .find_map(|i| self.graph[i].output_view(name))
+            .or_else(|| self.transient_view(name))
+    }
+
+    /// Map every graph resource that has a backing image to its view.
+    pub fn image_views(&self) -> HashMap<String, Handle<ImageView>> {
+        let mut views = HashMap::new();
         for idx in self.graph.node_indices() {
             let node = &self.graph[idx];
             for out in node.outputs() {
-                if out.name == output {
-                    return Some(node.name().to_string());
+                if let Some(view) = node.output_view(&out.name) {
+                    views.insert(out.name, view);
                 }
             }
         }
-        None
+        for (name, view) in &self.transient_views {
+            views.entry(name.clone()).or_insert(*view);
+        }
+        views
     }
 
-    pub fn validate(&self) -> Result<(), String> {
-        if is_cyclic_directed(&self.graph) {
-            return Err("Render graph contains cycles".to_string());
-        }
-        for edge in self.graph.edge_references() {
-            let src = edge.source();
-            let dst = edge.target();
-            let src_node = &self.graph[src];
-            let dst_node = &self.graph[dst];
-            for out in src_node.outputs() {
-                for inp in dst_node.inputs() {
-                    if out.name == inp.name && out.format != inp.format {
-                        return Err(format!("Format mismatch for resource {}", out.name));
-                    }
+    /// Retrieve the name of the node that produces the specified output resource.
+    pub fn node_name_for_output(&self, output: &str) -> Option<String> {
+        for idx in self.graph.node_indices() {
+            let node = &self.graph[idx];
+            for out in node.outputs() {
+                if out.name == output {
+                    return Some(node.name().to_string());
                 }
             }
         }
-        Ok(())
+        None
     }
 
-    pub fn execute(&mut self, ctx: &mut Context) -> Result<(), GPUError> {
-        self.validate().map_err(|_| GPUError::LibraryError())?;
+    /// Validate the graph and execute its nodes in topological order.
+    pub fn execute(&mut self, ctx: &mut Context) -> Result<(), GraphError> {
+        self.validate()?;
         let mut topo = Topo::new(&self.graph);
         while let Some(idx) = topo.next(&self.graph) {
             let node = self.graph.node_weight_mut(idx).unwrap();
@@ -421,6 +751,8 @@ impl From<&RenderGraph> for SerializableRenderGraph {
                     name: node.name().to_string(),
                     inputs: node.inputs(),
                     outputs: node.outputs(),
+                    kind: g.node_kind(node.as_ref()),
+                    pipelines: g.pipeline_descs(node.name()).to_vec(),
                 }
             })
             .collect();
@@ -451,29 +783,208 @@ impl From<&RenderGraph> for SerializableRenderGraph {
             nodes,
             canvases,
             edges,
+            transients: g.transients.clone(),
+            outputs: g.marked_outputs.clone(),
         }
     }
 }
 
 impl RenderGraph {
+    /// Kind tag recorded for `node` when serializing the graph.
+    fn node_kind(&self, node: &dyn GraphNode) -> GraphNodeKind {
+        let any = node.as_any();
+        if any.is::<CanvasNode>() {
+            GraphNodeKind::Canvas
+        } else if let Some(rp) = any.downcast_ref::<RenderPassNode>() {
+            let canvas = self.graph.node_indices().find_map(|i| {
+                let other = &self.graph[i];
+                other
+                    .as_any()
+                    .downcast_ref::<CanvasNode>()
+                    .filter(|cn| cn.canvas().render_pass() == rp.pass())
+                    .map(|_| other.name().to_string())
+            });
+            GraphNodeKind::RenderPass { canvas }
+        } else if any.is::<ExternalImageNode>() {
+            GraphNodeKind::ExternalImage
+        } else if let Some(comp) = any.downcast_ref::<CompositionNode>() {
+            GraphNodeKind::Composition {
+                blend_mode: comp.mode(),
+            }
+        } else if let Some(custom) = node.custom_desc() {
+            GraphNodeKind::Custom(custom)
+        } else {
+            GraphNodeKind::Simple
+        }
+    }
+
     pub fn from_desc(
         desc: SerializableRenderGraph,
         ctx: &mut Context,
-    ) -> Result<Self, GPUError> {
+    ) -> Result<Self, GraphError> {
+        Self::from_desc_with_registry(desc, ctx, &NodeRegistry::new())
+    }
+
+    /// Rebuild a graph, creating custom nodes with the factories in `registry`.
+    pub fn from_desc_with_registry(
+        desc: SerializableRenderGraph,
+        ctx: &mut Context,
+        registry: &NodeRegistry,
+    ) -> Result<Self, GraphError> {
         let mut g = RenderGraph::new();
-        let mut canvas_map: HashMap<String, CanvasDesc> =
+        let canvas_descs: HashMap<String, CanvasDesc> =
             desc.canvases.into_iter().map(|c| (c.name, c.canvas)).collect();
-        for n in desc.nodes {
-            if let Some(cdesc) = canvas_map.remove(&n.name) {
-                let canvas = Canvas::from_desc(ctx, &cdesc)?;
-                g.add_node(CanvasNode::new(n.name, canvas));
-            } else {
-                g.add_node(SimpleNode::from(n));
+        let mut canvases: HashMap<String, Canvas> = HashMap::new();
+        let added = add_desc_nodes(
+            &mut g,
+            desc.nodes,
+            desc.edges,
+            ctx,
+            registry,
+            &canvas_descs,
+            &mut canvases,
+        );
+        if let Err(err) = added {
+            // Nothing has used the partial graph, so it is destroyed now.
+            let mut objects = g.into_gpu_objects();
+            for c in canvases.values() {
+                for obj in c.gpu_objects() {
+                    if !objects.contains(&obj) {
+                        objects.push(obj);
+                    }
+                }
             }
+            for obj in objects {
+                obj.destroy(ctx);
+            }
+            return Err(err);
         }
-        for (a, b) in desc.edges {
-            g.connect(&a, &b);
-        }
+        g.transients = desc.transients;
+        g.marked_outputs = desc.outputs;
         Ok(g)
     }
 }
+
+/// Add the nodes and edges of a serialized graph to `g`, building canvases
+/// into `canvases` as nodes need them.
+fn add_desc_nodes(
+    g: &mut RenderGraph,
+    nodes: Vec<GraphNodeDesc>,
+    edges: Vec<(String, String)>,
+    ctx: &mut Context,
+    registry: &NodeRegistry,
+    canvas_descs: &HashMap<String, CanvasDesc>,
+    canvases: &mut HashMap<String, Canvas>,
+) -> Result<(), GraphError> {
+    let mut canvas = |name: &str, ctx: &mut Context| {
+        build_canvas(name, ctx, canvas_descs, canvases, &mut Vec::new())
+    };
+    for mut n in nodes {
+        let pipelines = std::mem::take(&mut n.pipelines);
+        let kind = match n.kind.clone() {
+            // Files written before node kinds were recorded only list
+            // canvases in `canvases`.
+            GraphNodeKind::Simple if canvas_descs.contains_key(&n.name) => {
+                GraphNodeKind::Canvas
+            }
+            kind => kind,
+        };
+        let node: Box<dyn GraphNode> = match kind {
+            GraphNodeKind::Simple => Box::new(SimpleNode::from(n)),
+            GraphNodeKind::Canvas => {
+                let c = canvas(&n.name, ctx)?;
+                // Inputs other than a borrowed depth attachment were declared
+                // with `CanvasNode::sample`.
+                let depth = c
+                    .target()
+                    .depth
+                    .as_ref()
+                    .filter(|d| d.read_only)
+                    .map(|d| d.name.clone());
+                let mut node = CanvasNode::new(n.name, c);
+                for input in n.inputs {
+                    if Some(&input.name) != depth.as_ref() {
+                        node = node.sample(input.name, input.format);
+                    }
+                }
+                Box::new(node)
+            }
+            GraphNodeKind::RenderPass { canvas: owner } => {
+                let owner = owner.ok_or_else(|| {
+                    format!("render pass node '{}' does not name its canvas", n.name)
+                })?;
+                let pass = canvas(&owner, ctx)?.render_pass();
+                Box::new(RenderPassNode::new(n.name, pass, n.inputs, n.outputs))
+            }
+            GraphNodeKind::ExternalImage => {
+                let format = n
+                    .outputs
+                    .first()
+                    .map(|o| o.format)
+                    .ok_or_else(|| format!("external image '{}' has no output", n.name))?;
+                Box::new(ExternalImageNode::new(n.name, format))
+            }
+            GraphNodeKind::Composition { blend_mode } => {
+                let format = n
+                    .outputs
+                    .first()
+                    .map(|o| o.format)
+                    .ok_or_else(|| format!("composition '{}' has no output", n.name))?;
+                let node = CompositionNode::new(n.inputs, format)
+                    .map_err(|e| format!("composition '{}': {}", n.name, e))?;
+                Box::new(node.with_name(n.name).blend_mode(blend_mode))
+            }
+            GraphNodeKind::Custom(custom) => registry.create(&n, &custom)?,
+        };
+        let name = node.name().to_string();
+        g.add_boxed_node(node)?;
+        for pipeline in pipelines {
+            g.add_pipeline_desc(&name, pipeline);
+        }
+    }
+    for (a, b) in edges {
+        g.connect(&a, &b)?;
+    }
+    Ok(())
+}
+
+/// Build the canvas `name` from its description, first building the canvases
+/// that own its read-only depth attachments. `visiting` guards against
+/// canvases borrowing depth from each other.
+fn build_canvas(
+    name: &str,
+    ctx: &mut Context,
+    descs: &HashMap<String, CanvasDesc>,
+    built: &mut HashMap<String, Canvas>,
+    visiting: &mut Vec<String>,
+) -> Result<Canvas, GraphError> {
+    if let Some(c) = built.get(name) {
+        return Ok(c.clone());
+    }
+    let cdesc = descs
+        .get(name)
+        .ok_or_else(|| format!("no canvas description for node '{}'", name))?;
+    if visiting.iter().any(|v| v == name) {
+        return Err(format!("canvas '{}' borrows its own depth attachment", name).into());
+    }
+    visiting.push(name.to_string());
+    let mut sources = Vec::new();
+    for att in cdesc.attachments.iter().filter(|a| a.read_only) {
+        let owner = descs
+            .iter()
+            .find(|(_, d)| d.attachments.iter().any(|a| a.name == att.name && !a.read_only))
+            .map(|(owner, _)| owner.clone())
+            .ok_or_else(|| {
+                format!(
+                    "read-only attachment '{}' of canvas '{}' is not written by any canvas",
+                    att.name, name
+                )
+            })?;
+        sources.push(build_canvas(&owner, ctx, descs, built, visiting)?);
+    }
+    visiting.pop();
+    let sources: Vec<&Canvas> = sources.iter().collect();
+    let c = Canvas::from_desc_with_sources(ctx, cdesc, &sources)?;
+    built.insert(name.to_string(), c.clone());
+    Ok(c)
+}
@@ -0,0 +1,61 @@
+use dashi::utils::*;
+use dashi::*;
+use std::collections::HashMap;
+
+/// Per-frame state handed to [`GraphNode::record`](super::GraphNode::record).
+///
+/// Gives a node access to the frame's command list and to the image views of
+/// every graph resource, so it can record its own draws, dispatches and blits.
+pub struct PassContext<'a> {
+    pub ctx: &'a mut Context,
+    pub list: &'a mut CommandList,
+    /// Extent of the frame being rendered.
+    pub extent: [u32; 2],
+    images: &'a HashMap<String, Handle<ImageView>>,
+    swapchain: Option<Handle<ImageView>>,
+}
+
+impl<'a> PassContext<'a> {
+    pub fn new(
+        ctx: &'a mut Context,
+        list: &'a mut CommandList,
+        extent: [u32; 2],
+        images: &'a HashMap<String, Handle<ImageView>>,
+        swapchain: Option<Handle<ImageView>>,
+    ) -> Self {
+        Self {
+            ctx,
+            list,
+            extent,
+            images,
+            swapchain,
+        }
+    }
+
+    /// Look up the view of a graph resource by name.
+    pub fn image(&self, name: &str) -> Option<Handle<ImageView>> {
+        self.images.get(name).copied()
+    }
+
+    /// The swapchain image acquired for this frame, if presenting to a display.
+    pub fn swapchain(&self) -> Option<Handle<ImageView>> {
+        self.swapchain
+    }
+
+    /// Viewport covering the whole frame.
+    pub fn full_viewport(&self) -> Viewport {
+        Viewport {
+            area: FRect2D {
+                w: self.extent[0] as f32,
+                h: self.extent[1] as f32,
+                ..Default::default()
+            },
+            scissor: Rect2D {
+                w: self.extent[0],
+                h: self.extent[1],
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Pipelines declared alongside graph nodes.
+//!
+//! A serialized node may list [`GraphPipelineDesc`]s naming SPIR-V files, the
+//! graph output they draw into, fixed-function state and resource bindings.
+//! [`Renderer::load_graph_pipelines`](crate::renderer::Renderer::load_graph_pipelines)
+//! builds them into [`PSO`](crate::material::PSO)s when a graph is loaded from
+//! YAML or JSON, so passes can be added without recompiling.
+
+use super::RenderGraph;
+use crate::material::{PipelineState, VertexBufferLayout, VertexKind};
+use dashi::CullMode;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Face culling for a declared pipeline.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineCull {
+    #[default]
+    None,
+    Back,
+    Front,
+}
+
+impl From<PipelineCull> for CullMode {
+    fn from(cull: PipelineCull) -> Self {
+        match cull {
+            PipelineCull::None => CullMode::None,
+            PipelineCull::Back => CullMode::Back,
+            PipelineCull::Front => CullMode::Front,
+        }
+    }
+}
+
+/// A graphics pipeline described in a graph file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GraphPipelineDesc {
+    pub name: String,
+    /// Path to the vertex shader SPIR-V.
+    pub vertex: String,
+    /// Path to the fragment shader SPIR-V.
+    pub fragment: String,
+    /// Graph output the pipeline renders into.
+    pub output: String,
+    #[serde(default)]
+    pub depth: bool,
+    #[serde(default)]
+    pub cull: PipelineCull,
+    /// Blend, stencil and rasterizer keys, as in material files.
+    #[serde(flatten)]
+    pub state: PipelineState,
+    /// Named layout of the vertex buffer, [`Vertex`](crate::renderer::Vertex)
+    /// unless given, matching the meshes the renderer draws.
+    #[serde(default)]
+    pub vertex_layout: VertexKind,
+    /// Explicit vertex buffers, used instead of `vertex_layout`.
+    #[serde(default)]
+    pub vertex_buffers: Vec<VertexBufferLayout>,
+    /// Register as the pipeline for this material instead of for the pass
+    /// that owns `output`.
+    #[serde(default)]
+    pub material: Option<String>,
+    /// Shader variable names bound to differently named entries of the
+    /// `ResourceManager`. Unlisted variables bind by their own name.
+    #[serde(default)]
+    pub bindings: BTreeMap<String, String>,
+}
+
+/// Read a SPIR-V binary from disk.
+pub fn read_spirv(path: &str) -> Result<Vec<u32>, String> {
+    const SPIRV_MAGIC: u32 = 0x0723_0203;
+    let bytes = std::fs::read(path).map_err(|e| format!("failed to read '{}': {}", path, e))?;
+    if bytes.len() % 4 != 0 {
+        return Err(format!("'{}' is not a SPIR-V binary", path));
+    }
+    let words: Vec<u32> = bytes
+        .chunks_exact(4)
+        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect();
+    if words.first() != Some(&SPIRV_MAGIC) {
+        return Err(format!("'{}' is not a SPIR-V binary", path));
+    }
+    Ok(words)
+}
+
+impl RenderGraph {
+    /// Declare a pipeline for `node`, built when the renderer loads the graph.
+    pub fn add_pipeline_desc(&mut self, node: &str, desc: GraphPipelineDesc) {
+        self.pipelines.entry(node.to_string()).or_default().push(desc);
+    }
+
+    /// Pipelines declared for `node`.
+    pub fn pipeline_descs(&self, node: &str) -> &[GraphPipelineDesc] {
+        self.pipelines.get(node).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Every declared pipeline with the name of its node, in node order.
+    pub fn all_pipeline_descs(&self) -> Vec<(String, GraphPipelineDesc)> {
+        self.graph
+            .node_indices()
+            .flat_map(|i| {
+                let name = self.graph[i].name();
+                self.pipeline_descs(name)
+                    .iter()
+                    .map(move |d| (name.to_string(), d.clone()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_graph::SerializableRenderGraph;
+
+    #[test]
+    fn pipelines_parse_from_yaml_with_defaults() {
+        let yaml = r#"
+nodes:
+  - name: main
+    inputs: []
+    outputs:
+      - name: color
+        format: RGBA8
+    pipelines:
+      - name: sky
+        vertex: sky.vert.spv
+        fragment: sky.frag.spv
+        output: color
+        cull: back
+        blend: alpha
+        vertex_layout: static
+        bindings:
+          env_map: skybox
+edges: []
+"#;
+        let desc: SerializableRenderGraph = serde_yaml::from_str(yaml).unwrap();
+        let pipeline = &desc.nodes[0].pipelines[0];
+        assert_eq!(pipeline.name, "sky");
+        assert_eq!(pipeline.output, "color");
+        assert!(!pipeline.depth);
+        assert_eq!(pipeline.cull, PipelineCull::Back);
+        assert_eq!(pipeline.state.blend, crate::material::Blend::Alpha);
+        assert_eq!(pipeline.state.stencil, None);
+        assert_eq!(pipeline.vertex_layout, VertexKind::Static);
+        assert!(pipeline.vertex_buffers.is_empty());
+        assert_eq!(pipeline.material, None);
+        assert_eq!(pipeline.bindings.get("env_map").map(String::as_str), Some("skybox"));
+    }
+
+    #[test]
+    fn read_spirv_rejects_non_spirv_files() {
+        let path = std::env::temp_dir().join(format!("koji_not_spirv_{}", std::process::id()));
+        std::fs::write(&path, [1u8, 2, 3, 4]).unwrap();
+        let err = read_spirv(path.to_str().unwrap()).unwrap_err();
+        assert!(err.contains("not a SPIR-V binary"));
+
+        std::fs::write(&path, 0x0723_0203u32.to_le_bytes()).unwrap();
+        assert_eq!(read_spirv(path.to_str().unwrap()).unwrap(), vec![0x0723_0203]);
+        std::fs::remove_file(path).unwrap();
+        assert!(read_spirv("does/not/exist.spv").is_err());
+    }
+}
@@ -0,0 +1,185 @@
+//! Node kinds for serialized graphs and a registry for custom nodes.
+//!
+//! Every [`GraphNodeDesc`] carries a [`GraphNodeKind`] tag so that
+//! [`RenderGraph::from_desc`](super::RenderGraph::from_desc) can rebuild the
+//! concrete node type. Nodes defined outside this crate serialize as
+//! [`GraphNodeKind::Custom`] through [`GraphNode::custom_desc`] and are
+//! rebuilt by the factory registered under their type name in a
+//! [`NodeRegistry`].
+
+use super::{BlendMode, GraphNode, GraphNodeDesc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Type name and parameters of a node built by a [`NodeRegistry`] factory.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CustomNodeDesc {
+    pub type_name: String,
+    #[serde(default)]
+    pub params: serde_json::Value,
+}
+
+/// Concrete type of a serialized graph node.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GraphNodeKind {
+    /// A [`SimpleNode`](super::SimpleNode) describing resources only.
+    #[default]
+    Simple,
+    /// A [`CanvasNode`](super::CanvasNode) built from the matching entry in
+    /// `canvases`.
+    Canvas,
+    /// A [`RenderPassNode`](super::RenderPassNode) drawing into the render
+    /// pass of the named canvas node.
+    RenderPass {
+        #[serde(default)]
+        canvas: Option<String>,
+    },
+    /// An [`ExternalImageNode`](super::ExternalImageNode) named after its
+    /// single output.
+    ExternalImage,
+    /// A [`CompositionNode`](super::CompositionNode) blending its inputs into
+    /// its output's format.
+    Composition {
+        #[serde(default)]
+        blend_mode: BlendMode,
+    },
+    /// A node rebuilt by a [`NodeRegistry`] factory.
+    Custom(CustomNodeDesc),
+}
+
+/// Builds a custom node from its description and parameters.
+pub type NodeFactory =
+    Box<dyn Fn(&GraphNodeDesc, &serde_json::Value) -> Result<Box<dyn GraphNode>, String>>;
+
+/// Factories for custom node types, keyed by type name.
+#[derive(Default)]
+pub struct NodeRegistry {
+    factories: HashMap<String, NodeFactory>,
+}
+
+impl NodeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the factory used for nodes serialized with `type_name`.
+    pub fn register<F>(&mut self, type_name: impl Into<String>, factory: F)
+    where
+        F: Fn(&GraphNodeDesc, &serde_json::Value) -> Result<Box<dyn GraphNode>, String>
+            + 'static,
+    {
+        self.factories.insert(type_name.into(), Box::new(factory));
+    }
+
+    pub fn contains(&self, type_name: &str) -> bool {
+        self.factories.contains_key(type_name)
+    }
+
+    /// Build a custom node with the factory registered for its type name.
+    pub fn create(
+        &self,
+        desc: &GraphNodeDesc,
+        custom: &CustomNodeDesc,
+    ) -> Result<Box<dyn GraphNode>, String> {
+        let factory = self.factories.get(&custom.type_name).ok_or_else(|| {
+            format!(
+                "unknown node type '{}' for node '{}'",
+                custom.type_name, desc.name
+            )
+        })?;
+        factory(desc, &custom.params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_graph::{
+        ExternalImageNode, RenderGraph, ResourceDesc, SerializableRenderGraph,
+    };
+    use dashi::*;
+
+    struct BlurNode {
+        name: String,
+        radius: u32,
+    }
+
+    impl GraphNode for BlurNode {
+        fn name(&self) -> &str {
+            &self.name
+        }
+        fn inputs(&self) -> Vec<ResourceDesc> {
+            vec![ResourceDesc {
+                name: "env".into(),
+                format: Format::RGBA8,
+            }]
+        }
+        fn outputs(&self) -> Vec<ResourceDesc> {
+            Vec::new()
+        }
+        fn execute(&mut self, _ctx: &mut Context) -> Result<(), GPUError> {
+            Ok(())
+        }
+        fn custom_desc(&self) -> Option<CustomNodeDesc> {
+            Some(CustomNodeDesc {
+                type_name: "blur".into(),
+                params: serde_json::json!({ "radius": self.radius }),
+            })
+        }
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    fn blur_registry() -> NodeRegistry {
+        let mut registry = NodeRegistry::new();
+        registry.register("blur", |desc, params| {
+            Ok(Box::new(BlurNode {
+                name: desc.name.clone(),
+                radius: params["radius"].as_u64().ok_or("missing radius")? as u32,
+            }))
+        });
+        registry
+    }
+
+    #[test]
+    fn node_kinds_are_tagged_in_yaml() {
+        let mut graph = RenderGraph::new();
+        graph
+            .add_node(ExternalImageNode::new("env", Format::RGBA8))
+            .unwrap();
+        graph
+            .add_node(BlurNode {
+                name: "blur".into(),
+                radius: 4,
+            })
+            .unwrap();
+        let yaml = crate::render_graph::to_yaml(&graph).unwrap();
+        assert!(yaml.contains("type: external_image"));
+        assert!(yaml.contains("type: custom"));
+
+        let desc: SerializableRenderGraph = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(desc.nodes[0].kind, GraphNodeKind::ExternalImage);
+        let GraphNodeKind::Custom(custom) = &desc.nodes[1].kind else {
+            panic!("expected a custom node");
+        };
+        let node = blur_registry().create(&desc.nodes[1], custom).unwrap();
+        let blur = node.as_any().downcast_ref::<BlurNode>().unwrap();
+        assert_eq!(blur.radius, 4);
+    }
+
+    #[test]
+    fn missing_kind_defaults_to_simple_and_unknown_types_fail() {
+        let desc: GraphNodeDesc =
+            serde_yaml::from_str("name: a\ninputs: []\noutputs: []\n").unwrap();
+        assert_eq!(desc.kind, GraphNodeKind::Simple);
+
+        let custom = CustomNodeDesc {
+            type_name: "missing".into(),
+            params: serde_json::Value::Null,
+        };
+        let err = NodeRegistry::new().create(&desc, &custom).err().unwrap();
+        assert!(err.contains("unknown node type 'missing'"));
+    }
+}
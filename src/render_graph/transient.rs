@@ -0,0 +1,355 @@
+//! Transient graph resources and image sharing.
+//!
+//! A transient resource is an image that only lives between the passes that
+//! write and read it. [`RenderGraph`] derives each transient's lifetime from
+//! the topological order and lets resources whose lifetimes don't overlap
+//! share one backing image. Sharing is limited to resources with the same
+//! format and extent. This is not memory aliasing: dashi allocates memory per
+//! image, so differently shaped transients never share memory, and every
+//! shared image stays allocated for the whole frame.
+
+use super::RenderGraph;
+use crate::format::FormatClass;
+use crate::utils::GpuObject;
+use dashi::utils::*;
+use dashi::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Declaration of a graph-owned image that is allocated by the graph.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TransientDesc {
+    pub name: String,
+    pub format: Format,
+    pub extent: [u32; 2],
+}
+
+/// Size in bytes of one texel of `format`.
+pub fn format_texel_size(format: Format) -> u64 {
+    match format {
+        Format::R8Sint | Format::R8Uint => 1,
+        Format::RGB8 => 3,
+        Format::BGRA8 | Format::BGRA8Unorm | Format::RGBA8 | Format::RGBA8Unorm => 4,
+        Format::RGBA32F => 16,
+        Format::D24S8 => 4,
+    }
+}
+
+impl TransientDesc {
+    pub fn byte_size(&self) -> u64 {
+        self.extent[0] as u64 * self.extent[1] as u64 * format_texel_size(self.format)
+    }
+
+    fn compatible(&self, other: &TransientDesc) -> bool {
+        self.format == other.format && self.extent == other.extent
+    }
+}
+
+/// Span of topological positions during which a transient must stay alive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResourceLifetime {
+    pub name: String,
+    /// Position of the first node accessing the resource.
+    pub first: usize,
+    /// Position of the last node accessing the resource.
+    pub last: usize,
+    pub bytes: u64,
+}
+
+impl ResourceLifetime {
+    pub fn overlaps(&self, other: &ResourceLifetime) -> bool {
+        self.first <= other.last && other.first <= self.last
+    }
+}
+
+/// One backing image shared by transients with disjoint lifetimes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SharedImage {
+    pub bytes: u64,
+    pub resources: Vec<String>,
+}
+
+/// CPU-side summary of transient memory use.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransientMemoryReport {
+    pub lifetimes: Vec<ResourceLifetime>,
+    pub images: Vec<SharedImage>,
+    /// Bytes allocated if every transient had its own image.
+    pub unshared_bytes: u64,
+    /// Bytes allocated for the shared images. This is what the graph
+    /// actually allocates.
+    pub shared_bytes: u64,
+    /// Largest sum of bytes of transients alive at the same position. An
+    /// allocator that aliased memory across formats could get down to this.
+    pub peak_live_bytes: u64,
+}
+
+impl fmt::Display for TransientMemoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for lt in &self.lifetimes {
+            writeln!(f, "{}: passes {}..={} ({} bytes)", lt.name, lt.first, lt.last, lt.bytes)?;
+        }
+        for (i, image) in self.images.iter().enumerate() {
+            writeln!(f, "image {}: {} bytes [{}]", i, image.bytes, image.resources.join(", "))?;
+        }
+        writeln!(f, "total without sharing: {} bytes", self.unshared_bytes)?;
+        writeln!(f, "total with sharing: {} bytes", self.shared_bytes)?;
+        write!(f, "peak live: {} bytes", self.peak_live_bytes)
+    }
+}
+
+impl RenderGraph {
+    /// Declare a transient image owned and allocated by the graph.
+    pub fn add_transient(&mut self, name: impl Into<String>, format: Format, extent: [u32; 2]) {
+        let desc = TransientDesc {
+            name: name.into(),
+            format,
+            extent,
+        };
+        self.transients.retain(|t| t.name != desc.name);
+        self.transients.push(desc);
+    }
+
+    pub fn transients(&self) -> &[TransientDesc] {
+        &self.transients
+    }
+
+    pub fn is_transient(&self, name: &str) -> bool {
+        self.transients.iter().any(|t| t.name == name)
+    }
+
+    /// Lifetimes of all transients that are accessed by at least one node,
+    /// ordered by first use.
+    pub fn transient_lifetimes(&self) -> Vec<ResourceLifetime> {
+        let order: Vec<String> = self
+            .execution_order()
+            .into_iter()
+            .map(|i| self.node(i).name().to_string())
+            .collect();
+        let mut spans: HashMap<&str, (usize, usize)> = HashMap::new();
+        for access in self.resource_accesses() {
+            if !self.is_transient(&access.resource) {
+                continue;
+            }
+            let pos = order.iter().position(|n| *n == access.node).unwrap();
+            let desc = self
+                .transients
+                .iter()
+                .find(|t| t.name == access.resource)
+                .unwrap();
+            let span = spans.entry(desc.name.as_str()).or_insert((pos, pos));
+            span.0 = span.0.min(pos);
+            span.1 = span.1.max(pos);
+        }
+        let mut lifetimes: Vec<ResourceLifetime> = self
+            .transients
+            .iter()
+            .filter_map(|t| {
+                spans.get(t.name.as_str()).map(|&(first, last)| ResourceLifetime {
+                    name: t.name.clone(),
+                    first,
+                    last,
+                    bytes: t.byte_size(),
+                })
+            })
+            .collect();
+        lifetimes.sort_by_key(|l| (l.first, l.last));
+        lifetimes
+    }
+
+    /// Assign transients to shared images and report memory with and without
+    /// sharing.
+    pub fn transient_memory_report(&self) -> TransientMemoryReport {
+        let lifetimes = self.transient_lifetimes();
+        let mut images: Vec<(SharedImage, &TransientDesc, Vec<&ResourceLifetime>)> = Vec::new();
+        for lt in &lifetimes {
+            let desc = self.transients.iter().find(|t| t.name == lt.name).unwrap();
+            let free = images.iter_mut().find(|(_, d, users)| {
+                d.compatible(desc) && users.iter().all(|u| !u.overlaps(lt))
+            });
+            match free {
+                Some((image, _, users)) => {
+                    image.resources.push(lt.name.clone());
+                    users.push(lt);
+                }
+                None => images.push((
+                    SharedImage {
+                        bytes: lt.bytes,
+                        resources: vec![lt.name.clone()],
+                    },
+                    desc,
+                    vec![lt],
+                )),
+            }
+        }
+        let images: Vec<SharedImage> = images.into_iter().map(|(s, _, _)| s).collect();
+        let last = lifetimes.iter().map(|l| l.last + 1).max().unwrap_or(0);
+        let peak_live_bytes = (0..last)
+            .map(|pos| {
+                lifetimes
+                    .iter()
+                    .filter(|l| l.first <= pos && pos <= l.last)
+                    .map(|l| l.bytes)
+                    .sum()
+            })
+            .max()
+            .unwrap_or(0);
+        TransientMemoryReport {
+            unshared_bytes: lifetimes.iter().map(|l| l.bytes).sum(),
+            shared_bytes: images.iter().map(|s| s.bytes).sum(),
+            peak_live_bytes,
+            lifetimes,
+            images,
+        }
+    }
+
+    /// Create one image per shared image of the report and bind every
+    /// transient to its view. Transients that are never accessed are not
+    /// allocated.
+    ///
+    /// Returns the images of a previous allocation. Frames in flight may still
+    /// use them, so the caller destroys them once those frames complete.
+    ///
+    /// Transient lifetimes skip culled nodes, so the marked outputs cannot be
+    /// changed after this, see [`RenderGraph::mark_output`].
+    pub fn allocate_transients(&mut self, ctx: &mut Context) -> Result<Vec<GpuObject>, GPUError> {
+        let report = self.transient_memory_report();
+        let mut views = HashMap::new();
+        let mut images = Vec::new();
+        for shared in &report.images {
+            let desc = self
+                .transients
+                .iter()
+                .find(|t| t.name == shared.resources[0])
+                .unwrap();
+            match make_transient(ctx, desc) {
+                Ok((image, view)) => {
+                    images.push((image, view));
+                    for name in &shared.resources {
+                        views.insert(name.clone(), view);
+                    }
+                }
+                Err(err) => {
+                    for (image, view) in images {
+                        ctx.destroy_image_view(view);
+                        ctx.destroy_image(image);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        self.transient_views = views;
+        self.transients_allocated = true;
+        let old = std::mem::replace(&mut self.transient_images, images);
+        Ok(old
+            .into_iter()
+            .flat_map(|(image, view)| [GpuObject::ImageView(view), GpuObject::Image(image)])
+            .collect())
+    }
+
+    pub(crate) fn transient_view(&self, name: &str) -> Option<Handle<ImageView>> {
+        self.transient_views.get(name).copied()
+    }
+}
+
+fn make_transient(
+    ctx: &mut Context,
+    desc: &TransientDesc,
+) -> Result<(Handle<Image>, Handle<ImageView>), GPUError> {
+    let image = ctx.make_image(&ImageInfo {
+        debug_name: &desc.name,
+        dim: [desc.extent[0], desc.extent[1], 1],
+        format: desc.format,
+        mip_levels: 1,
+        layers: 1,
+        initial_data: None,
+    })?;
+    let view = ctx.make_image_view(&ImageViewInfo {
+        debug_name: &desc.name,
+        img: image,
+        layer: 0,
+        mip_level: 0,
+        aspect: FormatClass::of(desc.format).aspect(),
+    });
+    match view {
+        Ok(view) => Ok((image, view)),
+        Err(err) => {
+            ctx.destroy_image(image);
+            Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_graph::{GraphNodeDesc, ResourceDesc, SimpleNode};
+
+    fn res(name: &str) -> ResourceDesc {
+        ResourceDesc {
+            name: name.into(),
+            format: Format::RGBA8,
+        }
+    }
+
+    fn node(name: &str, inputs: &[&str], outputs: &[&str]) -> SimpleNode {
+        SimpleNode::from(GraphNodeDesc {
+            name: name.into(),
+            inputs: inputs.iter().map(|n| res(n)).collect(),
+            outputs: outputs.iter().map(|n| res(n)).collect(),
+            kind: Default::default(),
+            pipelines: Vec::new(),
+        })
+    }
+
+    /// a -> b -> c -> d, where `t0` lives in a..=b and `t1` in c..=d.
+    fn chain() -> RenderGraph {
+        let mut graph = RenderGraph::new();
+        graph.add_node(node("a", &[], &["t0"])).unwrap();
+        graph.add_node(node("b", &["t0"], &["mid"])).unwrap();
+        graph.add_node(node("c", &["mid"], &["t1"])).unwrap();
+        graph.add_node(node("d", &["t1"], &["out"])).unwrap();
+        graph.connect("a", "b").unwrap();
+        graph.connect("b", "c").unwrap();
+        graph.connect("c", "d").unwrap();
+        graph.add_transient("t0", Format::RGBA8, [64, 64]);
+        graph.add_transient("t1", Format::RGBA8, [64, 64]);
+        graph.add_transient("mid", Format::RGBA8, [64, 64]);
+        graph
+    }
+
+    #[test]
+    fn lifetimes_follow_topological_order() {
+        let lifetimes = chain().transient_lifetimes();
+        let names: Vec<_> = lifetimes.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, vec!["t0", "mid", "t1"]);
+        assert_eq!((lifetimes[0].first, lifetimes[0].last), (0, 1));
+        assert_eq!((lifetimes[1].first, lifetimes[1].last), (1, 2));
+        assert_eq!((lifetimes[2].first, lifetimes[2].last), (2, 3));
+    }
+
+    #[test]
+    fn disjoint_transients_share_images() {
+        let report = chain().transient_memory_report();
+        let size = 64 * 64 * 4;
+        assert_eq!(report.unshared_bytes, 3 * size);
+        assert_eq!(report.shared_bytes, 2 * size);
+        assert_eq!(report.peak_live_bytes, 2 * size);
+        assert!(report
+            .images
+            .iter()
+            .any(|s| s.resources == vec!["t0".to_string(), "t1".to_string()]));
+        assert!(report.to_string().contains("total with sharing"));
+    }
+
+    #[test]
+    fn incompatible_transients_do_not_share() {
+        let mut graph = chain();
+        graph.add_transient("t1", Format::RGBA32F, [64, 64]);
+        let report = graph.transient_memory_report();
+        assert_eq!(report.images.len(), 3);
+        assert_eq!(report.shared_bytes, report.unshared_bytes);
+        assert!(report.peak_live_bytes < report.shared_bytes);
+    }
+}
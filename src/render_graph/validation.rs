@@ -0,0 +1,358 @@
+//! Structural checks for [`RenderGraph`].
+//!
+//! [`RenderGraph::add_node`] and [`RenderGraph::connect`] reject duplicate and
+//! unknown node names as they are called. [`RenderGraph::validate`] checks
+//! the assembled graph and reports every problem it finds rather than
+//! stopping at the first one.
+
+use super::RenderGraph;
+use crate::canvas::CanvasError;
+use crate::material::PipelineError;
+use dashi::{Format, GPUError};
+use petgraph::algo::is_cyclic_directed;
+use petgraph::visit::EdgeRef;
+use std::fmt;
+
+/// A problem found while building or validating a [`RenderGraph`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum GraphValidationError {
+    /// The graph's edges form a cycle.
+    Cycle,
+    /// A node was added with a name already used by another node.
+    DuplicateNode { name: String },
+    /// `connect` referenced a node that has not been added.
+    UnknownNode { name: String, from: String, to: String },
+    /// A connected producer and consumer disagree on a resource's format.
+    FormatMismatch {
+        resource: String,
+        producer: String,
+        consumer: String,
+        produced: Format,
+        consumed: Format,
+    },
+    /// A node reads a resource that no node writes.
+    MissingProducer { node: String, resource: String },
+    /// More than one node writes the same resource.
+    MultipleProducers {
+        resource: String,
+        producers: Vec<String>,
+    },
+    /// An output was marked or unmarked after the transient images were
+    /// allocated for the previous outputs.
+    OutputsFixed { resource: String },
+}
+
+impl fmt::Display for GraphValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphValidationError::Cycle => write!(f, "render graph contains cycles"),
+            GraphValidationError::DuplicateNode { name } => {
+                write!(f, "duplicate node name '{}'", name)
+            }
+            GraphValidationError::UnknownNode { name, from, to } => write!(
+                f,
+                "cannot connect '{}' to '{}': unknown node '{}'",
+                from, to, name
+            ),
+            GraphValidationError::FormatMismatch {
+                resource,
+                producer,
+                consumer,
+                produced,
+                consumed,
+            } => write!(
+                f,
+                "format mismatch for resource '{}': '{}' writes {:?} but '{}' reads {:?}",
+                resource, producer, produced, consumer, consumed
+            ),
+            GraphValidationError::MissingProducer { node, resource } => write!(
+                f,
+                "node '{}' reads resource '{}' which no node produces",
+                node, resource
+            ),
+            GraphValidationError::MultipleProducers {
+                resource,
+                producers,
+            } => write!(
+                f,
+                "resource '{}' is produced by multiple nodes: {}",
+                resource,
+                producers.join(", ")
+            ),
+            GraphValidationError::OutputsFixed { resource } => write!(
+                f,
+                "cannot mark or unmark output '{}' after transient images were allocated",
+                resource
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GraphValidationError {}
+
+/// An error from building, loading or executing a [`RenderGraph`].
+#[derive(Debug)]
+pub enum GraphError {
+    /// The graph failed structural validation.
+    Validation(Vec<GraphValidationError>),
+    /// A serialized graph could not be parsed or describes something that
+    /// cannot be built.
+    Desc(String),
+    /// A canvas of the graph could not be built.
+    Canvas(CanvasError),
+    /// A GPU operation failed.
+    Gpu(GPUError),
+    /// A pipeline of a graph node could not be built or bound.
+    Pipeline {
+        node: String,
+        pipeline: String,
+        error: PipelineError,
+    },
+    /// Bind groups sampling a replaced graph image could not be rebuilt.
+    Rebind(PipelineError),
+    /// The running graph holds a node the renderer added itself, such as
+    /// the deferred passes, which replacing the graph would drop.
+    RendererNode(String),
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::Validation(errors) => {
+                let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}", errors.join("; "))
+            }
+            GraphError::Desc(msg) => write!(f, "{}", msg),
+            GraphError::Canvas(err) => write!(f, "{}", err),
+            GraphError::Gpu(err) => write!(f, "GPU error: {:?}", err),
+            GraphError::Pipeline {
+                node,
+                pipeline,
+                error,
+            } => write!(f, "pipeline '{}' on node '{}': {:?}", pipeline, node, error),
+            GraphError::Rebind(err) => write!(f, "failed to rebind graph images: {:?}", err),
+            GraphError::RendererNode(name) => write!(
+                f,
+                "node '{}' was added by the renderer and would be lost by replacing the graph",
+                name
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+impl From<GraphValidationError> for GraphError {
+    fn from(err: GraphValidationError) -> Self {
+        GraphError::Validation(vec![err])
+    }
+}
+
+impl From<Vec<GraphValidationError>> for GraphError {
+    fn from(errors: Vec<GraphValidationError>) -> Self {
+        GraphError::Validation(errors)
+    }
+}
+
+impl From<CanvasError> for GraphError {
+    fn from(err: CanvasError) -> Self {
+        GraphError::Canvas(err)
+    }
+}
+
+impl From<GPUError> for GraphError {
+    fn from(err: GPUError) -> Self {
+        GraphError::Gpu(err)
+    }
+}
+
+impl From<String> for GraphError {
+    fn from(msg: String) -> Self {
+        GraphError::Desc(msg)
+    }
+}
+
+impl RenderGraph {
+    /// Check the graph for cycles, format mismatches across edges, inputs
+    /// without a producer and outputs with several producers.
+    ///
+    /// A node that lists a resource as both input and output updates it in
+    /// place and does not count as an additional producer.
+    pub fn validate(&self) -> Result<(), Vec<GraphValidationError>> {
+        let mut errors = Vec::new();
+        if is_cyclic_directed(&self.graph) {
+            errors.push(GraphValidationError::Cycle);
+        }
+
+        for edge in self.graph.edge_references() {
+            let src_node = &self.graph[edge.source()];
+            let dst_node = &self.graph[edge.target()];
+            for out in src_node.outputs() {
+                for inp in dst_node.inputs() {
+                    if out.name == inp.name && out.format != inp.format {
+                        errors.push(GraphValidationError::FormatMismatch {
+                            resource: out.name.clone(),
+                            producer: src_node.name().to_string(),
+                            consumer: dst_node.name().to_string(),
+                            produced: out.format,
+                            consumed: inp.format,
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut producers: Vec<(String, Vec<String>)> = Vec::new();
+        for idx in self.graph.node_indices() {
+            let node = &self.graph[idx];
+            let inputs = node.inputs();
+            for out in node.outputs() {
+                if inputs.iter().any(|i| i.name == out.name) {
+                    continue;
+                }
+                match producers.iter_mut().find(|(r, _)| *r == out.name) {
+                    Some((_, nodes)) => nodes.push(node.name().to_string()),
+                    None => producers.push((out.name, vec![node.name().to_string()])),
+                }
+            }
+        }
+        for (resource, nodes) in &producers {
+            if nodes.len() > 1 {
+                errors.push(GraphValidationError::MultipleProducers {
+                    resource: resource.clone(),
+                    producers: nodes.clone(),
+                });
+            }
+        }
+
+        for idx in self.graph.node_indices() {
+            let node = &self.graph[idx];
+            for inp in node.inputs() {
+                if !producers.iter().any(|(r, _)| *r == inp.name) {
+                    errors.push(GraphValidationError::MissingProducer {
+                        node: node.name().to_string(),
+                        resource: inp.name,
+                    });
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render_graph::{GraphNodeDesc, ResourceDesc, SimpleNode};
+
+    fn res(name: &str, format: Format) -> ResourceDesc {
+        ResourceDesc {
+            name: name.into(),
+            format,
+        }
+    }
+
+    fn node(name: &str, inputs: Vec<ResourceDesc>, outputs: Vec<ResourceDesc>) -> SimpleNode {
+        SimpleNode::from(GraphNodeDesc {
+            name: name.into(),
+            inputs,
+            outputs,
+            kind: Default::default(),
+            pipelines: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn add_node_and_connect_reject_bad_names() {
+        let mut graph = RenderGraph::new();
+        graph.add_node(node("a", Vec::new(), Vec::new())).unwrap();
+        assert_eq!(
+            graph.add_node(node("a", Vec::new(), Vec::new())),
+            Err(GraphValidationError::DuplicateNode { name: "a".into() })
+        );
+        assert_eq!(
+            graph.connect("a", "b"),
+            Err(GraphValidationError::UnknownNode {
+                name: "b".into(),
+                from: "a".into(),
+                to: "b".into(),
+            })
+        );
+        assert_eq!(graph.node_names(), vec!["a".to_string()]);
+    }
+
+    #[test]
+    fn removed_nodes_free_their_names() {
+        let mut graph = RenderGraph::new();
+        for name in ["a", "b", "c"] {
+            graph.add_node(node(name, Vec::new(), Vec::new())).unwrap();
+        }
+        graph.connect("a", "c").unwrap();
+        assert_eq!(graph.remove_node("a").unwrap().name(), "a");
+        assert!(graph.remove_node("a").is_none());
+        assert!(graph.edges().is_empty());
+        graph.connect("b", "c").unwrap();
+        graph.add_node(node("a", Vec::new(), Vec::new())).unwrap();
+        assert_eq!(graph.edges(), vec![("b".to_string(), "c".to_string())]);
+    }
+
+    #[test]
+    fn validate_reports_every_problem() {
+        let mut graph = RenderGraph::new();
+        graph
+            .add_node(node("a", Vec::new(), vec![res("color", Format::RGBA8)]))
+            .unwrap();
+        graph
+            .add_node(node("b", Vec::new(), vec![res("color", Format::RGBA8)]))
+            .unwrap();
+        graph
+            .add_node(node(
+                "c",
+                vec![res("color", Format::RGBA32F), res("shadow", Format::D24S8)],
+                Vec::new(),
+            ))
+            .unwrap();
+        graph.connect("a", "c").unwrap();
+
+        let errors = graph.validate().unwrap_err();
+        assert_eq!(errors.len(), 3);
+        assert!(errors.contains(&GraphValidationError::FormatMismatch {
+            resource: "color".into(),
+            producer: "a".into(),
+            consumer: "c".into(),
+            produced: Format::RGBA8,
+            consumed: Format::RGBA32F,
+        }));
+        assert!(errors.contains(&GraphValidationError::MultipleProducers {
+            resource: "color".into(),
+            producers: vec!["a".into(), "b".into()],
+        }));
+        assert!(errors.contains(&GraphValidationError::MissingProducer {
+            node: "c".into(),
+            resource: "shadow".into(),
+        }));
+    }
+
+    #[test]
+    fn in_place_updates_are_not_extra_producers() {
+        let mut graph = RenderGraph::new();
+        graph
+            .add_node(node("a", Vec::new(), vec![res("color", Format::RGBA8)]))
+            .unwrap();
+        graph
+            .add_node(node(
+                "b",
+                vec![res("color", Format::RGBA8)],
+                vec![res("color", Format::RGBA8)],
+            ))
+            .unwrap();
+        graph.connect("a", "b").unwrap();
+        graph.connect("b", "a").unwrap();
+        assert_eq!(graph.validate(), Err(vec![GraphValidationError::Cycle]));
+    }
+}
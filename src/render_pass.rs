@@ -3,17 +3,27 @@ use dashi::*;
 use indexmap::IndexMap;
 use serde::Deserialize;
 
+use crate::format::FormatClass;
 use crate::render_graph::{GraphNode, RenderPassNode, ResourceDesc};
 
 
 pub struct NamedAttachment {
     pub name: String,
     pub format: Format,
+    /// Existing image and view to render into instead of allocating one.
+    pub image: Option<(Handle<Image>, Handle<ImageView>)>,
+    /// Depth attachment that is tested against but never cleared or
+    /// written.
+    pub read_only: bool,
 }
 
 pub struct NamedSubpass {
     pub name: String,
     pub color_attachments: Vec<String>,
+    /// Attachments written by earlier subpasses and read in this one with
+    /// `subpassLoad`. dashi render passes cannot declare these yet, so
+    /// building a pass that lists any fails.
+    pub input_attachments: Vec<String>,
     pub depth_stencil_attachment: Option<String>,
     pub depends_on: Vec<String>,
 }
@@ -26,6 +36,7 @@ pub struct RenderPassBuilder {
     viewport: Viewport,
     debug_name: &'static str,
     extent: [u32; 2],
+    samples: u32,
 }
 
 pub struct AllRenderAttachments {
@@ -37,6 +48,13 @@ pub struct RenderAttachment {
     pub name: String,
     pub format: Format,
     pub attachment: Attachment,
+    /// Image behind `attachment.img`.
+    pub image: Handle<Image>,
+    /// Samples per texel of `image`.
+    pub samples: u32,
+    /// Depth attachment borrowed from another pass for depth testing only.
+    /// The image is owned by that pass.
+    pub read_only: bool,
 }
 
 #[derive(Clone)]
@@ -48,6 +66,39 @@ pub struct RenderTarget {
 
 type RenderTargets = Vec<RenderTarget>;
 
+/// Whether attachments can use `samples` samples per texel. dashi images
+/// and render passes are single-sampled, so only 1 is supported. Zero is
+/// treated as 1.
+pub fn supports_samples(samples: u32) -> bool {
+    samples <= 1
+}
+
+/// Create the image and view backing a render pass attachment.
+pub(crate) fn make_attachment_image(
+    ctx: &mut Context,
+    name: &str,
+    format: Format,
+    extent: [u32; 2],
+) -> Result<(Handle<Image>, Handle<ImageView>), GPUError> {
+    let image = ctx.make_image(&ImageInfo {
+        debug_name: name,
+        dim: [extent[0], extent[1], 1],
+        format,
+        mip_levels: 1,
+        layers: 1,
+        initial_data: None,
+    })?;
+
+    let view = ctx.make_image_view(&ImageViewInfo {
+        debug_name: name,
+        img: image,
+        layer: 0,
+        mip_level: 0,
+        aspect: FormatClass::of(format).aspect(),
+    })?;
+    Ok((image, view))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct YamlAttachment {
     pub name: String,
@@ -59,6 +110,10 @@ pub struct YamlSubpass {
     pub name: String,
     pub color_attachments: Vec<String>,
     #[serde(default)]
+    pub input_attachments: Vec<String>,
+    #[serde(default)]
+    pub depth_stencil_attachment: Option<String>,
+    #[serde(default)]
     pub depends_on: Vec<String>,
 }
 
@@ -96,6 +151,13 @@ impl RenderPassBuilder {
         self
     }
 
+    /// Render with `samples` samples per texel. Only single-sampled passes
+    /// can be built; see [`supports_samples`].
+    pub fn samples(mut self, samples: u32) -> Self {
+        self.samples = samples;
+        self
+    }
+
     pub fn color_attachment(mut self, name: impl Into<String>, format: Format) -> Self {
         let name_str = name.into();
         self.attachments.insert(
@@ -103,11 +165,15 @@ impl RenderPassBuilder {
             NamedAttachment {
                 name: name_str.clone(),
                 format,
+                image: None,
+                read_only: false,
             },
         );
         self
     }
 
+    /// Add a depth, stencil or depth-stencil attachment. Subpasses use the
+    /// first such attachment as their depth target.
     pub fn depth_attachment(mut self, name: impl Into<String>, format: Format) -> Self {
         let name_str = name.into();
         self.attachments.insert(
@@ -115,20 +181,72 @@ impl RenderPassBuilder {
             NamedAttachment {
                 name: name_str.clone(),
                 format,
+                image: None,
+                read_only: false,
             },
         );
         self
     }
 
+    /// Test against an existing depth image without clearing or writing it,
+    /// e.g. the depth buffer of an earlier geometry pass.
+    pub fn read_only_depth_attachment(
+        mut self,
+        name: impl Into<String>,
+        format: Format,
+        image: Handle<Image>,
+        view: Handle<ImageView>,
+    ) -> Self {
+        let name_str = name.into();
+        self.attachments.insert(
+            name_str.clone(),
+            NamedAttachment {
+                name: name_str.clone(),
+                format,
+                image: Some((image, view)),
+                read_only: true,
+            },
+        );
+        self
+    }
+
+    fn first_depth_attachment(&self) -> Option<&String> {
+        self.attachments
+            .iter()
+            .find(|(_, a)| FormatClass::of(a.format).is_depth_stencil_attachment())
+            .map(|(k, _)| k)
+    }
+
     pub fn subpass<C, Dep>(
+        self,
+        name: impl Into<String>,
+        color_attachments: C,
+        depends_on: Dep,
+    ) -> Self
+    where
+        C: IntoIterator,
+        C::Item: ToString,
+        Dep: IntoIterator,
+        Dep::Item: ToString,
+    {
+        self.subpass_with_inputs(name, color_attachments, Vec::<String>::new(), depends_on)
+    }
+
+    /// Add a subpass that also reads `input_attachments`, written by the
+    /// subpasses it depends on, as input attachments. Building fails while
+    /// dashi cannot declare input attachments.
+    pub fn subpass_with_inputs<C, I, Dep>(
         mut self,
         name: impl Into<String>,
         color_attachments: C,
+        input_attachments: I,
         depends_on: Dep,
     ) -> Self
     where
         C: IntoIterator,
         C::Item: ToString,
+        I: IntoIterator,
+        I::Item: ToString,
         Dep: IntoIterator,
         Dep::Item: ToString,
     {
@@ -136,6 +254,10 @@ impl RenderPassBuilder {
             .into_iter()
             .map(|s| s.to_string())
             .collect();
+        let input_attachments = input_attachments
+            .into_iter()
+            .map(|s| s.to_string())
+            .collect();
         let depends_on = depends_on.into_iter().map(|d| d.to_string()).collect();
 
         let global_depth = self
@@ -146,19 +268,14 @@ impl RenderPassBuilder {
                     .iter()
                     .any(|sp| sp.depth_stencil_attachment.as_ref() == Some(*k))
             })
-            .or_else(|| {
-                self.attachments
-                    .keys()
-                    .find(|k| matches!(self.attachments.get(*k).unwrap().format, Format::D24S8))
-            });
+            .or_else(|| self.first_depth_attachment())
+            .cloned();
 
         self.subpasses.push(NamedSubpass {
             name: name.into(),
             color_attachments,
-            depth_stencil_attachment: match global_depth {
-                Some(a) => Some(a.clone()),
-                None => None,
-            },
+            input_attachments,
+            depth_stencil_attachment: global_depth,
             depends_on,
         });
         self
@@ -177,6 +294,14 @@ impl RenderPassBuilder {
             name_to_subpass_index.insert(sp.name.clone(), i as u32);
         }
 
+        let samples = self.samples.max(1);
+        let reads_inputs = self
+            .subpasses
+            .iter()
+            .any(|sp| !sp.input_attachments.is_empty());
+        if !supports_samples(samples) || reads_inputs {
+            return Err(GPUError::LibraryError());
+        }
         let mut color_refs_storage = Vec::new();
         let mut depth_refs_storage = Vec::new();
         let mut deps_storage = Vec::new();
@@ -193,13 +318,25 @@ impl RenderPassBuilder {
                 .collect::<Vec<_>>();
             color_refs_storage.push(color_refs);
 
-            let depth_ref =
-                sub.depth_stencil_attachment
-                    .as_ref()
-                    .map(|name| AttachmentDescription {
-                        format: self.attachments.get(name).unwrap().format,
+            let depth_ref = sub.depth_stencil_attachment.as_ref().map(|name| {
+                let att = self.attachments.get(name).unwrap();
+                if att.read_only {
+                    // Keep the existing contents instead of clearing them.
+                    AttachmentDescription {
+                        format: att.format,
+                        load_op: LoadOp::Load,
+                        store_op: StoreOp::Store,
+                        stencil_load_op: LoadOp::Load,
+                        stencil_store_op: StoreOp::Store,
+                        ..Default::default()
+                    }
+                } else {
+                    AttachmentDescription {
+                        format: att.format,
                         ..Default::default()
-                    });
+                    }
+                }
+            });
             depth_refs_storage.push(depth_ref);
 
             let deps = sub
@@ -232,26 +369,10 @@ impl RenderPassBuilder {
 
         let mut name_to_render_attachment = IndexMap::new();
         for (name, att) in &self.attachments {
-            let image = ctx.make_image(&ImageInfo {
-                debug_name: &att.name,
-                dim: [self.extent[0], self.extent[1], 1],
-                format: att.format,
-                mip_levels: 1,
-                layers: 1,
-                initial_data: None,
-            })?;
-
-            let view = ctx.make_image_view(&ImageViewInfo {
-                debug_name: &att.name,
-                img: image,
-                layer: 0,
-                mip_level: 0,
-                aspect: if att.format == Format::D24S8 {
-                    AspectMask::DepthStencil
-                } else {
-                    Default::default()
-                }
-            })?;
+            let (image, view) = match att.image {
+                Some(existing) => existing,
+                None => make_attachment_image(ctx, &att.name, att.format, self.extent)?,
+            };
 
             let clear = match att.format {
                 Format::R8Sint => dashi::ClearValue::IntColor([0, 0, 0, 1]),
@@ -275,6 +396,9 @@ impl RenderPassBuilder {
                     name: name.clone(),
                     format: att.format,
                     attachment,
+                    image,
+                    samples,
+                    read_only: att.read_only,
                 },
             );
         }
@@ -289,11 +413,7 @@ impl RenderPassBuilder {
                     .iter()
                     .any(|sp| sp.depth_stencil_attachment.as_ref() == Some(*k))
             })
-            .or_else(|| {
-                self.attachments
-                    .keys()
-                    .find(|k| matches!(self.attachments.get(*k).unwrap().format, Format::D24S8))
-            });
+            .or_else(|| self.first_depth_attachment());
 
         for sp in &self.subpasses {
             let colors = sp
@@ -350,10 +470,14 @@ impl RenderPassBuilder {
     pub fn from_yaml_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
         let file = std::fs::File::open(path)?;
         let config: YamlRenderPass = serde_yaml::from_reader(file)?;
-        return Ok(RenderPassBuilder::from_yaml(config));
+        Ok(RenderPassBuilder::from_yaml(config)?)
     }
 
-    pub fn from_yaml(config: YamlRenderPass) -> Self {
+    /// Build a render pass description from its YAML form.
+    ///
+    /// Returns an error if a subpass names a depth/stencil attachment that is
+    /// not declared.
+    pub fn from_yaml(config: YamlRenderPass) -> Result<Self, String> {
         let mut builder = RenderPassBuilder::new();
 
         if let Some(name) = config.debug_name {
@@ -369,18 +493,34 @@ impl RenderPassBuilder {
         }
 
         for att in config.attachments {
-            builder = builder.color_attachment(att.name, att.format);
+            builder = if FormatClass::of(att.format).is_color() {
+                builder.color_attachment(att.name, att.format)
+            } else {
+                builder.depth_attachment(att.name, att.format)
+            };
         }
 
         for sp in config.subpasses {
-            builder = builder.subpass(
+            let depth = sp.depth_stencil_attachment;
+            builder = builder.subpass_with_inputs(
                 sp.name,
                 sp.color_attachments,
+                sp.input_attachments,
                 sp.depends_on,
             );
+            if let Some(depth) = depth {
+                if !builder.attachments.contains_key(&depth) {
+                    let subpass = &builder.subpasses.last().unwrap().name;
+                    return Err(format!(
+                        "subpass '{}' uses unknown depth/stencil attachment '{}'",
+                        subpass, depth
+                    ));
+                }
+                builder.subpasses.last_mut().unwrap().depth_stencil_attachment = Some(depth);
+            }
         }
 
-        builder
+        Ok(builder)
     }
 }
 
@@ -513,14 +653,37 @@ mod tests {
         "#;
 
         let parsed: YamlRenderPass = serde_yaml::from_str(yaml).unwrap();
-        let builder = RenderPassBuilder::from_yaml(parsed);
+        let builder = RenderPassBuilder::from_yaml(parsed).unwrap();
 
         assert_eq!(builder.subpasses.len(), 1);
         assert!(builder.attachments.contains_key("color"));
         assert!(builder.attachments.contains_key("depth"));
+        assert_eq!(
+            builder.subpasses[0].depth_stencil_attachment.as_deref(),
+            Some("depth")
+        );
         assert_eq!(builder.extent, [640, 480]);
     }
 
+    #[test]
+    fn from_yaml_rejects_unknown_depth_attachment() {
+        let yaml = r#"
+            debug_name: bad_depth
+            attachments:
+              - name: color
+                format: RGBA8
+            subpasses:
+              - name: main
+                color_attachments: [color]
+                depth_stencil_attachment: depth
+                depends_on: []
+        "#;
+
+        let parsed: YamlRenderPass = serde_yaml::from_str(yaml).unwrap();
+        let err = RenderPassBuilder::from_yaml(parsed).err().unwrap();
+        assert!(err.contains("'depth'"));
+    }
+
     #[test]
     #[serial]
     fn build_multiple_subpasses_with_deps() {
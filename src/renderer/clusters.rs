@@ -0,0 +1,500 @@
+//! Clustered light culling.
+//!
+//! [`Renderer::enable_clustered_lights`] splits the frustum of the active
+//! camera slot, the one frustum culling uses, into a grid of view-space
+//! froxels. Every frame a compute pass tests each light added with
+//! [`Renderer::add_light`] against every froxel and publishes the result as
+//! two storage buffers. [`LIGHT_CLUSTERS`] holds the offset and
+//! count of each cluster's run in [`LIGHT_INDICES`], which holds light
+//! indices into `bindless_lights`. [`bin_lights`] is the CPU reference of the
+//! same binning.
+
+use super::{cascade_splits, Renderer};
+use crate::material::{build_cluster_pipeline, LightDesc, LightKind, PipelineError};
+use crate::render_graph::GraphError;
+use bytemuck::{Pod, Zeroable};
+use dashi::utils::*;
+use dashi::*;
+use glam::{Mat4, Vec3};
+
+/// Storage buffer with an `(offset, count)` pair per cluster.
+pub const LIGHT_CLUSTERS: &str = "light_clusters";
+/// Storage buffer with the light indices of every cluster.
+pub const LIGHT_INDICES: &str = "light_indices";
+/// Lights the culling pass reads. Must match `MAX_LIGHTS` in
+/// `light_clusters.comp`.
+pub const MAX_CLUSTERED_LIGHTS: usize = 64;
+/// Compute pipeline id of the culling pass.
+const CLUSTER_PIPELINE: &str = "light_clusters";
+const CLUSTER_PARAMS: &str = "cluster_params";
+const CLUSTER_BOUNDS: &str = "cluster_bounds";
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Number of froxels along each axis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClusterGrid {
+    /// Screen tiles along x and y.
+    pub tiles: [u32; 2],
+    /// Depth slices, spaced exponentially between the camera planes.
+    pub slices: u32,
+}
+
+impl Default for ClusterGrid {
+    fn default() -> Self {
+        Self {
+            tiles: [16, 9],
+            slices: 24,
+        }
+    }
+}
+
+impl ClusterGrid {
+    pub fn count(&self) -> usize {
+        (self.tiles[0] * self.tiles[1] * self.slices) as usize
+    }
+
+    /// Index of the cluster at tile `x`, `y` and depth slice `z`.
+    pub fn index(&self, x: u32, y: u32, z: u32) -> usize {
+        (x + y * self.tiles[0] + z * self.tiles[0] * self.tiles[1]) as usize
+    }
+}
+
+/// View-space bounding box of one cluster, as laid out in the bounds buffer.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct ClusterBounds {
+    pub min: [f32; 4],
+    pub max: [f32; 4],
+}
+
+/// Light lists of every cluster, laid out as the GPU publishes them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClusterAssignment {
+    /// `(offset, count)` into `indices` per cluster.
+    pub clusters: Vec<[u32; 2]>,
+    /// [`MAX_CLUSTERED_LIGHTS`] slots per cluster.
+    pub indices: Vec<u32>,
+}
+
+impl ClusterAssignment {
+    /// Lights affecting `cluster`.
+    pub fn lights(&self, cluster: usize) -> &[u32] {
+        let [offset, count] = self.clusters[cluster];
+        &self.indices[offset as usize..(offset + count) as usize]
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct ClusterParams {
+    view: [[f32; 4]; 4],
+    grid: [u32; 4],
+}
+
+/// View transform aligned with the perspective `view_proj` seen from `eye`,
+/// and the near and far distances of its frustum. Assumes a `[0, 1]` depth
+/// range, as frustum culling does.
+fn camera_frame(view_proj: Mat4, eye: Vec3) -> (Mat4, f32, f32) {
+    let inv = view_proj.inverse();
+    let at = |x: f32, y: f32, z: f32| inv.project_point3(Vec3::new(x, y, z));
+    let forward = at(0.0, 0.0, 1.0) - at(0.0, 0.0, 0.0);
+    let up = at(0.0, 1.0, 0.0) - at(0.0, -1.0, 0.0);
+    let view = Mat4::look_to_rh(eye, forward.normalize(), up.normalize());
+    let depth = |p: Vec3| -view.transform_point3(p).z;
+    (view, depth(at(0.0, 0.0, 0.0)), depth(at(0.0, 0.0, 1.0)))
+}
+
+/// Bounding boxes of every cluster of `grid` in the view space of the
+/// camera with perspective `view_proj` at `eye`. Tile row 0 starts at NDC
+/// y = -1, and slice 0 starts at the near plane.
+pub fn cluster_bounds(grid: &ClusterGrid, view_proj: Mat4, eye: Vec3) -> Vec<ClusterBounds> {
+    let inv = view_proj.inverse();
+    let (view, near, far) = camera_frame(view_proj, eye);
+    // View-space point on the near plane through NDC `x`, `y`.
+    let on_near = |x: f32, y: f32| view.transform_point3(inv.project_point3(Vec3::new(x, y, 0.0)));
+    let [tiles_x, tiles_y] = grid.tiles;
+    let mut depths = vec![near];
+    depths.extend(cascade_splits(near, far, grid.slices, 1.0));
+
+    let mut bounds = Vec::with_capacity(grid.count());
+    for z in 0..grid.slices as usize {
+        for y in 0..tiles_y {
+            for x in 0..tiles_x {
+                let ndc_x = [x, x + 1].map(|t| t as f32 / tiles_x as f32 * 2.0 - 1.0);
+                let ndc_y = [y, y + 1].map(|t| t as f32 / tiles_y as f32 * 2.0 - 1.0);
+                let mut min = Vec3::splat(f32::MAX);
+                let mut max = Vec3::splat(f32::MIN);
+                for nx in ndc_x {
+                    for ny in ndc_y {
+                        let corner = on_near(nx, ny);
+                        for depth in [depths[z], depths[z + 1]] {
+                            let p = corner * (depth / -corner.z);
+                            min = min.min(p);
+                            max = max.max(p);
+                        }
+                    }
+                }
+                bounds.push(ClusterBounds {
+                    min: min.extend(0.0).into(),
+                    max: max.extend(0.0).into(),
+                });
+            }
+        }
+    }
+    bounds
+}
+
+/// View-space sphere a light reaches, or `None` if it reaches everywhere.
+/// Directional lights and lights without a positive range are unbounded.
+fn light_sphere(light: &LightDesc, view: Mat4) -> Option<(Vec3, f32)> {
+    if light.kind() == LightKind::Directional || light.range <= 0.0 {
+        return None;
+    }
+    let mut radius = light.range;
+    if light.kind() == LightKind::Area {
+        radius += 0.5 * glam::Vec2::from(light.area_extent).length();
+    }
+    Some((view.transform_point3(Vec3::from(light.position)), radius))
+}
+
+/// CPU reference of the culling pass: assign the first
+/// [`MAX_CLUSTERED_LIGHTS`] of `lights` to every cluster they reach.
+pub fn bin_lights(
+    grid: &ClusterGrid,
+    view_proj: Mat4,
+    eye: Vec3,
+    lights: &[LightDesc],
+) -> ClusterAssignment {
+    let bounds = cluster_bounds(grid, view_proj, eye);
+    let (view, _, _) = camera_frame(view_proj, eye);
+    let spheres: Vec<_> = lights
+        .iter()
+        .take(MAX_CLUSTERED_LIGHTS)
+        .map(|l| light_sphere(l, view))
+        .collect();
+    let mut out = ClusterAssignment {
+        clusters: Vec::with_capacity(bounds.len()),
+        indices: vec![0; bounds.len() * MAX_CLUSTERED_LIGHTS],
+    };
+    for (cluster, b) in bounds.iter().enumerate() {
+        let lo = Vec3::from_slice(&b.min);
+        let hi = Vec3::from_slice(&b.max);
+        let offset = cluster * MAX_CLUSTERED_LIGHTS;
+        let mut count = 0;
+        for (index, sphere) in spheres.iter().enumerate() {
+            let hit = match sphere {
+                Some((center, radius)) => {
+                    let d = *center - center.clamp(lo, hi);
+                    d.length_squared() <= radius * radius
+                }
+                None => true,
+            };
+            if hit {
+                out.indices[offset + count] = index as u32;
+                count += 1;
+            }
+        }
+        out.clusters.push([offset as u32, count as u32]);
+    }
+    out
+}
+
+/// GPU state owned by the culling pass.
+pub(super) struct ClusterPass {
+    grid: ClusterGrid,
+    /// Camera the bounds were last built for.
+    view_proj: Mat4,
+    eye: Vec3,
+    /// View transform of that camera, written to the parameters.
+    view: Mat4,
+    params: Handle<Buffer>,
+    bounds: Handle<Buffer>,
+    clusters: Handle<Buffer>,
+    indices: Handle<Buffer>,
+    /// Light count the pipeline's bind groups were created with.
+    bound_lights: usize,
+}
+
+fn storage_buffer(
+    ctx: &mut Context,
+    name: &'static str,
+    size: usize,
+) -> Result<Handle<Buffer>, GPUError> {
+    ctx.make_buffer(&BufferInfo {
+        debug_name: name,
+        byte_size: size as u32,
+        visibility: MemoryVisibility::CpuAndGpu,
+        usage: BufferUsage::STORAGE,
+        initial_data: None,
+    })
+}
+
+fn upload_bounds(ctx: &mut Context, pass: &ClusterPass) -> Result<(), GPUError> {
+    let bounds = cluster_bounds(&pass.grid, pass.view_proj, pass.eye);
+    let bytes: &[u8] = bytemuck::cast_slice(&bounds);
+    let slice = ctx.map_buffer_mut(pass.bounds)?;
+    slice[..bytes.len()].copy_from_slice(bytes);
+    ctx.unmap_buffer(pass.bounds)?;
+    Ok(())
+}
+
+impl Renderer {
+    /// Allocate the cluster buffers for `grid`, register [`LIGHT_CLUSTERS`]
+    /// and [`LIGHT_INDICES`] with the
+    /// [`ResourceManager`](crate::utils::ResourceManager) and bin the lights
+    /// every frame from then on. Calling this again does nothing.
+    ///
+    /// The clusters split the frustum of the camera slot that frustum culling
+    /// uses, see [`Renderer::set_culling_camera`]. Until
+    /// [`Renderer::set_camera`] sets that slot they split a 60 degree
+    /// perspective from the origin, looking down -z.
+    ///
+    /// A culling pipeline that cannot be built fails with
+    /// [`GraphError::Pipeline`] naming [`LIGHT_CLUSTERS`]. Its buffers are
+    /// destroyed again and clustering stays off.
+    pub fn enable_clustered_lights(&mut self, grid: ClusterGrid) -> Result<(), GraphError> {
+        if self.clusters.is_some() {
+            return Ok(());
+        }
+        let ctx = self.get_ctx();
+        let count = grid.count();
+        let params = ctx.make_buffer(&BufferInfo {
+            debug_name: CLUSTER_PARAMS,
+            byte_size: std::mem::size_of::<ClusterParams>() as u32,
+            visibility: MemoryVisibility::CpuAndGpu,
+            usage: BufferUsage::UNIFORM,
+            initial_data: None,
+        })?;
+        let bounds = storage_buffer(
+            ctx,
+            CLUSTER_BOUNDS,
+            count * std::mem::size_of::<ClusterBounds>(),
+        )?;
+        let clusters = storage_buffer(ctx, LIGHT_CLUSTERS, count * 8)?;
+        let indices = storage_buffer(ctx, LIGHT_INDICES, count * MAX_CLUSTERED_LIGHTS * 4)?;
+        let res = &mut self.resource_manager;
+        res.register_ubo(CLUSTER_PARAMS, params);
+        res.register_storage(CLUSTER_BOUNDS, bounds);
+        res.register_storage(LIGHT_CLUSTERS, clusters);
+        res.register_storage(LIGHT_INDICES, indices);
+
+        let built = build_cluster_pipeline(ctx, res)
+            .and_then(|mut pso| pso.create_bind_groups(res).map(|groups| (pso, groups)));
+        let (pso, groups) = match built {
+            Ok(built) => built,
+            Err(error) => {
+                for (name, buffer) in [
+                    (CLUSTER_PARAMS, params),
+                    (CLUSTER_BOUNDS, bounds),
+                    (LIGHT_CLUSTERS, clusters),
+                    (LIGHT_INDICES, indices),
+                ] {
+                    res.bindings.remove(name);
+                    ctx.destroy_buffer(buffer);
+                }
+                return Err(GraphError::Pipeline {
+                    node: LIGHT_CLUSTERS.to_string(),
+                    pipeline: "light_cluster_pipeline".to_string(),
+                    error,
+                });
+            }
+        };
+        self.register_compute_pipeline(CLUSTER_PIPELINE, pso, groups);
+
+        let aspect = self.width as f32 / self.height.max(1) as f32;
+        let view_proj = self
+            .culling
+            .view_proj
+            .unwrap_or_else(|| Mat4::perspective_rh(60f32.to_radians(), aspect, 0.1, 100.0));
+        let eye = self.culling.eye.unwrap_or(Vec3::ZERO);
+        let pass = ClusterPass {
+            grid,
+            view_proj,
+            eye,
+            view: camera_frame(view_proj, eye).0,
+            params,
+            bounds,
+            clusters,
+            indices,
+            bound_lights: self.lights.len(),
+        };
+        upload_bounds(ctx, &pass)?;
+        self.clusters = Some(pass);
+        Ok(())
+    }
+
+    /// Grid set with [`Renderer::enable_clustered_lights`].
+    pub fn cluster_grid(&self) -> Option<ClusterGrid> {
+        self.clusters.as_ref().map(|p| p.grid)
+    }
+
+    /// Read back the light lists written by the last frame's culling pass.
+    pub fn read_light_clusters(&mut self) -> Option<ClusterAssignment> {
+        let pass = self.clusters.as_ref()?;
+        let ctx = self.get_ctx();
+        let count = pass.grid.count();
+        let slice = ctx.map_buffer::<u8>(pass.clusters).ok()?;
+        let clusters = bytemuck::cast_slice::<u8, [u32; 2]>(&slice[..count * 8]).to_vec();
+        ctx.unmap_buffer(pass.clusters).ok()?;
+        let len = count * MAX_CLUSTERED_LIGHTS;
+        let slice = ctx.map_buffer::<u8>(pass.indices).ok()?;
+        let indices = bytemuck::cast_slice::<u8, u32>(&slice[..len * 4]).to_vec();
+        ctx.unmap_buffer(pass.indices).ok()?;
+        Some(ClusterAssignment { clusters, indices })
+    }
+
+    /// Rebuild the cluster bounds when the camera moved, write the culling
+    /// parameters, rebuild the bind groups when lights were added or removed,
+    /// and queue the culling dispatch. If the bind groups cannot be rebuilt
+    /// the previous ones stay, the rebuild is tried again next frame and the
+    /// dispatch still runs.
+    pub(super) fn update_clusters(&mut self) -> Result<(), PipelineError> {
+        let count = self.lights.len();
+        let Some(pass) = self.clusters.as_mut() else {
+            return Ok(());
+        };
+        let ctx = unsafe { &mut *self.ctx };
+        let view_proj = self.culling.view_proj.unwrap_or(pass.view_proj);
+        let eye = self.culling.eye.unwrap_or(pass.eye);
+        if view_proj != pass.view_proj || eye != pass.eye {
+            pass.view_proj = view_proj;
+            pass.eye = eye;
+            pass.view = camera_frame(view_proj, eye).0;
+            let _ = upload_bounds(ctx, pass);
+        }
+        let params = ClusterParams {
+            view: pass.view.to_cols_array_2d(),
+            grid: [
+                pass.grid.tiles[0],
+                pass.grid.tiles[1],
+                pass.grid.slices,
+                count.min(MAX_CLUSTERED_LIGHTS) as u32,
+            ],
+        };
+        if let Ok(slice) = ctx.map_buffer_mut(pass.params) {
+            let bytes = bytemuck::bytes_of(&params);
+            slice[..bytes.len()].copy_from_slice(bytes);
+            let _ = ctx.unmap_buffer(pass.params);
+        }
+        let groups = (pass.grid.count() as u32).div_ceil(WORKGROUP_SIZE);
+        let mut rebound = Ok(());
+        if count != pass.bound_lights {
+            let res = &self.resource_manager;
+            if let Some((pso, bind_groups)) = self.compute_pipelines.get_mut(CLUSTER_PIPELINE) {
+                rebound = pso.create_bind_groups(res).map(|new| *bind_groups = new);
+            }
+            if rebound.is_ok() {
+                pass.bound_lights = count;
+            }
+        }
+        self.queue_compute(CLUSTER_PIPELINE, [groups, 1, 1]);
+        rebound
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 60 degree perspective at the origin, looking down -z.
+    fn camera() -> Mat4 {
+        Mat4::perspective_rh(60f32.to_radians(), 1.0, 1.0, 100.0)
+    }
+
+    fn grid() -> ClusterGrid {
+        ClusterGrid {
+            tiles: [4, 4],
+            slices: 8,
+        }
+    }
+
+    #[test]
+    fn bounds_tile_the_frustum() {
+        let bounds = cluster_bounds(&grid(), camera(), Vec3::ZERO);
+        assert_eq!(bounds.len(), grid().count());
+        let first = bounds[grid().index(0, 0, 0)];
+        assert!((first.max[2] + 1.0).abs() < 1e-3);
+        assert!(first.min[0] < 0.0 && first.min[1] < 0.0);
+        let last = bounds[grid().index(3, 3, 7)];
+        assert!((last.min[2] + 100.0).abs() < 1e-2);
+        assert!(last.max[0] > 0.0 && last.max[1] > 0.0);
+    }
+
+    #[test]
+    fn point_lights_only_reach_nearby_clusters() {
+        let light = LightDesc::point([0.5, 0.5, -10.0], 0.1);
+        let out = bin_lights(&grid(), camera(), Vec3::ZERO, &[light]);
+        let hits: Vec<usize> = (0..grid().count())
+            .filter(|&c| !out.lights(c).is_empty())
+            .collect();
+        assert!(!hits.is_empty());
+        assert!(hits.len() < 4);
+        let bounds = cluster_bounds(&grid(), camera(), Vec3::ZERO);
+        for c in hits {
+            assert_eq!(out.lights(c), &[0]);
+            assert!(bounds[c].min[2] <= -9.9 && bounds[c].max[2] >= -10.1);
+        }
+
+        let behind = LightDesc::point([0.0, 0.0, 10.0], 1.0);
+        let out = bin_lights(&grid(), camera(), Vec3::ZERO, &[behind]);
+        assert!(out.clusters.iter().all(|c| c[1] == 0));
+    }
+
+    #[test]
+    fn unbounded_lights_reach_every_cluster() {
+        let lights = [
+            LightDesc::directional([0.0, -1.0, 0.0]),
+            LightDesc::point([0.0, 0.0, 10.0], 0.0),
+        ];
+        let out = bin_lights(&grid(), camera(), Vec3::ZERO, &lights);
+        for c in 0..grid().count() {
+            assert_eq!(out.lights(c), &[0, 1]);
+            assert_eq!(out.clusters[c][0] as usize, c * MAX_CLUSTERED_LIGHTS);
+        }
+    }
+
+    #[test]
+    fn area_extent_widens_the_reach() {
+        let position = [0.0, 0.0, -50.0];
+        let point = LightDesc::point(position, 0.5);
+        let area = LightDesc::area(position, [0.0, 0.0, 1.0], [20.0, 20.0]).with_range(0.5);
+        let out = bin_lights(&grid(), camera(), Vec3::ZERO, &[point, area]);
+        let reach = |light: u32| {
+            (0..grid().count())
+                .filter(|&c| out.lights(c).contains(&light))
+                .count()
+        };
+        assert!(reach(1) > reach(0));
+    }
+
+    #[test]
+    fn clusters_follow_the_camera() {
+        let eye = Vec3::new(5.0, 0.0, 0.0);
+        let view = Mat4::look_at_rh(eye, Vec3::new(5.0, 0.0, -10.0), Vec3::Y);
+        let moved = cluster_bounds(&grid(), camera() * view, eye);
+        let still = cluster_bounds(&grid(), camera(), Vec3::ZERO);
+        for (a, b) in moved.iter().zip(&still) {
+            for i in 0..3 {
+                assert!((a.min[i] - b.min[i]).abs() < 1e-2);
+                assert!((a.max[i] - b.max[i]).abs() < 1e-2);
+            }
+        }
+
+        let light = LightDesc::point([5.5, 0.5, -10.0], 0.1);
+        let out = bin_lights(&grid(), camera() * view, eye, &[light]);
+        let expected = bin_lights(
+            &grid(),
+            camera(),
+            Vec3::ZERO,
+            &[LightDesc::point([0.5, 0.5, -10.0], 0.1)],
+        );
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn lights_past_the_limit_are_ignored() {
+        let lights = vec![LightDesc::directional([0.0, -1.0, 0.0]); MAX_CLUSTERED_LIGHTS + 4];
+        let out = bin_lights(&grid(), camera(), Vec3::ZERO, &lights);
+        assert_eq!(out.clusters[0][1] as usize, MAX_CLUSTERED_LIGHTS);
+    }
+}
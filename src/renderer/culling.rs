@@ -0,0 +1,292 @@
+//! Bounding volumes and frustum culling.
+//!
+//! Drawables take their [`Bounds`] from the mesh when they are registered or
+//! updated: the bounds it carries, as glTF meshes do from their accessor
+//! min/max, or else those of its vertices. Culling is on by default and
+//! [`Renderer::set_frustum_culling`] turns it off. Before recording,
+//! [`Renderer::present_frame`] tests every visible static and skeletal
+//! drawable against the frustum of the culling camera set with
+//! [`Renderer::set_camera`] and skips the ones outside it. Shadow passes
+//! still draw culled meshes, since they may cast shadows into view.
+//!
+//! Skeletal meshes are tested with their bind-pose bounds, so an animation
+//! that moves vertices well outside the bind pose can be culled while part
+//! of it is still on screen.
+
+use super::Renderer;
+use glam::{Mat4, Vec3, Vec4};
+
+/// Axis-aligned box and bounding sphere of a mesh in model space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub min: Vec3,
+    pub max: Vec3,
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl Bounds {
+    /// Bounds of a box, with the sphere through its corners.
+    pub fn from_aabb(min: Vec3, max: Vec3) -> Self {
+        let center = (min + max) * 0.5;
+        Self {
+            min,
+            max,
+            center,
+            radius: (max - center).length(),
+        }
+    }
+
+    /// Bounds of a point cloud, or `None` if it is empty. The sphere is
+    /// centered on the box and reaches the farthest point.
+    pub fn from_points(points: impl Iterator<Item = Vec3> + Clone) -> Option<Self> {
+        let (min, max) = points.clone().fold(None, |acc, p| match acc {
+            None => Some((p, p)),
+            Some((min, max)) => Some((p.min(min), p.max(max))),
+        })?;
+        let center = (min + max) * 0.5;
+        let radius = points.map(|p| p.distance(center)).fold(0.0, f32::max);
+        Some(Self {
+            min,
+            max,
+            center,
+            radius,
+        })
+    }
+
+    /// Bounds enclosing these bounds after `transform`.
+    pub fn transformed(&self, transform: Mat4) -> Self {
+        let corners = (0..8).map(|i| {
+            transform.transform_point3(Vec3::new(
+                if i & 1 == 0 { self.min.x } else { self.max.x },
+                if i & 2 == 0 { self.min.y } else { self.max.y },
+                if i & 4 == 0 { self.min.z } else { self.max.z },
+            ))
+        });
+        let (min, max) = corners.fold((Vec3::MAX, Vec3::MIN), |(min, max), p| {
+            (min.min(p), max.max(p))
+        });
+        let scale = transform
+            .x_axis
+            .truncate()
+            .length()
+            .max(transform.y_axis.truncate().length())
+            .max(transform.z_axis.truncate().length());
+        Self {
+            min,
+            max,
+            center: transform.transform_point3(self.center),
+            radius: self.radius * scale,
+        }
+    }
+}
+
+/// The six planes of a view-projection's clip volume, pointing inward.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    pub planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Planes of `view_proj`, assuming a `[0, 1]` depth range.
+    pub fn from_view_proj(view_proj: Mat4) -> Self {
+        let [r0, r1, r2, r3] = [0, 1, 2, 3].map(|i| view_proj.row(i));
+        let planes = [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2]
+            .map(|p| p / p.truncate().length().max(f32::EPSILON));
+        Self { planes }
+    }
+
+    /// Whether `bounds`, already in world space, may be inside. Tests the
+    /// sphere first and then the box.
+    pub fn intersects(&self, bounds: &Bounds) -> bool {
+        self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+            if normal.dot(bounds.center) + plane.w < -bounds.radius {
+                return false;
+            }
+            let farthest = Vec3::select(normal.cmpge(Vec3::ZERO), bounds.max, bounds.min);
+            normal.dot(farthest) + plane.w >= 0.0
+        })
+    }
+}
+
+/// Drawables kept and skipped by the last frame's frustum culling.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CullStats {
+    pub visible: u32,
+    pub culled: u32,
+}
+
+/// Culling state of the renderer.
+pub(super) struct Culling {
+    pub(super) enabled: bool,
+    /// Camera slot whose frustum drawables are tested against.
+    pub(super) camera: usize,
+    /// View-projection last set for [`Culling::camera`].
+    pub(super) view_proj: Option<Mat4>,
+    /// Position last set for [`Culling::camera`], used to sort transparent
+    /// draws.
+    pub(super) eye: Option<Vec3>,
+    pub(super) stats: CullStats,
+}
+
+impl Default for Culling {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            camera: 0,
+            view_proj: None,
+            eye: None,
+            stats: CullStats::default(),
+        }
+    }
+}
+
+/// Whether any of `instances` places `bounds` inside `frustum`. Meshes
+/// without bounds are never culled.
+fn any_in_view(
+    frustum: &Frustum,
+    bounds: Option<&Bounds>,
+    mut instances: impl Iterator<Item = Mat4>,
+) -> bool {
+    let Some(bounds) = bounds else {
+        return true;
+    };
+    instances.any(|model| frustum.intersects(&bounds.transformed(model)))
+}
+
+impl Renderer {
+    /// Turn frustum culling on or off. It is on by default.
+    pub fn set_frustum_culling(&mut self, enabled: bool) {
+        self.culling.enabled = enabled;
+    }
+
+    /// Cull against camera slot `index` instead of slot 0. Takes effect once
+    /// [`Renderer::set_camera`] sets that slot.
+    pub fn set_culling_camera(&mut self, index: usize) {
+        if self.culling.camera != index {
+            self.culling.camera = index;
+            self.culling.view_proj = None;
+            self.culling.eye = None;
+        }
+    }
+
+    /// Drawables kept and skipped by the last frame.
+    pub fn cull_stats(&self) -> CullStats {
+        self.culling.stats
+    }
+
+    /// Mark every visible static and skeletal drawable as in or out of view.
+    /// Nothing is culled before the culling camera has been set.
+    pub(super) fn cull_drawables(&mut self) {
+        let frustum = match self.culling.view_proj {
+            Some(view_proj) if self.culling.enabled => Some(Frustum::from_view_proj(view_proj)),
+            _ => None,
+        };
+        let mut stats = CullStats::default();
+        let mut count = |in_view: bool| {
+            if in_view {
+                stats.visible += 1;
+            } else {
+                stats.culled += 1;
+            }
+            in_view
+        };
+        for drawable in self
+            .drawables
+            .iter_mut()
+            .filter(|d| d.visible && !d.instances.is_empty())
+        {
+            let in_view = match &frustum {
+                Some(f) => {
+                    let models = drawable.instances.iter().map(|i| i.model());
+                    any_in_view(f, drawable.bounds.as_ref(), models)
+                }
+                None => true,
+            };
+            drawable.in_view = count(in_view);
+        }
+        for drawable in self.skeletal_meshes.iter_mut().filter(|d| d.visible) {
+            // Skinning places the vertices, so test the bind-pose bounds. They
+            // miss poses that reach outside the bind pose.
+            let in_view = match &frustum {
+                Some(f) => {
+                    let bounds = drawable.bounds.as_ref();
+                    any_in_view(f, bounds, std::iter::once(Mat4::IDENTITY))
+                }
+                None => true,
+            };
+            drawable.in_view = count(in_view);
+        }
+        self.culling.stats = stats;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera() -> Mat4 {
+        let proj = Mat4::perspective_rh(60f32.to_radians(), 1.0, 0.1, 100.0);
+        proj * Mat4::look_at_rh(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO, Vec3::Y)
+    }
+
+    fn unit_box() -> Bounds {
+        Bounds::from_aabb(Vec3::splat(-0.5), Vec3::splat(0.5))
+    }
+
+    #[test]
+    fn bounds_from_points() {
+        let points = [Vec3::new(-1.0, 0.0, 0.0), Vec3::new(1.0, 2.0, 0.0)];
+        let bounds = Bounds::from_points(points.into_iter()).unwrap();
+        assert_eq!(bounds.min, Vec3::new(-1.0, 0.0, 0.0));
+        assert_eq!(bounds.max, Vec3::new(1.0, 2.0, 0.0));
+        assert_eq!(bounds.center, Vec3::new(0.0, 1.0, 0.0));
+        assert!((bounds.radius - 2f32.sqrt()).abs() < 1e-6);
+        assert!(Bounds::from_points(std::iter::empty()).is_none());
+    }
+
+    #[test]
+    fn transformed_bounds_follow_the_model() {
+        let model = Mat4::from_scale_rotation_translation(
+            Vec3::splat(2.0),
+            glam::Quat::IDENTITY,
+            Vec3::new(10.0, 0.0, 0.0),
+        );
+        let moved = unit_box().transformed(model);
+        assert_eq!(moved.min, Vec3::new(9.0, -1.0, -1.0));
+        assert_eq!(moved.max, Vec3::new(11.0, 1.0, 1.0));
+        assert_eq!(moved.center, Vec3::new(10.0, 0.0, 0.0));
+        assert!((moved.radius - 2.0 * unit_box().radius).abs() < 1e-6);
+    }
+
+    #[test]
+    fn frustum_keeps_boxes_in_view() {
+        let frustum = Frustum::from_view_proj(camera());
+        let at = |x: f32, y: f32, z: f32| {
+            unit_box().transformed(Mat4::from_translation(Vec3::new(x, y, z)))
+        };
+        assert!(frustum.intersects(&at(0.0, 0.0, 0.0)));
+        // Straddling the left plane.
+        assert!(frustum.intersects(&at(-2.9, 0.0, 0.0)));
+        // Behind the camera, past the far plane, and off to each side.
+        assert!(!frustum.intersects(&at(0.0, 0.0, 10.0)));
+        assert!(!frustum.intersects(&at(0.0, 0.0, -200.0)));
+        assert!(!frustum.intersects(&at(-20.0, 0.0, 0.0)));
+        assert!(!frustum.intersects(&at(0.0, 20.0, 0.0)));
+    }
+
+    #[test]
+    fn any_instance_in_view_keeps_the_drawable() {
+        let frustum = Frustum::from_view_proj(camera());
+        let far = Mat4::from_translation(Vec3::new(50.0, 0.0, 0.0));
+        let bounds = unit_box();
+        assert!(!any_in_view(&frustum, Some(&bounds), [far].into_iter()));
+        assert!(any_in_view(
+            &frustum,
+            Some(&bounds),
+            [far, Mat4::IDENTITY].into_iter()
+        ));
+        assert!(any_in_view(&frustum, None, [far].into_iter()));
+    }
+}
@@ -0,0 +1,234 @@
+//! Built-in deferred shading path.
+//!
+//! [`Renderer::enable_deferred`] adds two canvases to the render graph. The
+//! `gbuffer` node rasterizes the static meshes registered with it into albedo,
+//! normal, material and position attachments. The `lighting` node then draws a single
+//! full-screen triangle that samples those attachments and accumulates every
+//! light added with [`Renderer::add_light`] into its `lit` attachment.
+
+use super::{RenderStage, Renderer};
+use crate::canvas::{Canvas, CanvasBuilder, CanvasError};
+use crate::material::{build_gbuffer_pipeline, build_lighting_pipeline, PipelineError};
+use crate::render_graph::{CanvasNode, GraphError, GraphValidationError};
+use crate::utils::{GpuObject, ResourceBinding};
+use dashi::utils::*;
+use dashi::*;
+
+/// Graph node writing the G-buffer.
+pub const GBUFFER_NODE: &str = "gbuffer";
+/// Graph node shading the G-buffer.
+pub const LIGHTING_NODE: &str = "lighting";
+pub const GBUFFER_ALBEDO: &str = "gbuffer_albedo";
+pub const GBUFFER_NORMAL: &str = "gbuffer_normal";
+pub const GBUFFER_MATERIAL: &str = "gbuffer_material";
+/// Position of each texel in the space of the mesh vertices, which is also
+/// the space light positions are given in.
+pub const GBUFFER_POSITION: &str = "gbuffer_position";
+pub const GBUFFER_DEPTH: &str = "gbuffer_depth";
+/// Color attachment of the lighting node holding the shaded image.
+pub const LIT_OUTPUT: &str = "lit";
+/// Lights the lighting pass reads. Must match `MAX_LIGHTS` in `lighting.frag`.
+pub const MAX_DEFERRED_LIGHTS: usize = 64;
+
+const GBUFFER_COLORS: [(&str, Format); 4] = [
+    (GBUFFER_ALBEDO, Format::RGBA8),
+    (GBUFFER_NORMAL, Format::RGBA8),
+    (GBUFFER_MATERIAL, Format::RGBA8),
+    (GBUFFER_POSITION, Format::RGBA32F),
+];
+
+/// GPU state owned by the deferred path.
+pub(super) struct DeferredPass {
+    /// Full-screen triangle drawn by the lighting node.
+    pub(super) triangle: Handle<Buffer>,
+    /// Uniform holding the number of lights to accumulate.
+    light_count: Handle<Buffer>,
+    /// Light count the lighting bind groups were created with.
+    bound_lights: usize,
+}
+
+fn gbuffer_canvas(ctx: &mut Context, output: [u32; 2]) -> Result<Canvas, CanvasError> {
+    let mut builder = CanvasBuilder::new().relative_extent(output, 1.0);
+    for (name, format) in GBUFFER_COLORS {
+        builder = builder.color_attachment(name, format);
+    }
+    builder
+        .depth_attachment(GBUFFER_DEPTH, Format::D24S8)
+        .build(ctx)
+}
+
+fn lighting_canvas(ctx: &mut Context, output: [u32; 2]) -> Result<Canvas, CanvasError> {
+    CanvasBuilder::new()
+        .relative_extent(output, 1.0)
+        .color_attachment(LIT_OUTPUT, Format::RGBA8)
+        .build(ctx)
+}
+
+impl Renderer {
+    /// Add the G-buffer and lighting passes to the render graph and register
+    /// their pipelines as [`RenderStage::GBuffer`] and
+    /// [`RenderStage::Lighting`].
+    ///
+    /// Static meshes registered with the [`GBUFFER_NODE`] node and no material
+    /// pipeline of their own are drawn with the G-buffer pipeline. Calling
+    /// this again does nothing. On failure the nodes, bindings and GPU objects
+    /// added so far are removed again.
+    pub fn enable_deferred(&mut self) -> Result<(), GraphError> {
+        if self.deferred.is_some() {
+            return Ok(());
+        }
+        let previous: Vec<(&str, Option<ResourceBinding>)> = GBUFFER_COLORS
+            .map(|(name, _)| name)
+            .into_iter()
+            .chain(["light_count"])
+            .map(|name| (name, self.resource_manager.bindings.get(name).cloned()))
+            .collect();
+        let mut created = Vec::new();
+        match self.build_deferred(&mut created) {
+            Ok(pass) => {
+                self.deferred = Some(pass);
+                Ok(())
+            }
+            Err(err) => {
+                // Nothing has recorded with these yet, so they are destroyed now.
+                self.graph.remove_node(LIGHTING_NODE);
+                self.graph.remove_node(GBUFFER_NODE);
+                for (name, binding) in previous {
+                    match binding {
+                        Some(binding) => self.resource_manager.bindings.insert(name.into(), binding),
+                        None => self.resource_manager.bindings.remove(name),
+                    };
+                }
+                let ctx = self.get_ctx();
+                for obj in created {
+                    obj.destroy(ctx);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Create the deferred passes, pushing every GPU object to `created`
+    /// until it is owned by the renderer.
+    fn build_deferred(&mut self, created: &mut Vec<GpuObject>) -> Result<DeferredPass, GraphError> {
+        let ctx = self.get_ctx();
+        for name in [GBUFFER_NODE, LIGHTING_NODE] {
+            if self.graph.node_names().iter().any(|n| n == name) {
+                return Err(GraphValidationError::DuplicateNode { name: name.into() }.into());
+            }
+        }
+        let gbuffer = gbuffer_canvas(ctx, self.size())?;
+        created.extend(gbuffer.gpu_objects());
+        let lit = lighting_canvas(ctx, self.size())?;
+        created.extend(lit.gpu_objects());
+
+        let mut lighting = CanvasNode::new(LIGHTING_NODE, lit.clone());
+        for (name, format) in GBUFFER_COLORS {
+            lighting = lighting.sample(name, format);
+        }
+        self.graph
+            .add_node(CanvasNode::new(GBUFFER_NODE, gbuffer.clone()))?;
+        self.graph.add_node(lighting)?;
+        self.graph.connect(GBUFFER_NODE, LIGHTING_NODE)?;
+
+        let sampler = ctx.make_sampler(&SamplerInfo::default())?;
+        created.push(GpuObject::Sampler(sampler));
+        for (name, _) in GBUFFER_COLORS {
+            let missing = || {
+                GraphError::Desc(format!(
+                    "canvas '{}' has no attachment '{}'",
+                    GBUFFER_NODE, name
+                ))
+            };
+            let image = gbuffer.image(name).ok_or_else(missing)?;
+            let view = gbuffer.view(name).ok_or_else(missing)?;
+            self.resource_manager
+                .register_combined(name, image, view, gbuffer.extent(), sampler);
+        }
+        let light_count = ctx.make_buffer(&BufferInfo {
+            debug_name: "deferred_light_count",
+            byte_size: 16,
+            visibility: MemoryVisibility::CpuAndGpu,
+            usage: BufferUsage::UNIFORM,
+            initial_data: Some(&[0u8; 16]),
+        })?;
+        created.push(GpuObject::Buffer(light_count));
+        self.resource_manager
+            .register_ubo("light_count", light_count);
+
+        let vertices: [[f32; 2]; 3] = [[-1.0, -1.0], [3.0, -1.0], [-1.0, 3.0]];
+        let bytes: &[u8] = bytemuck::cast_slice(&vertices);
+        let triangle = ctx.make_buffer(&BufferInfo {
+            debug_name: "deferred_fullscreen_triangle",
+            byte_size: bytes.len() as u32,
+            visibility: MemoryVisibility::Gpu,
+            usage: BufferUsage::VERTEX,
+            initial_data: Some(bytes),
+        })?;
+        created.push(GpuObject::Buffer(triangle));
+
+        let pipeline_error = |node: &str, pipeline: &str, error| GraphError::Pipeline {
+            node: node.into(),
+            pipeline: pipeline.into(),
+            error,
+        };
+        let mut gbuffer_pso = build_gbuffer_pipeline(ctx, gbuffer.output(GBUFFER_ALBEDO));
+        created.extend(gbuffer_pso.gpu_objects());
+        let mut lighting_pso = build_lighting_pipeline(ctx, lit.output(LIT_OUTPUT));
+        created.extend(lighting_pso.gpu_objects());
+        let gbuffer_groups = Self::check_mesh_pipeline(&gbuffer_pso)
+            .and_then(|_| gbuffer_pso.create_bind_groups(&self.resource_manager))
+            .map_err(|e| pipeline_error(GBUFFER_NODE, "gbuffer_pipeline", e))?;
+        created.extend(
+            gbuffer_groups
+                .iter()
+                .flatten()
+                .map(|g| GpuObject::BindGroup(g.bind_group)),
+        );
+        let lighting_groups = Self::check_mesh_pipeline(&lighting_pso)
+            .and_then(|_| lighting_pso.create_bind_groups(&self.resource_manager))
+            .map_err(|e| pipeline_error(LIGHTING_NODE, "lighting_pipeline", e))?;
+
+        self.stage_pipelines
+            .insert(RenderStage::GBuffer, (gbuffer_pso, gbuffer_groups));
+        self.stage_pipelines
+            .insert(RenderStage::Lighting, (lighting_pso, lighting_groups));
+        self.canvases.push(gbuffer);
+        self.canvases.push(lit);
+        Ok(DeferredPass {
+            triangle,
+            light_count,
+            bound_lights: self.lights.len(),
+        })
+    }
+
+    /// Whether [`Renderer::enable_deferred`] has been called.
+    pub fn deferred_enabled(&self) -> bool {
+        self.deferred.is_some()
+    }
+
+    /// Write the light count and rebuild the lighting bind groups when lights
+    /// were added or removed since they were created. On failure the previous
+    /// bind groups stay and the rebuild is tried again next frame.
+    pub(super) fn update_deferred(&mut self) -> Result<(), PipelineError> {
+        let count = self.lights.len();
+        let Some(pass) = self.deferred.as_mut() else {
+            return Ok(());
+        };
+        let ctx = unsafe { &mut *self.ctx };
+        let shaded = count.min(MAX_DEFERRED_LIGHTS) as u32;
+        if let Ok(slice) = ctx.map_buffer_mut(pass.light_count) {
+            slice[..4].copy_from_slice(bytemuck::bytes_of(&shaded));
+            let _ = ctx.unmap_buffer(pass.light_count);
+        }
+        if count == pass.bound_lights {
+            return Ok(());
+        }
+        let res = &self.resource_manager;
+        if let Some((pso, groups)) = self.stage_pipelines.get_mut(&RenderStage::Lighting) {
+            *groups = pso.create_bind_groups(res)?;
+        }
+        pass.bound_lights = count;
+        Ok(())
+    }
+}
@@ -6,8 +6,9 @@
 //! bone matrices each frame. The [`Renderer`](crate::renderer::Renderer)
 //! provides a helper to call this on registered meshes.
 use dashi::{utils::Handle, *};
-use glam::Mat4;
+use glam::{Mat4, Vec3, Vec4};
 use crate::animation::{Animator, Skeleton};
+use super::Bounds;
 
 use bytemuck::{Pod, Zeroable};
 
@@ -35,6 +36,54 @@ pub struct SkeletalVertex {
     pub joint_weights: [f32; 4],
 }
 
+/// Per-instance data uploaded to the `KOJI_instances` storage buffer.
+///
+/// Shaders index this buffer with `gl_InstanceIndex`; see
+/// `assets/shaders/instancing.slang` for the matching declaration.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Zeroable, Pod)]
+pub struct MeshInstance {
+    pub transform: [[f32; 4]; 4],
+    pub color: [f32; 4],
+    pub material_index: u32,
+    pub _pad: [u32; 3],
+}
+
+impl Default for MeshInstance {
+    fn default() -> Self {
+        Self {
+            transform: Mat4::IDENTITY.to_cols_array_2d(),
+            color: [1.0, 1.0, 1.0, 1.0],
+            material_index: 0,
+            _pad: [0; 3],
+        }
+    }
+}
+
+impl MeshInstance {
+    /// Create an instance placed with the given model matrix.
+    pub fn from_transform(transform: Mat4) -> Self {
+        Self {
+            transform: transform.to_cols_array_2d(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_color(mut self, color: Vec4) -> Self {
+        self.color = color.to_array();
+        self
+    }
+
+    pub fn with_material_index(mut self, index: u32) -> Self {
+        self.material_index = index;
+        self
+    }
+
+    pub fn model(&self) -> Mat4 {
+        Mat4::from_cols_array_2d(&self.transform)
+    }
+}
+
 pub struct StaticMesh {
     pub material_id: String,
     pub vertices: Vec<Vertex>,
@@ -42,9 +91,19 @@ pub struct StaticMesh {
     pub vertex_buffer: Option<Handle<Buffer>>,
     pub index_buffer: Option<Handle<Buffer>>,
     pub index_count: usize,
+    /// Model-space bounds known without looking at the vertices, such as a
+    /// glTF accessor's min/max.
+    pub bounds: Option<Bounds>,
 }
 
 impl StaticMesh {
+    /// Model-space bounds: [`StaticMesh::bounds`](#structfield.bounds) if
+    /// set, otherwise those of the vertices, or `None` without vertices.
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.bounds
+            .or_else(|| Bounds::from_points(self.vertices.iter().map(|v| Vec3::from(v.position))))
+    }
+
     pub fn upload(&mut self, ctx: &mut Context) -> Result<(), GPUError> {
         let bytes: &[u8] = bytemuck::cast_slice(&self.vertices);
         self.vertex_buffer = Some(ctx.make_buffer(&BufferInfo {
@@ -72,6 +131,14 @@ impl StaticMesh {
 }
 
 impl SkeletalMesh {
+    /// Bind-pose bounds: [`SkeletalMesh::bounds`](#structfield.bounds) if
+    /// set, otherwise those of the vertices, or `None` without vertices.
+    /// Animated poses can reach outside them.
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.bounds
+            .or_else(|| Bounds::from_points(self.vertices.iter().map(|v| Vec3::from(v.position))))
+    }
+
     pub fn upload(&mut self, ctx: &mut Context) -> Result<(), GPUError> {
         let bytes: &[u8] = bytemuck::cast_slice(&self.vertices);
         self.vertex_buffer = Some(ctx.make_buffer(&BufferInfo {
@@ -128,6 +195,9 @@ pub struct SkeletalMesh {
     pub index_count: usize,
     pub skeleton: Skeleton,
     pub bone_buffer: Option<Handle<Buffer>>,
+    /// Bind-pose bounds known without looking at the vertices, such as a
+    /// glTF accessor's min/max.
+    pub bounds: Option<Bounds>,
 }
 
 /// A runtime instance of a skeletal mesh with its own animator and GPU buffer.
@@ -202,6 +272,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn mesh_instance_matches_gpu_layout() {
+        assert_eq!(
+            std::mem::size_of::<MeshInstance>(),
+            crate::utils::INSTANCE_ELEMENT_SIZE
+        );
+        let inst = MeshInstance::from_transform(Mat4::from_translation(glam::Vec3::X));
+        assert_eq!(inst.model().w_axis, Vec4::new(1.0, 0.0, 0.0, 1.0));
+        assert_eq!(inst.color, [1.0; 4]);
+    }
+
     #[test]
     #[serial]
     #[cfg_attr(not(feature = "gpu_tests"), ignore)]
@@ -214,11 +295,15 @@ mod tests {
             vertex_buffer: None,
             index_buffer: None,
             index_count: 0,
+            bounds: None,
         };
         mesh.upload(&mut ctx).unwrap();
         assert!(mesh.vertex_buffer.unwrap().valid());
         assert!(mesh.index_buffer.unwrap().valid());
         assert_eq!(mesh.index_count, 3);
+        assert!(mesh.bounds().is_some());
+        mesh.bounds = Some(Bounds::from_aabb(Vec3::ZERO, Vec3::ONE));
+        assert_eq!(mesh.bounds().map(|b| b.max), Some(Vec3::ONE));
         ctx.destroy();
     }
 
@@ -237,6 +322,7 @@ mod tests {
             index_count: 0,
             skeleton,
             bone_buffer: None,
+            bounds: None,
         };
         mesh.upload(&mut ctx).unwrap();
         let bone_buf = mesh.bone_buffer.expect("bone buffer");
@@ -262,6 +348,7 @@ mod tests {
             index_count: 0,
             skeleton,
             bone_buffer: None,
+            bounds: None,
         };
         mesh.upload(&mut ctx).unwrap();
         let mats = vec![Mat4::IDENTITY, Mat4::from_translation(glam::Vec3::new(1.0, 2.0, 3.0))];
@@ -290,6 +377,7 @@ mod tests {
             index_count: 0,
             skeleton,
             bone_buffer: None,
+            bounds: None,
         };
         let mats = vec![Mat4::IDENTITY];
         mesh.update_bones(&mut ctx, &mats).unwrap();
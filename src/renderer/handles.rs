@@ -0,0 +1,399 @@
+//! Generational handles for drawables registered with the
+//! [`Renderer`](crate::renderer::Renderer).
+//!
+//! A handle stays valid until its drawable is removed. Slots are reused after
+//! removal, but the generation counter is bumped so stale handles are
+//! rejected instead of silently addressing a different object.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use super::{SkeletalMesh, StaticMesh};
+use crate::text::TextRenderable;
+
+/// Typed, generational reference to a registered drawable.
+pub struct DrawableHandle<T: ?Sized> {
+    index: u32,
+    generation: u32,
+    _marker: PhantomData<fn(&T)>,
+}
+
+/// Handle returned by [`Renderer::register_static_mesh`](crate::renderer::Renderer::register_static_mesh).
+pub type StaticMeshHandle = DrawableHandle<StaticMesh>;
+/// Handle returned by [`Renderer::register_text_mesh`](crate::renderer::Renderer::register_text_mesh).
+pub type TextMeshHandle = DrawableHandle<dyn TextRenderable>;
+/// Handle returned by [`Renderer::register_skeletal_mesh`](crate::renderer::Renderer::register_skeletal_mesh).
+pub type SkeletalMeshHandle = DrawableHandle<SkeletalMesh>;
+
+impl<T: ?Sized> DrawableHandle<T> {
+    fn new(index: u32, generation: u32) -> Self {
+        Self {
+            index,
+            generation,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl<T: ?Sized> Clone for DrawableHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for DrawableHandle<T> {}
+
+impl<T: ?Sized> PartialEq for DrawableHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T: ?Sized> Eq for DrawableHandle<T> {}
+
+impl<T: ?Sized> Hash for DrawableHandle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for DrawableHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DrawableHandle")
+            .field("index", &self.index)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+/// Any drawable handle, used by operations shared between drawable kinds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DrawableId {
+    Static(StaticMeshHandle),
+    Text(TextMeshHandle),
+    Skeletal(SkeletalMeshHandle),
+}
+
+impl From<StaticMeshHandle> for DrawableId {
+    fn from(h: StaticMeshHandle) -> Self {
+        DrawableId::Static(h)
+    }
+}
+
+impl From<TextMeshHandle> for DrawableId {
+    fn from(h: TextMeshHandle) -> Self {
+        DrawableId::Text(h)
+    }
+}
+
+impl From<SkeletalMeshHandle> for DrawableId {
+    fn from(h: SkeletalMeshHandle) -> Self {
+        DrawableId::Skeletal(h)
+    }
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// Slot map storing drawables of one kind, addressed by [`DrawableHandle`].
+pub(crate) struct DrawableSlots<T, H: ?Sized> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+    _marker: PhantomData<fn(&H)>,
+}
+
+impl<T, H: ?Sized> Default for DrawableSlots<T, H> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, H: ?Sized> DrawableSlots<T, H> {
+    pub fn insert(&mut self, value: T) -> DrawableHandle<H> {
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index as usize];
+            slot.value = Some(value);
+            DrawableHandle::new(index, slot.generation)
+        } else {
+            self.slots.push(Slot {
+                generation: 0,
+                value: Some(value),
+            });
+            DrawableHandle::new((self.slots.len() - 1) as u32, 0)
+        }
+    }
+
+    pub fn get(&self, handle: DrawableHandle<H>) -> Option<&T> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|s| s.generation == handle.generation)
+            .and_then(|s| s.value.as_ref())
+    }
+
+    pub fn get_mut(&mut self, handle: DrawableHandle<H>) -> Option<&mut T> {
+        self.slots
+            .get_mut(handle.index as usize)
+            .filter(|s| s.generation == handle.generation)
+            .and_then(|s| s.value.as_mut())
+    }
+
+    /// Remove the value addressed by `handle`, invalidating the handle.
+    pub fn remove(&mut self, handle: DrawableHandle<H>) -> Option<T> {
+        let slot = self
+            .slots
+            .get_mut(handle.index as usize)
+            .filter(|s| s.generation == handle.generation)?;
+        let value = slot.value.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(handle.index);
+        Some(value)
+    }
+
+    pub fn contains(&self, handle: DrawableHandle<H>) -> bool {
+        self.get(handle).is_some()
+    }
+
+    /// Iterate over every stored value.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().filter_map(|s| s.value.as_ref())
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.slots.iter_mut().filter_map(|s| s.value.as_mut())
+    }
+}
+
+/// Resources retired by the CPU that may still be referenced by frames in
+/// flight. Entries become ready once `frames_in_flight` further frames have
+/// been submitted.
+pub(crate) struct ReleaseQueue<T> {
+    frames_in_flight: u64,
+    pending: Vec<(u64, T)>,
+}
+
+impl<T> ReleaseQueue<T> {
+    pub fn new(frames_in_flight: u64) -> Self {
+        Self {
+            frames_in_flight,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Queue `item` as retired during frame `frame`.
+    pub fn push(&mut self, frame: u64, item: T) {
+        self.pending.push((frame, item));
+    }
+
+    /// Remove and return every item that is no longer in use at frame `frame`.
+    pub fn drain_ready(&mut self, frame: u64) -> Vec<T> {
+        let mut ready = Vec::new();
+        let mut i = 0;
+        while i < self.pending.len() {
+            if frame >= self.pending[i].0 + self.frames_in_flight {
+                ready.push(self.pending.swap_remove(i).1);
+            } else {
+                i += 1;
+            }
+        }
+        ready
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+/// The shared instance buffer has no room for `requested` more instances.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InstanceCapacityError {
+    pub requested: u32,
+    /// Instances the buffer holds, `MAX_INSTANCES`.
+    pub capacity: u32,
+}
+
+impl std::fmt::Display for InstanceCapacityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "no room for {} instances in the instance buffer of {}",
+            self.requested, self.capacity
+        )
+    }
+}
+
+impl std::error::Error for InstanceCapacityError {}
+
+/// Ranges of the shared instance buffer in use by drawables.
+pub(crate) struct InstanceRanges {
+    capacity: u32,
+    /// First slot never handed out.
+    cursor: u32,
+    /// Released `(first, count)` ranges below `cursor`, sorted by `first`
+    /// and never adjacent to each other or to `cursor`.
+    free: Vec<(u32, u32)>,
+}
+
+impl InstanceRanges {
+    pub fn new(capacity: u32) -> Self {
+        Self {
+            capacity,
+            cursor: 0,
+            free: Vec::new(),
+        }
+    }
+
+    /// First slot of `count` consecutive free slots, reusing released ranges
+    /// before growing. `None` when the buffer has no room left.
+    pub fn reserve(&mut self, count: u32) -> Option<u32> {
+        if count == 0 {
+            return Some(0);
+        }
+        if let Some(pos) = self.free.iter().position(|&(_, len)| len >= count) {
+            let (first, len) = self.free[pos];
+            if len == count {
+                self.free.remove(pos);
+            } else {
+                self.free[pos] = (first + count, len - count);
+            }
+            return Some(first);
+        }
+        let first = self.cursor;
+        if first.checked_add(count)? > self.capacity {
+            return None;
+        }
+        self.cursor += count;
+        Some(first)
+    }
+
+    /// Make `count` slots starting at `first` available again, merging them
+    /// with adjacent free ranges so churn does not fragment the buffer.
+    pub fn free(&mut self, first: u32, count: u32) {
+        if count == 0 {
+            return;
+        }
+        let pos = self.free.partition_point(|&(f, _)| f < first);
+        let (mut first, mut count) = (first, count);
+        if let Some(&(next, len)) = self.free.get(pos) {
+            if first + count == next {
+                count += len;
+                self.free.remove(pos);
+            }
+        }
+        if pos > 0 {
+            let (prev, len) = self.free[pos - 1];
+            if prev + len == first {
+                first = prev;
+                count += len;
+                self.free.remove(pos - 1);
+            }
+        }
+        if first + count == self.cursor {
+            self.cursor = first;
+        } else {
+            let pos = self.free.partition_point(|&(f, _)| f < first);
+            self.free.insert(pos, (first, count));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_handle_is_rejected_after_reuse() {
+        let mut slots: DrawableSlots<u32, StaticMesh> = DrawableSlots::default();
+        let a = slots.insert(1);
+        let b = slots.insert(2);
+        assert_eq!(slots.remove(a), Some(1));
+        assert!(slots.get(a).is_none());
+
+        let c = slots.insert(3);
+        assert_eq!(c.index(), a.index());
+        assert_ne!(c, a);
+        assert!(slots.get(a).is_none());
+        assert_eq!(slots.get(c), Some(&3));
+        assert_eq!(slots.get(b), Some(&2));
+    }
+
+    #[test]
+    fn removing_twice_is_a_no_op() {
+        let mut slots: DrawableSlots<u32, StaticMesh> = DrawableSlots::default();
+        let a = slots.insert(7);
+        assert_eq!(slots.remove(a), Some(7));
+        assert_eq!(slots.remove(a), None);
+        assert!(!slots.contains(a));
+    }
+
+    #[test]
+    fn iteration_skips_removed_slots() {
+        let mut slots: DrawableSlots<u32, StaticMesh> = DrawableSlots::default();
+        let a = slots.insert(1);
+        slots.insert(2);
+        slots.remove(a);
+        assert_eq!(slots.iter().copied().collect::<Vec<_>>(), vec![2]);
+        slots.iter_mut().for_each(|v| *v += 1);
+        assert_eq!(slots.iter().copied().collect::<Vec<_>>(), vec![3]);
+    }
+
+    #[test]
+    fn release_queue_waits_for_frames_in_flight() {
+        let mut queue = ReleaseQueue::new(2);
+        queue.push(5, "a");
+        queue.push(6, "b");
+        assert!(queue.drain_ready(5).is_empty());
+        assert!(queue.drain_ready(6).is_empty());
+        assert_eq!(queue.drain_ready(7), vec!["a"]);
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.drain_ready(8), vec!["b"]);
+        assert_eq!(queue.len(), 0);
+    }
+
+    #[test]
+    fn instance_ranges_report_exhaustion() {
+        let mut ranges = InstanceRanges::new(8);
+        assert_eq!(ranges.reserve(6), Some(0));
+        assert_eq!(ranges.reserve(3), None);
+        assert_eq!(ranges.reserve(2), Some(6));
+        assert_eq!(ranges.reserve(1), None);
+    }
+
+    #[test]
+    fn freed_ranges_coalesce() {
+        let mut ranges = InstanceRanges::new(8);
+        let a = ranges.reserve(2).unwrap();
+        let b = ranges.reserve(2).unwrap();
+        let c = ranges.reserve(2).unwrap();
+        ranges.reserve(2).unwrap();
+        ranges.free(a, 2);
+        ranges.free(c, 2);
+        ranges.free(b, 2);
+        assert_eq!(ranges.free, vec![(0, 6)]);
+        assert_eq!(ranges.reserve(6), Some(0));
+
+        // Freeing the last range shrinks the buffer back to its start.
+        let mut ranges = InstanceRanges::new(8);
+        let a = ranges.reserve(3).unwrap();
+        let b = ranges.reserve(3).unwrap();
+        ranges.free(a, 3);
+        ranges.free(b, 3);
+        assert!(ranges.free.is_empty());
+        assert_eq!(ranges.reserve(8), Some(0));
+    }
+}
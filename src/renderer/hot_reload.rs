@@ -0,0 +1,304 @@
+//! Polling-based hot reload of render graph files and the SPIR-V they use.
+//!
+//! The renderer checks watched files once per frame, before recording. A
+//! changed graph file is parsed, validated, its transients allocated and its
+//! declared pipelines built before anything is replaced. If any step fails the
+//! objects built for the new graph are destroyed, the previous graph and
+//! pipelines stay active and the error is kept for
+//! [`Renderer::reload_error`]. On success the bindings and bind groups that
+//! sampled an image of the replaced graph are pointed at the image of the
+//! same name in the new one, and the replaced graph, its canvases,
+//! transients and node state, and the replaced pipelines and bind groups are
+//! destroyed once the frames in flight are done with them.
+//!
+//! A reload is rejected while [`Renderer::enable_deferred`] is active, since
+//! the new graph would drop its G-buffer and lighting nodes, and when a
+//! binding samples a graph image the new graph no longer has.
+//!
+//! Only the graph file and the SPIR-V named by its pipeline declarations are
+//! watched. Shaders of pipelines registered directly, including materials
+//! built with [`MaterialPipeline::from_yaml`](crate::material::MaterialPipeline::from_yaml),
+//! are not watched and are not reloaded.
+
+use super::deferred::GBUFFER_NODE;
+use super::{primary_render_pass, GraphPipeline, Renderer};
+use crate::canvas::RetiredAttachment;
+use crate::render_graph::{
+    from_json_with_registry, from_yaml_with_registry, GraphError, NodeRegistry, RenderGraph,
+};
+use crate::utils::ResourceBinding;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Modification time and size of a file, or `None` if it can't be read.
+type FileStamp = Option<(Option<SystemTime>, u64)>;
+
+fn stamp(path: &Path) -> FileStamp {
+    std::fs::metadata(path)
+        .ok()
+        .map(|m| (m.modified().ok(), m.len()))
+}
+
+/// Detects changes to a set of files by polling their metadata.
+#[derive(Default)]
+pub(crate) struct FileWatcher {
+    files: Vec<(PathBuf, FileStamp)>,
+}
+
+impl FileWatcher {
+    /// Watch exactly `paths`. Files already watched keep their last stamp so
+    /// pending changes are not lost.
+    pub(crate) fn set_files(&mut self, paths: impl IntoIterator<Item = PathBuf>) {
+        let old = std::mem::take(&mut self.files);
+        for path in paths {
+            if self.files.iter().any(|(p, _)| *p == path) {
+                continue;
+            }
+            let s = old
+                .iter()
+                .find(|(p, _)| *p == path)
+                .map(|(_, s)| *s)
+                .unwrap_or_else(|| stamp(&path));
+            self.files.push((path, s));
+        }
+    }
+
+    /// Whether any watched file changed since the last call.
+    pub(crate) fn poll(&mut self) -> bool {
+        let mut changed = false;
+        for (path, last) in &mut self.files {
+            let now = stamp(path);
+            if now != *last {
+                *last = now;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+/// Hot reload state held by a [`Renderer`].
+pub(crate) struct HotReload {
+    graph_path: PathBuf,
+    registry: NodeRegistry,
+    graph_file: FileWatcher,
+    shaders: FileWatcher,
+    last_error: Option<String>,
+}
+
+impl Renderer {
+    /// Watch a YAML or JSON graph file, and the SPIR-V files its pipelines
+    /// use, and reload them between frames when they change.
+    ///
+    /// The current graph is kept until the file changes. Files ending in
+    /// `.json` are parsed as JSON, everything else as YAML.
+    pub fn watch_graph_file(&mut self, path: impl Into<PathBuf>) {
+        self.watch_graph_file_with_registry(path, NodeRegistry::new());
+    }
+
+    /// Like [`Renderer::watch_graph_file`], rebuilding custom nodes with
+    /// the factories in `registry`.
+    pub fn watch_graph_file_with_registry(
+        &mut self,
+        path: impl Into<PathBuf>,
+        registry: NodeRegistry,
+    ) {
+        let graph_path = path.into();
+        let mut graph_file = FileWatcher::default();
+        graph_file.set_files([graph_path.clone()]);
+        let mut reload = HotReload {
+            graph_path,
+            registry,
+            graph_file,
+            shaders: FileWatcher::default(),
+            last_error: None,
+        };
+        reload.shaders.set_files(self.shader_paths());
+        self.hot_reload = Some(reload);
+    }
+
+    /// Stop watching files for changes.
+    pub fn unwatch_graph_file(&mut self) {
+        self.hot_reload = None;
+    }
+
+    /// Error from the most recent failed reload, cleared by the next
+    /// successful one.
+    pub fn reload_error(&self) -> Option<&str> {
+        self.hot_reload
+            .as_ref()
+            .and_then(|r| r.last_error.as_deref())
+    }
+
+    /// Check watched files and reload what changed.
+    ///
+    /// Returns `Ok(true)` if the graph or its pipelines were replaced. On
+    /// failure the previous version stays active and the message is kept for
+    /// [`Renderer::reload_error`]. `present_frame` calls this before each
+    /// frame.
+    pub fn poll_hot_reload(&mut self) -> Result<bool, GraphError> {
+        let Some(mut reload) = self.hot_reload.take() else {
+            return Ok(false);
+        };
+        let graph_changed = reload.graph_file.poll();
+        let shaders_changed = reload.shaders.poll();
+        let result = if graph_changed {
+            self.reload_graph(&reload).map(|_| true)
+        } else if shaders_changed {
+            self.reload_pipelines().map(|_| true)
+        } else {
+            Ok(false)
+        };
+        match &result {
+            Ok(true) => {
+                reload.last_error = None;
+                reload.shaders.set_files(self.shader_paths());
+            }
+            Ok(false) => {}
+            Err(e) => reload.last_error = Some(e.to_string()),
+        }
+        self.hot_reload = Some(reload);
+        result
+    }
+
+    fn shader_paths(&self) -> Vec<PathBuf> {
+        self.graph
+            .all_pipeline_descs()
+            .into_iter()
+            .flat_map(|(_, d)| [PathBuf::from(d.vertex), PathBuf::from(d.fragment)])
+            .collect()
+    }
+
+    fn reload_graph(&mut self, reload: &HotReload) -> Result<(), GraphError> {
+        if self.deferred.is_some() {
+            return Err(GraphError::RendererNode(GBUFFER_NODE.to_string()));
+        }
+        let ctx = self.get_ctx();
+        let data = std::fs::read_to_string(&reload.graph_path).map_err(|e| {
+            GraphError::Desc(format!(
+                "failed to read '{}': {}",
+                reload.graph_path.display(),
+                e
+            ))
+        })?;
+        let is_json = reload
+            .graph_path
+            .extension()
+            .is_some_and(|ext| ext == "json");
+        let graph = if is_json {
+            from_json_with_registry(ctx, &data, &reload.registry)
+        } else {
+            from_yaml_with_registry(ctx, &data, &reload.registry)
+        };
+        let mut graph = graph?;
+        let built = match self.prepare_graph(&mut graph) {
+            Ok(built) => built,
+            Err(err) => {
+                // The new graph was never recorded, so it is destroyed now.
+                for obj in graph.into_gpu_objects() {
+                    obj.destroy(ctx);
+                }
+                return Err(err);
+            }
+        };
+
+        let replaced = canvas_attachments(&self.graph);
+        let old = std::mem::replace(&mut self.graph, graph);
+        for obj in old.into_gpu_objects() {
+            self.gpu_releases.push(self.frame_index, obj);
+        }
+        self.canvases = self.graph.canvases();
+        self.render_pass = primary_render_pass(&self.graph, &self.canvases);
+        self.install_graph_pipelines(built);
+        self.set_clear_color(self.clear_color);
+        self.set_clear_depth(self.clear_depth);
+        self.rebind_resized(&replaced).map_err(GraphError::Rebind)
+    }
+
+    /// Validate a freshly loaded graph, allocate its transients, size it to
+    /// the output, check that it still provides every graph image a binding
+    /// samples and build its pipelines.
+    fn prepare_graph(&mut self, graph: &mut RenderGraph) -> Result<Vec<GraphPipeline>, GraphError> {
+        let ctx = self.get_ctx();
+        graph.validate()?;
+        for obj in graph.allocate_transients(ctx)? {
+            obj.destroy(ctx);
+        }
+        for att in graph.resize(ctx, self.size())? {
+            ctx.destroy_image_view(att.view);
+            ctx.destroy_image(att.image);
+        }
+
+        let provided = canvas_attachments(graph);
+        for att in canvas_attachments(&self.graph) {
+            if provided.iter().any(|a| a.name == att.name) {
+                continue;
+            }
+            let sampled_by = self.resource_manager.bindings.iter().find(|(_, b)| match b {
+                ResourceBinding::Texture(tex)
+                | ResourceBinding::CombinedImageSampler { texture: tex, .. } => {
+                    tex.view == att.view
+                }
+                _ => false,
+            });
+            if let Some((binding, _)) = sampled_by {
+                return Err(GraphError::Desc(format!(
+                    "binding '{}' samples '{}', which the reloaded graph no longer has",
+                    binding, att.name
+                )));
+            }
+        }
+        Self::build_graph_pipelines(ctx, &mut self.resource_manager, graph)
+    }
+
+    fn reload_pipelines(&mut self) -> Result<(), GraphError> {
+        let ctx = self.get_ctx();
+        let built = Self::build_graph_pipelines(ctx, &mut self.resource_manager, &self.graph)?;
+        self.install_graph_pipelines(built);
+        Ok(())
+    }
+}
+
+/// Every canvas attachment of `graph` by name, in the form the resize path
+/// uses to find the bindings that sample it.
+fn canvas_attachments(graph: &RenderGraph) -> Vec<RetiredAttachment> {
+    let mut attachments = Vec::new();
+    for canvas in graph.canvases() {
+        let target = canvas.target();
+        for att in target.colors.iter().chain(target.depth.as_ref()) {
+            attachments.push(RetiredAttachment {
+                name: att.name.clone(),
+                image: att.image,
+                view: att.attachment.img,
+            });
+        }
+    }
+    attachments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watcher_reports_changes_once() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("koji_watch_{}.yaml", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut watcher = FileWatcher::default();
+        watcher.set_files([path.clone()]);
+        assert!(!watcher.poll());
+
+        std::fs::write(&path, "a").unwrap();
+        assert!(watcher.poll());
+        assert!(!watcher.poll());
+
+        std::fs::write(&path, "abc").unwrap();
+        watcher.set_files([path.clone()]);
+        assert!(watcher.poll());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(watcher.poll());
+    }
+}
@@ -1,20 +1,59 @@
+mod clusters;
+pub use clusters::{
+    bin_lights, cluster_bounds, ClusterAssignment, ClusterBounds, ClusterGrid, LIGHT_CLUSTERS,
+    LIGHT_INDICES, MAX_CLUSTERED_LIGHTS,
+};
+use clusters::ClusterPass;
+mod culling;
+pub use culling::{Bounds, CullStats, Frustum};
+use culling::Culling;
+mod deferred;
+pub use deferred::{
+    GBUFFER_ALBEDO, GBUFFER_DEPTH, GBUFFER_MATERIAL, GBUFFER_NODE, GBUFFER_NORMAL,
+    GBUFFER_POSITION, LIGHTING_NODE, LIT_OUTPUT, MAX_DEFERRED_LIGHTS,
+};
+use deferred::DeferredPass;
 mod drawable;
 pub use drawable::*;
+mod handles;
+mod hot_reload;
+mod shadows;
+pub use shadows::{
+    atlas_rect, cascade_splits, directional_view_proj, frustum_corners, light_views,
+    spot_view_proj, LightShadows, ShadowCamera, ShadowError, ShadowKind, ShadowView,
+    DEFAULT_SHADOW_ATLAS_SIZE, MAX_CASCADES, MAX_SHADOW_VIEWS, SHADOW_ATLAS, SHADOW_ATLAS_GRID,
+    SHADOW_VIEWS,
+};
+use shadows::ShadowPass;
+mod sorting;
+pub use sorting::DEFAULT_STAGE_ORDER;
+pub use handles::{
+    DrawableHandle, DrawableId, InstanceCapacityError, SkeletalMeshHandle, StaticMeshHandle,
+    TextMeshHandle,
+};
+use handles::{DrawableSlots, InstanceRanges, ReleaseQueue};
+use hot_reload::HotReload;
 mod time_stats;
 pub use time_stats::*;
 
-use crate::canvas::CanvasBuilder;
-use crate::material::{BindlessLights, LightDesc, PSOBindGroupResources, CPSO, PSO};
-use crate::render_graph::{CanvasNode, CompositionNode, RenderGraph};
+use crate::canvas::{CanvasBuilder, RetiredAttachment};
+use crate::material::{
+    BindlessLights, LightDesc, PSOBindGroupResources, PipelineBuilder, PipelineError, CPSO, PSO,
+};
+use crate::render_graph::{
+    read_spirv, CanvasNode, CompositionNode, GraphError, GraphPipelineDesc, ImageLayout,
+    PassContext, RenderGraph,
+};
 use crate::render_pass::*;
 use crate::text::{FontRegistry, TextRenderable};
-use crate::utils::{diff_rgba8, ResourceBinding, ResourceManager};
+use crate::utils::{diff_rgba8, GpuObject, ResourceBinding, ResourceManager, Texture};
 use dashi::utils::*;
 use dashi::*;
 use glam::{Mat4, Vec3};
 use bytemuck::{Pod, Zeroable};
-use crate::utils::{CAMERA_ELEMENT_SIZE, MAX_CAMERAS};
+use crate::utils::{CAMERA_ELEMENT_SIZE, INSTANCE_ELEMENT_SIZE, MAX_CAMERAS, MAX_INSTANCES};
 use std::collections::HashMap;
+use ash::vk;
 use winit::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
 use winit::event_loop::ControlFlow;
 use winit::platform::run_return::EventLoopExtRunReturn;
@@ -45,6 +84,15 @@ pub mod test_hooks {
 pub enum RenderStage {
     Opaque,
     Text,
+    /// Static meshes written to the G-buffer, see [`Renderer::enable_deferred`].
+    GBuffer,
+    /// Full-screen pass shading the G-buffer.
+    Lighting,
+    /// Skinned meshes.
+    Skeletal,
+    /// Static meshes blended over the scene, drawn back to front. See
+    /// [`Renderer::set_material_stage`].
+    Transparent,
     // Extend as needed...
 }
 
@@ -95,6 +143,142 @@ impl<'a> DrawableNode<'a> {
     }
 }
 
+fn vk_layout(layout: ImageLayout) -> vk::ImageLayout {
+    match layout {
+        ImageLayout::Undefined => vk::ImageLayout::UNDEFINED,
+        ImageLayout::ColorAttachment => vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        ImageLayout::DepthStencilAttachment => vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        ImageLayout::ShaderReadOnly => vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        ImageLayout::TransferSrc => vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        ImageLayout::TransferDst => vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+    }
+}
+
+/// Number of frames that may be recorded before the GPU finishes the oldest.
+const FRAMES_IN_FLIGHT: usize = 2;
+
+/// A registered static mesh and the range of `KOJI_instances` it draws.
+struct StaticDrawable {
+    node: String,
+    mesh: StaticMesh,
+    _dynamic_buffers: Option<DynamicBuffer>,
+    instances: Vec<MeshInstance>,
+    first_instance: u32,
+    instance_capacity: u32,
+    visible: bool,
+    /// Model-space bounds, from [`StaticMesh::bounds`] when the mesh is
+    /// registered or its vertices are replaced.
+    bounds: Option<Bounds>,
+    /// Inside the culling frustum in the current frame.
+    in_view: bool,
+}
+
+struct TextDrawable {
+    node: String,
+    mesh: Box<dyn TextRenderable>,
+    visible: bool,
+}
+
+struct SkeletalDrawable {
+    node: String,
+    mesh: SkeletalMesh,
+    instances: Vec<SkeletalInstance>,
+    visible: bool,
+    /// Bind-pose bounds, from [`SkeletalMesh::bounds`] at registration.
+    bounds: Option<Bounds>,
+    /// Inside the culling frustum in the current frame.
+    in_view: bool,
+}
+
+/// Drawables attached to a graph node, in registration order.
+#[derive(Default)]
+struct NodeDrawables {
+    statics: Vec<StaticMeshHandle>,
+    texts: Vec<TextMeshHandle>,
+    skeletal: Vec<SkeletalMeshHandle>,
+}
+
+/// Where a pipeline declared in the graph is registered.
+#[derive(Clone, PartialEq)]
+enum GraphPipelineKey {
+    Material(String),
+    Pass(String),
+}
+
+/// Shader variables a graph pipeline binds to differently named
+/// `ResourceManager` entries, as `(variable, resource)`.
+type BindingAliases = Vec<(String, String)>;
+
+/// A graph-declared pipeline built but not yet registered.
+struct GraphPipeline {
+    key: GraphPipelineKey,
+    pso: PSO,
+    bind_groups: [Option<PSOBindGroupResources>; 4],
+    aliases: BindingAliases,
+}
+
+/// Run `f` with `aliases` registered in `resources`, and restore the previous
+/// bindings of the aliased variables afterwards so they never outlive the
+/// bind groups made from them.
+fn with_aliases<T>(
+    resources: &mut ResourceManager,
+    aliases: &[(String, String)],
+    f: impl FnOnce(&mut ResourceManager) -> Result<T, PipelineError>,
+) -> Result<T, PipelineError> {
+    let mut previous = Vec::new();
+    let mut missing = None;
+    for (variable, resource) in aliases {
+        match resources.get(resource).cloned() {
+            Some(binding) => {
+                let old = resources.bindings.insert(variable.clone(), binding);
+                previous.push((variable.clone(), old));
+            }
+            None => {
+                missing = Some(resource.clone());
+                break;
+            }
+        }
+    }
+    let result = match missing {
+        Some(resource) => Err(PipelineError::MissingResource(resource)),
+        None => f(resources),
+    };
+    for (variable, old) in previous.into_iter().rev() {
+        match old {
+            Some(binding) => resources.bindings.insert(variable, binding),
+            None => resources.bindings.remove(&variable),
+        };
+    }
+    result
+}
+
+/// Aliases of the graph pipeline registered under `key`, if it is one.
+fn graph_aliases<'a>(
+    installed: &'a [(GraphPipelineKey, BindingAliases)],
+    key: &GraphPipelineKey,
+) -> &'a [(String, String)] {
+    installed
+        .iter()
+        .find(|(k, _)| k == key)
+        .map_or(&[], |(_, aliases)| aliases.as_slice())
+}
+
+/// GPU objects owned by a registered pipeline and its bind groups. Buffers and
+/// textures the bind groups reference belong to the `ResourceManager`.
+fn pipeline_gpu_objects(
+    pso: &PSO,
+    bind_groups: &[Option<PSOBindGroupResources>; 4],
+) -> Vec<GpuObject> {
+    let mut objects = pso.gpu_objects();
+    objects.extend(
+        bind_groups
+            .iter()
+            .flatten()
+            .map(|b| GpuObject::BindGroup(b.bind_group)),
+    );
+    objects
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
 struct CameraData {
@@ -117,9 +301,22 @@ pub struct Renderer {
     resource_manager: ResourceManager,
     fonts: FontRegistry,
     lights: BindlessLights,
-    drawables: HashMap<String, Vec<(StaticMesh, Option<DynamicBuffer>)>>,
-    text_drawables: HashMap<String, Vec<Box<dyn TextRenderable>>>,
-    skeletal_meshes: HashMap<String, Vec<(SkeletalMesh, Vec<SkeletalInstance>)>>,
+    /// Deferred shading resources, see [`Renderer::enable_deferred`].
+    deferred: Option<DeferredPass>,
+    /// Shadow atlas and views, see [`Renderer::set_light_shadows`].
+    shadows: Option<ShadowPass>,
+    /// Light culling grid, see [`Renderer::enable_clustered_lights`].
+    clusters: Option<ClusterPass>,
+    /// Frustum culling camera and statistics.
+    culling: Culling,
+    /// Stage order of canvas nodes, see [`Renderer::set_stage_order`].
+    stage_orders: HashMap<String, Vec<RenderStage>>,
+    /// Stage of static meshes by material, see [`Renderer::set_material_stage`].
+    material_stages: HashMap<String, RenderStage>,
+    drawables: DrawableSlots<StaticDrawable, StaticMesh>,
+    text_drawables: DrawableSlots<TextDrawable, dyn TextRenderable>,
+    skeletal_meshes: DrawableSlots<SkeletalDrawable, SkeletalMesh>,
+    node_drawables: HashMap<String, NodeDrawables>,
     command_list: FramedCommandList,
     semaphores: Vec<Handle<Semaphore>>,
     /// Tracks frame timing statistics for the renderer.
@@ -127,13 +324,77 @@ pub struct Renderer {
     /// Handle to the GPU uniform buffer storing [`TimeStats`] data.
     time_buffer: Option<Handle<Buffer>>,
     camera_buffer: Option<Handle<Buffer>>,
+    /// Shared storage buffer holding [`MeshInstance`] data for all static meshes.
+    instance_buffer: Option<Handle<Buffer>>,
+    /// Slots of `instance_buffer` in use by static drawables.
+    instance_ranges: InstanceRanges,
+    /// Number of frames submitted so far.
+    frame_index: u64,
+    /// Buffers of removed or replaced drawables awaiting destruction.
+    buffer_releases: ReleaseQueue<Handle<Buffer>>,
+    /// Instance ranges of removed or resized drawables awaiting reuse.
+    instance_releases: ReleaseQueue<(u32, u32)>,
+    /// Canvas images replaced by a resize awaiting destruction.
+    image_releases: ReleaseQueue<RetiredAttachment>,
+    /// Graph objects replaced by a node rebuild or a reload awaiting
+    /// destruction.
+    gpu_releases: ReleaseQueue<GpuObject>,
     clear_color: [f32; 4],
     clear_depth: f32,
     graph: crate::render_graph::RenderGraph,
+    /// Entries of `pipelines` and `material_pipelines` registered from the
+    /// graph's pipeline declarations, with the binding aliases their bind
+    /// groups are rebuilt with.
+    graph_pipelines: Vec<(GraphPipelineKey, BindingAliases)>,
+    /// Watched graph file and shaders, see [`Renderer::watch_graph_file`].
+    hot_reload: Option<HotReload>,
+    /// Why the last frame could not rebind the deferred or clustered lighting
+    /// passes to the current lights, see [`Renderer::light_binding_error`].
+    light_error: Option<String>,
     width: u32,
     height: u32,
 }
 
+/// Rebuild the bind groups in `groups` that sample one of the `stale` views.
+fn rebind_stale<F>(
+    groups: &mut [Option<PSOBindGroupResources>; 4],
+    stale: &[Handle<ImageView>],
+    mut create: F,
+) -> Result<(), PipelineError>
+where
+    F: FnMut(usize) -> Result<PSOBindGroupResources, PipelineError>,
+{
+    for (set, group) in groups.iter_mut().enumerate() {
+        let uses_stale = group
+            .as_ref()
+            .is_some_and(|g| g.textures.values().any(|t| stale.contains(&t.view)));
+        if uses_stale {
+            *group = Some(create(set)?);
+        }
+    }
+    Ok(())
+}
+
+/// Render pass of the first canvas, used when no pipeline names its own.
+fn primary_render_pass(
+    graph: &RenderGraph,
+    canvases: &[crate::canvas::Canvas],
+) -> Handle<RenderPass> {
+    let mut render_pass = Handle::<RenderPass>::default();
+    if let Some(first_canvas) = canvases.first() {
+        if let Some(first_att) = first_canvas.target().colors.first() {
+            if let Some((rp, _)) = graph.render_pass_for_output(&first_att.name) {
+                render_pass = rp;
+            } else {
+                render_pass = first_canvas.render_pass();
+            }
+        } else {
+            render_pass = first_canvas.render_pass();
+        }
+    }
+    render_pass
+}
+
 impl Renderer {
     fn get_ctx(&mut self) -> &'static mut Context {
         unsafe { &mut *self.ctx }
@@ -143,7 +404,7 @@ impl Renderer {
         width: u32,
         height: u32,
         ctx: &mut Context,
-        graph: RenderGraph,
+        mut graph: RenderGraph,
         headless: bool,
     ) -> Result<Self, GPUError> {
         let clear_color = [0.1, 0.2, 0.3, 1.0];
@@ -164,22 +425,20 @@ impl Renderer {
             })?)
         };
 
-        let mut canvases = graph.canvases();
-        let mut render_pass = Handle::<RenderPass>::default();
-        if let Some(first_canvas) = canvases.first() {
-            if let Some(first_att) = first_canvas.target().colors.first() {
-                if let Some((rp, _)) = graph.render_pass_for_output(&first_att.name) {
-                    render_pass = rp;
-                } else {
-                    render_pass = first_canvas.render_pass();
-                }
-            } else {
-                render_pass = first_canvas.render_pass();
-            }
+        // Relative canvases may have been built for a different output size.
+        for att in graph.resize(ctx, [width, height])? {
+            ctx.destroy_image_view(att.view);
+            ctx.destroy_image(att.image);
         }
+        let mut canvases = graph.canvases();
+        let render_pass = primary_render_pass(&graph, &canvases);
         let targets: Vec<RenderTarget> = Vec::new();
+        // A fresh graph has no previous transients to hand back.
+        for obj in graph.allocate_transients(ctx)? {
+            obj.destroy(ctx);
+        }
 
-        let command_list = FramedCommandList::new(&mut ctx, "RendererCmdList", 2);
+        let command_list = FramedCommandList::new(&mut ctx, "RendererCmdList", FRAMES_IN_FLIGHT);
         let semaphores = ctx.make_semaphores(2)?;
 
         let mut resource_manager = ResourceManager::new(&mut ctx, 4096)?;
@@ -187,6 +446,7 @@ impl Renderer {
         lights.register(&mut resource_manager);
         resource_manager.register_time_buffers(&mut ctx);
         resource_manager.register_camera_buffers(&mut ctx);
+        resource_manager.register_instance_buffers(&mut ctx);
         let time_buffer = match resource_manager.get("time") {
             Some(ResourceBinding::Uniform(h)) => Some(*h),
             _ => None,
@@ -195,6 +455,10 @@ impl Renderer {
             Some(ResourceBinding::Uniform(h)) => Some(*h),
             _ => None,
         };
+        let instance_buffer = match resource_manager.get("instances") {
+            Some(ResourceBinding::Storage(h)) => Some(*h),
+            _ => None,
+        };
 
         let mut renderer = Self {
             ctx,
@@ -208,18 +472,35 @@ impl Renderer {
             skeletal_pipeline: None,
             compute_pipelines: HashMap::new(),
             compute_queue: Vec::new(),
-            drawables: HashMap::new(),
-            text_drawables: HashMap::new(),
-            skeletal_meshes: HashMap::new(),
+            drawables: DrawableSlots::default(),
+            text_drawables: DrawableSlots::default(),
+            skeletal_meshes: DrawableSlots::default(),
+            node_drawables: HashMap::new(),
             resource_manager,
             fonts: FontRegistry::new(),
             lights,
+            deferred: None,
+            shadows: None,
+            clusters: None,
+            culling: Culling::default(),
+            stage_orders: HashMap::new(),
+            material_stages: HashMap::new(),
             command_list,
             semaphores,
             time_stats: TimeStats::new(),
             time_buffer,
             camera_buffer,
+            instance_buffer,
+            instance_ranges: InstanceRanges::new(MAX_INSTANCES as u32),
+            frame_index: 0,
+            buffer_releases: ReleaseQueue::new(FRAMES_IN_FLIGHT as u64),
+            instance_releases: ReleaseQueue::new(FRAMES_IN_FLIGHT as u64),
+            image_releases: ReleaseQueue::new(FRAMES_IN_FLIGHT as u64),
+            gpu_releases: ReleaseQueue::new(FRAMES_IN_FLIGHT as u64),
             graph: crate::render_graph::RenderGraph::new(),
+            graph_pipelines: Vec::new(),
+            hot_reload: None,
+            light_error: None,
             width,
             height,
             clear_color,
@@ -250,11 +531,11 @@ impl Renderer {
         height: u32,
         ctx: &mut Context,
         canvas: crate::canvas::Canvas,
-    ) -> Result<Self, GPUError> {
+    ) -> Result<Self, GraphError> {
         let mut graph = RenderGraph::new();
-        graph.add_canvas(&canvas);
+        graph.add_canvas(&canvas)?;
 
-        Self::with_graph(width, height, ctx, graph)
+        Ok(Self::with_graph(width, height, ctx, graph)?)
     }
 
     pub fn with_canvas_headless(
@@ -262,11 +543,11 @@ impl Renderer {
         height: u32,
         ctx: &mut Context,
         canvas: crate::canvas::Canvas,
-    ) -> Result<Self, GPUError> {
+    ) -> Result<Self, GraphError> {
         let mut graph = RenderGraph::new();
-        graph.add_canvas(&canvas);
+        graph.add_canvas(&canvas)?;
 
-        Self::with_graph_headless(width, height, ctx, graph)
+        Ok(Self::with_graph_headless(width, height, ctx, graph)?)
     }
 
     pub fn with_graph(
@@ -292,7 +573,8 @@ impl Renderer {
     /// ```no_run
     /// # use koji::renderer::Renderer;
     /// # use dashi::gpu::Context;
-    /// # fn build(ctx: &mut Context, yaml: &str) -> Result<(), String> {
+    /// # use koji::render_graph::GraphError;
+    /// # fn build(ctx: &mut Context, yaml: &str) -> Result<(), GraphError> {
     /// let renderer = Renderer::with_graph_from_yaml(800, 600, ctx, yaml)?;
     /// # Ok(()) }
     /// ```
@@ -301,9 +583,11 @@ impl Renderer {
         height: u32,
         ctx: &mut Context,
         data: &str,
-    ) -> Result<Self, String> {
+    ) -> Result<Self, GraphError> {
         let graph = crate::render_graph::from_yaml(ctx, data)?;
-        Self::with_graph(width, height, ctx, graph).map_err(|e| format!("{:?}", e))
+        let mut renderer = Self::with_graph(width, height, ctx, graph)?;
+        renderer.load_graph_pipelines()?;
+        Ok(renderer)
     }
 
     /// Construct a [`Renderer`] from a render graph described in JSON.
@@ -311,7 +595,8 @@ impl Renderer {
     /// ```no_run
     /// # use koji::renderer::Renderer;
     /// # use dashi::gpu::Context;
-    /// # fn build(ctx: &mut Context, json: &str) -> Result<(), String> {
+    /// # use koji::render_graph::GraphError;
+    /// # fn build(ctx: &mut Context, json: &str) -> Result<(), GraphError> {
     /// let renderer = Renderer::with_graph_from_json(800, 600, ctx, json)?;
     /// # Ok(()) }
     /// ```
@@ -320,12 +605,160 @@ impl Renderer {
         height: u32,
         ctx: &mut Context,
         data: &str,
-    ) -> Result<Self, String> {
+    ) -> Result<Self, GraphError> {
         let graph = crate::render_graph::from_json(ctx, data)?;
-        Self::with_graph(width, height, ctx, graph).map_err(|e| format!("{:?}", e))
+        let mut renderer = Self::with_graph(width, height, ctx, graph)?;
+        renderer.load_graph_pipelines()?;
+        Ok(renderer)
+    }
+
+    /// Build and register every pipeline declared in the graph.
+    ///
+    /// Each [`GraphPipelineDesc`](crate::render_graph::GraphPipelineDesc) is
+    /// compiled against its target output. Entries in `bindings` alias
+    /// existing `ResourceManager` resources under the shader's variable names
+    /// while the pipeline and its bind groups are created, and are removed
+    /// again afterwards. The pipeline is registered for its material if one
+    /// is named, otherwise for the canvas pass that owns the output. Called
+    /// by [`Renderer::with_graph_from_yaml`] and
+    /// [`Renderer::with_graph_from_json`].
+    pub fn load_graph_pipelines(&mut self) -> Result<(), GraphError> {
+        let ctx = self.get_ctx();
+        let built = Self::build_graph_pipelines(ctx, &mut self.resource_manager, &self.graph)?;
+        self.install_graph_pipelines(built);
+        Ok(())
+    }
+
+    /// Build the pipelines declared in `graph` without registering them, so a
+    /// failure leaves the current pipelines untouched. Pipelines built before
+    /// the failure are destroyed.
+    fn build_graph_pipelines(
+        ctx: &mut Context,
+        resources: &mut ResourceManager,
+        graph: &RenderGraph,
+    ) -> Result<Vec<GraphPipeline>, GraphError> {
+        let mut built = Vec::new();
+        for (node, desc) in graph.all_pipeline_descs() {
+            match Self::build_graph_pipeline(ctx, resources, graph, &node, &desc) {
+                Ok(pipeline) => built.push(pipeline),
+                Err(err) => {
+                    // None of these were registered or recorded yet.
+                    for p in built {
+                        for obj in pipeline_gpu_objects(&p.pso, &p.bind_groups) {
+                            obj.destroy(ctx);
+                        }
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        Ok(built)
+    }
+
+    fn build_graph_pipeline(
+        ctx: &mut Context,
+        resources: &mut ResourceManager,
+        graph: &RenderGraph,
+        node: &str,
+        desc: &GraphPipelineDesc,
+    ) -> Result<GraphPipeline, GraphError> {
+        let error = |error| GraphError::Pipeline {
+            node: node.to_string(),
+            pipeline: desc.name.clone(),
+            error,
+        };
+        let key = match &desc.material {
+            Some(material) => GraphPipelineKey::Material(material.clone()),
+            None => graph
+                .canvases()
+                .into_iter()
+                .find(|c| c.format(&desc.output).is_some())
+                .map(|c| GraphPipelineKey::Pass(c.target().name.clone()))
+                .ok_or_else(|| error(PipelineError::UndefinedCanvasOutput(desc.output.clone())))?,
+        };
+        let vert = read_spirv(&desc.vertex)?;
+        let frag = read_spirv(&desc.fragment)?;
+        let aliases: BindingAliases = desc
+            .bindings
+            .iter()
+            .map(|(variable, resource)| (variable.clone(), resource.clone()))
+            .collect();
+
+        let mut builder = PipelineBuilder::new(ctx, desc.name.clone())
+            .vertex_shader(&vert)
+            .fragment_shader(&frag)
+            .depth_enable(desc.depth)
+            .cull_mode(desc.cull.into())
+            .state(desc.state.clone())
+            .render_pass(graph.output(&desc.output));
+        if let Some(buffers) = desc.vertex_layout.resolve(&desc.vertex_buffers) {
+            builder = builder.vertex_buffers(buffers);
+        }
+        let (pso, bind_groups) = with_aliases(resources, &aliases, |res| {
+            let mut pso = builder.build_with_resources(res)?;
+            let groups = Self::check_mesh_pipeline(&pso).and_then(|_| pso.create_bind_groups(res));
+            Ok((pso, groups))
+        })
+        .map_err(error)?;
+        let bind_groups = match bind_groups {
+            Ok(groups) => groups,
+            Err(e) => {
+                for obj in pso.gpu_objects() {
+                    obj.destroy(ctx);
+                }
+                return Err(error(e));
+            }
+        };
+        Ok(GraphPipeline {
+            key,
+            pso,
+            bind_groups,
+            aliases,
+        })
+    }
+
+    /// Register built graph pipelines. Pipelines they replace, and pipelines
+    /// of an earlier graph that the current graph no longer declares, are
+    /// released once the frames in flight are done with them.
+    fn install_graph_pipelines(&mut self, built: Vec<GraphPipeline>) {
+        let previous = std::mem::take(&mut self.graph_pipelines);
+        for p in built {
+            let entry = (p.pso, p.bind_groups);
+            let replaced = match &p.key {
+                GraphPipelineKey::Material(id) => self.material_pipelines.insert(id.clone(), entry),
+                GraphPipelineKey::Pass(pass) => self.pipelines.insert(pass.clone(), entry),
+            };
+            if let Some((pso, groups)) = replaced {
+                self.retire_pipeline(&pso, &groups);
+            }
+            self.graph_pipelines.push((p.key, p.aliases));
+        }
+        for (key, _) in previous {
+            if self.graph_pipelines.iter().any(|(k, _)| *k == key) {
+                continue;
+            }
+            let removed = match &key {
+                GraphPipelineKey::Material(id) => self.material_pipelines.remove(id),
+                GraphPipelineKey::Pass(pass) => self.pipelines.remove(pass),
+            };
+            if let Some((pso, groups)) = removed {
+                self.retire_pipeline(&pso, &groups);
+            }
+        }
     }
 
-    pub fn new(width: u32, height: u32, _title: &str, ctx: &mut Context) -> Result<Self, GPUError> {
+    fn retire_pipeline(&mut self, pso: &PSO, bind_groups: &[Option<PSOBindGroupResources>; 4]) {
+        for obj in pipeline_gpu_objects(pso, bind_groups) {
+            self.gpu_releases.push(self.frame_index, obj);
+        }
+    }
+
+    pub fn new(
+        width: u32,
+        height: u32,
+        _title: &str,
+        ctx: &mut Context,
+    ) -> Result<Self, GraphError> {
         let canvas = CanvasBuilder::new()
             .extent([width, height])
             .color_attachment("color", Format::RGBA8)
@@ -339,7 +772,7 @@ impl Renderer {
         height: u32,
         _title: &str,
         ctx: &mut Context,
-    ) -> Result<Self, GPUError> {
+    ) -> Result<Self, GraphError> {
         let canvas = CanvasBuilder::new()
             .extent([width, height])
             .color_attachment("color", Format::RGBA8)
@@ -388,6 +821,10 @@ impl Renderer {
         if index >= MAX_CAMERAS {
             return;
         }
+        if index == self.culling.camera {
+            self.culling.view_proj = Some(view_proj);
+            self.culling.eye = Some(cam_pos);
+        }
         if let Some(buf) = self.camera_buffer {
             let ctx = self.get_ctx();
             let data = CameraData {
@@ -411,42 +848,70 @@ impl Renderer {
         &self.time_stats
     }
 
+    /// Meshes bind a single vertex buffer, so pipelines drawing them may
+    /// read at most one.
+    fn check_mesh_pipeline(pso: &PSO) -> Result<(), PipelineError> {
+        match pso.vertex_buffers.len() {
+            0 | 1 => Ok(()),
+            count => Err(PipelineError::VertexInput(format!(
+                "pipeline reads {} vertex buffers, but meshes bind one",
+                count
+            ))),
+        }
+    }
+
+    /// Draw `stage` with `pso`. Fails if the pipeline reads more than one
+    /// vertex buffer.
     pub fn register_pso(
         &mut self,
         stage: RenderStage,
         pso: PSO,
         bind_group_resources: [Option<PSOBindGroupResources>; 4],
-    ) {
+    ) -> Result<(), PipelineError> {
+        Self::check_mesh_pipeline(&pso)?;
         self.stage_pipelines
             .insert(stage, (pso, bind_group_resources));
+        Ok(())
     }
 
+    /// Draw the meshes of `pass` with `pso`. Fails if the pipeline reads more
+    /// than one vertex buffer.
     pub fn register_pipeline_for_pass(
         &mut self,
         pass: &str,
         pso: PSO,
         bind_group_resources: [Option<PSOBindGroupResources>; 4],
-    ) {
+    ) -> Result<(), PipelineError> {
+        Self::check_mesh_pipeline(&pso)?;
         self.pipelines
             .insert(pass.to_string(), (pso, bind_group_resources));
+        Ok(())
     }
 
+    /// Draw skeletal meshes with `pso`. Fails if the pipeline reads more
+    /// than one vertex buffer.
     pub fn register_skeletal_pso(
         &mut self,
         pso: PSO,
         bind_group_resources: [Option<PSOBindGroupResources>; 4],
-    ) {
+    ) -> Result<(), PipelineError> {
+        Self::check_mesh_pipeline(&pso)?;
         self.skeletal_pipeline = Some((pso, bind_group_resources));
+        Ok(())
     }
 
+    /// Draw meshes of `material_id` with `pso`. Fails if the pipeline reads
+    /// more than one vertex buffer.
     pub fn register_material_pipeline(
         &mut self,
         material_id: &str,
         pso: PSO,
         bind_group_resources: [Option<PSOBindGroupResources>; 4],
-    ) {
+    ) -> Result<(), PipelineError> {
+        Self::check_mesh_pipeline(&pso)?;
         self.material_pipelines
             .insert(material_id.to_string(), (pso, bind_group_resources));
+        Ok(())
     }
 
     pub fn register_compute_pipeline(
@@ -473,48 +938,121 @@ impl Renderer {
         self.canvases.get(index)
     }
 
+    /// Register a static mesh drawn once, see
+    /// [`Renderer::register_static_mesh_instanced`].
     pub fn register_static_mesh<'a, N: Into<DrawableNode<'a>>>(
+        &mut self,
+        mesh: StaticMesh,
+        dynamic_buffers: Option<DynamicBuffer>,
+        material_id: String,
+        node: N,
+    ) -> Result<StaticMeshHandle, InstanceCapacityError> {
+        self.register_static_mesh_instanced(
+            mesh,
+            dynamic_buffers,
+            vec![MeshInstance::default()],
+            material_id,
+            node,
+        )
+    }
+
+    /// Upload a static mesh once and draw it for every entry in `instances`.
+    ///
+    /// Instance data is written to the shared `KOJI_instances` storage buffer
+    /// and the mesh is drawn with a single instanced draw call.
+    ///
+    /// Fails, registering nothing, when the instance buffer has no room for
+    /// `instances`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mesh fails to upload.
+    pub fn register_static_mesh_instanced<'a, N: Into<DrawableNode<'a>>>(
         &mut self,
         mut mesh: StaticMesh,
         dynamic_buffers: Option<DynamicBuffer>,
+        instances: Vec<MeshInstance>,
         material_id: String,
         node: N,
-    ) {
+    ) -> Result<StaticMeshHandle, InstanceCapacityError> {
+        let capacity = instances.len() as u32;
+        let first_instance = self.reserve_instances(capacity)?;
         mesh.material_id = material_id;
         mesh
             .upload(self.get_ctx())
             .expect("Failed to upload mesh to GPU");
+        self.write_instances(first_instance, &instances);
         let name = node.into().resolve();
-        self.drawables
-            .entry(name)
-            .or_default()
-            .push((mesh, dynamic_buffers));
+        let handle = self.drawables.insert(StaticDrawable {
+            node: name.clone(),
+            bounds: mesh.bounds(),
+            mesh,
+            _dynamic_buffers: dynamic_buffers,
+            instances,
+            first_instance,
+            instance_capacity: capacity,
+            visible: true,
+            in_view: true,
+        });
+        self.node_drawables.entry(name).or_default().statics.push(handle);
+        Ok(handle)
+    }
+
+    /// Reserve `count` consecutive slots in the instance buffer.
+    ///
+    /// Ranges released by removed drawables are reused before growing. Fails
+    /// once `MAX_INSTANCES` slots are in use.
+    fn reserve_instances(&mut self, count: u32) -> Result<u32, InstanceCapacityError> {
+        self.instance_ranges
+            .reserve(count)
+            .ok_or(InstanceCapacityError {
+                requested: count,
+                capacity: MAX_INSTANCES as u32,
+            })
+    }
+
+    fn write_instances(&mut self, first_instance: u32, instances: &[MeshInstance]) {
+        if instances.is_empty() {
+            return;
+        }
+        if let Some(buf) = self.instance_buffer {
+            let ctx = self.get_ctx();
+            let offset = first_instance as usize * INSTANCE_ELEMENT_SIZE;
+            let bytes: &[u8] = bytemuck::cast_slice(instances);
+            let slice = ctx.map_buffer_mut(buf).unwrap();
+            slice[offset..offset + bytes.len()].copy_from_slice(bytes);
+            ctx.unmap_buffer(buf).unwrap();
+        }
     }
 
     pub fn register_text_mesh<'a, T: TextRenderable + 'static, N: Into<DrawableNode<'a>>>(
         &mut self,
         mesh: T,
         node: N,
-    ) {
+    ) -> TextMeshHandle {
         let name = node.into().resolve();
-        self.text_drawables
-            .entry(name)
-            .or_default()
-            .push(Box::new(mesh));
+        let handle = self.text_drawables.insert(TextDrawable {
+            node: name.clone(),
+            mesh: Box::new(mesh),
+            visible: true,
+        });
+        self.node_drawables.entry(name).or_default().texts.push(handle);
+        handle
     }
 
-    pub fn update_text_mesh<'a, T: TextRenderable + 'static, N: Into<DrawableNode<'a>>>(
+    /// Replace the mesh behind a text handle. The previous mesh's buffers are
+    /// released once the frames in flight have completed.
+    pub fn update_text_mesh<T: TextRenderable + 'static>(
         &mut self,
-        node: N,
-        idx: usize,
+        handle: TextMeshHandle,
         mesh: T,
     ) {
-        let name = node.into().resolve();
-        if let Some(vec) = self.text_drawables.get_mut(&name) {
-            if let Some(slot) = vec.get_mut(idx) {
-                *slot = Box::new(mesh);
-            }
-        }
+        let old = match self.text_drawables.get_mut(handle) {
+            Some(drawable) => std::mem::replace(&mut drawable.mesh, Box::new(mesh)),
+            None => return,
+        };
+        self.retire_buffer(Some(old.vertex_buffer()));
+        self.retire_buffer(old.index_buffer());
     }
 
     /// Upload a skeletal mesh and its instances.
@@ -524,7 +1062,7 @@ impl Renderer {
         instances: Vec<SkeletalInstance>,
         material_id: String,
         node: N,
-    ) {
+    ) -> SkeletalMeshHandle {
         mesh.material_id = material_id;
         mesh
             .upload(self.get_ctx())
@@ -534,10 +1072,228 @@ impl Renderer {
                 .register_storage("bone_buf", inst.bone_buffer);
         }
         let name = node.into().resolve();
-        self.skeletal_meshes
-            .entry(name)
-            .or_default()
-            .push((mesh, instances));
+        let handle = self.skeletal_meshes.insert(SkeletalDrawable {
+            node: name.clone(),
+            bounds: mesh.bounds(),
+            mesh,
+            instances,
+            visible: true,
+            in_view: true,
+        });
+        self.node_drawables.entry(name).or_default().skeletal.push(handle);
+        handle
+    }
+
+    /// Unregister a drawable so it is no longer drawn.
+    ///
+    /// The handle becomes invalid immediately, while the drawable's GPU
+    /// buffers are destroyed only after the frames currently in flight have
+    /// completed. Returns `false` if the handle was already removed.
+    pub fn remove_drawable(&mut self, id: impl Into<DrawableId>) -> bool {
+        match id.into() {
+            DrawableId::Static(handle) => {
+                let drawable = match self.drawables.remove(handle) {
+                    Some(d) => d,
+                    None => return false,
+                };
+                if let Some(list) = self.node_drawables.get_mut(&drawable.node) {
+                    list.statics.retain(|h| *h != handle);
+                }
+                self.retire_buffer(drawable.mesh.vertex_buffer);
+                self.retire_buffer(drawable.mesh.index_buffer);
+                self.retire_instances(drawable.first_instance, drawable.instance_capacity);
+            }
+            DrawableId::Text(handle) => {
+                let drawable = match self.text_drawables.remove(handle) {
+                    Some(d) => d,
+                    None => return false,
+                };
+                if let Some(list) = self.node_drawables.get_mut(&drawable.node) {
+                    list.texts.retain(|h| *h != handle);
+                }
+                self.retire_buffer(Some(drawable.mesh.vertex_buffer()));
+                self.retire_buffer(drawable.mesh.index_buffer());
+            }
+            DrawableId::Skeletal(handle) => {
+                let drawable = match self.skeletal_meshes.remove(handle) {
+                    Some(d) => d,
+                    None => return false,
+                };
+                if let Some(list) = self.node_drawables.get_mut(&drawable.node) {
+                    list.skeletal.retain(|h| *h != handle);
+                }
+                self.retire_buffer(drawable.mesh.vertex_buffer);
+                self.retire_buffer(drawable.mesh.index_buffer);
+                self.retire_buffer(drawable.mesh.bone_buffer);
+                for inst in &drawable.instances {
+                    if let Some(ResourceBinding::Storage(buf)) = self.resource_manager.get("bone_buf") {
+                        if *buf == inst.bone_buffer {
+                            self.resource_manager.remove("bone_buf");
+                        }
+                    }
+                    self.retire_buffer(Some(inst.bone_buffer));
+                }
+            }
+        }
+        true
+    }
+
+    /// Show or hide a drawable without unregistering it. Returns `false` if the
+    /// handle is no longer valid.
+    pub fn set_visible(&mut self, id: impl Into<DrawableId>, visible: bool) -> bool {
+        let flag = match id.into() {
+            DrawableId::Static(h) => self.drawables.get_mut(h).map(|d| &mut d.visible),
+            DrawableId::Text(h) => self.text_drawables.get_mut(h).map(|d| &mut d.visible),
+            DrawableId::Skeletal(h) => self.skeletal_meshes.get_mut(h).map(|d| &mut d.visible),
+        };
+        match flag {
+            Some(flag) => {
+                *flag = visible;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether `id` still refers to a registered drawable.
+    pub fn contains_drawable(&self, id: impl Into<DrawableId>) -> bool {
+        match id.into() {
+            DrawableId::Static(h) => self.drawables.contains(h),
+            DrawableId::Text(h) => self.text_drawables.contains(h),
+            DrawableId::Skeletal(h) => self.skeletal_meshes.contains(h),
+        }
+    }
+
+    /// Number of GPU buffers waiting for in-flight frames before destruction.
+    pub fn pending_releases(&self) -> usize {
+        self.buffer_releases.len()
+    }
+
+    fn retire_buffer(&mut self, buffer: Option<Handle<Buffer>>) {
+        if let Some(buf) = buffer {
+            if buf.valid() {
+                self.buffer_releases.push(self.frame_index, buf);
+            }
+        }
+    }
+
+    fn retire_instances(&mut self, first: u32, count: u32) {
+        if count > 0 {
+            self.instance_releases.push(self.frame_index, (first, count));
+        }
+    }
+
+    /// Destroy buffers and recycle instance ranges no longer used by the GPU.
+    fn release_retired(&mut self) {
+        let ctx = self.get_ctx();
+        for buf in self.buffer_releases.drain_ready(self.frame_index) {
+            ctx.destroy_buffer(buf);
+        }
+        for (first, count) in self.instance_releases.drain_ready(self.frame_index) {
+            self.instance_ranges.free(first, count);
+        }
+        for att in self.image_releases.drain_ready(self.frame_index) {
+            ctx.destroy_image_view(att.view);
+            ctx.destroy_image(att.image);
+        }
+        for obj in self.gpu_releases.drain_ready(self.frame_index) {
+            obj.destroy(ctx);
+        }
+    }
+
+    /// Current output size in pixels.
+    pub fn size(&self) -> [u32; 2] {
+        [self.width, self.height]
+    }
+
+    /// Change the output size and reallocate canvases sized relative to it.
+    ///
+    /// `ResourceManager` textures that pointed at a replaced canvas image are
+    /// updated, and bind groups that sampled one are rebuilt, failing with
+    /// [`GraphError::Rebind`] if one cannot be. The display swapchain is not
+    /// recreated, since dashi has no API for it. The old images are destroyed
+    /// once the frames in flight have finished with them.
+    /// [`Renderer::render_loop`] calls this when the window is resized.
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), GraphError> {
+        if width == 0 || height == 0 || [width, height] == self.size() {
+            return Ok(());
+        }
+        let ctx = self.get_ctx();
+        self.width = width;
+        self.height = height;
+        let retired = self.graph.resize(ctx, [width, height])?;
+        if retired.is_empty() {
+            return Ok(());
+        }
+        let resized = self.graph.canvases();
+        for canvas in &mut self.canvases {
+            if let Some(new) = resized
+                .iter()
+                .find(|c| c.render_pass() == canvas.render_pass())
+            {
+                canvas.adopt_images(new);
+            }
+        }
+        self.rebind_resized(&retired).map_err(GraphError::Rebind)?;
+        for att in retired {
+            self.image_releases.push(self.frame_index, att);
+        }
+        Ok(())
+    }
+
+    /// Point bindings and bind groups that used a retired canvas image at its
+    /// replacement.
+    fn rebind_resized(&mut self, retired: &[RetiredAttachment]) -> Result<(), PipelineError> {
+        let stale: Vec<Handle<ImageView>> = retired.iter().map(|a| a.view).collect();
+        let canvases = &self.canvases;
+        let replacement = |view: Handle<ImageView>| -> Option<Texture> {
+            let name = &retired.iter().find(|a| a.view == view)?.name;
+            let canvas = canvases.iter().find(|c| c.view(name).is_some())?;
+            Some(Texture {
+                handle: canvas.image(name)?,
+                view: canvas.view(name)?,
+                dim: canvas.extent(),
+            })
+        };
+        for binding in self.resource_manager.bindings.values_mut() {
+            match binding {
+                ResourceBinding::Texture(tex)
+                | ResourceBinding::CombinedImageSampler { texture: tex, .. } => {
+                    if let Some(new) = replacement(tex.view) {
+                        *tex = new;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let res = &mut self.resource_manager;
+        for (pso, groups) in self
+            .stage_pipelines
+            .values_mut()
+            .chain(self.skeletal_pipeline.as_mut())
+        {
+            rebind_stale(groups, &stale, |set| pso.create_bind_group(set, res))?;
+        }
+        let keyed = self
+            .pipelines
+            .iter_mut()
+            .map(|(pass, entry)| (GraphPipelineKey::Pass(pass.clone()), entry))
+            .chain(
+                self.material_pipelines
+                    .iter_mut()
+                    .map(|(id, entry)| (GraphPipelineKey::Material(id.clone()), entry)),
+            );
+        for (key, (pso, groups)) in keyed {
+            let aliases = graph_aliases(&self.graph_pipelines, &key);
+            with_aliases(res, aliases, |res| {
+                rebind_stale(groups, &stale, |set| pso.create_bind_group(set, res))
+            })?;
+        }
+        for (pso, groups) in self.compute_pipelines.values_mut() {
+            rebind_stale(groups, &stale, |set| pso.create_bind_group(set, res))?;
+        }
+        Ok(())
     }
 
     pub fn add_light(&mut self, light: LightDesc) -> u32 {
@@ -551,6 +1307,16 @@ impl Renderer {
         self.lights.update_light(ctx, index, light);
     }
 
+    /// Remove the light at `index`. Later lights move down one index, and
+    /// their shadow settings move with them.
+    pub fn remove_light(&mut self, index: usize) {
+        if self.lights.light(index).is_none() {
+            return;
+        }
+        self.lights.remove_light(index);
+        self.shift_light_shadows(index as u32);
+    }
+
     pub fn resources(&mut self) -> &mut ResourceManager {
         &mut self.resource_manager
     }
@@ -566,18 +1332,21 @@ impl Renderer {
     /// Main render pass. The provided callback receives all window events as well
     /// as a final [`Event::MainEventsCleared`] each frame so the caller can
     /// update and draw.
-    pub fn render_loop<F>(&mut self, mut draw_fn: F)
+    ///
+    /// Returns when the window is closed, or with the error of a failed resize
+    /// or frame.
+    pub fn render_loop<F>(&mut self, mut draw_fn: F) -> Result<(), GraphError>
     where
         for<'a> F: FnMut(&mut Renderer, Event<'a, ()>),
     {
         if self.display.is_none() {
             draw_fn(self, Event::MainEventsCleared);
-            self.present_frame().unwrap();
-            return;
+            return self.present_frame().map_err(GraphError::Gpu);
         }
 
         'running: loop {
             let mut should_exit = false;
+            let mut resized = None;
             let mut events: Vec<Event<'static, ()>> = Vec::new();
             {
                 let event_loop = self.display.as_mut().unwrap().winit_event_loop();
@@ -588,6 +1357,9 @@ impl Renderer {
                         ..
                     } = event
                     {
+                        if let WindowEvent::Resized(size) = win_event {
+                            resized = Some([size.width, size.height]);
+                        }
                         if matches!(
                             win_event,
                             WindowEvent::CloseRequested
@@ -608,6 +1380,9 @@ impl Renderer {
                     }
                 });
             }
+            if let Some([width, height]) = resized {
+                self.resize(width, height)?;
+            }
             for event in events {
                 draw_fn(self, event);
             }
@@ -615,69 +1390,122 @@ impl Renderer {
                 break 'running;
             }
             draw_fn(self, Event::MainEventsCleared);
-            self.present_frame().unwrap();
+            self.present_frame()?;
         }
+        Ok(())
     }
-    pub fn update_static_mesh<'a, N: Into<DrawableNode<'a>>>(
-        &mut self,
-        node: N,
-        idx: usize,
-        vertices: &[Vertex],
-    ) {
-        let name = node.into().resolve();
-        if let Some(list) = self.drawables.get_mut(&name) {
-            if let Some(mesh) = list.get_mut(idx) {
-                mesh.0.vertices = vertices.to_vec();
-                mesh.0
-                    .upload(unsafe { &mut *self.ctx })
+    /// Replace the vertices of a static mesh and re-upload it.
+    pub fn update_static_mesh(&mut self, handle: StaticMeshHandle, vertices: &[Vertex]) {
+        let ctx = self.get_ctx();
+        let (old_vb, old_ib) = match self.drawables.get_mut(handle) {
+            Some(drawable) => {
+                let old = (drawable.mesh.vertex_buffer, drawable.mesh.index_buffer);
+                drawable.mesh.vertices = vertices.to_vec();
+                // Bounds given with the old vertices no longer apply.
+                drawable.mesh.bounds = None;
+                drawable.bounds = drawable.mesh.bounds();
+                drawable
+                    .mesh
+                    .upload(ctx)
                     .expect("Failed to update mesh to GPU");
+                old
             }
+            None => return,
+        };
+        self.retire_buffer(old_vb);
+        self.retire_buffer(old_ib);
+    }
+
+    /// Set the model matrix of the first instance of a static mesh.
+    pub fn set_static_mesh_transform(&mut self, handle: StaticMeshHandle, transform: Mat4) {
+        let (first_instance, instance) = match self.drawables.get_mut(handle) {
+            Some(drawable) => match drawable.instances.first_mut() {
+                Some(first) => {
+                    first.transform = transform.to_cols_array_2d();
+                    (drawable.first_instance, *first)
+                }
+                None => return,
+            },
+            None => return,
+        };
+        self.write_instances(first_instance, &[instance]);
+    }
+
+    /// Replace the per-instance data of a static mesh.
+    ///
+    /// The instance count may change; if it grows beyond the range reserved at
+    /// registration, a new range is reserved in the instance buffer and the
+    /// old one is released once the frames in flight are done with it. Fails,
+    /// leaving the mesh unchanged, when the instance buffer has no room for
+    /// `instances`.
+    pub fn update_static_mesh_instances(
+        &mut self,
+        handle: StaticMeshHandle,
+        instances: &[MeshInstance],
+    ) -> Result<(), InstanceCapacityError> {
+        let (mut first, capacity) = match self.drawables.get(handle) {
+            Some(drawable) => (drawable.first_instance, drawable.instance_capacity),
+            None => return Ok(()),
+        };
+        let count = instances.len() as u32;
+        let capacity = if count > capacity {
+            let old = first;
+            first = self.reserve_instances(count)?;
+            self.retire_instances(old, capacity);
+            count
+        } else {
+            capacity
+        };
+        self.write_instances(first, instances);
+        if let Some(drawable) = self.drawables.get_mut(handle) {
+            drawable.instances = instances.to_vec();
+            drawable.first_instance = first;
+            drawable.instance_capacity = capacity;
         }
+        Ok(())
     }
 
     /// Update bone matrices for a specific skeletal instance.
-    pub fn update_skeletal_bones<'a, N: Into<DrawableNode<'a>>>(
+    pub fn update_skeletal_bones(
         &mut self,
-        node: N,
-        mesh_idx: usize,
+        handle: SkeletalMeshHandle,
         inst_idx: usize,
         matrices: &[Mat4],
     ) {
         let ctx = self.get_ctx();
-        let name = node.into().resolve();
-        if let Some(meshes) = self.skeletal_meshes.get_mut(&name) {
-            if let Some((_mesh, instances)) = meshes.get_mut(mesh_idx) {
-                if let Some(inst) = instances.get_mut(inst_idx) {
-                    inst.animator.matrices.clone_from_slice(matrices);
-                    let _ = inst.update_gpu(ctx);
-                }
+        if let Some(drawable) = self.skeletal_meshes.get_mut(handle) {
+            if let Some(inst) = drawable.instances.get_mut(inst_idx) {
+                inst.animator.matrices.clone_from_slice(matrices);
+                let _ = inst.update_gpu(ctx);
             }
         }
     }
 
     /// Advance an animation player and upload the new bone matrices.
-    pub fn play_animation<'a, N: Into<DrawableNode<'a>>>(
-        &mut self,
-        node: N,
-        mesh_idx: usize,
-        inst_idx: usize,
-        dt: f32,
-    ) {
+    pub fn play_animation(&mut self, handle: SkeletalMeshHandle, inst_idx: usize, dt: f32) {
         let ctx = self.get_ctx();
-        let name = node.into().resolve();
-        if let Some(meshes) = self.skeletal_meshes.get_mut(&name) {
-            if let Some((_mesh, instances)) = meshes.get_mut(mesh_idx) {
-                if let Some(inst) = instances.get_mut(inst_idx) {
-                    if let Some(player) = inst.player.as_mut() {
-                        let local = player.advance(dt);
-                        inst.animator.update_from_nodes(&local);
-                        let _ = inst.update_gpu(ctx);
-                    }
+        if let Some(drawable) = self.skeletal_meshes.get_mut(handle) {
+            if let Some(inst) = drawable.instances.get_mut(inst_idx) {
+                if let Some(player) = inst.player.as_mut() {
+                    let local = player.advance(dt);
+                    inst.animator.update_from_nodes(&local);
+                    let _ = inst.update_gpu(ctx);
                 }
             }
         }
     }
 
+    /// Whether the graph composites its outputs into the swapchain itself.
+    fn has_composition(&self) -> bool {
+        self.graph.execution_order().into_iter().any(|idx| {
+            self.graph
+                .node(idx)
+                .as_any()
+                .downcast_ref::<CompositionNode>()
+                .is_some()
+        })
+    }
+
     /// Helper to build [`DrawBegin`] for a target/pipeline pair.
     fn prepare_draw_begin<'a>(
         width: u32,
@@ -714,9 +1542,21 @@ impl Renderer {
         }
     }
 
+    /// Why the last frame could not rebuild the bind groups of the deferred
+    /// lighting or light culling pass after lights were added or removed, if
+    /// it failed. Those passes keep their previous bind groups, which still
+    /// see the lights that existed when they were built, and the rebuild is
+    /// tried again every frame.
+    pub fn light_binding_error(&self) -> Option<&str> {
+        self.light_error.as_deref()
+    }
+
     /// Present one frame to display (for tests or non-interactive draw)
     pub fn present_frame(&mut self) -> Result<(), GPUError> {
+        // Failures are kept for `reload_error` and the previous graph stays.
+        let _ = self.poll_hot_reload();
         let ctx = self.get_ctx();
+        self.release_retired();
         self.time_stats.update();
         if let Some(buf) = self.time_buffer {
             let data = [self.time_stats.total_time, self.time_stats.delta_time];
@@ -726,6 +1566,14 @@ impl Renderer {
             ctx.unmap_buffer(buf)?;
         }
         self.lights.upload_all(ctx);
+        let deferred = self.update_deferred();
+        self.update_shadows()?;
+        let clusters = self.update_clusters();
+        self.light_error = deferred
+            .and(clusters)
+            .err()
+            .map(|e| format!("failed to rebind lights: {:?}", e));
+        self.cull_drawables();
         let (img, acquire_sem) = if let Some(display) = self.display.as_mut() {
             let (img, sem, _img_idx, _) = ctx.acquire_new_image(display)?;
             (Some(img), Some(sem))
@@ -737,7 +1585,11 @@ impl Renderer {
         let height = self.height;
         let use_canvas_blit = self.display.is_some()
             && self.targets.is_empty()
-            && !self.canvases.is_empty();
+            && !self.canvases.is_empty()
+            && !self.has_composition();
+        let images = self.graph.image_views();
+        let barriers = self.graph.barrier_plan();
+        let mut recorded = Ok(());
 
         self.command_list.record(|list| {
             for task in self.compute_queue.drain(..) {
@@ -755,172 +1607,159 @@ impl Renderer {
                     });
                 }
             }
-            for idx in self.graph.topo_indices() {
+            if let Some(pass) = &self.shadows {
+                shadows::record_shadow_pass(
+                    ctx,
+                    list,
+                    pass,
+                    &self.drawables,
+                    &self.skeletal_meshes,
+                );
+            }
+            for idx in self.graph.execution_order() {
+                for barrier in barriers.before(self.graph.node(idx).name()) {
+                    if let Some(view) = images.get(&barrier.resource) {
+                        ctx.transition_image(list.cmd_buf, *view, vk_layout(barrier.new_layout));
+                    }
+                }
     if let Some(canvas_node) = self.graph.node(idx).as_any().downcast_ref::<CanvasNode>() {
         let node_name = self.graph.node(idx).name().to_string();
         #[cfg(test)]
         draw_log::log(format!("pass:{}", node_name));
         let canvas = canvas_node.canvas().clone();
         let target = canvas.target();
+        let [canvas_width, canvas_height] = canvas.extent();
         let mut attachments = Vec::new();
-        let mut current_pipeline: Option<Handle<GraphicsPipeline>> = None;
-        let mut started = false;
-
-        if let Some(draw_list) = self.drawables.get(&node_name) {
-            for (_idx, (mesh, _dynamic_buffers)) in draw_list.iter().enumerate() {
-                let (pso, bind_groups) =
-                    if let Some(entry) = self.material_pipelines.get(&mesh.material_id) {
-                        entry
-                    } else if let Some(entry) = self.pipelines.get(&target.name) {
-                        entry
+        let order = self
+            .stage_orders
+            .get(&node_name)
+            .map_or(DEFAULT_STAGE_ORDER, |o| o.as_slice());
+        for stage in order {
+            match stage {
+                RenderStage::Opaque | RenderStage::Transparent => {
+                    let transparent = *stage == RenderStage::Transparent;
+                    let mut draws = Vec::new();
+                    let handles = self.node_drawables.get(&node_name).map(|n| &n.statics);
+                    for handle in handles.into_iter().flatten() {
+                        let drawable = match self.drawables.get(*handle) {
+                            Some(d) if d.visible && d.in_view && !d.instances.is_empty() => d,
+                            _ => continue,
+                        };
+                        let mesh = &drawable.mesh;
+                        let mesh_stage = self
+                            .material_stages
+                            .get(&mesh.material_id)
+                            .copied()
+                            .unwrap_or(RenderStage::Opaque);
+                        if (mesh_stage == RenderStage::Transparent) != transparent {
+                            continue;
+                        }
+                        let stage_fallback = if transparent {
+                            self.stage_pipelines.get(&RenderStage::Transparent)
+                        } else {
+                            None
+                        };
+                        let (pso, bind_groups) =
+                            if let Some(entry) = self.material_pipelines.get(&mesh.material_id) {
+                                entry
+                            } else if let Some(entry) = stage_fallback {
+                                entry
+                            } else if let Some(entry) = self.pipelines.get(&target.name) {
+                                entry
+                            } else if let Some(entry) = self
+                                .stage_pipelines
+                                .get(&RenderStage::GBuffer)
+                                .filter(|_| node_name == GBUFFER_NODE)
+                            {
+                                entry
+                            } else {
+                                continue;
+                            };
+                        draws.push((drawable, pso, bind_groups));
+                    }
+                    let draws = if transparent {
+                        let eye = self.culling.eye;
+                        sorting::back_to_front(draws, |(d, _, _)| {
+                            eye.map_or(0.0, |eye| eye.distance(d.world_center()))
+                        })
                     } else {
-                        continue;
+                        // Each registered PSO owns its pipeline, so the PSO's
+                        // address identifies the pipeline.
+                        sorting::by_pipeline_and_material(draws, |&(d, pso, _)| {
+                            (pso as *const PSO, d.mesh.material_id.as_str())
+                        })
                     };
 
-                if Some(pso.pipeline) != current_pipeline {
-                    let draw_begin = Self::prepare_draw_begin(
-                        width,
-                        height,
-                        &target,
-                        pso.pipeline,
-                        &mut attachments,
-                        true,
-                    );
-                    list.begin_drawing(&draw_begin).unwrap();
-                    list.set_viewport(draw_begin.viewport);
-                    list.set_scissor(draw_begin.viewport.scissor);
+                    let mut current_pipeline: Option<Handle<GraphicsPipeline>> = None;
+                    let mut started = false;
                     #[cfg(test)]
-                    draw_log::log(if started { "bind_static" } else { "begin_static" });
-                    started = true;
-                    current_pipeline = Some(pso.pipeline);
-                }
+                    let tag = if transparent { "transparent" } else { "static" };
+                    for (drawable, pso, bind_groups) in draws {
+                        let mesh = &drawable.mesh;
+                        if Some(pso.pipeline) != current_pipeline {
+                            let draw_begin = Self::prepare_draw_begin(
+                                canvas_width,
+                                canvas_height,
+                                &target,
+                                pso.pipeline,
+                                &mut attachments,
+                                true,
+                            );
+                            list.begin_drawing(&draw_begin).unwrap();
+                            list.set_viewport(draw_begin.viewport);
+                            list.set_scissor(draw_begin.viewport.scissor);
+                            #[cfg(test)]
+                            draw_log::log(format!(
+                                "{}_{}",
+                                if started { "bind" } else { "begin" },
+                                tag
+                            ));
+                            started = true;
+                            current_pipeline = Some(pso.pipeline);
+                        }
 
-                let vb = mesh.vertex_buffer.expect("Vertex buffer missing");
-                let ib = mesh.index_buffer;
-                let draw: dashi::Command = if let Some(ib) = ib {
-                    Command::DrawIndexed(DrawIndexed {
-                        index_count: mesh.index_count as u32,
-                        instance_count: 1,
-                        vertices: vb,
-                        indices: ib,
-                        bind_groups: [
-                            bind_groups[0].as_ref().map(|bgr| bgr.bind_group),
-                            bind_groups[1].as_ref().map(|bgr| bgr.bind_group),
-                            bind_groups[2].as_ref().map(|bgr| bgr.bind_group),
-                            bind_groups[3].as_ref().map(|bgr| bgr.bind_group),
-                        ],
-                        ..Default::default()
-                    })
-                } else {
-                    Command::Draw(Draw {
-                        count: mesh.index_count as u32,
-                        instance_count: 1,
-                        vertices: vb,
-                        bind_groups: [
+                        let vb = mesh.vertex_buffer.expect("Vertex buffer missing");
+                        let bind_groups = [
                             bind_groups[0].as_ref().map(|bgr| bgr.bind_group),
                             bind_groups[1].as_ref().map(|bgr| bgr.bind_group),
                             bind_groups[2].as_ref().map(|bgr| bgr.bind_group),
                             bind_groups[3].as_ref().map(|bgr| bgr.bind_group),
-                        ],
-                        ..Default::default()
-                    })
-                };
-                list.append(draw);
-            }
-        }
-        if started {
-            list.end_drawing().unwrap();
-            #[cfg(test)]
-            draw_log::log("end_static");
-        }
-
-        if let Some(text_list) = self.text_drawables.get(&node_name) {
-            if let Some((pso, bind_groups)) = self.stage_pipelines.get(&RenderStage::Text) {
-                if !text_list.is_empty() {
-                    let mut attachments = Vec::new();
-                    let draw_begin = Self::prepare_draw_begin(
-                        width,
-                        height,
-                        &target,
-                        pso.pipeline,
-                        &mut attachments,
-                        false,
-                    );
-                    list.begin_drawing(&draw_begin).unwrap();
-                    list.set_viewport(draw_begin.viewport);
-                    list.set_scissor(draw_begin.viewport.scissor);
-
-                    for mesh in text_list {
-                        let vb = mesh.vertex_buffer();
-                        let ib = mesh.index_buffer();
-                        let draw = if let Some(ib) = ib {
+                        ];
+                        let instance_count = drawable.instances.len() as u32;
+                        let draw: dashi::Command = if let Some(ib) = mesh.index_buffer {
                             Command::DrawIndexed(DrawIndexed {
-                                index_count: mesh.index_count() as u32,
-                                instance_count: 1,
+                                index_count: mesh.index_count as u32,
+                                instance_count,
+                                first_instance: drawable.first_instance,
                                 vertices: vb,
                                 indices: ib,
-                                bind_groups: [
-                                    bind_groups[0].as_ref().map(|b| b.bind_group),
-                                    bind_groups[1].as_ref().map(|b| b.bind_group),
-                                    bind_groups[2].as_ref().map(|b| b.bind_group),
-                                    bind_groups[3].as_ref().map(|b| b.bind_group),
-                                ],
+                                bind_groups,
                                 ..Default::default()
                             })
                         } else {
                             Command::Draw(Draw {
-                                count: mesh.index_count() as u32,
-                                instance_count: 1,
+                                count: mesh.index_count as u32,
+                                instance_count,
+                                first_instance: drawable.first_instance,
                                 vertices: vb,
-                                bind_groups: [
-                                    bind_groups[0].as_ref().map(|b| b.bind_group),
-                                    bind_groups[1].as_ref().map(|b| b.bind_group),
-                                    bind_groups[2].as_ref().map(|b| b.bind_group),
-                                    bind_groups[3].as_ref().map(|b| b.bind_group),
-                                ],
+                                bind_groups,
                                 ..Default::default()
                             })
                         };
                         list.append(draw);
                     }
-
-                    list.end_drawing().unwrap();
+                    if started {
+                        list.end_drawing().unwrap();
+                        #[cfg(test)]
+                        draw_log::log(format!("end_{}", tag));
+                    }
                 }
-            }
-        }
-
-        if let Some(skel_list) = self.skeletal_meshes.get_mut(&node_name) {
-            for (mesh, instances) in skel_list {
-                let (pso, bind_groups) =
-                    if let Some(entry) = self.material_pipelines.get(&mesh.material_id) {
-                        entry
-                    } else if let Some(entry) = &self.skeletal_pipeline {
-                        entry
-                    } else {
-                        continue;
-                    };
-                let layout = pso.bind_group_layouts[0].expect("layout");
-                let mut attachments = Vec::new();
-                let mut started = false;
-
-                for inst in instances.iter_mut() {
-                    inst.update_gpu(ctx).unwrap();
-                    let inst_bg = ctx
-                        .make_bind_group(&BindGroupInfo {
-                            debug_name: "skel_instance_bg",
-                            layout,
-                            set: 0,
-                            bindings: &[BindingInfo {
-                                binding: 0,
-                                resource: ShaderResource::StorageBuffer(inst.bone_buffer),
-                            }],
-                        })
-                        .unwrap();
-
-                    if !started {
+                RenderStage::Lighting if node_name == LIGHTING_NODE => {
+                    let lighting = self.stage_pipelines.get(&RenderStage::Lighting);
+                    if let (Some(pass), Some((pso, bind_groups))) = (&self.deferred, lighting) {
                         let draw_begin = Self::prepare_draw_begin(
-                            width,
-                            height,
+                            canvas_width,
+                            canvas_height,
                             &target,
                             pso.pipeline,
                             &mut attachments,
@@ -929,79 +1768,196 @@ impl Renderer {
                         list.begin_drawing(&draw_begin).unwrap();
                         list.set_viewport(draw_begin.viewport);
                         list.set_scissor(draw_begin.viewport.scissor);
-                        #[cfg(test)]
-                        draw_log::log("begin_skeletal");
-                        started = true;
-                    }
-
-                    let vb = mesh.vertex_buffer.expect("Vertex buffer missing");
-                    let ib = mesh.index_buffer;
-                    let draw: dashi::Command = if let Some(ib) = ib {
-                        Command::DrawIndexed(DrawIndexed {
-                            index_count: mesh.index_count as u32,
+                        list.append(Command::Draw(Draw {
+                            count: 3,
                             instance_count: 1,
-                            vertices: vb,
-                            indices: ib,
+                            vertices: pass.triangle,
                             bind_groups: [
-                                Some(inst_bg),
+                                bind_groups[0].as_ref().map(|bgr| bgr.bind_group),
                                 bind_groups[1].as_ref().map(|bgr| bgr.bind_group),
                                 bind_groups[2].as_ref().map(|bgr| bgr.bind_group),
                                 bind_groups[3].as_ref().map(|bgr| bgr.bind_group),
                             ],
                             ..Default::default()
-                        })
-                    } else {
-                        Command::Draw(Draw {
-                            count: mesh.index_count as u32,
-                            instance_count: 1,
-                            vertices: vb,
-                            bind_groups: [
-                                Some(inst_bg),
-                                bind_groups[1].as_ref().map(|bgr| bgr.bind_group),
-                                bind_groups[2].as_ref().map(|bgr| bgr.bind_group),
-                                bind_groups[3].as_ref().map(|bgr| bgr.bind_group),
-                            ],
-                            ..Default::default()
-                        })
-                    };
-                    list.append(draw);
+                        }));
+                        list.end_drawing().unwrap();
+                        #[cfg(test)]
+                        draw_log::log("deferred_lighting");
+                    }
                 }
-                if started {
-                    list.end_drawing().unwrap();
-                    #[cfg(test)]
-                    draw_log::log("end_skeletal");
+                RenderStage::Text => {
+                    let handles = self.node_drawables.get(&node_name).map(|n| &n.texts);
+                    if let Some(handles) = handles {
+                        let text_pipeline = self.stage_pipelines.get(&RenderStage::Text);
+                        if let Some((pso, bind_groups)) = text_pipeline {
+                            let text_list: Vec<&TextDrawable> = handles
+                                .iter()
+                                .filter_map(|h| self.text_drawables.get(*h))
+                                .filter(|t| t.visible)
+                                .collect();
+                            if !text_list.is_empty() {
+                                let mut attachments = Vec::new();
+                                let draw_begin = Self::prepare_draw_begin(
+                                    canvas_width,
+                                    canvas_height,
+                                    &target,
+                                    pso.pipeline,
+                                    &mut attachments,
+                                    false,
+                                );
+                                list.begin_drawing(&draw_begin).unwrap();
+                                list.set_viewport(draw_begin.viewport);
+                                list.set_scissor(draw_begin.viewport.scissor);
+
+                                for text in text_list {
+                                    let mesh = &text.mesh;
+                                    let vb = mesh.vertex_buffer();
+                                    let ib = mesh.index_buffer();
+                                    let draw = if let Some(ib) = ib {
+                                        Command::DrawIndexed(DrawIndexed {
+                                            index_count: mesh.index_count() as u32,
+                                            instance_count: 1,
+                                            vertices: vb,
+                                            indices: ib,
+                                            bind_groups: [
+                                                bind_groups[0].as_ref().map(|b| b.bind_group),
+                                                bind_groups[1].as_ref().map(|b| b.bind_group),
+                                                bind_groups[2].as_ref().map(|b| b.bind_group),
+                                                bind_groups[3].as_ref().map(|b| b.bind_group),
+                                            ],
+                                            ..Default::default()
+                                        })
+                                    } else {
+                                        Command::Draw(Draw {
+                                            count: mesh.index_count() as u32,
+                                            instance_count: 1,
+                                            vertices: vb,
+                                            bind_groups: [
+                                                bind_groups[0].as_ref().map(|b| b.bind_group),
+                                                bind_groups[1].as_ref().map(|b| b.bind_group),
+                                                bind_groups[2].as_ref().map(|b| b.bind_group),
+                                                bind_groups[3].as_ref().map(|b| b.bind_group),
+                                            ],
+                                            ..Default::default()
+                                        })
+                                    };
+                                    list.append(draw);
+                                }
+
+                                list.end_drawing().unwrap();
+                            }
+                        }
+                    }
+                }
+                RenderStage::Skeletal => {
+                    let handles = self.node_drawables.get(&node_name).map(|n| &n.skeletal);
+                    if let Some(handles) = handles {
+                        for handle in handles {
+                            let drawable = match self.skeletal_meshes.get(*handle) {
+                                Some(d) if d.visible && d.in_view => d,
+                                _ => continue,
+                            };
+                            let (mesh, instances) = (&drawable.mesh, &drawable.instances);
+                            let material = self.material_pipelines.get(&mesh.material_id);
+                            let (pso, bind_groups) = if let Some(entry) = material {
+                                entry
+                            } else if let Some(entry) = &self.skeletal_pipeline {
+                                entry
+                            } else {
+                                continue;
+                            };
+                            let layout = pso.bind_group_layouts[0].expect("layout");
+                            let mut attachments = Vec::new();
+                            let mut started = false;
+
+                            for inst in instances.iter() {
+                                inst.update_gpu(ctx).unwrap();
+                                let bones = ShaderResource::StorageBuffer(inst.bone_buffer);
+                                let inst_bg = ctx
+                                    .make_bind_group(&BindGroupInfo {
+                                        debug_name: "skel_instance_bg",
+                                        layout,
+                                        set: 0,
+                                        bindings: &[BindingInfo {
+                                            binding: 0,
+                                            resource: bones,
+                                        }],
+                                    })
+                                    .unwrap();
+
+                                if !started {
+                                    let draw_begin = Self::prepare_draw_begin(
+                                        canvas_width,
+                                        canvas_height,
+                                        &target,
+                                        pso.pipeline,
+                                        &mut attachments,
+                                        false,
+                                    );
+                                    list.begin_drawing(&draw_begin).unwrap();
+                                    list.set_viewport(draw_begin.viewport);
+                                    list.set_scissor(draw_begin.viewport.scissor);
+                                    #[cfg(test)]
+                                    draw_log::log("begin_skeletal");
+                                    started = true;
+                                }
+                                let vb =
+                                    mesh.vertex_buffer.expect("Vertex buffer missing");
+                                let ib = mesh.index_buffer;
+                                let draw: dashi::Command = if let Some(ib) = ib {
+                                    Command::DrawIndexed(DrawIndexed {
+                                        index_count: mesh.index_count as u32,
+                                        instance_count: 1,
+                                        vertices: vb,
+                                        indices: ib,
+                                        bind_groups: [
+                                            Some(inst_bg),
+                                            bind_groups[1].as_ref().map(|bgr| bgr.bind_group),
+                                            bind_groups[2].as_ref().map(|bgr| bgr.bind_group),
+                                            bind_groups[3].as_ref().map(|bgr| bgr.bind_group),
+                                        ],
+                                        ..Default::default()
+                                    })
+                                } else {
+                                    Command::Draw(Draw {
+                                        count: mesh.index_count as u32,
+                                        instance_count: 1,
+                                        vertices: vb,
+                                        bind_groups: [
+                                            Some(inst_bg),
+                                            bind_groups[1].as_ref().map(|bgr| bgr.bind_group),
+                                            bind_groups[2].as_ref().map(|bgr| bgr.bind_group),
+                                            bind_groups[3].as_ref().map(|bgr| bgr.bind_group),
+                                        ],
+                                        ..Default::default()
+                                    })
+                                };
+                                list.append(draw);
+                            }
+                            if started {
+                                list.end_drawing().unwrap();
+                                #[cfg(test)]
+                                draw_log::log("end_skeletal");
+                            }
+                        }
+                    }
                 }
+                // G-buffer draws are the opaque draws of the G-buffer node, and
+                // only the lighting node records the lighting stage.
+                RenderStage::GBuffer | RenderStage::Lighting => {}
             }
         }
-    } else if self
-                    .graph
-                    .node(idx)
-                    .as_any()
-                    .downcast_ref::<CompositionNode>()
-                    .is_some()
-                {
-                    let inputs = self.graph.node(idx).inputs();
-                    if let (Some(img), Some(first)) = (img, inputs.first()) {
-                        if let Some(src) = self
-                            .canvases
-                            .iter()
-                            .map(|c| c.target())
-                            .flat_map(|t| t.colors.iter())
-                            .find(|a| a.name == first.name)
-                        {
-                            list.blit_image(ImageBlit {
-                                src: src.attachment.img,
-                                dst: img,
-                                filter: Filter::Nearest,
-                                ..Default::default()
-                            });
-                        }
+    } else {
+                    // Every other node records its own work through the pass context.
+                    let node = self.graph.node_mut(idx);
+                    let result = node.execute(ctx).and_then(|()| {
+                        let mut pass = PassContext::new(ctx, list, [width, height], &images, img);
+                        node.record(&mut pass)
+                    });
+                    if let Err(err) = result {
+                        recorded = Err(err);
+                        break;
                     }
-                    // execute any additional node behavior
-                    self.graph.node_mut(idx).execute(ctx).unwrap();
-                } else {
-                    // For other node types just execute
-                    self.graph.node_mut(idx).execute(ctx).unwrap();
                 }
             }
 
@@ -1018,6 +1974,10 @@ impl Renderer {
                 }
             }
         });
+        for obj in self.graph.take_retired() {
+            self.gpu_releases.push(self.frame_index, obj);
+        }
+        recorded?;
 
         let mut wait_sems = Vec::new();
         if let Some(sem) = acquire_sem {
@@ -1028,6 +1988,8 @@ impl Renderer {
             signal_sems: &self.semaphores,
         });
 
+        self.frame_index += 1;
+
         if let Some(display) = self.display.as_ref() {
             ctx.present_display(display, &self.semaphores)?;
         }
@@ -1035,18 +1997,28 @@ impl Renderer {
     }
 
     /// Read back the specified color attachment into a CPU-accessible RGBA8 buffer.
+    ///
+    /// Images produced by other graph nodes, such as the `swapchain` output of
+    /// a [`CompositionNode`], can be read back as well.
     pub fn read_color_target(&mut self, name: &str) -> Vec<u8> {
         let ctx = self.get_ctx();
-        let attachment = self
+        let view = self
             .canvases
             .iter()
             .map(|c| c.target())
             .chain(self.targets.iter())
             .find_map(|t| t.colors.iter().find(|a| a.name == name))
+            .map(|a| a.attachment.img)
+            .or_else(|| self.graph.image_view(name))
             .expect("color attachment not found");
 
-        let view = attachment.attachment.img;
-        let byte_size = (self.width * self.height * 4) as u32;
+        let [width, height] = self
+            .canvases
+            .iter()
+            .find(|c| c.view(name).is_some())
+            .map(|c| c.extent())
+            .unwrap_or(self.size());
+        let byte_size = width * height * 4;
         let buffer = ctx
             .make_buffer(&BufferInfo {
                 debug_name: "readback",
@@ -0,0 +1,727 @@
+//! Shadow mapping for directional and spot lights.
+//!
+//! Lights opt in with [`Renderer::set_light_shadows`]. Every frame the
+//! renderer derives one view per spot light and one per cascade of each
+//! directional light, renders the registered static and skeletal meshes into
+//! a tile of a depth atlas for each view, and uploads the views to the
+//! `shadow_views` storage buffer. Lit shaders sample `shadow_atlas` with the
+//! matrices and atlas rectangles found there, as `shadows_lit.frag` does.
+
+use super::{DrawableSlots, Renderer, SkeletalDrawable, SkeletalMesh, StaticDrawable, StaticMesh};
+use crate::canvas::{Canvas, CanvasBuilder};
+use crate::material::{
+    build_shadow_pipeline, build_skinned_shadow_pipeline, LightDesc, PSOBindGroupResources, PSO,
+};
+use crate::render_graph::GraphError;
+use ash::vk;
+use bytemuck::{Pod, Zeroable};
+use dashi::utils::*;
+use dashi::*;
+use glam::{Mat4, Vec3};
+use std::collections::BTreeMap;
+
+/// Depth atlas holding every shadow view, registered as a combined sampler.
+pub const SHADOW_ATLAS: &str = "shadow_atlas";
+/// Storage buffer with a view count followed by [`ShadowView`]s.
+pub const SHADOW_VIEWS: &str = "shadow_views";
+/// Atlas size used when shadows are enabled implicitly.
+pub const DEFAULT_SHADOW_ATLAS_SIZE: u32 = 4096;
+/// The atlas is split into a square grid of this many tiles per side.
+pub const SHADOW_ATLAS_GRID: u32 = 4;
+/// Views that fit in the atlas. Lights beyond it are not shadowed.
+pub const MAX_SHADOW_VIEWS: usize = (SHADOW_ATLAS_GRID * SHADOW_ATLAS_GRID) as usize;
+/// Cascades a directional light may request.
+pub const MAX_CASCADES: u32 = 4;
+
+/// Bytes before the first view in the `shadow_views` buffer.
+const VIEWS_HEADER: usize = 16;
+
+/// Projection used to render a light's shadow.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShadowKind {
+    /// Orthographic cascades along [`LightDesc::direction`], splitting the
+    /// shadow camera's frustum up to `max_distance`. `split_lambda` blends
+    /// logarithmic (1.0) and uniform (0.0) split distances.
+    Directional {
+        cascades: u32,
+        split_lambda: f32,
+        max_distance: f32,
+    },
+    /// Perspective view from [`LightDesc::position`] along
+    /// [`LightDesc::direction`], reaching [`LightDesc::range`]. `fov` is the
+    /// full cone angle in radians.
+    Spot { fov: f32 },
+}
+
+/// Shadow parameters of one light.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LightShadows {
+    pub kind: ShadowKind,
+    /// Depth offset applied before comparing against the atlas.
+    pub bias: f32,
+}
+
+impl LightShadows {
+    pub fn directional(cascades: u32) -> Self {
+        Self {
+            kind: ShadowKind::Directional {
+                cascades: cascades.clamp(1, MAX_CASCADES),
+                split_lambda: 0.75,
+                max_distance: 50.0,
+            },
+            bias: 0.002,
+        }
+    }
+
+    pub fn spot(fov: f32) -> Self {
+        Self {
+            kind: ShadowKind::Spot { fov },
+            bias: 0.0005,
+        }
+    }
+}
+
+/// An error from [`Renderer::set_light_shadows`].
+#[derive(Debug)]
+pub enum ShadowError {
+    /// No light has this index.
+    UnknownLight(u32),
+    /// Shadows were not enabled yet and enabling them failed.
+    Enable(GraphError),
+}
+
+impl std::fmt::Display for ShadowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShadowError::UnknownLight(light) => write!(f, "no light has index {}", light),
+            ShadowError::Enable(err) => write!(f, "failed to enable shadows: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ShadowError {}
+
+impl From<GraphError> for ShadowError {
+    fn from(err: GraphError) -> Self {
+        ShadowError::Enable(err)
+    }
+}
+
+/// Perspective camera whose frustum directional cascades cover.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShadowCamera {
+    /// World to view transform.
+    pub view: Mat4,
+    /// Vertical field of view in radians.
+    pub fov_y: f32,
+    pub aspect: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for ShadowCamera {
+    fn default() -> Self {
+        Self {
+            view: Mat4::IDENTITY,
+            fov_y: 60f32.to_radians(),
+            aspect: 1.0,
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+}
+
+/// One rendered shadow view, as laid out in the `shadow_views` buffer.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct ShadowView {
+    pub view_proj: [[f32; 4]; 4],
+    /// Offset and scale of the view's tile in atlas UVs.
+    pub atlas_rect: [f32; 4],
+    /// Index of the light in [`BindlessLights`](crate::material::BindlessLights).
+    pub light_index: u32,
+    /// Cascade number, 0 for spot lights.
+    pub cascade: u32,
+    /// View-space distance where the cascade ends, 0 for spot lights.
+    pub split_far: f32,
+    pub bias: f32,
+}
+
+/// Far distance of each cascade between `near` and `far`.
+pub fn cascade_splits(near: f32, far: f32, count: u32, lambda: f32) -> Vec<f32> {
+    let count = count.max(1);
+    (1..=count)
+        .map(|i| {
+            let p = i as f32 / count as f32;
+            let log = near * (far / near).powf(p);
+            let uniform = near + (far - near) * p;
+            lambda * log + (1.0 - lambda) * uniform
+        })
+        .collect()
+}
+
+/// World-space corners of the slice of `camera`'s frustum between `near`
+/// and `far`.
+pub fn frustum_corners(camera: &ShadowCamera, near: f32, far: f32) -> [Vec3; 8] {
+    let tan = (camera.fov_y * 0.5).tan();
+    let inv = camera.view.inverse();
+    let mut corners = [Vec3::ZERO; 8];
+    for (i, d) in [near, far].into_iter().enumerate() {
+        let h = d * tan;
+        let w = h * camera.aspect;
+        let quad = [(-w, -h), (w, -h), (w, h), (-w, h)];
+        for (j, (x, y)) in quad.into_iter().enumerate() {
+            corners[i * 4 + j] = inv.transform_point3(Vec3::new(x, y, -d));
+        }
+    }
+    corners
+}
+
+fn up_for(direction: Vec3) -> Vec3 {
+    if direction.y.abs() > 0.99 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    }
+}
+
+/// Orthographic light matrix enclosing `corners`, looking along `direction`.
+///
+/// The projection bounds the corners' sphere so its size doesn't change as
+/// the camera rotates, and extends toward the light to catch casters in
+/// front of the slice.
+pub fn directional_view_proj(direction: Vec3, corners: &[Vec3; 8]) -> Mat4 {
+    let dir = direction.try_normalize().unwrap_or(Vec3::NEG_Y);
+    let center = corners.iter().copied().sum::<Vec3>() / 8.0;
+    let radius = corners
+        .iter()
+        .map(|c| c.distance(center))
+        .fold(f32::EPSILON, f32::max);
+    let eye = center - dir * radius * 2.0;
+    let view = Mat4::look_at_rh(eye, center, up_for(dir));
+    let proj = Mat4::orthographic_rh(-radius, radius, -radius, radius, 0.0, radius * 4.0);
+    proj * view
+}
+
+/// Perspective light matrix of a spot light.
+pub fn spot_view_proj(position: Vec3, direction: Vec3, fov: f32, range: f32) -> Mat4 {
+    let dir = direction.try_normalize().unwrap_or(Vec3::NEG_Z);
+    let far = range.max(0.1);
+    let near = (far * 0.01).max(0.01);
+    let view = Mat4::look_at_rh(position, position + dir, up_for(dir));
+    Mat4::perspective_rh(fov, 1.0, near, far) * view
+}
+
+/// UV offset and scale of atlas tile `tile`.
+pub fn atlas_rect(tile: usize) -> [f32; 4] {
+    let grid = SHADOW_ATLAS_GRID as usize;
+    let scale = 1.0 / grid as f32;
+    [
+        (tile % grid) as f32 * scale,
+        (tile / grid) as f32 * scale,
+        scale,
+        scale,
+    ]
+}
+
+/// Shadow views of one light, nearest cascade first. Atlas rectangles are
+/// assigned by the renderer.
+pub fn light_views(
+    light: &LightDesc,
+    light_index: u32,
+    shadows: &LightShadows,
+    camera: &ShadowCamera,
+) -> Vec<ShadowView> {
+    let view = |view_proj: Mat4, cascade: u32, split_far: f32| ShadowView {
+        view_proj: view_proj.to_cols_array_2d(),
+        light_index,
+        cascade,
+        split_far,
+        bias: shadows.bias,
+        ..Default::default()
+    };
+    match shadows.kind {
+        ShadowKind::Directional {
+            cascades,
+            split_lambda,
+            max_distance,
+        } => {
+            let far = camera.far.min(max_distance);
+            let direction = Vec3::from(light.direction);
+            let mut near = camera.near;
+            cascade_splits(camera.near, far, cascades, split_lambda)
+                .into_iter()
+                .enumerate()
+                .map(|(i, split)| {
+                    let corners = frustum_corners(camera, near, split);
+                    near = split;
+                    view(directional_view_proj(direction, &corners), i as u32, split)
+                })
+                .collect()
+        }
+        ShadowKind::Spot { fov } => {
+            let position = Vec3::from(light.position);
+            let direction = Vec3::from(light.direction);
+            vec![view(
+                spot_view_proj(position, direction, fov, light.range),
+                0,
+                0.0,
+            )]
+        }
+    }
+}
+
+/// GPU state owned by the shadow pass.
+pub(super) struct ShadowPass {
+    atlas: Canvas,
+    static_pso: PSO,
+    /// Set 0 of `static_pso`, holding the instance buffer.
+    static_groups: [Option<PSOBindGroupResources>; 4],
+    skinned_pso: PSO,
+    views_buffer: Handle<Buffer>,
+    /// Per atlas tile: the view matrix uniform and its set 1 bind groups for
+    /// the static and skinned pipelines.
+    tiles: Vec<(Handle<Buffer>, [Handle<BindGroup>; 2])>,
+    lights: BTreeMap<u32, LightShadows>,
+    camera: ShadowCamera,
+    views: Vec<ShadowView>,
+}
+
+fn tile_viewport(tile: usize, atlas_size: u32) -> Viewport {
+    let size = atlas_size / SHADOW_ATLAS_GRID;
+    let x = (tile as u32 % SHADOW_ATLAS_GRID) * size;
+    let y = (tile as u32 / SHADOW_ATLAS_GRID) * size;
+    Viewport {
+        area: FRect2D {
+            x: x as f32,
+            y: y as f32,
+            w: size as f32,
+            h: size as f32,
+        },
+        scissor: Rect2D {
+            x,
+            y,
+            w: size,
+            h: size,
+        },
+        ..Default::default()
+    }
+}
+
+fn view_bind_group(
+    ctx: &mut Context,
+    pso: &PSO,
+    uniform: Handle<Buffer>,
+) -> Result<Handle<BindGroup>, GPUError> {
+    ctx.make_bind_group(&BindGroupInfo {
+        debug_name: "shadow_view_bg",
+        layout: pso.bind_group_layouts[1].ok_or(GPUError::LibraryError())?,
+        set: 1,
+        bindings: &[BindingInfo {
+            binding: 0,
+            resource: ShaderResource::Buffer(uniform),
+        }],
+    })
+}
+
+impl Renderer {
+    /// Allocate an `atlas_size` square shadow atlas and the shadow pipelines,
+    /// and register [`SHADOW_ATLAS`] and [`SHADOW_VIEWS`] with the
+    /// [`ResourceManager`](crate::utils::ResourceManager). Calling this
+    /// again does nothing.
+    pub fn enable_shadows(&mut self, atlas_size: u32) -> Result<(), GraphError> {
+        if self.shadows.is_none() {
+            self.shadows = Some(self.build_shadow_pass(atlas_size)?);
+        }
+        Ok(())
+    }
+
+    fn build_shadow_pass(&mut self, atlas_size: u32) -> Result<ShadowPass, GraphError> {
+        let ctx = self.get_ctx();
+        let atlas_size = atlas_size.max(SHADOW_ATLAS_GRID);
+        let atlas = CanvasBuilder::new()
+            .extent([atlas_size, atlas_size])
+            .depth_attachment(SHADOW_ATLAS, Format::D24S8)
+            .build(ctx)?;
+
+        let mut static_pso = build_shadow_pipeline(ctx, atlas.output(SHADOW_ATLAS));
+        let skinned_pso = build_skinned_shadow_pipeline(ctx, atlas.output(SHADOW_ATLAS));
+        let static_group = static_pso
+            .create_bind_group(0, &self.resource_manager)
+            .map_err(|error| GraphError::Pipeline {
+                node: SHADOW_ATLAS.to_string(),
+                pipeline: "shadow_pipeline".to_string(),
+                error,
+            })?;
+        let static_groups = [Some(static_group), None, None, None];
+
+        let views_buffer = ctx.make_buffer(&BufferInfo {
+            debug_name: SHADOW_VIEWS,
+            byte_size: (VIEWS_HEADER + MAX_SHADOW_VIEWS * std::mem::size_of::<ShadowView>()) as u32,
+            visibility: MemoryVisibility::CpuAndGpu,
+            usage: BufferUsage::STORAGE,
+            initial_data: None,
+        })?;
+        let mut tiles = Vec::with_capacity(MAX_SHADOW_VIEWS);
+        for _ in 0..MAX_SHADOW_VIEWS {
+            let uniform = ctx.make_buffer(&BufferInfo {
+                debug_name: "shadow_view",
+                byte_size: std::mem::size_of::<Mat4>() as u32,
+                visibility: MemoryVisibility::CpuAndGpu,
+                usage: BufferUsage::UNIFORM,
+                initial_data: None,
+            })?;
+            let groups = [
+                view_bind_group(ctx, &static_pso, uniform)?,
+                view_bind_group(ctx, &skinned_pso, uniform)?,
+            ];
+            tiles.push((uniform, groups));
+        }
+
+        let sampler = ctx.make_sampler(&SamplerInfo::default())?;
+        let image = atlas.image(SHADOW_ATLAS).ok_or(GPUError::LibraryError())?;
+        let view = atlas.view(SHADOW_ATLAS).ok_or(GPUError::LibraryError())?;
+        self.resource_manager
+            .register_combined(SHADOW_ATLAS, image, view, atlas.extent(), sampler);
+        self.resource_manager
+            .register_storage(SHADOW_VIEWS, views_buffer);
+
+        Ok(ShadowPass {
+            atlas,
+            static_pso,
+            static_groups,
+            skinned_pso,
+            views_buffer,
+            tiles,
+            lights: BTreeMap::new(),
+            camera: ShadowCamera {
+                aspect: self.width as f32 / self.height.max(1) as f32,
+                ..Default::default()
+            },
+            views: Vec::new(),
+        })
+    }
+
+    /// Opt the light at `light` into shadows, or out of them with `None`.
+    ///
+    /// Shadows are enabled with [`DEFAULT_SHADOW_ATLAS_SIZE`] if
+    /// [`Renderer::enable_shadows`] wasn't called yet. Fails with
+    /// [`ShadowError::UnknownLight`] if no light has index `light`. The
+    /// settings follow the light when [`Renderer::remove_light`] shifts its
+    /// index.
+    pub fn set_light_shadows(
+        &mut self,
+        light: u32,
+        shadows: Option<LightShadows>,
+    ) -> Result<(), ShadowError> {
+        if self.lights.light(light as usize).is_none() {
+            return Err(ShadowError::UnknownLight(light));
+        }
+        let pass = match self.shadows.take() {
+            Some(pass) => pass,
+            None => self.build_shadow_pass(DEFAULT_SHADOW_ATLAS_SIZE)?,
+        };
+        let pass = self.shadows.insert(pass);
+        match shadows {
+            Some(settings) => pass.lights.insert(light, settings),
+            None => pass.lights.remove(&light),
+        };
+        Ok(())
+    }
+
+    /// Drop the shadow settings of the removed light at `removed` and move
+    /// those of later lights down with their indices.
+    pub(super) fn shift_light_shadows(&mut self, removed: u32) {
+        if let Some(pass) = self.shadows.as_mut() {
+            pass.lights = shift_after_removal(std::mem::take(&mut pass.lights), removed);
+        }
+    }
+
+    /// Camera whose frustum the directional cascades cover.
+    pub fn set_shadow_camera(&mut self, camera: ShadowCamera) {
+        if let Some(pass) = self.shadows.as_mut() {
+            pass.camera = camera;
+        }
+    }
+
+    /// Views rendered in the last frame, in atlas tile order.
+    pub fn shadow_views(&self) -> &[ShadowView] {
+        self.shadows.as_ref().map_or(&[], |p| p.views.as_slice())
+    }
+
+    /// Recompute the shadow views and upload them and their matrices.
+    pub(super) fn update_shadows(&mut self) -> Result<(), GPUError> {
+        let Some(pass) = self.shadows.as_mut() else {
+            return Ok(());
+        };
+        let ctx = unsafe { &mut *self.ctx };
+        let mut views = Vec::new();
+        for (&index, settings) in &pass.lights {
+            if let Some(light) = self.lights.light(index as usize) {
+                views.extend(light_views(&light, index, settings, &pass.camera));
+            }
+        }
+        views.truncate(MAX_SHADOW_VIEWS);
+        for (tile, view) in views.iter_mut().enumerate() {
+            view.atlas_rect = atlas_rect(tile);
+            let slice = ctx.map_buffer_mut(pass.tiles[tile].0)?;
+            let bytes = bytemuck::bytes_of(&view.view_proj);
+            slice[..bytes.len()].copy_from_slice(bytes);
+            ctx.unmap_buffer(pass.tiles[tile].0)?;
+        }
+
+        let slice = ctx.map_buffer_mut(pass.views_buffer)?;
+        let count = views.len() as u32;
+        slice[..4].copy_from_slice(bytemuck::bytes_of(&count));
+        let bytes: &[u8] = bytemuck::cast_slice(&views);
+        slice[VIEWS_HEADER..VIEWS_HEADER + bytes.len()].copy_from_slice(bytes);
+        ctx.unmap_buffer(pass.views_buffer)?;
+        pass.views = views;
+        Ok(())
+    }
+}
+
+/// Re-key per-light settings after the light at `removed` was removed and
+/// later lights moved down one index.
+fn shift_after_removal<T>(lights: BTreeMap<u32, T>, removed: u32) -> BTreeMap<u32, T> {
+    lights
+        .into_iter()
+        .filter(|(index, _)| *index != removed)
+        .map(|(index, value)| (if index > removed { index - 1 } else { index }, value))
+        .collect()
+}
+
+/// Render every visible static and skeletal mesh into each view's atlas
+/// tile, then make the atlas readable by shaders.
+pub(super) fn record_shadow_pass(
+    ctx: &mut Context,
+    list: &mut CommandList,
+    pass: &ShadowPass,
+    statics: &DrawableSlots<StaticDrawable, StaticMesh>,
+    skeletal: &DrawableSlots<SkeletalDrawable, SkeletalMesh>,
+) {
+    if pass.views.is_empty() {
+        return;
+    }
+    let target = pass.atlas.target();
+    let [size, _] = pass.atlas.extent();
+    let mut attachments = Vec::new();
+
+    let draw_begin = Renderer::prepare_draw_begin(
+        size,
+        size,
+        target,
+        pass.static_pso.pipeline,
+        &mut attachments,
+        true,
+    );
+    list.begin_drawing(&draw_begin).unwrap();
+    let instances = pass.static_groups[0].as_ref().map(|g| g.bind_group);
+    for (tile, (_, groups)) in pass.tiles.iter().enumerate().take(pass.views.len()) {
+        let viewport = tile_viewport(tile, size);
+        list.set_viewport(viewport);
+        list.set_scissor(viewport.scissor);
+        for drawable in statics.iter() {
+            if !drawable.visible || drawable.instances.is_empty() {
+                continue;
+            }
+            let mesh = &drawable.mesh;
+            let Some(vb) = mesh.vertex_buffer else {
+                continue;
+            };
+            let bind_groups = [instances, Some(groups[0]), None, None];
+            let instance_count = drawable.instances.len() as u32;
+            let draw = if let Some(ib) = mesh.index_buffer {
+                Command::DrawIndexed(DrawIndexed {
+                    index_count: mesh.index_count as u32,
+                    instance_count,
+                    first_instance: drawable.first_instance,
+                    vertices: vb,
+                    indices: ib,
+                    bind_groups,
+                    ..Default::default()
+                })
+            } else {
+                Command::Draw(Draw {
+                    count: mesh.index_count as u32,
+                    instance_count,
+                    first_instance: drawable.first_instance,
+                    vertices: vb,
+                    bind_groups,
+                    ..Default::default()
+                })
+            };
+            list.append(draw);
+        }
+    }
+
+    if skeletal.iter().any(|d| d.visible) {
+        let draw_begin = Renderer::prepare_draw_begin(
+            size,
+            size,
+            target,
+            pass.skinned_pso.pipeline,
+            &mut attachments,
+            true,
+        );
+        list.begin_drawing(&draw_begin).unwrap();
+        let layout = pass.skinned_pso.bind_group_layouts[0].expect("layout");
+        for drawable in skeletal.iter().filter(|d| d.visible) {
+            let mesh = &drawable.mesh;
+            let Some(vb) = mesh.vertex_buffer else {
+                continue;
+            };
+            for inst in &drawable.instances {
+                inst.update_gpu(ctx).unwrap();
+                let bones = ctx
+                    .make_bind_group(&BindGroupInfo {
+                        debug_name: "shadow_bones_bg",
+                        layout,
+                        set: 0,
+                        bindings: &[BindingInfo {
+                            binding: 0,
+                            resource: ShaderResource::StorageBuffer(inst.bone_buffer),
+                        }],
+                    })
+                    .unwrap();
+                for (tile, (_, groups)) in pass.tiles.iter().enumerate().take(pass.views.len()) {
+                    let viewport = tile_viewport(tile, size);
+                    list.set_viewport(viewport);
+                    list.set_scissor(viewport.scissor);
+                    let bind_groups = [Some(bones), Some(groups[1]), None, None];
+                    let draw = if let Some(ib) = mesh.index_buffer {
+                        Command::DrawIndexed(DrawIndexed {
+                            index_count: mesh.index_count as u32,
+                            instance_count: 1,
+                            vertices: vb,
+                            indices: ib,
+                            bind_groups,
+                            ..Default::default()
+                        })
+                    } else {
+                        Command::Draw(Draw {
+                            count: mesh.index_count as u32,
+                            instance_count: 1,
+                            vertices: vb,
+                            bind_groups,
+                            ..Default::default()
+                        })
+                    };
+                    list.append(draw);
+                }
+            }
+        }
+    }
+    list.end_drawing().unwrap();
+
+    if let Some(view) = pass.atlas.view(SHADOW_ATLAS) {
+        ctx.transition_image(
+            list.cmd_buf,
+            view,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec4;
+
+    fn project(m: Mat4, p: Vec3) -> Vec3 {
+        let clip = m * Vec4::new(p.x, p.y, p.z, 1.0);
+        clip.truncate() / clip.w
+    }
+
+    fn in_clip_volume(ndc: Vec3) -> bool {
+        let e = 1e-4;
+        ndc.x.abs() <= 1.0 + e && ndc.y.abs() <= 1.0 + e && ndc.z >= -e && ndc.z <= 1.0 + e
+    }
+
+    #[test]
+    fn removal_shifts_later_light_settings() {
+        let lights: BTreeMap<u32, &str> = [(0, "a"), (2, "c"), (3, "d")].into_iter().collect();
+        let shifted = shift_after_removal(lights.clone(), 2);
+        assert_eq!(shifted.into_iter().collect::<Vec<_>>(), vec![(0, "a"), (2, "d")]);
+        let shifted = shift_after_removal(lights, 1);
+        assert_eq!(
+            shifted.into_iter().collect::<Vec<_>>(),
+            vec![(0, "a"), (1, "c"), (2, "d")]
+        );
+    }
+
+    #[test]
+    fn splits_blend_uniform_and_logarithmic() {
+        assert_eq!(
+            cascade_splits(1.0, 101.0, 4, 0.0),
+            vec![26.0, 51.0, 76.0, 101.0]
+        );
+        let log = cascade_splits(1.0, 100.0, 2, 1.0);
+        assert!((log[0] - 10.0).abs() < 1e-3);
+        assert!((log[1] - 100.0).abs() < 1e-3);
+        let mixed = cascade_splits(0.1, 50.0, 3, 0.75);
+        assert!(mixed.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(cascade_splits(0.1, 50.0, 0, 0.5).len(), 1);
+    }
+
+    #[test]
+    fn cascades_enclose_their_frustum_slice() {
+        let camera = ShadowCamera {
+            view: Mat4::look_at_rh(Vec3::new(0.0, 2.0, 5.0), Vec3::ZERO, Vec3::Y),
+            ..Default::default()
+        };
+        let light = LightDesc {
+            direction: [-0.3, -1.0, -0.2],
+            ..Default::default()
+        };
+        let views = light_views(&light, 2, &LightShadows::directional(3), &camera);
+        assert_eq!(views.len(), 3);
+        assert!(views.iter().all(|v| v.light_index == 2));
+        assert!(views.windows(2).all(|w| w[0].split_far < w[1].split_far));
+        assert!((views[2].split_far - 50.0).abs() < 1e-3);
+
+        let mut near = camera.near;
+        for view in &views {
+            let m = Mat4::from_cols_array_2d(&view.view_proj);
+            for corner in frustum_corners(&camera, near, view.split_far) {
+                assert!(in_clip_volume(project(m, corner)));
+            }
+            near = view.split_far;
+        }
+    }
+
+    #[test]
+    fn spot_views_look_down_the_light_axis() {
+        let light = LightDesc {
+            position: [0.0, 4.0, 0.0],
+            direction: [0.0, -1.0, 0.0],
+            range: 10.0,
+            ..Default::default()
+        };
+        let views = light_views(
+            &light,
+            0,
+            &LightShadows::spot(1.0),
+            &ShadowCamera::default(),
+        );
+        assert_eq!(views.len(), 1);
+        let m = Mat4::from_cols_array_2d(&views[0].view_proj);
+        let on_axis = project(m, Vec3::new(0.0, 0.0, 0.0));
+        assert!(on_axis.x.abs() < 1e-4 && on_axis.y.abs() < 1e-4);
+        assert!(in_clip_volume(on_axis));
+        let beyond = project(m, Vec3::new(0.0, -7.0, 0.0));
+        assert!(beyond.z > 1.0);
+    }
+
+    #[test]
+    fn tiles_cover_the_atlas_grid() {
+        assert_eq!(atlas_rect(0), [0.0, 0.0, 0.25, 0.25]);
+        assert_eq!(atlas_rect(5), [0.25, 0.25, 0.25, 0.25]);
+        assert_eq!(atlas_rect(MAX_SHADOW_VIEWS - 1), [0.75, 0.75, 0.25, 0.25]);
+        let viewport = tile_viewport(6, 1024);
+        assert_eq!(viewport.scissor.x, 512);
+        assert_eq!(viewport.scissor.y, 256);
+        assert_eq!(viewport.scissor.w, 256);
+        assert_eq!(std::mem::size_of::<ShadowView>(), 96);
+    }
+}
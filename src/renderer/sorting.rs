@@ -0,0 +1,122 @@
+//! Stage order and draw sorting within canvas nodes.
+//!
+//! Each canvas node records its drawables stage by stage, in the order given
+//! by [`Renderer::set_stage_order`] or [`DEFAULT_STAGE_ORDER`]. Opaque static
+//! meshes are grouped by pipeline and then material so each pipeline is bound
+//! once. Static meshes whose material was moved to
+//! [`RenderStage::Transparent`] are drawn back to front from the camera set
+//! with [`Renderer::set_camera`].
+//!
+//! A static mesh and all of its instances are one instanced draw, so
+//! transparent meshes are sorted as a whole by the center of their first
+//! instance. Instances of one mesh are not sorted against each other or
+//! interleaved with other meshes; give overlapping transparent objects their
+//! own meshes when their order matters.
+
+use super::{RenderStage, Renderer, StaticDrawable};
+use glam::Vec3;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Stages a canvas node records when it has no order of its own.
+pub const DEFAULT_STAGE_ORDER: &[RenderStage] = &[
+    RenderStage::Opaque,
+    RenderStage::Lighting,
+    RenderStage::Text,
+    RenderStage::Skeletal,
+    RenderStage::Transparent,
+];
+
+/// For each key, the position of its first occurrence among distinct keys.
+fn first_seen_ranks<K: Hash + Eq>(keys: &[K]) -> Vec<usize> {
+    let mut ranks: HashMap<&K, usize> = HashMap::new();
+    keys.iter()
+        .map(|key| {
+            let next = ranks.len();
+            *ranks.entry(key).or_insert(next)
+        })
+        .collect()
+}
+
+/// Group `draws` by pipeline, then by material, keeping the order in which
+/// pipelines and materials first appear.
+pub(super) fn by_pipeline_and_material<T, P: Hash + Eq, M: Hash + Eq>(
+    draws: Vec<T>,
+    key: impl Fn(&T) -> (P, M),
+) -> Vec<T> {
+    let (pipelines, materials): (Vec<P>, Vec<M>) = draws.iter().map(key).unzip();
+    let pipeline_rank = first_seen_ranks(&pipelines);
+    let material_rank = first_seen_ranks(&materials);
+    let mut ranked: Vec<_> = draws.into_iter().enumerate().collect();
+    ranked.sort_by_key(|(i, _)| (pipeline_rank[*i], material_rank[*i]));
+    ranked.into_iter().map(|(_, draw)| draw).collect()
+}
+
+/// Sort `draws` from the farthest to the nearest. Ties keep their order.
+pub(super) fn back_to_front<T>(mut draws: Vec<T>, distance: impl Fn(&T) -> f32) -> Vec<T> {
+    draws.sort_by(|a, b| distance(b).total_cmp(&distance(a)));
+    draws
+}
+
+impl StaticDrawable {
+    /// World-space center of the first instance, used to sort the whole
+    /// drawable.
+    pub(super) fn world_center(&self) -> Vec3 {
+        let model = self
+            .instances
+            .first()
+            .map(|i| i.model())
+            .unwrap_or_default();
+        match &self.bounds {
+            Some(bounds) => model.transform_point3(bounds.center),
+            None => model.w_axis.truncate(),
+        }
+    }
+}
+
+impl Renderer {
+    /// Record the stages of canvas node `node` in `order`. Stages left out
+    /// are not recorded for that node.
+    pub fn set_stage_order(&mut self, node: &str, order: &[RenderStage]) {
+        self.stage_orders.insert(node.to_string(), order.to_vec());
+    }
+
+    /// Stages canvas node `node` records, in order.
+    pub fn stage_order(&self, node: &str) -> &[RenderStage] {
+        self.stage_orders
+            .get(node)
+            .map_or(DEFAULT_STAGE_ORDER, |o| o.as_slice())
+    }
+
+    /// Draw static meshes using `material_id` in `stage`. Only
+    /// [`RenderStage::Opaque`], the default, and
+    /// [`RenderStage::Transparent`] apply to static meshes. Transparent
+    /// meshes without a material pipeline use the pipeline registered with
+    /// [`Renderer::register_pso`] for [`RenderStage::Transparent`].
+    pub fn set_material_stage(&mut self, material_id: &str, stage: RenderStage) {
+        self.material_stages.insert(material_id.to_string(), stage);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opaque_draws_group_by_pipeline_then_material() {
+        let draws = vec![(1, "a"), (2, "b"), (1, "c"), (2, "a"), (1, "a"), (1, "c")];
+        let sorted = by_pipeline_and_material(draws, |&d| d);
+        assert_eq!(
+            sorted,
+            vec![(1, "a"), (1, "a"), (1, "c"), (1, "c"), (2, "b"), (2, "a")]
+        );
+    }
+
+    #[test]
+    fn transparent_draws_go_back_to_front() {
+        let draws = vec![("near", 1.0), ("far", 10.0), ("tie", 1.0), ("mid", 5.0)];
+        let sorted = back_to_front(draws, |&(_, d)| d);
+        let names: Vec<&str> = sorted.iter().map(|(n, _)| *n).collect();
+        assert_eq!(names, vec!["far", "mid", "near", "tie"]);
+    }
+}
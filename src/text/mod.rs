@@ -181,6 +181,7 @@ impl TextRenderer2D {
             vertex_buffer: None,
             index_buffer: None,
             index_count: 0,
+            bounds: None,
         }
     }
 
@@ -224,6 +225,7 @@ impl TextRenderer2D {
             vertex_buffer: None,
             index_buffer: None,
             index_count: 0,
+            bounds: None,
         }
     }
 
@@ -191,6 +191,7 @@ impl StaticText {
             vertex_buffer: None,
             index_buffer: None,
             index_count: 0,
+            bounds: None,
         };
         mesh.upload(ctx)?;
         Ok(Self {
@@ -0,0 +1,36 @@
+use dashi::utils::Handle;
+use dashi::*;
+
+/// A GPU object that its owner no longer uses.
+///
+/// Frames still in flight may reference it, so owners hand these back to the
+/// caller instead of destroying them. The renderer queues them and destroys
+/// them once every frame that could use them has completed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GpuObject {
+    Buffer(Handle<Buffer>),
+    Image(Handle<Image>),
+    ImageView(Handle<ImageView>),
+    Sampler(Handle<Sampler>),
+    BindGroup(Handle<BindGroup>),
+    BindGroupLayout(Handle<BindGroupLayout>),
+    GraphicsPipeline(Handle<GraphicsPipeline>),
+    GraphicsPipelineLayout(Handle<GraphicsPipelineLayout>),
+    RenderPass(Handle<RenderPass>),
+}
+
+impl GpuObject {
+    pub fn destroy(self, ctx: &mut Context) {
+        match self {
+            GpuObject::Buffer(h) => ctx.destroy_buffer(h),
+            GpuObject::Image(h) => ctx.destroy_image(h),
+            GpuObject::ImageView(h) => ctx.destroy_image_view(h),
+            GpuObject::Sampler(h) => ctx.destroy_sampler(h),
+            GpuObject::BindGroup(h) => ctx.destroy_bind_group(h),
+            GpuObject::BindGroupLayout(h) => ctx.destroy_bind_group_layout(h),
+            GpuObject::GraphicsPipeline(h) => ctx.destroy_graphics_pipeline(h),
+            GpuObject::GraphicsPipelineLayout(h) => ctx.destroy_graphics_pipeline_layout(h),
+            GpuObject::RenderPass(h) => ctx.destroy_render_pass(h),
+        }
+    }
+}
@@ -34,12 +34,18 @@ impl From<BufferUsage> for DashiBufferUsage {
 pub mod allocator;
 pub mod resource_list;
 pub mod frame_diff;
+pub mod gpu_object;
 pub use allocator::*;
 pub use resource_list::*;
 pub use frame_diff::diff_rgba8;
+pub use gpu_object::GpuObject;
 
 pub const CAMERA_ELEMENT_SIZE: usize = 20 * std::mem::size_of::<f32>();
 pub const MAX_CAMERAS: usize = 4;
+/// Size in bytes of one entry of the `KOJI_instances` storage buffer.
+pub const INSTANCE_ELEMENT_SIZE: usize = 24 * std::mem::size_of::<f32>();
+/// Number of instances the shared instance buffer can hold.
+pub const MAX_INSTANCES: usize = 16384;
 
 pub struct TextureInfo {
     pub image: Handle<Image>,
@@ -127,6 +133,7 @@ impl From<DHObject> for ResourceBuffer {
     }
 }
 
+#[derive(Clone)]
 pub enum ResourceBinding {
     Texture(Texture),
     Uniform(Handle<Buffer>),
@@ -240,6 +247,26 @@ impl ResourceManager {
             .insert("KOJI_cameras".into(), ResourceBinding::Uniform(buf.handle));
     }
 
+    /// Create the shared per-instance storage buffer.
+    ///
+    /// The buffer is too large for the sub-allocator, so it is created as a
+    /// standalone buffer and registered under `instances` and `KOJI_instances`.
+    pub fn register_instance_buffers(&mut self, ctx: &mut Context) {
+        let buffer = ctx
+            .make_buffer(&BufferInfo {
+                debug_name: "KOJI_instances",
+                byte_size: (INSTANCE_ELEMENT_SIZE * MAX_INSTANCES) as u32,
+                visibility: MemoryVisibility::CpuAndGpu,
+                usage: DashiBufferUsage::STORAGE,
+                initial_data: None,
+            })
+            .unwrap();
+        self.bindings
+            .insert("instances".into(), ResourceBinding::Storage(buffer));
+        self.bindings
+            .insert("KOJI_instances".into(), ResourceBinding::Storage(buffer));
+    }
+
       pub fn register_ubo(&mut self, key: impl Into<String>, handle: Handle<Buffer>) {
         self.bindings.insert(key.into(), ResourceBinding::Uniform(handle));
     }
@@ -474,6 +501,27 @@ mod tests {
         ctx.destroy();
     }
 
+    #[test]
+    #[serial]
+    fn register_instance_buffers_binding() {
+        let mut ctx = setup_ctx();
+        let mut manager = ResourceManager::new(&mut ctx, 1024).unwrap();
+
+        manager.register_instance_buffers(&mut ctx);
+
+        let handle = match manager.get("KOJI_instances") {
+            Some(ResourceBinding::Storage(h)) => *h,
+            _ => panic!("Expected storage binding"),
+        };
+        match manager.get("instances") {
+            Some(ResourceBinding::Storage(h)) => assert_eq!(*h, handle),
+            _ => panic!("Expected storage binding"),
+        }
+        assert!(manager.buffers.entries.is_empty());
+        ctx.destroy_buffer(handle);
+        ctx.destroy();
+    }
+
     #[test]
     #[serial]
     fn register_ubo_binding() {
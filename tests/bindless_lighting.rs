@@ -37,7 +37,7 @@ pub fn run() {
         .build(&mut ctx)
         .unwrap();
     let mut graph = RenderGraph::new();
-    graph.add_canvas(&canvas);
+    graph.add_canvas(&canvas).unwrap();
 
     let mut renderer = Renderer::with_graph(320, 240, &mut ctx, graph).unwrap();
 
@@ -47,10 +47,10 @@ pub fn run() {
         .render_pass(renderer.graph().output("color"))
         .build();
     let bgr = pso.create_bind_groups(&renderer.resources()).unwrap();
-    renderer.register_pipeline_for_pass("main", pso, bgr);
+    renderer.register_pipeline_for_pass("main", pso, bgr).unwrap();
 
     let mut lights = BindlessLights::new();
-    let light = LightDesc{ position:[0.0,0.0,0.0], intensity:1.0, color:[1.0,1.0,1.0], range:1.0, direction:[0.0,0.0,-1.0], _pad:0 };
+    let light = LightDesc{ position:[0.0,0.0,0.0], intensity:1.0, color:[1.0,1.0,1.0], range:1.0, direction:[0.0,0.0,-1.0], ..Default::default() };
     lights.add_light(&mut ctx, renderer.resources(), light);
     lights.register(renderer.resources());
 
@@ -65,9 +65,10 @@ pub fn run() {
         vertex_buffer: None,
         index_buffer: None,
         index_count: 0,
+        bounds: None,
     };
     let out = renderer.graph().output("color");
-    renderer.register_static_mesh(mesh,None,"lighting".into(), out);
+    renderer.register_static_mesh(mesh,None,"lighting".into(), out).unwrap();
 
     renderer.present_frame().unwrap();
     ctx.destroy();
@@ -37,7 +37,7 @@ pub fn run() {
         .build(&mut ctx)
         .unwrap();
     let mut graph = RenderGraph::new();
-    graph.add_canvas(&canvas);
+    graph.add_canvas(&canvas).unwrap();
 
     let mut renderer = Renderer::with_graph(320, 240, &mut ctx, graph).unwrap();
 
@@ -49,7 +49,7 @@ pub fn run() {
         .render_pass(renderer.graph().output("color"))
         .build();
     let bgr = pso.create_bind_groups(&renderer.resources()).unwrap();
-    renderer.register_pipeline_for_pass("main", pso, bgr);
+    renderer.register_pipeline_for_pass("main", pso, bgr).unwrap();
 
     let mut bindless = BindlessData::new();
     let tex_data:[u8;4] = [255,0,0,255];
@@ -74,8 +74,9 @@ pub fn run() {
         vertex_buffer: None,
         index_buffer: None,
         index_count: 0,
+        bounds: None,
     };
-    renderer.register_static_mesh(mesh,None,"bindless".into(), "canvas");
+    renderer.register_static_mesh(mesh,None,"bindless".into(), "canvas").unwrap();
     renderer.present_frame().unwrap();
     ctx.destroy();
 }
@@ -0,0 +1,77 @@
+#![cfg(feature = "gpu_tests")]
+
+use dashi::gpu::{Context, ContextInfo};
+use dashi::Format;
+use koji::canvas::{Canvas, CanvasBuilder, CanvasDesc, CanvasError};
+use koji::render_graph::{CanvasNode, GraphNode, RenderGraph, ResourceUsage};
+use serial_test::serial;
+
+fn geometry(ctx: &mut Context) -> Canvas {
+    CanvasBuilder::new()
+        .relative_extent([16, 16], 1.0)
+        .color_attachment("albedo", Format::RGBA8)
+        .depth_attachment("depth", Format::D24S8)
+        .build(ctx)
+        .unwrap()
+}
+
+#[test]
+#[serial]
+fn read_only_depth_borrows_the_source_image() {
+    let mut ctx = Context::headless(&ContextInfo::default()).unwrap();
+    let gbuffer = geometry(&mut ctx);
+    let decals = CanvasBuilder::new()
+        .relative_extent([16, 16], 1.0)
+        .color_attachment("decals", Format::RGBA8)
+        .read_only_depth_attachment(&gbuffer, "depth")
+        .build(&mut ctx)
+        .unwrap();
+    assert!(decals.depth_read_only());
+    assert!(!gbuffer.depth_read_only());
+    assert!(decals.view("depth") == gbuffer.view("depth"));
+    assert!(CanvasDesc::from(&decals).attachments[1].read_only);
+
+    let node = CanvasNode::new("decals", decals);
+    assert_eq!(node.inputs().len(), 1);
+    assert!(node.outputs().iter().all(|o| o.name != "depth"));
+    assert!(node
+        .resource_usage()
+        .contains(&("depth".to_string(), ResourceUsage::DepthRead)));
+
+    let mut graph = RenderGraph::new();
+    graph.add_node(CanvasNode::new("gbuffer", gbuffer)).unwrap();
+    graph.add_node(node).unwrap();
+    graph.connect("gbuffer", "decals").unwrap();
+    assert!(graph.validate().is_ok());
+
+    let retired = graph.resize(&mut ctx, [32, 32]).unwrap();
+    assert!(!retired.is_empty());
+    let canvases = graph.canvases();
+    assert!(canvases[1].view("depth") == canvases[0].view("depth"));
+
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn read_only_depth_requires_a_depth_source() {
+    let mut ctx = Context::headless(&ContextInfo::default()).unwrap();
+    let gbuffer = geometry(&mut ctx);
+    let missing = CanvasBuilder::new()
+        .extent([16, 16])
+        .color_attachment("decals", Format::RGBA8)
+        .read_only_depth_attachment(&gbuffer, "albedo")
+        .build(&mut ctx);
+    assert!(matches!(
+        missing,
+        Err(CanvasError::MissingDepthSource { ref attachment }) if attachment == "albedo"
+    ));
+
+    let mut desc = CanvasDesc::from(&gbuffer);
+    desc.attachments[1].read_only = true;
+    assert!(Canvas::from_desc(&mut ctx, &desc).is_err());
+    let rebuilt = Canvas::from_desc_with_sources(&mut ctx, &desc, &[&gbuffer]).unwrap();
+    assert!(rebuilt.depth_read_only());
+
+    ctx.destroy();
+}
@@ -1,5 +1,9 @@
 use dashi::Format;
-use koji::canvas::{from_json, from_yaml, to_json, to_yaml, AttachmentDesc, CanvasDesc};
+use koji::canvas::{
+    from_json, from_yaml, scaled_extent, to_json, to_yaml, AttachmentDesc, CanvasDesc,
+};
+use koji::format::FormatClass;
+use koji::render_pass::supports_samples;
 
 #[test]
 fn canvas_yaml_roundtrip() {
@@ -12,6 +16,8 @@ fn canvas_yaml_roundtrip() {
                 clear_color: None,
                 clear_depth: None,
                 clear_stencil: None,
+                samples: 1,
+                read_only: false,
             },
             AttachmentDesc {
                 name: "depth".into(),
@@ -19,8 +25,13 @@ fn canvas_yaml_roundtrip() {
                 clear_color: None,
                 clear_depth: None,
                 clear_stencil: None,
+                samples: 1,
+                read_only: false,
             },
         ],
+        scale: None,
+        samples: 1,
+        subpasses: Vec::new(),
     };
     let yaml = to_yaml(&desc).unwrap();
     let loaded = from_yaml(&yaml).unwrap();
@@ -37,9 +48,85 @@ fn canvas_json_roundtrip() {
             clear_color: None,
             clear_depth: None,
             clear_stencil: None,
+            samples: 4,
+            read_only: false,
         }],
+        scale: Some(0.5),
+        samples: 4,
+        subpasses: Vec::new(),
     };
     let json = to_json(&desc).unwrap();
     let loaded = from_json(&json).unwrap();
     assert_eq!(desc, loaded);
 }
+
+#[test]
+fn relative_canvas_extents() {
+    let desc = from_yaml("extent: [4, 4]\nattachments: []\n").unwrap();
+    assert_eq!(desc.scale, None);
+    assert!(!to_yaml(&desc).unwrap().contains("scale"));
+
+    assert_eq!(scaled_extent([1280, 720], 1.0), [1280, 720]);
+    assert_eq!(scaled_extent([1280, 720], 0.5), [640, 360]);
+    assert_eq!(scaled_extent([1, 1], 0.25), [1, 1]);
+}
+
+#[test]
+fn sample_counts_default_to_one() {
+    let desc =
+        from_yaml("extent: [4, 4]\nattachments:\n  - name: color\n    format: RGBA8\n").unwrap();
+    assert_eq!(desc.samples, 1);
+    assert_eq!(desc.attachments[0].samples, 1);
+    assert!(!to_yaml(&desc).unwrap().contains("samples"));
+
+    assert!(supports_samples(0));
+    assert!(supports_samples(1));
+    assert!(!supports_samples(4));
+}
+
+#[test]
+fn read_only_depth_roundtrip() {
+    let yaml =
+        "extent: [4, 4]\nattachments:\n  - name: depth\n    format: D24S8\n    read_only: true\n";
+    let desc = from_yaml(yaml).unwrap();
+    assert!(desc.attachments[0].read_only);
+    assert_eq!(from_yaml(&to_yaml(&desc).unwrap()).unwrap(), desc);
+
+    let color =
+        from_yaml("extent: [4, 4]\nattachments:\n  - name: color\n    format: RGBA8\n").unwrap();
+    assert!(!color.attachments[0].read_only);
+    assert!(!to_yaml(&color).unwrap().contains("read_only"));
+    assert!(!FormatClass::of(color.attachments[0].format).has_depth());
+    assert!(FormatClass::of(desc.attachments[0].format).has_depth());
+}
+
+#[test]
+fn subpasses_roundtrip() {
+    let yaml = r#"
+extent: [4, 4]
+attachments:
+  - name: albedo
+    format: RGBA8
+  - name: lit
+    format: RGBA8
+subpasses:
+  - name: gbuffer
+    color_attachments: [albedo]
+  - name: lighting
+    color_attachments: [lit]
+    input_attachments: [albedo]
+    depends_on: [gbuffer]
+"#;
+    let desc = from_yaml(yaml).unwrap();
+    assert_eq!(desc.subpasses.len(), 2);
+    assert!(desc.subpasses[0].input_attachments.is_empty());
+    assert_eq!(
+        desc.subpasses[1].input_attachments,
+        vec!["albedo".to_string()]
+    );
+    assert_eq!(from_yaml(&to_yaml(&desc).unwrap()).unwrap(), desc);
+
+    let single = from_yaml("extent: [4, 4]\nattachments: []\n").unwrap();
+    assert!(single.subpasses.is_empty());
+    assert!(!to_yaml(&single).unwrap().contains("subpasses"));
+}
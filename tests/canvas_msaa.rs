@@ -0,0 +1,61 @@
+#![cfg(feature = "gpu_tests")]
+
+use dashi::gpu::{Context, ContextInfo};
+use dashi::Format;
+use inline_spirv::include_spirv;
+use koji::canvas::{Canvas, CanvasBuilder, CanvasDesc, CanvasError};
+use koji::material::{PipelineBuilder, PipelineError};
+use koji::utils::ResourceManager;
+use serial_test::serial;
+
+#[test]
+#[serial]
+fn multisampled_canvases_are_rejected() {
+    let mut ctx = Context::headless(&ContextInfo::default()).unwrap();
+    let msaa = CanvasBuilder::new()
+        .extent([8, 8])
+        .samples(4)
+        .color_attachment("color", Format::RGBA8)
+        .depth_attachment("depth", Format::D24S8)
+        .build(&mut ctx);
+    assert!(matches!(msaa, Err(CanvasError::UnsupportedSamples(4))));
+
+    let canvas = CanvasBuilder::new()
+        .extent([8, 8])
+        .color_attachment("color", Format::RGBA8)
+        .build(&mut ctx)
+        .unwrap();
+    assert_eq!(canvas.samples(), 1);
+
+    let mut res = ResourceManager::new(&mut ctx, 1024).unwrap();
+    let vert = include_spirv!("assets/shaders/test_triangle.vert", vert);
+    let frag = include_spirv!("assets/shaders/test_triangle.frag", frag);
+    let pso = PipelineBuilder::new(&mut ctx, "msaa_pso")
+        .vertex_shader(vert)
+        .fragment_shader(frag)
+        .render_pass(canvas.output("color"))
+        .samples(4)
+        .build_with_resources(&mut res);
+    assert!(matches!(pso, Err(PipelineError::UnsupportedSamples(4))));
+
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn mixed_sample_counts_are_rejected() {
+    let mut ctx = Context::headless(&ContextInfo::default()).unwrap();
+    let canvas = CanvasBuilder::new()
+        .extent([8, 8])
+        .color_attachment("color", Format::RGBA8)
+        .build(&mut ctx)
+        .unwrap();
+    let mut desc = CanvasDesc::from(&canvas);
+    desc.attachments[0].samples = 2;
+    assert!(matches!(
+        Canvas::from_desc(&mut ctx, &desc),
+        Err(CanvasError::MixedSamples { samples: 2, canvas: 1, .. })
+    ));
+
+    ctx.destroy();
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "gpu_tests")]
+
+use dashi::gpu::{Context, ContextInfo};
+use dashi::Format;
+use koji::canvas::CanvasBuilder;
+use koji::renderer::Renderer;
+use koji::utils::ResourceBinding;
+use serial_test::serial;
+
+#[test]
+#[serial]
+fn relative_canvas_follows_output_size() {
+    let mut ctx = Context::headless(&ContextInfo::default()).unwrap();
+    let canvas = CanvasBuilder::new()
+        .relative_extent([16, 16], 0.5)
+        .color_attachment("ssao", Format::RGBA8)
+        .build(&mut ctx)
+        .unwrap();
+    assert_eq!(canvas.extent(), [8, 8]);
+    assert_eq!(canvas.scale(), Some(0.5));
+
+    let image = canvas.image("ssao").unwrap();
+    let view = canvas.view("ssao").unwrap();
+    let mut renderer = Renderer::with_canvas_headless(16, 16, &mut ctx, canvas).unwrap();
+    renderer
+        .resources()
+        .register_texture("ssao_tex", image, view, [8, 8]);
+    renderer.present_frame().unwrap();
+    assert_eq!(renderer.read_color_target("ssao").len(), 8 * 8 * 4);
+
+    renderer.resize(32, 24).unwrap();
+    assert_eq!(renderer.size(), [32, 24]);
+    let resized = renderer.graph().canvases().remove(0);
+    assert_eq!(resized.extent(), [16, 12]);
+    let new_view = resized.view("ssao").unwrap();
+    assert!(new_view != view);
+    match renderer.resources().get("ssao_tex") {
+        Some(ResourceBinding::Texture(tex)) => {
+            assert!(tex.view == new_view);
+            assert_eq!(tex.dim, [16, 12]);
+        }
+        _ => panic!("texture binding missing"),
+    }
+
+    renderer.present_frame().unwrap();
+    assert_eq!(renderer.read_color_target("ssao").len(), 16 * 12 * 4);
+
+    drop(renderer);
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn fixed_canvas_ignores_resize() {
+    let mut ctx = Context::headless(&ContextInfo::default()).unwrap();
+    let canvas = CanvasBuilder::new()
+        .extent([8, 8])
+        .color_attachment("color", Format::RGBA8)
+        .build(&mut ctx)
+        .unwrap();
+    let view = canvas.view("color").unwrap();
+    let mut renderer = Renderer::with_canvas_headless(8, 8, &mut ctx, canvas).unwrap();
+
+    renderer.resize(16, 16).unwrap();
+    let canvas = renderer.graph().canvases().remove(0);
+    assert_eq!(canvas.extent(), [8, 8]);
+    assert!(canvas.view("color").unwrap() == view);
+
+    drop(renderer);
+    ctx.destroy();
+}
@@ -0,0 +1,96 @@
+#![cfg(feature = "gpu_tests")]
+
+use dashi::gpu::{Context, ContextInfo};
+use dashi::*;
+use inline_spirv::include_spirv;
+use koji::canvas::{Canvas, CanvasBuilder, CanvasDesc, CanvasError};
+use koji::material::PipelineBuilder;
+use serial_test::serial;
+
+fn deferred(ctx: &mut Context) -> Canvas {
+    CanvasBuilder::new()
+        .extent([16, 16])
+        .color_attachment("albedo", Format::RGBA8)
+        .color_attachment("normal", Format::RGBA8)
+        .color_attachment("lit", Format::RGBA8)
+        .depth_attachment("depth", Format::D24S8)
+        .subpass("gbuffer", ["albedo", "normal"], &[] as &[&str])
+        .subpass("lighting", ["lit"], ["gbuffer"])
+        .build(ctx)
+        .unwrap()
+}
+
+#[test]
+#[serial]
+fn subpasses_resolve_by_name() {
+    let mut ctx = Context::headless(&ContextInfo::default()).unwrap();
+    let canvas = deferred(&mut ctx);
+    assert_eq!(canvas.subpasses().len(), 2);
+    assert_eq!(canvas.subpass_index("gbuffer"), Some(0));
+    assert_eq!(canvas.subpass_index("lighting"), Some(1));
+    assert_eq!(canvas.subpass_index("normal"), Some(0));
+    assert_eq!(canvas.subpass_index("lit"), Some(1));
+    assert_eq!(canvas.subpass_index("depth"), Some(0));
+    assert_eq!(canvas.subpass_index("missing"), None);
+    assert_eq!(canvas.target().colors.len(), 3);
+
+    let desc = CanvasDesc::from(&canvas);
+    assert_eq!(desc.subpasses.len(), 2);
+    let rebuilt = Canvas::from_desc(&mut ctx, &desc).unwrap();
+    assert_eq!(rebuilt.subpass_index("lighting"), Some(1));
+
+    let vert = include_spirv!("assets/shaders/test_triangle.vert", vert);
+    let frag = include_spirv!("assets/shaders/test_triangle.frag", frag);
+    PipelineBuilder::new(&mut ctx, "lighting_pso")
+        .vertex_shader(vert)
+        .fragment_shader(frag)
+        .render_pass(canvas.output("lighting"))
+        .build();
+
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn subpasses_must_name_declared_attachments() {
+    let mut ctx = Context::headless(&ContextInfo::default()).unwrap();
+    let result = CanvasBuilder::new()
+        .extent([16, 16])
+        .color_attachment("lit", Format::RGBA8)
+        .subpass_with_inputs("lighting", ["lit"], ["albedo"], &[] as &[&str])
+        .build(&mut ctx);
+    assert!(matches!(
+        result,
+        Err(CanvasError::UnknownAttachment { ref subpass, ref attachment })
+            if subpass == "lighting" && attachment == "albedo"
+    ));
+
+    let single = CanvasBuilder::new()
+        .extent([16, 16])
+        .color_attachment("color", Format::RGBA8)
+        .build(&mut ctx)
+        .unwrap();
+    assert_eq!(single.subpasses()[0].name, "main");
+    assert!(CanvasDesc::from(&single).subpasses.is_empty());
+
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn input_attachments_are_rejected() {
+    let mut ctx = Context::headless(&ContextInfo::default()).unwrap();
+    let result = CanvasBuilder::new()
+        .extent([4, 4])
+        .color_attachment("albedo", Format::RGBA8)
+        .color_attachment("lit", Format::RGBA8)
+        .subpass("gbuffer", ["albedo"], &[] as &[&str])
+        .subpass_with_inputs("lighting", ["lit"], ["albedo"], ["gbuffer"])
+        .build(&mut ctx);
+    assert!(matches!(
+        result,
+        Err(CanvasError::InputAttachments { ref subpass }) if subpass == "lighting"
+    ));
+
+    ctx.destroy();
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "gpu_tests")]
+
+use dashi::gpu::{Context, ContextInfo};
+use dashi::Format;
+use glam::{Mat4, Vec3};
+use koji::canvas::CanvasBuilder;
+use koji::material::LightDesc;
+use koji::renderer::{bin_lights, ClusterGrid, Renderer};
+use serial_test::serial;
+
+fn renderer(ctx: &mut Context) -> Renderer {
+    let canvas = CanvasBuilder::new()
+        .extent([16, 16])
+        .color_attachment("color", Format::RGBA8)
+        .build(ctx)
+        .unwrap();
+    Renderer::with_canvas_headless(16, 16, ctx, canvas).unwrap()
+}
+
+#[test]
+#[serial]
+fn gpu_binning_matches_the_cpu_reference() {
+    let mut ctx = Context::headless(&ContextInfo::default()).unwrap();
+    let mut renderer = renderer(&mut ctx);
+    let grid = ClusterGrid {
+        tiles: [4, 4],
+        slices: 6,
+    };
+    renderer.enable_clustered_lights(grid).unwrap();
+    assert_eq!(renderer.cluster_grid(), Some(grid));
+    let eye = Vec3::new(0.0, 2.0, 8.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let view_proj = Mat4::perspective_rh(60f32.to_radians(), 1.0, 0.5, 40.0) * view;
+    renderer.set_camera(0, view_proj, eye);
+
+    let lights = vec![
+        LightDesc::point([0.0, 0.0, 0.0], 1.5),
+        LightDesc::point([3.0, 1.0, -6.0], 2.0),
+        LightDesc::spot([-2.0, 3.0, 0.0], [0.0, -1.0, 0.0], 0.3, 0.5).with_range(4.0),
+        LightDesc::directional([0.0, -1.0, 0.0]),
+    ];
+    for light in &lights {
+        renderer.add_light(*light);
+    }
+    renderer.present_frame().unwrap();
+    assert_eq!(renderer.light_binding_error(), None);
+    let gpu = renderer.read_light_clusters().unwrap();
+    let cpu = bin_lights(&grid, view_proj, eye, &lights);
+    for cluster in 0..grid.count() {
+        assert_eq!(
+            gpu.lights(cluster),
+            cpu.lights(cluster),
+            "cluster {}",
+            cluster
+        );
+    }
+
+    // Every cluster sees the directional light.
+    assert!((0..grid.count()).all(|c| gpu.lights(c).contains(&3)));
+
+    drop(renderer);
+    ctx.destroy();
+}
@@ -33,7 +33,7 @@ pub fn run() {
         .build(&mut ctx)
         .unwrap();
     let mut graph = RenderGraph::new();
-    graph.add_canvas(&canvas);
+    graph.add_canvas(&canvas).unwrap();
 
     let mut renderer = Renderer::with_graph(64, 64, &mut ctx, graph).unwrap();
 
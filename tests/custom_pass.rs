@@ -38,8 +38,8 @@ subpasses:
         .unwrap();
     let mut graph = RenderGraph::new();
     let config: YamlRenderPass = serde_yaml::from_str(yaml).unwrap();
-    graph.add_node::<RenderPassBuilderNode>(RenderPassBuilder::from_yaml(config).into());
-    graph.add_canvas(&canvas);
+    graph.add_node::<RenderPassBuilderNode>(RenderPassBuilder::from_yaml(config).unwrap().into()).unwrap();
+    graph.add_canvas(&canvas).unwrap();
 
     let mut renderer = Renderer::with_graph(640, 480, &mut ctx, graph).unwrap();
 
@@ -52,7 +52,7 @@ subpasses:
         .render_pass(renderer.graph().output("first"))
         .build();
     let bgr_first = pso_first.create_bind_groups(&renderer.resources()).unwrap();
-    renderer.register_pipeline_for_pass("first", pso_first, bgr_first);
+    renderer.register_pipeline_for_pass("first", pso_first, bgr_first).unwrap();
 
     let mut pso_second = PipelineBuilder::new(&mut ctx, "second_pso")
         .vertex_shader(vert)
@@ -60,7 +60,7 @@ subpasses:
         .render_pass(renderer.graph().output("second"))
         .build();
     let bgr_second = pso_second.create_bind_groups(&renderer.resources()).unwrap();
-    renderer.register_pipeline_for_pass("second", pso_second, bgr_second);
+    renderer.register_pipeline_for_pass("second", pso_second, bgr_second).unwrap();
 
     let mesh = StaticMesh {
         material_id: "color".into(),
@@ -73,8 +73,9 @@ subpasses:
         vertex_buffer: None,
         index_buffer: None,
         index_count: 0,
+        bounds: None,
     };
-    renderer.register_static_mesh(mesh, None, "color".into(), "canvas");
+    renderer.register_static_mesh(mesh, None, "color".into(), "canvas").unwrap();
 
     renderer.present_frame().unwrap();
     ctx.destroy();
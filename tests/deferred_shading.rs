@@ -0,0 +1,154 @@
+#![cfg(feature = "gpu_tests")]
+
+use dashi::gpu::{Context, ContextInfo};
+use dashi::Format;
+use koji::canvas::CanvasBuilder;
+use koji::material::LightDesc;
+use koji::render_graph::{CanvasNode, GraphError, ImageLayout, RenderGraph, ResourceUsage};
+use koji::renderer::{
+    Renderer, StaticMesh, Vertex, GBUFFER_ALBEDO, GBUFFER_NODE, LIGHTING_NODE, LIT_OUTPUT,
+};
+use serial_test::serial;
+
+fn triangle() -> StaticMesh {
+    let vertex = |position: [f32; 3]| Vertex {
+        position,
+        normal: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
+        uv: [0.0, 0.0],
+        color: [1.0, 1.0, 1.0, 1.0],
+    };
+    StaticMesh {
+        material_id: "deferred".into(),
+        vertices: vec![
+            vertex([0.0, -1.0, 0.0]),
+            vertex([1.0, 1.0, 0.0]),
+            vertex([-1.0, 1.0, 0.0]),
+        ],
+        indices: None,
+        vertex_buffer: None,
+        index_buffer: None,
+        index_count: 0,
+        bounds: None,
+    }
+}
+
+fn renderer(ctx: &mut Context) -> Renderer {
+    let canvas = CanvasBuilder::new()
+        .extent([16, 16])
+        .color_attachment("color", Format::RGBA8)
+        .build(ctx)
+        .unwrap();
+    Renderer::with_canvas_headless(16, 16, ctx, canvas).unwrap()
+}
+
+#[test]
+#[serial]
+fn deferred_nodes_are_added_to_the_graph() {
+    let mut ctx = Context::headless(&ContextInfo::default()).unwrap();
+    let mut renderer = renderer(&mut ctx);
+    assert!(!renderer.deferred_enabled());
+    renderer.enable_deferred().unwrap();
+    renderer.enable_deferred().unwrap();
+    assert!(renderer.deferred_enabled());
+
+    let graph = renderer.graph();
+    assert!(graph.validate().is_ok());
+    assert_eq!(
+        graph.node_name_for_output(GBUFFER_ALBEDO).as_deref(),
+        Some(GBUFFER_NODE)
+    );
+    assert_eq!(
+        graph.node_name_for_output(LIT_OUTPUT).as_deref(),
+        Some(LIGHTING_NODE)
+    );
+    let plan = graph.barrier_plan();
+    let sampled = plan
+        .before(LIGHTING_NODE)
+        .find(|b| b.resource == GBUFFER_ALBEDO)
+        .expect("albedo barrier");
+    assert_eq!(sampled.dst_usage, ResourceUsage::ShaderRead);
+    assert_eq!(sampled.new_layout, ImageLayout::ShaderReadOnly);
+
+    drop(renderer);
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn failed_enable_leaves_the_renderer_unchanged() {
+    let mut ctx = Context::headless(&ContextInfo::default()).unwrap();
+    let canvas = CanvasBuilder::new()
+        .extent([16, 16])
+        .color_attachment("color", Format::RGBA8)
+        .build(&mut ctx)
+        .unwrap();
+    let mut graph = RenderGraph::new();
+    graph.add_node(CanvasNode::new(LIGHTING_NODE, canvas)).unwrap();
+    let mut renderer = Renderer::with_graph_headless(16, 16, &mut ctx, graph).unwrap();
+
+    assert!(matches!(
+        renderer.enable_deferred(),
+        Err(GraphError::Validation(_))
+    ));
+    assert!(!renderer.deferred_enabled());
+    assert_eq!(renderer.graph().node_names(), vec![LIGHTING_NODE.to_string()]);
+    assert!(renderer.resources().get(GBUFFER_ALBEDO).is_none());
+
+    drop(renderer);
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn lighting_pass_shades_the_gbuffer() {
+    let mut ctx = Context::headless(&ContextInfo::default()).unwrap();
+    let mut renderer = renderer(&mut ctx);
+    renderer.set_clear_color([0.0, 0.0, 0.0, 0.0]);
+    renderer.enable_deferred().unwrap();
+    renderer.register_static_mesh(triangle(), None, "deferred".into(), GBUFFER_NODE).unwrap();
+
+    // No lights yet, so the lit image stays black.
+    renderer.present_frame().unwrap();
+    let dark = renderer.read_color_target(LIT_OUTPUT);
+    assert_eq!(dark.len(), 16 * 16 * 4);
+    let center = (8 * 16 + 8) * 4;
+    assert_eq!(dark[center], 0);
+
+    // Adding a light rebuilds the lighting bind groups.
+    renderer.add_light(LightDesc {
+        position: [0.0, 0.0, 1.0],
+        intensity: 1.0,
+        color: [1.0, 1.0, 1.0],
+        ..Default::default()
+    });
+    renderer.present_frame().unwrap();
+    let lit = renderer.read_color_target(LIT_OUTPUT);
+    assert!(lit[center] > 0);
+
+    drop(renderer);
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn point_lights_shade_from_the_surface_position() {
+    let mut ctx = Context::headless(&ContextInfo::default()).unwrap();
+    let mut renderer = renderer(&mut ctx);
+    renderer.set_clear_color([0.0, 0.0, 0.0, 0.0]);
+    renderer.enable_deferred().unwrap();
+    renderer.register_static_mesh(triangle(), None, "deferred".into(), GBUFFER_NODE).unwrap();
+
+    // A light just above the center hits the center head-on and the rest of
+    // the triangle at a grazing angle.
+    renderer.add_light(LightDesc::point([0.0, 0.0, 0.1], 1.0));
+    renderer.present_frame().unwrap();
+    let lit = renderer.read_color_target(LIT_OUTPUT);
+    let center = (8 * 16 + 8) * 4;
+    let edge = (13 * 16 + 8) * 4;
+    assert!(lit[edge] > 0);
+    assert!(lit[center] > lit[edge]);
+
+    drop(renderer);
+    ctx.destroy();
+}
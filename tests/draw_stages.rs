@@ -0,0 +1,139 @@
+#![cfg(feature = "gpu_tests")]
+
+use dashi::gpu::{Context, ContextInfo};
+use dashi::Format;
+use glam::{Mat4, Vec3, Vec4};
+use inline_spirv::inline_spirv;
+use koji::canvas::CanvasBuilder;
+use koji::material::PipelineBuilder;
+use koji::renderer::*;
+use serial_test::serial;
+
+fn vert() -> Vec<u32> {
+    inline_spirv!(
+        r"#version 450
+        struct Instance { mat4 transform; vec4 color; uint material_index; uint p0; uint p1; uint p2; };
+        layout(set=0, binding=0) readonly buffer InstanceBuffer { Instance instances[]; } KOJI_instances;
+        layout(location=0) in vec3 pos;
+        layout(location=1) in vec3 normal;
+        layout(location=2) in vec4 tangent;
+        layout(location=3) in vec2 uv;
+        layout(location=4) in vec4 color;
+        layout(location=0) out vec4 v_color;
+        void main(){
+            Instance inst = KOJI_instances.instances[gl_InstanceIndex];
+            v_color = inst.color;
+            gl_Position = inst.transform * vec4(pos, 1.0);
+        }",
+        vert
+    )
+    .to_vec()
+}
+
+fn frag() -> Vec<u32> {
+    inline_spirv!(
+        r"#version 450
+        layout(location=0) in vec4 v_color;
+        layout(location=0) out vec4 o;
+        void main(){ o = v_color; }",
+        frag
+    )
+    .to_vec()
+}
+
+fn quad() -> StaticMesh {
+    let vertex = |x: f32, y: f32| Vertex {
+        position: [x, y, 0.0],
+        normal: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
+        uv: [0.0, 0.0],
+        color: [1.0; 4],
+    };
+    StaticMesh {
+        material_id: String::new(),
+        vertices: vec![
+            vertex(-0.5, -0.5),
+            vertex(0.5, -0.5),
+            vertex(0.5, 0.5),
+            vertex(-0.5, -0.5),
+            vertex(0.5, 0.5),
+            vertex(-0.5, 0.5),
+        ],
+        indices: None,
+        vertex_buffer: None,
+        index_buffer: None,
+        index_count: 0,
+        bounds: None,
+    }
+}
+
+fn at_depth(z: f32, color: Vec4) -> Vec<MeshInstance> {
+    vec![
+        MeshInstance::from_transform(Mat4::from_translation(Vec3::new(0.0, 0.0, z)))
+            .with_color(color),
+    ]
+}
+
+fn center(renderer: &mut Renderer) -> [u8; 4] {
+    let pixels = renderer.read_color_target("color");
+    let i = (32 * 64 + 32) * 4;
+    [pixels[i], pixels[i + 1], pixels[i + 2], pixels[i + 3]]
+}
+
+#[test]
+#[serial]
+fn transparent_meshes_draw_back_to_front() {
+    let mut ctx = Context::headless(&ContextInfo::default()).unwrap();
+    let canvas = CanvasBuilder::new()
+        .extent([64, 64])
+        .color_attachment("color", Format::RGBA8)
+        .build(&mut ctx)
+        .unwrap();
+    let mut renderer = Renderer::with_canvas_headless(64, 64, &mut ctx, canvas).unwrap();
+    renderer.set_clear_color([0.0, 0.0, 0.0, 1.0]);
+
+    let (vert, frag) = (vert(), frag());
+    let mut pso = PipelineBuilder::new(&mut ctx, "transparent_pso")
+        .vertex_shader(&vert)
+        .fragment_shader(&frag)
+        .vertex_layout::<Vertex>()
+        .render_pass(renderer.graph().output("color"))
+        .build_with_resources(renderer.resources())
+        .unwrap();
+    let bgr = pso.create_bind_groups(renderer.resources()).unwrap();
+    renderer.register_pso(RenderStage::Transparent, pso, bgr).unwrap();
+    renderer.set_material_stage("glass", RenderStage::Transparent);
+
+    // Registered nearest first, so only sorting puts the red quad on top.
+    let red = Vec4::new(1.0, 0.0, 0.0, 1.0);
+    let green = Vec4::new(0.0, 1.0, 0.0, 1.0);
+    renderer.register_static_mesh_instanced(
+        quad(),
+        None,
+        at_depth(0.2, red),
+        "glass".into(),
+        "canvas",
+    ).unwrap();
+    renderer.register_static_mesh_instanced(
+        quad(),
+        None,
+        at_depth(0.8, green),
+        "glass".into(),
+        "canvas",
+    ).unwrap();
+    renderer.set_camera(0, Mat4::IDENTITY, Vec3::new(0.0, 0.0, -1.0));
+    renderer.present_frame().unwrap();
+    assert_eq!(center(&mut renderer), [255, 0, 0, 255]);
+
+    // Leaving the stage out of the node's order skips it.
+    renderer.set_stage_order("canvas", &[RenderStage::Opaque, RenderStage::Text]);
+    assert_eq!(
+        renderer.stage_order("canvas"),
+        &[RenderStage::Opaque, RenderStage::Text]
+    );
+    renderer.present_frame().unwrap();
+    assert_eq!(center(&mut renderer), [0, 0, 0, 255]);
+
+    drop(renderer);
+    ctx.destroy();
+}
@@ -0,0 +1,87 @@
+#![cfg(feature = "gpu_tests")]
+
+use dashi::gpu::{Context, ContextInfo};
+use dashi::Format;
+use glam::{Mat4, Vec3};
+use koji::canvas::CanvasBuilder;
+use koji::renderer::{CullStats, MeshInstance, Renderer, StaticMesh, Vertex};
+use serial_test::serial;
+
+fn triangle() -> StaticMesh {
+    let vertex = |position: [f32; 3]| Vertex {
+        position,
+        normal: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
+        uv: [0.0, 0.0],
+        color: [1.0, 1.0, 1.0, 1.0],
+    };
+    StaticMesh {
+        material_id: String::new(),
+        vertices: vec![
+            vertex([-0.5, -0.5, 0.0]),
+            vertex([0.5, -0.5, 0.0]),
+            vertex([0.0, 0.5, 0.0]),
+        ],
+        indices: None,
+        vertex_buffer: None,
+        index_buffer: None,
+        index_count: 0,
+        bounds: None,
+    }
+}
+
+fn at(x: f32) -> Vec<MeshInstance> {
+    vec![MeshInstance::from_transform(Mat4::from_translation(
+        Vec3::new(x, 0.0, 0.0),
+    ))]
+}
+
+#[test]
+#[serial]
+fn meshes_outside_the_camera_are_culled() {
+    let mut ctx = Context::headless(&ContextInfo::default()).unwrap();
+    let canvas = CanvasBuilder::new()
+        .extent([16, 16])
+        .color_attachment("color", Format::RGBA8)
+        .build(&mut ctx)
+        .unwrap();
+    let mut renderer = Renderer::with_canvas_headless(16, 16, &mut ctx, canvas).unwrap();
+    let near =
+        renderer.register_static_mesh_instanced(triangle(), None, at(0.0), String::new(), "canvas").unwrap();
+    renderer.register_static_mesh_instanced(triangle(), None, at(100.0), String::new(), "canvas").unwrap();
+
+    // Culling is on by default, but nothing is culled before a camera is set.
+    renderer.present_frame().unwrap();
+    assert_eq!(
+        renderer.cull_stats(),
+        CullStats {
+            visible: 2,
+            culled: 0
+        }
+    );
+
+    let eye = Vec3::new(0.0, 0.0, 5.0);
+    let proj = Mat4::perspective_rh(60f32.to_radians(), 1.0, 0.1, 50.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    renderer.set_camera(0, proj * view, eye);
+    renderer.present_frame().unwrap();
+    assert_eq!(
+        renderer.cull_stats(),
+        CullStats {
+            visible: 1,
+            culled: 1
+        }
+    );
+
+    // Moving the visible mesh away culls it too.
+    renderer.update_static_mesh_instances(near, &at(-100.0)).unwrap();
+    renderer.present_frame().unwrap();
+    assert_eq!(renderer.cull_stats().culled, 2);
+
+    renderer.set_frustum_culling(false);
+    renderer.present_frame().unwrap();
+    assert_eq!(renderer.cull_stats().visible, 2);
+
+    drop(renderer);
+    ctx.destroy();
+}
@@ -1,3 +1,4 @@
+use glam::Vec3;
 use koji::gltf::{load_scene, MeshData};
 
 const TRIANGLE: &str = "tests/data/simple_triangle.gltf";
@@ -10,6 +11,18 @@ fn load_triangle() {
     assert!(matches!(scene.meshes[0].mesh, MeshData::Static(_)));
 }
 
+#[test]
+fn bounds_come_from_accessor_min_max() {
+    let scene = load_scene(TRIANGLE).expect("load");
+    let MeshData::Static(mesh) = &scene.meshes[0].mesh else {
+        panic!("expected static mesh");
+    };
+    let bounds = mesh.bounds.expect("accessor bounds");
+    assert_eq!(bounds.min, Vec3::ZERO);
+    assert_eq!(bounds.max, Vec3::new(1.0, 1.0, 0.0));
+    assert_eq!(bounds.center, Vec3::new(0.5, 0.5, 0.0));
+}
+
 #[test]
 fn load_simple_skin() {
     let scene = load_scene(SKIN).expect("load");
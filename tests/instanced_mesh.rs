@@ -0,0 +1,243 @@
+#![cfg(feature = "gpu_tests")]
+
+use dashi::gpu::{Context, ContextInfo};
+use dashi::Format;
+use glam::{Mat4, Vec3, Vec4};
+use inline_spirv::inline_spirv;
+use koji::canvas::CanvasBuilder;
+use koji::material::{PipelineBuilder, PipelineError, VertexBufferLayout, VertexFormat};
+use koji::renderer::*;
+use serial_test::serial;
+
+fn make_ctx() -> Context {
+    Context::headless(&ContextInfo::default()).unwrap()
+}
+
+fn vert() -> Vec<u32> {
+    inline_spirv!(
+        r"#version 450
+        struct Instance { mat4 transform; vec4 color; uint material_index; uint p0; uint p1; uint p2; };
+        layout(set=0, binding=0) readonly buffer InstanceBuffer { Instance instances[]; } KOJI_instances;
+        layout(location=0) in vec3 pos;
+        layout(location=0) out vec4 v_color;
+        void main(){
+            Instance inst = KOJI_instances.instances[gl_InstanceIndex];
+            v_color = inst.color;
+            gl_Position = inst.transform * vec4(pos, 1.0);
+        }",
+        vert
+    )
+    .to_vec()
+}
+
+fn frag() -> Vec<u32> {
+    inline_spirv!(
+        r"#version 450
+        layout(location=0) in vec4 v_color;
+        layout(location=0) out vec4 o;
+        void main(){ o = v_color; }",
+        frag
+    )
+    .to_vec()
+}
+
+fn make_vertex(pos: [f32; 3]) -> Vertex {
+    Vertex {
+        position: pos,
+        normal: [0.0, 0.0, 1.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
+        uv: [0.0, 0.0],
+        color: [1.0, 1.0, 1.0, 1.0],
+    }
+}
+
+fn triangle() -> StaticMesh {
+    StaticMesh {
+        material_id: String::new(),
+        vertices: vec![
+            make_vertex([-0.1, -0.1, 0.0]),
+            make_vertex([0.1, -0.1, 0.0]),
+            make_vertex([0.0, 0.1, 0.0]),
+        ],
+        indices: None,
+        vertex_buffer: None,
+        index_buffer: None,
+        index_count: 0,
+        bounds: None,
+    }
+}
+
+fn make_renderer(ctx: &mut Context) -> Renderer {
+    let canvas = CanvasBuilder::new()
+        .extent([64, 64])
+        .color_attachment("color", Format::RGBA8)
+        .build(ctx)
+        .unwrap();
+    let mut renderer = Renderer::with_canvas_headless(64, 64, ctx, canvas).unwrap();
+    renderer.set_clear_color([0.0, 0.0, 0.0, 1.0]);
+
+    let vert = vert();
+    let frag = frag();
+    let mut pso = PipelineBuilder::new(ctx, "instanced_pso")
+        .vertex_shader(&vert)
+        .fragment_shader(&frag)
+        .render_pass(renderer.graph().output("color"))
+        .build_with_resources(renderer.resources())
+        .unwrap();
+    let bgr = pso.create_bind_groups(renderer.resources()).unwrap();
+    renderer.register_pipeline_for_pass("main", pso, bgr).unwrap();
+    renderer
+}
+
+#[test]
+#[serial]
+fn instanced_static_mesh_renders() {
+    let mut ctx = make_ctx();
+    let mut renderer = make_renderer(&mut ctx);
+
+    let instances: Vec<MeshInstance> = (0..3)
+        .map(|i| {
+            MeshInstance::from_transform(Mat4::from_translation(Vec3::new(
+                -0.5 + 0.5 * i as f32,
+                0.0,
+                0.0,
+            )))
+            .with_color(Vec4::new(1.0, 0.0, 0.0, 1.0))
+        })
+        .collect();
+    let handle = renderer.register_static_mesh_instanced(
+        triangle(),
+        None,
+        instances.clone(),
+        "instanced".into(),
+        "canvas",
+    ).unwrap();
+    renderer.present_frame().unwrap();
+    let first = renderer.read_color_target("color");
+    assert!(first.chunks(4).any(|px| px[0] > 0));
+
+    // Moving every instance off screen leaves only the clear color.
+    let moved: Vec<MeshInstance> = instances
+        .iter()
+        .map(|i| MeshInstance {
+            transform: Mat4::from_translation(Vec3::new(10.0, 0.0, 0.0)).to_cols_array_2d(),
+            ..*i
+        })
+        .collect();
+    renderer.update_static_mesh_instances(handle, &moved).unwrap();
+    renderer.present_frame().unwrap();
+    let second = renderer.read_color_target("color");
+    assert!(second.chunks(4).all(|px| px[0] == 0));
+
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn removed_and_hidden_meshes_are_not_drawn() {
+    let mut ctx = make_ctx();
+    let mut renderer = make_renderer(&mut ctx);
+    let red = vec![MeshInstance::default().with_color(Vec4::new(1.0, 0.0, 0.0, 1.0))];
+
+    let first = renderer.register_static_mesh_instanced(
+        triangle(),
+        None,
+        red.clone(),
+        "instanced".into(),
+        "canvas",
+    ).unwrap();
+    let second = renderer.register_static_mesh_instanced(
+        triangle(),
+        None,
+        red.clone(),
+        "instanced".into(),
+        "canvas",
+    ).unwrap();
+
+    assert!(renderer.set_visible(second, false));
+    assert!(renderer.remove_drawable(first));
+    assert!(!renderer.remove_drawable(first));
+    assert!(!renderer.contains_drawable(first));
+    assert!(renderer.pending_releases() > 0);
+
+    renderer.present_frame().unwrap();
+    let pixels = renderer.read_color_target("color");
+    assert!(pixels.chunks(4).all(|px| px[0] == 0));
+
+    assert!(renderer.set_visible(second, true));
+    renderer.present_frame().unwrap();
+    let pixels = renderer.read_color_target("color");
+    assert!(pixels.chunks(4).any(|px| px[0] > 0));
+
+    // Buffers are destroyed once the frames in flight have completed.
+    renderer.present_frame().unwrap();
+    assert_eq!(renderer.pending_releases(), 0);
+
+    // The freed slot is reused, but the stale handle stays invalid.
+    let third = renderer.register_static_mesh_instanced(
+        triangle(),
+        None,
+        red,
+        "instanced".into(),
+        "canvas",
+    ).unwrap();
+    assert_eq!(third.index(), first.index());
+    assert_ne!(third, first);
+    assert!(!renderer.set_visible(first, true));
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn grown_ranges_are_released_after_frames_in_flight() {
+    let mut ctx = make_ctx();
+    let mut renderer = make_renderer(&mut ctx);
+    let max = koji::utils::MAX_INSTANCES;
+    let one = vec![MeshInstance::default()];
+
+    let grown = renderer
+        .register_static_mesh_instanced(triangle(), None, one.clone(), "instanced".into(), "canvas")
+        .unwrap();
+    renderer
+        .update_static_mesh_instances(grown, &vec![MeshInstance::default(); max - 1])
+        .unwrap();
+    assert_eq!(
+        renderer.update_static_mesh_instances(grown, &vec![MeshInstance::default(); max]),
+        Err(InstanceCapacityError {
+            requested: max as u32,
+            capacity: max as u32,
+        })
+    );
+
+    // The slot the mesh grew out of may still be read by frames in flight.
+    assert!(renderer
+        .register_static_mesh_instanced(triangle(), None, one.clone(), "instanced".into(), "canvas")
+        .is_err());
+    for _ in 0..3 {
+        renderer.present_frame().unwrap();
+    }
+    assert!(renderer
+        .register_static_mesh_instanced(triangle(), None, one, "instanced".into(), "canvas")
+        .is_ok());
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn pipelines_reading_several_vertex_buffers_are_rejected() {
+    let mut ctx = make_ctx();
+    let mut renderer = make_renderer(&mut ctx);
+    let vert = vert();
+    let frag = frag();
+    let result = PipelineBuilder::new(&mut ctx, "split_pso")
+        .vertex_shader(&vert)
+        .fragment_shader(&frag)
+        .render_pass(renderer.graph().output("color"))
+        .vertex_buffers(vec![
+            VertexBufferLayout::new(12).attribute(0, VertexFormat::Vec3, 0),
+            VertexBufferLayout::new(16).attribute(1, VertexFormat::Vec4, 0),
+        ])
+        .build_with_resources(renderer.resources());
+    assert!(matches!(result, Err(PipelineError::VertexInput(_))));
+    ctx.destroy();
+}
@@ -51,7 +51,7 @@ pub fn run() {
         .build(&mut ctx)
         .unwrap();
     let mut graph = RenderGraph::new();
-    graph.add_canvas(&canvas);
+    graph.add_canvas(&canvas).unwrap();
 
     let mut renderer = Renderer::with_graph(320, 240, &mut ctx, graph).expect("renderer");
 
@@ -76,7 +76,7 @@ pub fn run() {
     renderer.resources().register_combined("roughness_map", img, view,[1,1], sampler);
 
     let bgr = pso.create_bind_groups(&renderer.resources()).unwrap();
-    renderer.register_pipeline_for_pass("main", pso, bgr);
+    renderer.register_pipeline_for_pass("main", pso, bgr).unwrap();
 
     let mesh = StaticMesh {
         material_id: "pbr".into(),
@@ -85,8 +85,9 @@ pub fn run() {
         vertex_buffer: None,
         index_buffer: None,
         index_count: 0,
+        bounds: None,
     };
-    renderer.register_static_mesh(mesh,None,"pbr".into(), "canvas");
+    renderer.register_static_mesh(mesh,None,"pbr".into(), "canvas").unwrap();
 
 
     renderer.present_frame().unwrap();
@@ -62,7 +62,7 @@ pub fn run() {
         .build(&mut ctx)
         .unwrap();
     let mut graph = RenderGraph::new();
-    graph.add_canvas(&canvas);
+    graph.add_canvas(&canvas).unwrap();
 
     let mut renderer = Renderer::with_graph_headless(64, 64, &mut ctx, graph).unwrap();
 
@@ -97,7 +97,7 @@ pub fn run() {
     }
 
     let bgr = pso.create_bind_groups(renderer.resources()).unwrap();
-    renderer.register_pipeline_for_pass("main", pso, bgr);
+    renderer.register_pipeline_for_pass("main", pso, bgr).unwrap();
 
     let mesh = StaticMesh {
         material_id: "pbr".into(),
@@ -106,8 +106,9 @@ pub fn run() {
         vertex_buffer: None,
         index_buffer: None,
         index_count: 0,
+        bounds: None,
     };
-    renderer.register_static_mesh(mesh, None, "pbr".into(), "canvas");
+    renderer.register_static_mesh(mesh, None, "pbr".into(), "canvas").unwrap();
 
     renderer.present_frame().unwrap();
     ctx.destroy();
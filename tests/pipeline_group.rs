@@ -50,7 +50,7 @@ fn static_pipeline_groups_once() {
         .render_pass(renderer.graph().output("color"))
         .build();
     let bgr = pso.create_bind_groups(&renderer.resources()).unwrap();
-    renderer.register_pipeline_for_pass("main", pso, bgr);
+    renderer.register_pipeline_for_pass("main", pso, bgr).unwrap();
 
     let mesh1 = StaticMesh {
         material_id: "p".into(),
@@ -59,6 +59,7 @@ fn static_pipeline_groups_once() {
         vertex_buffer: None,
         index_buffer: None,
         index_count: 0,
+        bounds: None,
     };
     let mesh2 = StaticMesh {
         material_id: "p".into(),
@@ -67,9 +68,10 @@ fn static_pipeline_groups_once() {
         vertex_buffer: None,
         index_buffer: None,
         index_count: 0,
+        bounds: None,
     };
-    renderer.register_static_mesh(mesh1, None, "p".into(), "canvas");
-    renderer.register_static_mesh(mesh2, None, "p".into(), "canvas");
+    renderer.register_static_mesh(mesh1, None, "p".into(), "canvas").unwrap();
+    renderer.register_static_mesh(mesh2, None, "p".into(), "canvas").unwrap();
 
     renderer.present_frame().unwrap();
     let events = test_hooks::take_draw_events();
@@ -98,7 +100,7 @@ fn skeletal_pipeline_groups_once() {
         .render_pass(renderer.graph().output("color"))
         .build();
     let bgr = pso.create_bind_groups(&renderer.resources()).unwrap();
-    renderer.register_skeletal_pso(pso, bgr);
+    renderer.register_skeletal_pso(pso, bgr).unwrap();
 
     let skeleton = Skeleton { bones: vec![Bone::default()] };
     let mut mesh = SkeletalMesh {
@@ -110,6 +112,7 @@ fn skeletal_pipeline_groups_once() {
         index_count: 0,
         skeleton: skeleton.clone(),
         bone_buffer: None,
+        bounds: None,
     };
     let inst1 = SkeletalInstance::new(&mut ctx, Animator::new(skeleton.clone())).unwrap();
     let inst2 = SkeletalInstance::new(&mut ctx, Animator::new(skeleton)).unwrap();
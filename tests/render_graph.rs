@@ -3,8 +3,15 @@
 use dashi::gpu;
 use dashi::*;
 use koji::canvas::CanvasBuilder;
-use koji::render_graph::{CanvasNode, CompositionNode, GraphNode, RenderGraph, ResourceDesc};
+use dashi::utils::Handle;
+use koji::render_graph::{
+    BlendMode, CanvasNode, CompositionError, CompositionNode, ExternalImageNode, GraphError,
+    GraphNode, GraphValidationError, RenderGraph, RenderPassNode, ResourceDesc,
+};
+use koji::renderer::Renderer;
 use serial_test::serial;
+use std::cell::Cell;
+use std::rc::Rc;
 
 fn setup_ctx() -> gpu::Context {
     gpu::Context::headless(&Default::default()).unwrap()
@@ -19,30 +26,73 @@ fn composition_node_registers_inputs() {
             format: Format::RGBA8,
         }],
         Format::BGRA8,
-    );
+    )
+    .unwrap();
     assert_eq!(node.inputs().len(), 1);
     assert_eq!(node.outputs()[0].name, "swapchain");
 }
 
+#[test]
+fn composition_node_rejects_too_many_inputs() {
+    let inputs: Vec<ResourceDesc> = (0..=koji::render_graph::MAX_COMPOSITION_INPUTS)
+        .map(|i| ResourceDesc {
+            name: format!("in{}", i),
+            format: Format::RGBA8,
+        })
+        .collect();
+    assert!(matches!(
+        CompositionNode::new(inputs, Format::BGRA8),
+        Err(CompositionError::TooManyInputs { count: 9, max: 8 })
+    ));
+}
+
 #[test]
 #[serial]
 fn render_graph_executes_with_composition() {
     let mut ctx = setup_ctx();
     let mut graph = RenderGraph::new();
-    graph.register_external_image("input", Format::RGBA8);
+    graph.register_external_image("input", Format::RGBA8).unwrap();
     let node = CompositionNode::new(
         vec![ResourceDesc {
             name: "input".into(),
             format: Format::RGBA8,
         }],
         Format::BGRA8,
-    );
-    graph.add_node(node);
-    graph.connect("input", "composition");
+    )
+    .unwrap();
+    graph.add_node(node).unwrap();
+    graph.connect("input", "composition").unwrap();
     graph.execute(&mut ctx).unwrap();
     ctx.destroy();
 }
 
+#[test]
+#[serial]
+fn execute_reports_validation_errors() {
+    let mut ctx = setup_ctx();
+    let mut graph = RenderGraph::new();
+    let node = CompositionNode::new(
+        vec![ResourceDesc {
+            name: "missing".into(),
+            format: Format::RGBA8,
+        }],
+        Format::BGRA8,
+    )
+    .unwrap();
+    graph.add_node(node).unwrap();
+    match graph.execute(&mut ctx) {
+        Err(GraphError::Validation(errors)) => assert_eq!(
+            errors,
+            vec![GraphValidationError::MissingProducer {
+                node: "composition".into(),
+                resource: "missing".into(),
+            }]
+        ),
+        other => panic!("expected a validation error, got {:?}", other.err()),
+    }
+    ctx.destroy();
+}
+
 #[test]
 #[serial]
 fn graph_yaml_roundtrip() {
@@ -53,7 +103,7 @@ fn graph_yaml_roundtrip() {
         .build(&mut ctx)
         .unwrap();
     let mut graph = RenderGraph::new();
-    graph.add_canvas(&canvas);
+    graph.add_canvas(&canvas).unwrap();
     let yaml = koji::render_graph::to_yaml(&graph).unwrap();
     let loaded = koji::render_graph::from_yaml(&mut ctx, &yaml).unwrap();
     assert_eq!(graph.node_names(), loaded.node_names());
@@ -61,6 +111,97 @@ fn graph_yaml_roundtrip() {
     ctx.destroy();
 }
 
+#[test]
+#[serial]
+fn graph_yaml_roundtrip_keeps_node_kinds() {
+    let mut ctx = setup_ctx();
+    let canvas = CanvasBuilder::new()
+        .extent([1, 1])
+        .color_attachment("color", Format::RGBA8)
+        .build(&mut ctx)
+        .unwrap();
+    let mut graph = RenderGraph::new();
+    graph.add_canvas(&canvas).unwrap();
+    graph
+        .add_node(RenderPassNode::new(
+            "overlay",
+            canvas.render_pass(),
+            Vec::new(),
+            Vec::new(),
+        ))
+        .unwrap();
+    graph.register_external_image("input", Format::RGBA8).unwrap();
+    let composition = CompositionNode::new(
+        vec![ResourceDesc {
+            name: "input".into(),
+            format: Format::RGBA8,
+        }],
+        Format::BGRA8,
+    )
+    .unwrap();
+    graph.add_node(composition).unwrap();
+    graph.connect("input", "composition").unwrap();
+
+    let yaml = koji::render_graph::to_yaml(&graph).unwrap();
+    let loaded = koji::render_graph::from_yaml(&mut ctx, &yaml).unwrap();
+    assert_eq!(graph.node_names(), loaded.node_names());
+    for idx in loaded.topo_indices() {
+        let node = loaded.node(idx);
+        let any = node.as_any();
+        match node.name() {
+            "canvas" => assert!(any.is::<CanvasNode>()),
+            "overlay" => assert_eq!(
+                any.downcast_ref::<RenderPassNode>().unwrap().pass(),
+                canvas_pass(&loaded)
+            ),
+            "input" => assert!(any.is::<ExternalImageNode>()),
+            "composition" => assert_eq!(
+                any.downcast_ref::<CompositionNode>().unwrap().mode(),
+                BlendMode::Alpha
+            ),
+            other => panic!("unexpected node {}", other),
+        }
+    }
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn graph_yaml_roundtrip_keeps_sampled_inputs() {
+    let mut ctx = setup_ctx();
+    let canvas = CanvasBuilder::new()
+        .extent([1, 1])
+        .color_attachment("lit", Format::RGBA8)
+        .build(&mut ctx)
+        .unwrap();
+    let mut graph = RenderGraph::new();
+    graph.register_external_image("albedo", Format::RGBA8).unwrap();
+    graph
+        .add_node(CanvasNode::new("lighting", canvas).sample("albedo", Format::RGBA8))
+        .unwrap();
+    graph.connect("albedo", "lighting").unwrap();
+
+    let yaml = koji::render_graph::to_yaml(&graph).unwrap();
+    let loaded = koji::render_graph::from_yaml(&mut ctx, &yaml).unwrap();
+    let lighting = loaded
+        .topo_indices()
+        .into_iter()
+        .map(|idx| loaded.node(idx))
+        .find(|n| n.name() == "lighting")
+        .unwrap();
+    let inputs: Vec<_> = lighting
+        .inputs()
+        .into_iter()
+        .map(|r| (r.name, r.format))
+        .collect();
+    assert_eq!(inputs, vec![("albedo".to_string(), Format::RGBA8)]);
+    ctx.destroy();
+}
+
+fn canvas_pass(graph: &RenderGraph) -> Handle<gpu::RenderPass> {
+    graph.canvases()[0].render_pass()
+}
+
 #[test]
 #[serial]
 fn graph_json_roundtrip() {
@@ -71,7 +212,7 @@ fn graph_json_roundtrip() {
         .build(&mut ctx)
         .unwrap();
     let mut graph = RenderGraph::new();
-    graph.add_canvas(&canvas);
+    graph.add_canvas(&canvas).unwrap();
     let json = koji::render_graph::to_json(&graph).unwrap();
     let loaded = koji::render_graph::from_json(&mut ctx, &json).unwrap();
     assert_eq!(loaded.output_images(), vec!["img".to_string()]);
@@ -89,7 +230,7 @@ fn canvas_node_outputs() {
         .unwrap();
 
     let mut graph = RenderGraph::new();
-    graph.add_canvas(&canvas);
+    graph.add_canvas(&canvas).unwrap();
     assert_eq!(graph.output_images(), vec!["color".to_string()]);
     let rp = graph.render_pass_for_output("color");
     assert!(matches!(rp, Some((_p, Format::RGBA8))));
@@ -108,18 +249,107 @@ fn canvas_node_composition_executes() {
 
     let mut graph = RenderGraph::new();
     let canvas_node = CanvasNode::from(&canvas);
-    graph.add_node(canvas_node);
+    graph.add_node(canvas_node).unwrap();
     let comp = CompositionNode::new(
         vec![ResourceDesc {
             name: "color".into(),
             format: Format::RGBA8,
         }],
         Format::BGRA8,
-    );
-    graph.add_node(comp);
-    graph.connect("canvas", "composition");
+    )
+    .unwrap();
+    graph.add_node(comp).unwrap();
+    graph.connect("canvas", "composition").unwrap();
 
     graph.validate().unwrap();
     graph.execute(&mut ctx).unwrap();
     ctx.destroy();
 }
+
+fn solid_image(ctx: &mut gpu::Context, name: &str, rgba: [u8; 4]) -> Handle<ImageView> {
+    let data: Vec<u8> = rgba.iter().copied().cycle().take(16 * 16 * 4).collect();
+    let img = ctx
+        .make_image(&ImageInfo {
+            debug_name: name,
+            dim: [16, 16, 1],
+            format: Format::RGBA8,
+            mip_levels: 1,
+            layers: 1,
+            initial_data: Some(&data),
+        })
+        .unwrap();
+    ctx.make_image_view(&ImageViewInfo {
+        debug_name: name,
+        img,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+#[test]
+#[serial]
+fn composition_alpha_blends_all_inputs() {
+    let mut ctx = setup_ctx();
+    let bottom = solid_image(&mut ctx, "bottom", [255, 0, 0, 255]);
+    let top = solid_image(&mut ctx, "top", [0, 0, 255, 128]);
+
+    let mut graph = RenderGraph::new();
+    graph.register_external_image_view("bottom", Format::RGBA8, bottom).unwrap();
+    graph.register_external_image_view("top", Format::RGBA8, top).unwrap();
+    let composition = CompositionNode::new(
+        vec![
+            ResourceDesc { name: "bottom".into(), format: Format::RGBA8 },
+            ResourceDesc { name: "top".into(), format: Format::RGBA8 },
+        ],
+        Format::BGRA8,
+    )
+    .unwrap();
+    graph.add_node(composition).unwrap();
+    graph.connect("bottom", "composition").unwrap();
+    graph.connect("top", "composition").unwrap();
+
+    let mut renderer = Renderer::with_graph_headless(16, 16, &mut ctx, graph).unwrap();
+    renderer.present_frame().unwrap();
+    let pixels = renderer.read_color_target("swapchain");
+    // The target has the swapchain's BGRA8 format. Both inputs contribute:
+    // the half-transparent top layer lets red through.
+    for px in pixels.chunks(4) {
+        let (blue, green, red) = (px[0], px[1], px[2]);
+        assert!(red > 0 && red < 255, "red {}", red);
+        assert!(blue > 0, "blue {}", blue);
+        assert_eq!(green, 0);
+    }
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn render_pass_node_records_each_frame() {
+    let mut ctx = setup_ctx();
+    let canvas = CanvasBuilder::new()
+        .extent([1, 1])
+        .color_attachment("color", Format::RGBA8)
+        .build(&mut ctx)
+        .unwrap();
+
+    let frames = Rc::new(Cell::new(0));
+    let counter = frames.clone();
+    let mut graph = RenderGraph::new();
+    graph.add_canvas(&canvas).unwrap();
+    graph.add_node(
+        RenderPassNode::new("custom", canvas.render_pass(), Vec::new(), Vec::new()).recorder(
+            move |pass, _rp| {
+                assert!(pass.image("color").is_some());
+                counter.set(counter.get() + 1);
+                Ok(())
+            },
+        ),
+    ).unwrap();
+    graph.connect("canvas", "custom").unwrap();
+
+    let mut renderer = Renderer::with_graph_headless(1, 1, &mut ctx, graph).unwrap();
+    renderer.present_frame().unwrap();
+    renderer.present_frame().unwrap();
+    assert_eq!(frames.get(), 2);
+    ctx.destroy();
+}
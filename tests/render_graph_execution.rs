@@ -23,6 +23,7 @@ fn simple_mesh() -> StaticMesh {
         vertex_buffer: None,
         index_buffer: None,
         index_count: 0,
+        bounds: None,
     }
 }
 
@@ -37,7 +38,7 @@ fn execute_graph(ctx: &mut gpu::Context, graph: RenderGraph) -> Vec<String> {
         .render_pass(renderer.graph().output("first"))
         .build();
     let bgr_first = pso_first.create_bind_groups(renderer.resources()).unwrap();
-    renderer.register_material_pipeline("mat_first", pso_first, bgr_first);
+    renderer.register_material_pipeline("mat_first", pso_first, bgr_first).unwrap();
 
     let mut pso_second = PipelineBuilder::new(ctx, "pso_second")
         .vertex_shader(vert)
@@ -45,12 +46,12 @@ fn execute_graph(ctx: &mut gpu::Context, graph: RenderGraph) -> Vec<String> {
         .render_pass(renderer.graph().output("second"))
         .build();
     let bgr_second = pso_second.create_bind_groups(renderer.resources()).unwrap();
-    renderer.register_material_pipeline("mat_second", pso_second, bgr_second);
+    renderer.register_material_pipeline("mat_second", pso_second, bgr_second).unwrap();
 
     let mesh1 = simple_mesh();
     let mesh2 = simple_mesh();
-    renderer.register_static_mesh(mesh1, None, "mat_first".into(), "first");
-    renderer.register_static_mesh(mesh2, None, "mat_second".into(), "second");
+    renderer.register_static_mesh(mesh1, None, "mat_first".into(), "first").unwrap();
+    renderer.register_static_mesh(mesh2, None, "mat_second".into(), "second").unwrap();
 
     renderer.present_frame().unwrap();
     take_draw_events()
@@ -159,3 +160,239 @@ fn json_graph_roundtrip_execution() {
     );
     ctx.destroy();
 }
+
+fn write_spirv(data: &[u32], name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("{}_{}.spv", name, std::process::id()));
+    std::fs::write(&path, bytemuck::cast_slice(data)).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+#[serial]
+fn yaml_graph_declares_material_pipelines() {
+    let vert = write_spirv(include_spirv!("assets/shaders/test_triangle.vert", vert), "graph_vert");
+    let frag = write_spirv(include_spirv!("assets/shaders/test_triangle.frag", frag), "graph_frag");
+    let yaml = format!(
+        r#"nodes:
+  - name: first
+    inputs: []
+    outputs:
+      - name: first
+        format: RGBA8
+    pipelines:
+      - name: pso_first
+        vertex: {vert}
+        fragment: {frag}
+        output: first
+        material: mat_first
+  - name: second
+    inputs:
+      - name: first
+        format: RGBA8
+    outputs:
+      - name: second
+        format: RGBA8
+    pipelines:
+      - name: pso_second
+        vertex: {vert}
+        fragment: {frag}
+        output: second
+        cull: back
+        material: mat_second
+canvases:
+  - name: first
+    canvas:
+      extent: [1, 1]
+      attachments:
+        - name: first
+          format: RGBA8
+  - name: second
+    canvas:
+      extent: [1, 1]
+      attachments:
+        - name: second
+          format: RGBA8
+edges:
+  - [first, second]
+"#
+    );
+
+    let mut ctx = setup_ctx();
+    let graph = koji::render_graph::from_yaml(&mut ctx, &yaml).unwrap();
+    assert_eq!(graph.pipeline_descs("second")[0].material.as_deref(), Some("mat_second"));
+    let yaml_rt = koji::render_graph::to_yaml(&graph).unwrap();
+    assert!(yaml_rt.contains("pso_first"));
+
+    let mut renderer = Renderer::with_graph_headless(1, 1, &mut ctx, graph).unwrap();
+    renderer.load_graph_pipelines().unwrap();
+    renderer.register_static_mesh(simple_mesh(), None, "mat_first".into(), "first").unwrap();
+    renderer.register_static_mesh(simple_mesh(), None, "mat_second".into(), "second").unwrap();
+    renderer.present_frame().unwrap();
+    assert_eq!(
+        take_draw_events(),
+        vec![
+            "pass:first",
+            "begin_static",
+            "end_static",
+            "pass:second",
+            "begin_static",
+            "end_static",
+        ]
+    );
+    std::fs::remove_file(vert).unwrap();
+    std::fs::remove_file(frag).unwrap();
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn graph_pipeline_binding_aliases_do_not_outlive_the_build() {
+    let vert = write_spirv(include_spirv!("assets/shaders/test_triangle.vert", vert), "alias_vert");
+    let frag = write_spirv(include_spirv!("assets/shaders/test_triangle.frag", frag), "alias_frag");
+    let bad = format!(
+        "      - name: pso_bad\n        vertex: {vert}\n        fragment: missing_shader.spv\n        output: first\n        material: mat_bad\n"
+    );
+    let yaml = format!(
+        r#"nodes:
+  - name: first
+    inputs: []
+    outputs:
+      - name: first
+        format: RGBA8
+    pipelines:
+      - name: pso_good
+        vertex: {vert}
+        fragment: {frag}
+        output: first
+        material: mat_good
+        bindings:
+          tint: tint_source
+{bad}canvases:
+  - name: first
+    canvas:
+      extent: [1, 1]
+      attachments:
+        - name: first
+          format: RGBA8
+edges: []
+"#
+    );
+
+    let mut ctx = setup_ctx();
+    let graph = koji::render_graph::from_yaml(&mut ctx, &yaml).unwrap();
+    let mut renderer = Renderer::with_graph_headless(1, 1, &mut ctx, graph).unwrap();
+    renderer
+        .resources()
+        .register_variable("tint_source", &mut ctx, [1.0f32; 4]);
+    assert!(matches!(
+        renderer.load_graph_pipelines(),
+        Err(GraphError::Desc(_))
+    ));
+    assert!(renderer.resources().get("tint").is_none());
+    assert!(renderer.resources().get("tint_source").is_some());
+
+    // A successful build drops its aliases too once the bind groups exist.
+    let graph = koji::render_graph::from_yaml(&mut ctx, &yaml.replace(&bad, "")).unwrap();
+    let mut renderer = Renderer::with_graph_headless(1, 1, &mut ctx, graph).unwrap();
+    renderer
+        .resources()
+        .register_variable("tint_source", &mut ctx, [1.0f32; 4]);
+    renderer.load_graph_pipelines().unwrap();
+    assert!(renderer.resources().get("tint").is_none());
+    assert!(renderer.resources().get("tint_source").is_some());
+
+    std::fs::remove_file(vert).unwrap();
+    std::fs::remove_file(frag).unwrap();
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn watched_graph_file_reloads_between_frames() {
+    let canvas = |name: &str| {
+        format!(
+            "  - name: {name}\n    canvas:\n      extent: [1, 1]\n      attachments:\n        - name: {name}\n          format: RGBA8\n"
+        )
+    };
+    let node = |name: &str| {
+        format!(
+            "  - name: {name}\n    inputs: []\n    outputs:\n      - name: {name}\n        format: RGBA8\n"
+        )
+    };
+    let one = format!("nodes:\n{}canvases:\n{}edges: []\n", node("first"), canvas("first"));
+    let two = format!(
+        "nodes:\n{}{}canvases:\n{}{}edges: []\n",
+        node("first"),
+        node("second"),
+        canvas("first"),
+        canvas("second")
+    );
+    let path = std::env::temp_dir().join(format!("koji_hot_reload_{}.yaml", std::process::id()));
+    std::fs::write(&path, &one).unwrap();
+
+    let mut ctx = setup_ctx();
+    let graph = koji::render_graph::from_yaml(&mut ctx, &one).unwrap();
+    let mut renderer = Renderer::with_graph_headless(1, 1, &mut ctx, graph).unwrap();
+    renderer.watch_graph_file(&path);
+    assert!(matches!(renderer.poll_hot_reload(), Ok(false)));
+    let old_view = renderer.graph().image_view("first").unwrap();
+    let first = renderer.graph().canvas_for_output("first").unwrap().clone();
+    renderer.resources().register_texture(
+        "first_tex",
+        first.image("first").unwrap(),
+        old_view,
+        first.extent(),
+    );
+
+    std::fs::write(&path, "nodes: [").unwrap();
+    renderer.present_frame().unwrap();
+    assert!(renderer.reload_error().is_some());
+    assert_eq!(renderer.graph().node_names(), vec!["first".to_string()]);
+
+    std::fs::write(&path, &two).unwrap();
+    renderer.present_frame().unwrap();
+    assert_eq!(renderer.reload_error(), None);
+    assert_eq!(
+        renderer.graph().node_names(),
+        vec!["first".to_string(), "second".to_string()]
+    );
+    let new_view = renderer.graph().image_view("first").unwrap();
+    assert!(new_view != old_view);
+    match renderer.resources().get("first_tex") {
+        Some(ResourceBinding::Texture(tex)) => assert_eq!(tex.view, new_view),
+        _ => panic!("expected the texture binding to be kept"),
+    }
+
+    std::fs::write(&path, two.replace("first", "third")).unwrap();
+    renderer.present_frame().unwrap();
+    assert!(renderer.reload_error().unwrap().contains("first_tex"));
+    take_draw_events();
+
+    std::fs::remove_file(path).unwrap();
+    ctx.destroy();
+}
+
+#[test]
+#[serial]
+fn reload_is_rejected_while_deferred_is_enabled() {
+    let yaml = "nodes:\n  - name: first\n    inputs: []\n    outputs:\n      - name: first\n        format: RGBA8\ncanvases:\n  - name: first\n    canvas:\n      extent: [1, 1]\n      attachments:\n        - name: first\n          format: RGBA8\nedges: []\n";
+    let path = std::env::temp_dir().join(format!("koji_reload_deferred_{}.yaml", std::process::id()));
+    std::fs::write(&path, yaml).unwrap();
+
+    let mut ctx = setup_ctx();
+    let graph = koji::render_graph::from_yaml(&mut ctx, yaml).unwrap();
+    let mut renderer = Renderer::with_graph_headless(1, 1, &mut ctx, graph).unwrap();
+    renderer.enable_deferred().unwrap();
+    renderer.watch_graph_file(&path);
+
+    std::fs::write(&path, yaml.replace("[1, 1]", "[16, 16]")).unwrap();
+    assert!(matches!(
+        renderer.poll_hot_reload(),
+        Err(GraphError::RendererNode(_))
+    ));
+    assert!(renderer.graph().node_names().contains(&"gbuffer".to_string()));
+    take_draw_events();
+
+    std::fs::remove_file(path).unwrap();
+    ctx.destroy();
+}
@@ -90,7 +90,7 @@ fn render_triangle_and_cube() {
         .build(&mut ctx)
         .unwrap();
     let mut graph = RenderGraph::new();
-    graph.add_canvas(&canvas);
+    graph.add_canvas(&canvas).unwrap();
 
     let mut renderer = Renderer::with_graph(640, 480, &mut ctx, graph).expect("Error making Renderer");
 
@@ -109,7 +109,7 @@ fn render_triangle_and_cube() {
     let bind_group_resources = pso.create_bind_groups(&renderer.resources()).unwrap();
 
     // Register pipeline+resources
-    renderer.register_pipeline_for_pass("main", pso, bind_group_resources);
+    renderer.register_pipeline_for_pass("main", pso, bind_group_resources).unwrap();
 
     // Register triangle
     let triangle_mesh = StaticMesh {
@@ -119,6 +119,7 @@ fn render_triangle_and_cube() {
         vertex_buffer: None,
         index_buffer: None,
         index_count: 0,
+        bounds: None,
     };
 
     // Register cube
@@ -129,13 +130,15 @@ fn render_triangle_and_cube() {
         vertex_buffer: None,
         index_buffer: None,
         index_count: 0,
+        bounds: None,
     };
-    renderer.register_static_mesh(triangle_mesh, None, "color".into(), "canvas");
-    renderer.register_static_mesh(cube_mesh, None, "color".into(), "canvas");
+    renderer.register_static_mesh(triangle_mesh, None, "color".into(), "canvas").unwrap();
+    renderer.register_static_mesh(cube_mesh, None, "color".into(), "canvas").unwrap();
 
     // Main loop: just draw both objects with same pipeline/PSO/bind group
     renderer.render_loop(|_r, _event| {
         // Nothing to update per frame in this simple test
-    });
+    })
+    .unwrap();
 }
 
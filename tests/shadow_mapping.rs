@@ -0,0 +1,102 @@
+#![cfg(feature = "gpu_tests")]
+
+use dashi::gpu::{Context, ContextInfo};
+use dashi::Format;
+use koji::canvas::CanvasBuilder;
+use koji::material::LightDesc;
+use koji::renderer::{
+    LightShadows, Renderer, ShadowError, StaticMesh, Vertex, SHADOW_ATLAS, SHADOW_VIEWS,
+};
+use koji::utils::ResourceBinding;
+use serial_test::serial;
+
+fn quad() -> StaticMesh {
+    let vertex = |position: [f32; 3]| Vertex {
+        position,
+        normal: [0.0, 1.0, 0.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
+        uv: [0.0, 0.0],
+        color: [1.0, 1.0, 1.0, 1.0],
+    };
+    StaticMesh {
+        material_id: "shadowed".into(),
+        vertices: vec![
+            vertex([-1.0, 0.0, -1.0]),
+            vertex([1.0, 0.0, -1.0]),
+            vertex([1.0, 0.0, 1.0]),
+            vertex([-1.0, 0.0, 1.0]),
+        ],
+        indices: Some(vec![0, 1, 2, 2, 3, 0]),
+        vertex_buffer: None,
+        index_buffer: None,
+        index_count: 6,
+        bounds: None,
+    }
+}
+
+#[test]
+#[serial]
+fn lights_opt_into_shadow_views() {
+    let mut ctx = Context::headless(&ContextInfo::default()).unwrap();
+    let canvas = CanvasBuilder::new()
+        .extent([16, 16])
+        .color_attachment("color", Format::RGBA8)
+        .build(&mut ctx)
+        .unwrap();
+    let mut renderer = Renderer::with_canvas_headless(16, 16, &mut ctx, canvas).unwrap();
+    renderer.enable_shadows(256).unwrap();
+    assert!(matches!(
+        renderer.resources().get(SHADOW_ATLAS),
+        Some(ResourceBinding::CombinedImageSampler { .. })
+    ));
+    assert!(matches!(
+        renderer.resources().get(SHADOW_VIEWS),
+        Some(ResourceBinding::Storage(_))
+    ));
+    renderer.register_static_mesh(quad(), None, "shadowed".into(), "canvas").unwrap();
+
+    let sun = renderer.add_light(LightDesc {
+        direction: [-0.2, -1.0, -0.1],
+        intensity: 1.0,
+        ..Default::default()
+    });
+    let spot = renderer.add_light(LightDesc {
+        position: [0.0, 3.0, 0.0],
+        direction: [0.0, -1.0, 0.0],
+        range: 10.0,
+        intensity: 1.0,
+        ..Default::default()
+    });
+    renderer.present_frame().unwrap();
+    assert!(renderer.shadow_views().is_empty());
+
+    renderer
+        .set_light_shadows(sun, Some(LightShadows::directional(3)))
+        .unwrap();
+    renderer
+        .set_light_shadows(spot, Some(LightShadows::spot(1.0)))
+        .unwrap();
+    renderer.present_frame().unwrap();
+    let views = renderer.shadow_views();
+    assert_eq!(views.len(), 4);
+    assert!(views[..3].iter().all(|v| v.light_index == sun));
+    assert_eq!(views[3].light_index, spot);
+    assert_eq!(views[3].atlas_rect, [0.75, 0.0, 0.25, 0.25]);
+
+    renderer.set_light_shadows(sun, None).unwrap();
+    renderer.present_frame().unwrap();
+    assert_eq!(renderer.shadow_views().len(), 1);
+
+    assert!(matches!(
+        renderer.set_light_shadows(spot + 1, Some(LightShadows::spot(1.0))),
+        Err(ShadowError::UnknownLight(light)) if light == spot + 1
+    ));
+    renderer.remove_light(sun as usize);
+    renderer.present_frame().unwrap();
+    let views = renderer.shadow_views();
+    assert_eq!(views.len(), 1);
+    assert_eq!(views[0].light_index, spot - 1);
+
+    drop(renderer);
+    ctx.destroy();
+}
@@ -21,7 +21,7 @@ pub fn run() {
         .build(&mut ctx)
         .unwrap();
     let mut graph = RenderGraph::new();
-    graph.add_canvas(&canvas);
+    graph.add_canvas(&canvas).unwrap();
 
     let mut renderer = Renderer::with_graph(320, 240, &mut ctx, graph).unwrap();
 
@@ -31,7 +31,7 @@ pub fn run() {
     let player = AnimationPlayer::new(clip);
     let animator = Animator::new(mesh.skeleton.clone());
     let instance = SkeletalInstance::with_player(&mut ctx, animator, player).unwrap();
-    renderer.register_skeletal_mesh(mesh, vec![instance], "skin".into(), "canvas");
+    let handle = renderer.register_skeletal_mesh(mesh, vec![instance], "skin".into(), "canvas");
 
     let vert: &[u32] = include_spirv!("src/renderer/skinning.vert", vert, glsl);
     let frag: &[u32] = include_spirv!("src/renderer/skinning.frag", frag, glsl);
@@ -41,9 +41,9 @@ pub fn run() {
         .render_pass(renderer.graph().output("color"))
         .build();
     let bgr = pso.create_bind_groups(&renderer.resources()).unwrap();
-    renderer.register_skeletal_pso(pso,bgr);
+    renderer.register_skeletal_pso(pso,bgr).unwrap();
 
-    renderer.play_animation("canvas",0,0,0.5);
+    renderer.play_animation(handle,0,0.5);
     renderer.present_frame().unwrap();
     ctx.destroy();
 }
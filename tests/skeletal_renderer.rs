@@ -22,7 +22,7 @@ pub fn run_simple_skeleton() {
         .build(&mut ctx)
         .unwrap();
     let mut graph = RenderGraph::new();
-    graph.add_canvas(&canvas);
+    graph.add_canvas(&canvas).unwrap();
 
     let mut renderer = Renderer::with_graph(320, 240, &mut ctx, graph).unwrap();
 
@@ -33,7 +33,7 @@ pub fn run_simple_skeleton() {
     };
     let bone_count = mesh.skeleton.bone_count();
     let instance = SkeletalInstance::new(&mut ctx, Animator::new(mesh.skeleton.clone())).unwrap();
-    renderer.register_skeletal_mesh(mesh, vec![instance], "skin".into(), "canvas");
+    let handle = renderer.register_skeletal_mesh(mesh, vec![instance], "skin".into(), "canvas");
 
     let vert: &[u32] = include_spirv!("src/renderer/skinning.vert", vert, glsl);
     let frag: &[u32] = include_spirv!("src/renderer/skinning.frag", frag, glsl);
@@ -43,10 +43,10 @@ pub fn run_simple_skeleton() {
         .render_pass(renderer.graph().output("color"))
         .build();
     let bgr = pso.create_bind_groups(&renderer.resources()).unwrap();
-    renderer.register_skeletal_pso(pso, bgr);
+    renderer.register_skeletal_pso(pso, bgr).unwrap();
 
     let mats = vec![Mat4::IDENTITY; bone_count];
-    renderer.update_skeletal_bones("canvas", 0, 0, &mats);
+    renderer.update_skeletal_bones(handle, 0, &mats);
     renderer.present_frame().unwrap();
     ctx.destroy();
 }
@@ -64,7 +64,7 @@ pub fn run_update_bones_twice() {
         .build(&mut ctx)
         .unwrap();
     let mut graph = RenderGraph::new();
-    graph.add_canvas(&canvas);
+    graph.add_canvas(&canvas).unwrap();
 
     let mut renderer = Renderer::with_graph(320, 240, &mut ctx, graph).unwrap();
 
@@ -75,7 +75,7 @@ pub fn run_update_bones_twice() {
     };
     let bone_count = mesh.skeleton.bone_count();
     let instance = SkeletalInstance::new(&mut ctx, Animator::new(mesh.skeleton.clone())).unwrap();
-    renderer.register_skeletal_mesh(mesh, vec![instance], "skin".into(), "canvas");
+    let handle = renderer.register_skeletal_mesh(mesh, vec![instance], "skin".into(), "canvas");
 
     let vert: &[u32] = include_spirv!("src/renderer/skinning.vert", vert, glsl);
     let frag: &[u32] = include_spirv!("src/renderer/skinning.frag", frag, glsl);
@@ -85,11 +85,11 @@ pub fn run_update_bones_twice() {
         .render_pass(renderer.graph().output("color"))
         .build();
     let bgr = pso.create_bind_groups(&renderer.resources()).unwrap();
-    renderer.register_skeletal_pso(pso, bgr);
+    renderer.register_skeletal_pso(pso, bgr).unwrap();
 
     let mats = vec![Mat4::IDENTITY; bone_count];
-    renderer.update_skeletal_bones("canvas", 0, 0, &mats);
-    renderer.update_skeletal_bones("canvas", 0, 0, &mats);
+    renderer.update_skeletal_bones(handle, 0, &mats);
+    renderer.update_skeletal_bones(handle, 0, &mats);
     renderer.present_frame().unwrap();
     ctx.destroy();
 }
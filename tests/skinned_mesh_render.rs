@@ -21,7 +21,7 @@ pub fn run() {
         .build(&mut ctx)
         .unwrap();
     let mut graph = RenderGraph::new();
-    graph.add_canvas(&canvas);
+    graph.add_canvas(&canvas).unwrap();
 
     let mut renderer = Renderer::with_graph(320, 240, &mut ctx, graph).unwrap();
 
@@ -29,7 +29,7 @@ pub fn run() {
     let mesh = match &scene.meshes[0].mesh { MeshData::Skeletal(m) => m.clone(), _ => panic!("expected skel") };
     let bone_count = mesh.skeleton.bone_count();
     let instance = SkeletalInstance::new(&mut ctx, Animator::new(mesh.skeleton.clone())).unwrap();
-    renderer.register_skeletal_mesh(mesh, vec![instance], "skin".into(), "canvas");
+    let handle = renderer.register_skeletal_mesh(mesh, vec![instance], "skin".into(), "canvas");
 
     let vert: &[u32] = include_spirv!("src/renderer/skinning.vert", vert, glsl);
     let frag: &[u32] = include_spirv!("src/renderer/skinning.frag", frag, glsl);
@@ -39,10 +39,10 @@ pub fn run() {
         .render_pass(renderer.graph().output("color"))
         .build();
     let bgr = pso.create_bind_groups(&renderer.resources()).unwrap();
-    renderer.register_skeletal_pso(pso,bgr);
+    renderer.register_skeletal_pso(pso,bgr).unwrap();
 
     let mats = vec![Mat4::IDENTITY; bone_count];
-    renderer.update_skeletal_bones("canvas",0,0,&mats);
+    renderer.update_skeletal_bones(handle,0,&mats);
     renderer.present_frame().unwrap();
     ctx.destroy();
 }
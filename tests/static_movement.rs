@@ -40,7 +40,7 @@ pub fn run() {
         .build(&mut ctx)
         .unwrap();
     let mut graph = RenderGraph::new();
-    graph.add_canvas(&canvas);
+    graph.add_canvas(&canvas).unwrap();
 
     let mut renderer = Renderer::with_graph(320, 240, &mut ctx, graph).unwrap();
 
@@ -50,7 +50,7 @@ pub fn run() {
         .render_pass(renderer.graph().output("color"))
         .build();
     let bgr = pso.create_bind_groups(&renderer.resources()).unwrap();
-    renderer.register_pipeline_for_pass("main", pso, bgr);
+    renderer.register_pipeline_for_pass("main", pso, bgr).unwrap();
 
     let mesh = StaticMesh {
         material_id: "default".into(),
@@ -63,8 +63,9 @@ pub fn run() {
         vertex_buffer: None,
         index_buffer: None,
         index_count: 0,
+        bounds: None,
     };
-    renderer.register_static_mesh(mesh,None,"default".into(), "canvas");
+    let handle = renderer.register_static_mesh(mesh,None,"default".into(), "canvas").unwrap();
 
     // move vertices slightly
     let new_verts = vec![
@@ -72,7 +73,7 @@ pub fn run() {
         make_vertex([0.75,-0.25,0.0]),
         make_vertex([0.25,0.75,0.0]),
     ];
-    renderer.update_static_mesh("canvas",0,&new_verts);
+    renderer.update_static_mesh(handle,&new_verts);
 
     renderer.present_frame().unwrap();
     ctx.destroy();
@@ -69,7 +69,7 @@ pub fn run() {
         .build(&mut ctx)
         .unwrap();
     let mut graph = RenderGraph::new();
-    graph.add_canvas(&canvas);
+    graph.add_canvas(&canvas).unwrap();
 
     let mut renderer = Renderer::with_graph(320, 240, &mut ctx, graph).expect("renderer");
 
@@ -93,7 +93,7 @@ pub fn run() {
         .render_pass(renderer.graph().output("color"))
         .build();
     let bgr = pso.create_bind_groups(renderer.resources()).unwrap();
-    renderer.register_pso(RenderStage::Text, pso, bgr);
+    renderer.register_pso(RenderStage::Text, pso, bgr).unwrap();
 
     renderer.present_frame().unwrap();
     ctx.destroy();
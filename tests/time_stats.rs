@@ -41,7 +41,7 @@ fn renderer_updates_time_buffer() {
         .build(&mut ctx)
         .unwrap();
     let mut graph = RenderGraph::new();
-    graph.add_canvas(&canvas);
+    graph.add_canvas(&canvas).unwrap();
 
     let mut renderer = Renderer::with_graph(64, 64, &mut ctx, graph).unwrap();
 